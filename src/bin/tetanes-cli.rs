@@ -0,0 +1,231 @@
+//! A headless NES runner for scripted ROM execution
+//!
+//! USAGE:
+//!     tetanes-cli [OPTIONS] <path>
+//!
+//! OPTIONS:
+//!     --frames <frames>            Number of frames to run. [default: 60]
+//!     --seconds <seconds>          Number of seconds to run, overrides `--frames`.
+//!     -i, --input <input>          A `.json` input file of joypad events to replay.
+//!     -p, --patch <patch>          An `.ips`, `.ups` or `.bps` patch to apply to the ROM.
+//!     --frame-out <frame-out>      Write the final frame to a `.png` or `.ppm` file.
+//!     --audio-out <audio-out>      Write raw 32-bit float little-endian mono audio samples.
+//!     --hash-out <hash-out>        Write the CRC32 hash of the final frame.
+//!
+//! ARGS:
+//!     <path>    The NES ROM to load.
+
+use anyhow::{bail, Context};
+use pix_engine::prelude::{Image, PixelFormat};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    env,
+    ffi::OsStr,
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+use tetanes::{
+    audio::AudioMixer,
+    control_deck::ControlDeck,
+    input::{JoypadBtn, Slot},
+    mem::RamState,
+    ppu::Ppu,
+    video::VideoFilter,
+    NesResult,
+};
+
+fn main() -> NesResult<()> {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    pretty_env_logger::init();
+
+    let opt = Opt::from_args();
+
+    let mut deck = ControlDeck::new(opt.ram_state.unwrap_or_default());
+    deck.set_filter(VideoFilter::Pixellate);
+    let mut rom = BufReader::new(
+        File::open(&opt.path).with_context(|| format!("failed to open rom {:?}", opt.path))?,
+    );
+    match opt.patch {
+        Some(ref path) => {
+            let patch = fs::read(path).with_context(|| format!("failed to read patch {path:?}"))?;
+            deck.load_rom_with_patch(opt.path.to_string_lossy(), &mut rom, &patch)?;
+        }
+        None => deck.load_rom(opt.path.to_string_lossy(), &mut rom)?,
+    }
+    for code in &opt.genie_codes {
+        deck.add_genie_code(code.clone())?;
+    }
+
+    let mut events = match opt.input {
+        Some(ref path) => load_input(path)?,
+        None => vec![],
+    };
+    // Events are popped from the back in frame order
+    events.sort_by_key(|event| Reverse(event.frame));
+
+    let mut audio = AudioMixer::new(deck.sample_rate(), opt.sample_rate, 8192);
+    let mut callback = audio.open_callback()?;
+    let mut samples = vec![];
+
+    let run_cycles = opt.seconds.map(|seconds| deck.clock_rate() * seconds);
+    let mut total_cycles = 0.0;
+    loop {
+        let frame = deck.frame_number();
+        match run_cycles {
+            Some(cycles) if total_cycles >= cycles => break,
+            None if frame >= opt.frames => break,
+            _ => (),
+        }
+
+        while events.last().is_some_and(|event| event.frame <= frame) {
+            let event = events.pop().expect("valid input event");
+            deck.joypad_mut(event.slot)
+                .set_button(event.button.into(), event.pressed);
+        }
+
+        match deck.clock_frame()? {
+            ControlFlow::Continue(cycles) | ControlFlow::Break(cycles) => {
+                total_cycles += cycles as f32;
+            }
+        }
+
+        if opt.audio_out.is_some() {
+            audio.consume(deck.audio_samples(), false, 0.0);
+            let start = samples.len();
+            samples.resize(start + callback.len(), 0.0);
+            callback.read(&mut samples[start..]);
+        }
+        deck.clear_audio_samples();
+    }
+
+    let frame = deck.frame_buffer();
+    let hash = crc32fast::hash(frame);
+    println!("{hash:08x}");
+
+    if let Some(ref path) = opt.frame_out {
+        save_frame(path, frame)?;
+    }
+    if let Some(ref path) = opt.hash_out {
+        fs::write(path, format!("{hash:08x}\n"))
+            .with_context(|| format!("failed to write hash {path:?}"))?;
+    }
+    if let Some(ref path) = opt.audio_out {
+        let mut writer = BufWriter::new(
+            File::create(path).with_context(|| format!("failed to create audio {path:?}"))?,
+        );
+        for sample in &samples {
+            writer.write_all(&sample.to_le_bytes())?;
+        }
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+/// A scripted joypad event applied at the start of a given frame.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+struct InputEvent {
+    frame: u32,
+    #[serde(default)]
+    slot: Slot,
+    button: JoypadBtn,
+    pressed: bool,
+}
+
+fn load_input(path: &Path) -> NesResult<Vec<InputEvent>> {
+    let file = File::open(path).with_context(|| format!("failed to open input {path:?}"))?;
+    serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("failed to parse input {path:?}"))
+}
+
+fn save_frame(path: &Path, frame: &[u8]) -> NesResult<()> {
+    match path.extension().and_then(OsStr::to_str) {
+        Some("png") => Image::from_bytes(Ppu::WIDTH, Ppu::HEIGHT, frame, PixelFormat::Rgba)
+            .and_then(|image| image.save(path))
+            .with_context(|| format!("failed to save frame {path:?}")),
+        Some("ppm") => {
+            let mut writer = BufWriter::new(
+                File::create(path).with_context(|| format!("failed to create frame {path:?}"))?,
+            );
+            write!(writer, "P6\n{} {}\n255\n", Ppu::WIDTH, Ppu::HEIGHT)?;
+            for pixel in frame.chunks_exact(4) {
+                writer.write_all(&pixel[..3])?;
+            }
+            writer.flush()?;
+            Ok(())
+        }
+        _ => bail!("unsupported frame format {path:?}, expected `.png` or `.ppm`"),
+    }
+}
+
+#[derive(StructOpt, Debug)]
+#[must_use]
+#[structopt(
+    name = "tetanes-cli",
+    about = "A headless NES runner for scripted ROM execution",
+    author = "Luke Petherbridge <me@lukeworks.tech>"
+)]
+/// `TetaNES` Headless Command-Line Options
+struct Opt {
+    #[structopt(help = "The NES ROM to load.")]
+    path: PathBuf,
+    #[structopt(
+        long = "frames",
+        default_value = "60",
+        help = "Number of frames to run."
+    )]
+    frames: u32,
+    #[structopt(
+        long = "seconds",
+        help = "Number of seconds to run, overrides `--frames`."
+    )]
+    seconds: Option<f32>,
+    #[structopt(
+        short = "i",
+        long = "input",
+        help = "A `.json` input file of joypad events to replay."
+    )]
+    input: Option<PathBuf>,
+    #[structopt(
+        short = "p",
+        long = "patch",
+        help = "An `.ips`, `.ups` or `.bps` patch to apply to the ROM."
+    )]
+    patch: Option<PathBuf>,
+    #[structopt(
+        long = "frame-out",
+        help = "Write the final frame to a `.png` or `.ppm` file."
+    )]
+    frame_out: Option<PathBuf>,
+    #[structopt(
+        long = "audio-out",
+        help = "Write raw 32-bit float little-endian mono audio samples."
+    )]
+    audio_out: Option<PathBuf>,
+    #[structopt(long = "hash-out", help = "Write the CRC32 hash of the final frame.")]
+    hash_out: Option<PathBuf>,
+    #[structopt(
+        long = "sample-rate",
+        default_value = "44100",
+        help = "Audio output sample rate."
+    )]
+    sample_rate: f32,
+    #[structopt(
+        long = "ram_state",
+        help = "Choose power-up RAM state: 'all_zeros' (default), `all_ones`, `random`."
+    )]
+    ram_state: Option<RamState>,
+    #[structopt(
+        short = "g",
+        long = "genie-codes",
+        help = "List of Game Genie Codes (space separated)."
+    )]
+    genie_codes: Vec<String>,
+}
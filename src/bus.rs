@@ -5,14 +5,14 @@ use crate::{
     common::{Clock, Kind, NesRegion, Regional, Reset},
     cpu::{Cpu, Irq},
     genie::GenieCode,
-    input::{FourPlayer, Input, InputRegisters, Joypad, Slot, Zapper},
+    input::{FourPlayer, Input, InputRegisters, Joypad, Slot, VsInput, Zapper},
     mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
     mem::{Access, Mem, RamState},
     ppu::{Ppu, PpuRegisters},
     NesResult,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
 
 /// NES Bus
 ///
@@ -52,9 +52,14 @@ pub struct CpuBus {
     battery_backed: bool,
     prg_ram: Vec<u8>,
     prg_ram_protect: bool,
-    prg_rom: Vec<u8>,
+    #[serde(skip)]
+    prg_rom: Vec<u8>, // Excluded from save states, see `CpuBus::load_rom_from`
+    // Serialized as separate save state sections
+    #[serde(skip)]
     ppu: Ppu,
+    #[serde(skip)]
     apu: Apu,
+    #[serde(skip)]
     input: Input,
     oam_dma: bool,
     oam_dma_addr: u16,
@@ -126,6 +131,16 @@ impl CpuBus {
         self.ppu.mapper_mut()
     }
 
+    #[inline]
+    pub const fn input(&self) -> &Input {
+        &self.input
+    }
+
+    #[inline]
+    pub const fn input_mut(&mut self) -> &mut Input {
+        &mut self.input
+    }
+
     #[inline]
     pub const fn joypad(&self, slot: Slot) -> &Joypad {
         self.input.joypad(slot)
@@ -151,6 +166,16 @@ impl CpuBus {
         self.input.zapper_mut()
     }
 
+    #[inline]
+    pub const fn vs_input(&self) -> &VsInput {
+        self.input.vs_input()
+    }
+
+    #[inline]
+    pub const fn vs_input_mut(&mut self) -> &mut VsInput {
+        self.input.vs_input_mut()
+    }
+
     #[inline]
     pub fn load_cart(&mut self, cart: Cart) {
         // Start with ~20ms of audio capacity
@@ -158,6 +183,8 @@ impl CpuBus {
             .resize((Cpu::region_clock_rate(cart.region()) * 0.02) as usize, 0.0);
         self.battery_backed = cart.battery_backed();
         self.set_region(cart.region());
+        self.ppu.set_model(cart.ppu_model());
+        self.input.vs_input_mut().enabled = cart.is_vs_system();
         self.load_prg_rom(cart.prg_rom);
         self.load_prg_ram(cart.prg_ram);
         self.ppu.load_chr_rom(cart.chr_rom);
@@ -171,27 +198,58 @@ impl CpuBus {
         self.prg_rom = prg_rom;
     }
 
+    /// Moves cartridge ROM from another `CpuBus`. ROM is not serialized, so restoring a save state
+    /// requires reattaching the ROM of the currently loaded cartridge.
+    #[inline]
+    pub fn load_rom_from(&mut self, other: &mut Self) {
+        self.load_prg_rom(std::mem::take(&mut other.prg_rom));
+        self.ppu.load_chr_rom(other.ppu.take_chr_rom());
+        self.ppu.mapper_mut().load_rom_from(other.ppu.mapper_mut());
+    }
+
     #[inline]
     pub fn load_prg_ram(&mut self, prg_ram: Vec<u8>) {
         self.prg_ram = prg_ram;
     }
 
+    /// Whether the cartridge has battery-backed PRG-RAM or the mapper has battery-backed memory
+    /// of its own.
     #[inline]
     #[must_use]
-    pub const fn cart_battery_backed(&self) -> bool {
-        self.battery_backed
+    pub fn cart_battery_backed(&self) -> bool {
+        self.battery_backed || self.mapper().battery_backed()
     }
 
-    #[inline]
+    /// Returns battery-backed Save RAM, consisting of battery-backed PRG-RAM followed by any
+    /// battery-backed memory internal to the mapper.
     #[must_use]
-    pub fn sram(&self) -> &[u8] {
-        &self.prg_ram
+    pub fn sram(&self) -> Cow<'_, [u8]> {
+        let prg_ram = self.battery_prg_ram();
+        let battery_ram = self.mapper().battery_ram();
+        if battery_ram.is_empty() {
+            Cow::Borrowed(prg_ram)
+        } else {
+            Cow::Owned([prg_ram, &battery_ram].concat())
+        }
     }
 
-    #[inline]
-    pub fn load_sram(&mut self, sram: Vec<u8>) {
+    pub fn load_sram(&mut self, mut sram: Vec<u8>) {
         if self.cart_battery_backed() {
-            self.prg_ram = sram;
+            let battery_ram = sram.split_off(self.battery_prg_ram().len().min(sram.len()));
+            if !battery_ram.is_empty() {
+                self.mapper_mut().load_battery_ram(&battery_ram);
+            }
+            if self.battery_backed {
+                self.prg_ram = sram;
+            }
+        }
+    }
+
+    fn battery_prg_ram(&self) -> &[u8] {
+        if self.battery_backed {
+            &self.prg_ram
+        } else {
+            &[]
         }
     }
 
@@ -342,12 +400,23 @@ impl Clock for CpuBus {
         self.mapper_mut().clock();
         self.input.clock();
 
-        let apu_output = self.apu.output();
-        let mapper_output = match self.mapper() {
+        let mut apu_output = self.apu.output();
+        let mut mapper_output = match self.mapper() {
             Mapper::Exrom(ref exrom) => exrom.output(),
+            Mapper::Namco163(ref namco163) => namco163.output(),
+            Mapper::Fds(ref fds) => fds.output(),
             Mapper::Vrc6(ref vrc6) => vrc6.output(),
+            Mapper::Fme7(ref fme7) => fme7.output(),
+            Mapper::Vrc7(ref vrc7) => vrc7.output(),
+            Mapper::Nsf(ref nsf) => nsf.output(),
             _ => 0.0,
         };
+        if let Mapper::Nsf(ref nsf) = self.mapper() {
+            // Fade out at the end of each track
+            let volume = nsf.volume();
+            apu_output *= volume;
+            mapper_output *= volume;
+        }
         self.mix_audio(apu_output, mapper_output);
 
         1
@@ -424,6 +493,9 @@ impl Mem for CpuBus {
                 }
                 self.ppu.update_mirroring();
             }
+            // 2C05 PPUs swap PPUCTRL and PPUMASK
+            0x2000 if self.ppu.model().swaps_ctrl_mask() => self.ppu.write_mask(val),
+            0x2001 if self.ppu.model().swaps_ctrl_mask() => self.ppu.write_ctrl(val),
             0x2000 => self.ppu.write_ctrl(val),
             0x2001 => self.ppu.write_mask(val),
             0x2003 => self.ppu.write_oamaddr(val),
@@ -624,6 +696,29 @@ mod test {
         assert_eq!(bus.read(addr, Access::Read), orig_value, "read orig value");
     }
 
+    #[test]
+    fn save_state_excludes_rom() {
+        let mut bus = CpuBus::default();
+        let mut cart = Cart::empty();
+        cart.prg_rom[0x0000] = 0x66;
+        cart.chr_rom = vec![0x77; 0x2000];
+        bus.load_cart(cart);
+        bus.write(0x0000, 0x55, Access::Write);
+
+        let state = bincode::serialize(&bus).expect("valid serialize");
+        let mut loaded: CpuBus = bincode::deserialize(&state).expect("valid deserialize");
+        assert!(loaded.prg_rom.is_empty(), "prg_rom excluded");
+        assert_eq!(loaded.read(0x0000, Access::Read), 0x55, "wram");
+
+        loaded.load_rom_from(&mut bus);
+        assert_eq!(loaded.prg_rom[0x0000], 0x66, "prg_rom");
+        assert_eq!(
+            loaded.ppu_mut().take_chr_rom(),
+            vec![0x77; 0x2000],
+            "chr_rom"
+        );
+    }
+
     #[test]
     fn clock() {
         let mut bus = CpuBus::default();
@@ -681,6 +776,27 @@ mod test {
         todo!();
     }
 
+    #[test]
+    fn vs_system_2c05() {
+        let mut bus = CpuBus::default();
+        // NES 2.0 mapper 99 with an RC2C05-01 PPU
+        let mut rom = b"NES\x1a\x02\x01\x30\x69\x00\x00\x00\x00\x00\x08".to_vec();
+        rom.resize(16 + 0x8000 + 0x2000, 0x00);
+        let cart = Cart::from_rom("vs_system_2c05", &mut rom.as_slice(), RamState::default())
+            .expect("valid cart");
+        bus.load_cart(cart);
+
+        // PPUCTRL and PPUMASK are swapped
+        bus.write(0x2000, 0x80, Access::Write);
+        assert!(!bus.ppu.ctrl().nmi_enabled(), "$2000 writes PPUMASK");
+        bus.write(0x2001, 0x80, Access::Write);
+        assert!(bus.ppu.ctrl().nmi_enabled(), "$2001 writes PPUCTRL");
+        assert_eq!(bus.read(0x2002, Access::Read) & 0x1F, 0x1B, "ppu id");
+
+        bus.vs_input_mut().dip_switches = 0x03;
+        assert_eq!(bus.read(0x4016, Access::Read) & 0x58, 0x18, "dip switches");
+    }
+
     #[test]
     #[ignore = "todo"]
     fn write_apu_triangle() {
@@ -1,30 +1,40 @@
+mod archive;
+pub mod fds;
+pub mod game_db;
+pub mod nsf;
+pub mod patch;
+pub mod unif;
+
 use crate::{
     common::{NesRegion, Regional},
     mapper::{
-        m024_m026_vrc6::Vrc6Revision, Axrom, Bf909x, Cnrom, Exrom, Gxrom, Mapper, Mmc1Revision,
-        Nrom, Pxrom, Sxrom, Txrom, Uxrom, Vrc6,
+        eeprom::EepromKind, m024_m026_vrc6::Vrc6Revision, Action52, Axrom, BandaiFcg,
+        BandaiFcgRevision, Bf9096, Bf909x, Bnrom, BnromRevision, Caltron, Cnrom, CnromRevision,
+        Et4310, Exrom, Fds, Fme7, Fxrom, Gxrom, GxromRevision, IremG101, IremG101Revision,
+        JalecoSs88006, Mapper, Mmc1Revision, Mmc3Revision, Namco163, Nrom, Nsf, Pxrom, Rambo1,
+        Sxrom, TaitoTc0190, TaitoTc0190Revision, Txrom, TxromBoard, Unrom512, Uxrom, UxromRevision,
+        Vrc4, Vrc4Wiring, Vrc6, Vrc7, VsSystem, K1029,
     },
     mem::RamState,
-    ppu::Mirroring,
+    ppu::{model::PpuModel, Mirroring},
     NesResult,
 };
 use anyhow::{anyhow, bail, Context};
-#[cfg(not(target_arch = "wasm32"))]
-use std::{
-    collections::hash_map::DefaultHasher,
-    hash::{Hash, Hasher},
-};
+use fds::FdsDisk;
+use game_db::GameInfo;
+use nsf::NsfFile;
 use std::{
     fs::File,
     io::{BufReader, Read},
     path::Path,
 };
+use unif::Unif;
 
 const PRG_ROM_BANK_SIZE: usize = 0x4000;
 const CHR_ROM_BANK_SIZE: usize = 0x2000;
-
-#[cfg(not(target_arch = "wasm32"))]
-const GAME_DB: &[u8] = include_bytes!("../config/game_database.txt");
+const TRAINER_SIZE: usize = 0x0200;
+// Trainers are loaded at $7000 in the $6000-$7FFF PRG-RAM window
+const TRAINER_OFFSET: usize = 0x1000;
 
 /// An NES cartridge.
 #[derive(Default, Clone)]
@@ -32,6 +42,10 @@ const GAME_DB: &[u8] = include_bytes!("../config/game_database.txt");
 pub struct Cart {
     name: String,
     header: NesHeader,
+    crc32: u32,
+    sha1: String,
+    original_crc32: Option<u32>,
+    game_info: Option<GameInfo>,
     region: NesRegion,
     ram_state: RamState,
     pub(crate) mapper: Mapper,
@@ -47,6 +61,10 @@ impl Cart {
         let mut empty = Self {
             name: "Empty Cart".to_string(),
             header: NesHeader::default(),
+            crc32: 0,
+            sha1: String::new(),
+            original_crc32: None,
+            game_info: None,
             region: NesRegion::default(),
             ram_state: RamState::default(),
             mapper: Mapper::none(),
@@ -60,7 +78,7 @@ impl Cart {
         empty
     }
 
-    /// Load `Cart` from a ROM path.
+    /// Load `Cart` from a ROM path, which may be a `.zip` or gzip archive.
     ///
     /// # Errors
     ///
@@ -74,48 +92,69 @@ impl Cart {
         Self::from_rom(&path.to_string_lossy(), &mut rom, ram_state)
     }
 
-    /// Load `Cart` from ROM data.
+    /// Load `Cart` from `iNES`, `NES 2.0`, `UNIF`, `FDS` or `NSF` ROM data, optionally compressed
+    /// in a `.zip` or gzip archive.
     ///
     /// # Errors
     ///
-    /// If the NES header is invalid, or the ROM data does not match the header, then an error is
-    /// returned.
-    pub fn from_rom<S, F>(name: S, mut rom_data: &mut F, ram_state: RamState) -> NesResult<Self>
+    /// If the NES header is invalid, the `UNIF` board is not supported, the `FDS` BIOS is
+    /// missing, or the ROM data does not match the header, then an error is returned.
+    pub fn from_rom<S, F>(name: S, rom_data: &mut F, ram_state: RamState) -> NesResult<Self>
     where
         S: ToString,
         F: Read,
     {
-        let name = name.to_string();
-        let header = NesHeader::load(&mut rom_data)?;
+        Self::load(name.to_string(), rom_data, ram_state, None)
+    }
 
-        let mut prg_rom = vec![0x00; (header.prg_rom_banks as usize) * PRG_ROM_BANK_SIZE];
-        rom_data.read_exact(&mut prg_rom).with_context(|| {
-            let bytes_rem = rom_data
-                .read_to_end(&mut prg_rom)
-                .map_or_else(|_| "unknown".to_string(), |rem| rem.to_string());
-            format!(
-                "invalid rom header '{}'. prg-rom banks: {}. bytes remaining: {}",
-                name, header.prg_rom_banks, bytes_rem
-            )
-        })?;
+    /// Load `Cart` from ROM data, looked up in the game database by `original` PRG-ROM and
+    /// CHR-ROM if provided instead of its own.
+    fn load<F: Read>(
+        name: String,
+        rom_data: &mut F,
+        ram_state: RamState,
+        original: Option<(&[u8], &[u8])>,
+    ) -> NesResult<Self> {
+        let mut magic = [0x00; 4];
+        rom_data.read_exact(&mut magic)?;
+        let mut rom_data = (&magic[..]).chain(rom_data);
+        if archive::is_archive(&magic) {
+            let rom = archive::extract_rom(&magic, &mut rom_data)
+                .with_context(|| format!("failed to extract rom '{name}'"))?;
+            return Self::load(name, &mut rom.as_slice(), ram_state, original);
+        }
+        if fds::is_fds(&magic) {
+            return Self::load_fds(name, &mut rom_data, ram_state);
+        }
+        if nsf::is_nsf(&magic) {
+            return Self::load_nsf(name, &mut rom_data, ram_state);
+        }
+        let (mut header, trainer, prg_rom, chr_rom) = Self::load_rom_data(&name, &mut rom_data)?;
+
+        let (crc32, sha1) = Self::rom_hash(&prg_rom, &chr_rom);
+        let original_hash = original.map(|(prg_rom, chr_rom)| Self::rom_hash(prg_rom, chr_rom));
+        let original_crc32 = original_hash.as_ref().map(|(crc32, _)| *crc32);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let game_info = match original_hash {
+            Some((crc32, sha1)) => GameInfo::lookup(crc32, &sha1),
+            None => GameInfo::lookup(crc32, &sha1),
+        };
+        #[cfg(target_arch = "wasm32")]
+        let game_info: Option<GameInfo> = None;
+        if let Some(ref info) = game_info {
+            log::debug!("game database entry: {info:?}");
+            header.apply_game_info(info);
+        }
+        let region = header
+            .region()
+            .or_else(|| game_info.as_ref().and_then(|info| info.region))
+            .unwrap_or_default();
 
         let prg_ram_size = Self::calculate_ram_size(header.prg_ram_shift).context("prg_ram")?;
         let mut prg_ram = vec![0x00; prg_ram_size];
         RamState::fill(&mut prg_ram, ram_state);
 
-        let mut chr_rom = vec![0x00; (header.chr_rom_banks as usize) * CHR_ROM_BANK_SIZE];
-        if header.chr_rom_banks > 0 {
-            rom_data.read_exact(&mut chr_rom).with_context(|| {
-                let bytes_rem = rom_data
-                    .read_to_end(&mut chr_rom)
-                    .map_or_else(|_| "unknown".to_string(), |rem| rem.to_string());
-                format!(
-                    "invalid rom header \"{}\". chr-rom banks: {}. bytes remaining: {}",
-                    name, header.chr_rom_banks, bytes_rem,
-                )
-            })?;
-        }
-
         let mut chr_ram = vec![];
         if chr_rom.is_empty() {
             let chr_ram_size = Self::calculate_ram_size(header.chr_ram_shift).context("chr_ram")?;
@@ -123,19 +162,13 @@ impl Cart {
             RamState::fill(&mut chr_ram, ram_state);
         }
 
-        #[cfg(not(target_arch = "wasm32"))]
-        let region = {
-            let mut hasher = DefaultHasher::new();
-            prg_rom.hash(&mut hasher);
-            let hash = hasher.finish();
-            Self::lookup_region(hash)
-        };
-        #[cfg(target_arch = "wasm32")]
-        let region = NesRegion::default();
-
         let mut cart = Self {
             name,
             header,
+            crc32,
+            sha1,
+            original_crc32,
+            game_info,
             region,
             ram_state,
             mapper: Mapper::none(),
@@ -147,32 +180,379 @@ impl Cart {
         };
         cart.mapper = match cart.header.mapper_num {
             0 => Nrom::load(&mut cart),
-            1 => Sxrom::load(&mut cart, Mmc1Revision::BC),
-            2 => Uxrom::load(&mut cart),
-            3 => Cnrom::load(&mut cart),
-            4 => Txrom::load(&mut cart),
+            1 => {
+                let revision = if cart.has_chip("MMC1A") {
+                    Mmc1Revision::A
+                } else {
+                    Mmc1Revision::BC
+                };
+                Sxrom::load(&mut cart, revision)
+            }
+            2 => Uxrom::load(&mut cart, UxromRevision::Uxrom),
+            3 => Cnrom::load(&mut cart, CnromRevision::Cnrom),
+            4 => {
+                let mut mapper = Txrom::load(&mut cart, TxromBoard::Txrom);
+                if let Mapper::Txrom(ref mut txrom) = mapper {
+                    if cart.has_chip("MMC3A") {
+                        txrom.set_revision(Mmc3Revision::A);
+                    } else if cart.has_chip("ACCLAIM") || cart.has_chip("MC-ACC") {
+                        txrom.set_revision(Mmc3Revision::Acc);
+                    }
+                }
+                mapper
+            }
             5 => Exrom::load(&mut cart),
             7 => Axrom::load(&mut cart),
             9 => Pxrom::load(&mut cart),
+            10 => Fxrom::load(&mut cart),
+            11 => Gxrom::load(&mut cart, GxromRevision::ColorDreams),
+            13 => Cnrom::load(&mut cart, CnromRevision::Cprom),
+            15 => K1029::load(&mut cart),
+            16 | 153 | 157 | 159 => {
+                let revision = match (cart.mapper_num(), cart.submapper_num()) {
+                    (16, 4) => BandaiFcgRevision::Fcg,
+                    (16, 0) => BandaiFcgRevision::Unknown,
+                    _ => BandaiFcgRevision::Lz93d50,
+                };
+                let eeprom = match (cart.mapper_num(), cart.submapper_num()) {
+                    (16, 5) | (157, _) => Some(EepromKind::Eeprom24C02),
+                    (16, 0) if cart.battery_backed() => Some(EepromKind::Eeprom24C02),
+                    (159, _) => Some(EepromKind::Eeprom24C01),
+                    _ => None,
+                };
+                BandaiFcg::load(&mut cart, revision, eeprom)
+            }
+            18 => JalecoSs88006::load(&mut cart),
+            19 => Namco163::load(&mut cart),
+            21..=23 | 25 => {
+                let is_vrc2 = cart.has_chip("VRC2");
+                let (wiring, revision) =
+                    Vrc4Wiring::from_mapper(cart.mapper_num(), cart.submapper_num(), is_vrc2);
+                Vrc4::load(&mut cart, revision, wiring)
+            }
             24 => Vrc6::load(&mut cart, Vrc6Revision::A),
             26 => Vrc6::load(&mut cart, Vrc6Revision::B),
-            66 => Gxrom::load(&mut cart),
+            30 => {
+                // Header bits 0 and 3 select horizontal, vertical, switchable one-screen or
+                // four-screen mirroring. Battery-backed boards save to flash instead of PRG-RAM.
+                let mirroring = match cart.header.flags & 0x09 {
+                    0x00 => Mirroring::Horizontal,
+                    0x01 => Mirroring::Vertical,
+                    0x08 => Mirroring::SingleScreenA,
+                    _ => Mirroring::FourScreen,
+                };
+                let flash = cart.battery_backed();
+                Unrom512::load(&mut cart, mirroring, flash)
+            }
+            32 => {
+                // Submapper 1 is Major League, with fixed one-screen mirroring
+                let revision = if cart.submapper_num() == 1 {
+                    IremG101Revision::MajorLeague
+                } else {
+                    IremG101Revision::G101
+                };
+                IremG101::load(&mut cart, revision)
+            }
+            33 => TaitoTc0190::load(&mut cart, TaitoTc0190Revision::Tc0190),
+            34 => {
+                // Submapper 0 is ambiguous, but only NINA-001 boards have more than 8K CHR-ROM
+                let revision = match cart.submapper_num() {
+                    1 => BnromRevision::Nina001,
+                    2 => BnromRevision::Bnrom,
+                    _ if cart.chr_rom.len() > 0x2000 => BnromRevision::Nina001,
+                    _ => BnromRevision::Bnrom,
+                };
+                Bnrom::load(&mut cart, revision)
+            }
+            41 => Caltron::load(&mut cart),
+            48 => TaitoTc0190::load(&mut cart, TaitoTc0190Revision::Tc0690),
+            64 => Rambo1::load(&mut cart),
+            66 => Gxrom::load(&mut cart, GxromRevision::Gxrom),
+            69 => Fme7::load(&mut cart),
             71 => Bf909x::load(&mut cart),
+            79 => Gxrom::load(&mut cart, GxromRevision::Nina003006),
+            85 => Vrc7::load(&mut cart),
+            87 => Cnrom::load(&mut cart, CnromRevision::J87),
+            99 => VsSystem::load(&mut cart),
+            118 => Txrom::load(&mut cart, TxromBoard::TxSrom),
+            119 => Txrom::load(&mut cart, TxromBoard::Tqrom),
+            140 => Gxrom::load(&mut cart, GxromRevision::Jf11Jf14),
             155 => Sxrom::load(&mut cart, Mmc1Revision::A),
+            180 => Uxrom::load(&mut cart, UxromRevision::UnromReverse),
+            185 => {
+                // Submappers 4-7 enable CHR-ROM for a specific latch value
+                let chr_key = match cart.submapper_num() {
+                    submapper_num @ 4..=7 => Some(submapper_num & 0x03),
+                    _ => None,
+                };
+                Cnrom::load(&mut cart, CnromRevision::CopyProtect { chr_key })
+            }
+            206 => Txrom::load(&mut cart, TxromBoard::Namco108),
+            225 => Et4310::load(&mut cart),
+            228 => Action52::load(&mut cart),
+            232 => Bf9096::load(&mut cart),
             _ => bail!("unimplemented mapper: {}", cart.header.mapper_num),
         };
+        if !trainer.is_empty() {
+            cart.load_trainer(&trainer);
+        }
+
+        log::info!("Loaded `{}`", cart);
+        log::debug!("{:?}", cart);
+        Ok(cart)
+    }
+
+    /// Load `Cart` from ROM data with an `IPS`, `UPS` or `BPS` patch applied in memory. Compressed
+    /// ROMs are extracted before the patch is applied.
+    ///
+    /// The game database lookup uses the unpatched PRG-ROM and CHR-ROM, so patched games get the
+    /// original game's header corrections. The ROM hash covers the patched data, so save states
+    /// aren't shared between patched and unpatched games.
+    ///
+    /// # Errors
+    ///
+    /// If the patch is invalid or doesn't match the ROM, or the patched ROM fails to load, then
+    /// an error is returned.
+    pub fn from_rom_with_patch<S, F>(
+        name: S,
+        rom_data: &mut F,
+        patch: &[u8],
+        ram_state: RamState,
+    ) -> NesResult<Self>
+    where
+        S: ToString,
+        F: Read,
+    {
+        let name = name.to_string();
+        let mut rom = vec![];
+        rom_data.read_to_end(&mut rom)?;
+        if archive::is_archive(&rom) {
+            rom = archive::extract_rom(&rom, &mut rom.as_slice())
+                .with_context(|| format!("failed to extract rom '{name}'"))?;
+        }
+        let patched =
+            patch::apply(&rom, patch).with_context(|| format!("failed to patch rom '{name}'"))?;
+        // FDS and NSF files, or ROMs that are only valid once patched, use the patched data
+        match Self::load_rom_data(&name, &mut rom.as_slice()) {
+            Ok((_, _, prg_rom, chr_rom)) if !fds::is_fds(&rom) && !nsf::is_nsf(&rom) => Self::load(
+                name,
+                &mut patched.as_slice(),
+                ram_state,
+                Some((&prg_rom, &chr_rom)),
+            ),
+            _ => Self::load(name, &mut patched.as_slice(), ram_state, None),
+        }
+    }
+
+    /// Load a Famicom Disk System disk image, using the FDS BIOS as PRG-ROM. The ROM hash is
+    /// computed over the disk sides.
+    fn load_fds<F: Read>(name: String, disk_data: &mut F, ram_state: RamState) -> NesResult<Self> {
+        let disk =
+            FdsDisk::load(disk_data).with_context(|| format!("invalid fds disk '{name}'"))?;
+        let bios = Self::load_fds_bios()?;
+        let (crc32, sha1) = Self::rom_hash(&disk.sides.concat(), &[]);
+        let mut cart = Self {
+            name,
+            header: NesHeader {
+                version: 2,
+                mapper_num: 20,
+                ..NesHeader::default()
+            },
+            crc32,
+            sha1,
+            original_crc32: None,
+            game_info: None,
+            region: NesRegion::Ntsc,
+            ram_state,
+            mapper: Mapper::none(),
+            chr_rom: vec![],
+            chr_ram: vec![],
+            ex_ram: vec![],
+            prg_rom: bios,
+            prg_ram: vec![],
+        };
+        cart.mapper = Fds::load(&mut cart, disk.gapped_sides());
 
         log::info!("Loaded `{}`", cart);
         log::debug!("{:?}", cart);
         Ok(cart)
     }
 
+    /// Load an `NSF` or `NSFe` music file, using a synthetic cartridge that plays its tracks. The
+    /// ROM hash is computed over the program data.
+    fn load_nsf<F: Read>(name: String, rom_data: &mut F, ram_state: RamState) -> NesResult<Self> {
+        let file = NsfFile::load(rom_data).with_context(|| format!("invalid nsf '{name}'"))?;
+        let (crc32, sha1) = Self::rom_hash(&file.data, &[]);
+        let tv_mode = match file.region {
+            Some(NesRegion::Pal) => 1,
+            None => 2,
+            _ => 0,
+        };
+        let mut cart = Self {
+            name,
+            header: NesHeader {
+                version: 2,
+                tv_mode,
+                ..NesHeader::default()
+            },
+            crc32,
+            sha1,
+            original_crc32: None,
+            game_info: None,
+            region: file.region.unwrap_or_default(),
+            ram_state,
+            mapper: Mapper::none(),
+            chr_rom: vec![],
+            chr_ram: vec![],
+            ex_ram: vec![],
+            prg_rom: vec![],
+            prg_ram: vec![],
+        };
+        cart.mapper = Nsf::load(&mut cart, file);
+
+        log::info!("Loaded `{}`", cart);
+        log::debug!("{:?}", cart);
+        Ok(cart)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_fds_bios() -> NesResult<Vec<u8>> {
+        let path = crate::common::config_path(fds::BIOS_NAME);
+        let bios =
+            std::fs::read(&path).with_context(|| format!("failed to load fds bios {path:?}"))?;
+        if bios.len() != fds::BIOS_SIZE {
+            bail!(
+                "invalid fds bios {path:?}. expected: {} bytes, found: {}",
+                fds::BIOS_SIZE,
+                bios.len()
+            );
+        }
+        Ok(bios)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn load_fds_bios() -> NesResult<Vec<u8>> {
+        bail!("fds bios is not supported in the browser")
+    }
+
+    /// Reads the header, trainer, PRG-ROM and CHR-ROM of an `iNES`, `NES 2.0` or `UNIF` ROM.
+    #[allow(clippy::type_complexity)]
+    fn load_rom_data<F: Read>(
+        name: &str,
+        rom_data: &mut F,
+    ) -> NesResult<(NesHeader, Vec<u8>, Vec<u8>, Vec<u8>)> {
+        let mut magic = [0x00; 4];
+        rom_data.read_exact(&mut magic)?;
+        let mut rom_data = (&magic[..]).chain(rom_data);
+        if magic == unif::MAGIC {
+            let unif =
+                Unif::load(&mut rom_data).with_context(|| format!("invalid unif rom '{name}'"))?;
+            log::debug!("unif board: {}", unif.board);
+            Ok((unif.header, vec![], unif.prg_rom, unif.chr_rom))
+        } else {
+            Self::load_ines(name, &mut rom_data)
+        }
+    }
+
+    /// Reads an `iNES` or `NES 2.0` header, trainer, PRG-ROM and CHR-ROM.
+    #[allow(clippy::type_complexity)]
+    fn load_ines<F: Read>(
+        name: &str,
+        rom_data: &mut F,
+    ) -> NesResult<(NesHeader, Vec<u8>, Vec<u8>, Vec<u8>)> {
+        let header = NesHeader::load(rom_data)?;
+
+        let mut trainer = vec![];
+        if header.has_trainer() {
+            trainer.resize(TRAINER_SIZE, 0x00);
+            rom_data
+                .read_exact(&mut trainer)
+                .with_context(|| format!("invalid rom header '{name}'. missing trainer"))?;
+        }
+
+        let mut prg_rom = vec![0x00; (header.prg_rom_banks as usize) * PRG_ROM_BANK_SIZE];
+        rom_data.read_exact(&mut prg_rom).with_context(|| {
+            let bytes_rem = rom_data
+                .read_to_end(&mut prg_rom)
+                .map_or_else(|_| "unknown".to_string(), |rem| rem.to_string());
+            format!(
+                "invalid rom header '{}'. prg-rom banks: {}. bytes remaining: {}",
+                name, header.prg_rom_banks, bytes_rem
+            )
+        })?;
+
+        let mut chr_rom = vec![0x00; (header.chr_rom_banks as usize) * CHR_ROM_BANK_SIZE];
+        if header.chr_rom_banks > 0 {
+            rom_data.read_exact(&mut chr_rom).with_context(|| {
+                let bytes_rem = rom_data
+                    .read_to_end(&mut chr_rom)
+                    .map_or_else(|_| "unknown".to_string(), |rem| rem.to_string());
+                format!(
+                    "invalid rom header \"{}\". chr-rom banks: {}. bytes remaining: {}",
+                    name, header.chr_rom_banks, bytes_rem,
+                )
+            })?;
+        }
+        Ok((header, trainer, prg_rom, chr_rom))
+    }
+
     #[inline]
     #[must_use]
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    #[inline]
+    pub const fn header(&self) -> &NesHeader {
+        &self.header
+    }
+
+    /// Returns the region specified by the NES 2.0 header, falling back to the game database.
+    /// `None` if neither specify a region, or the ROM is multi-region.
+    #[must_use]
+    pub fn rom_region(&self) -> Option<NesRegion> {
+        self.header
+            .region()
+            .or_else(|| self.game_info.as_ref().and_then(|info| info.region))
+    }
+
+    /// CRC32 of PRG-ROM followed by CHR-ROM.
+    #[inline]
+    #[must_use]
+    pub const fn crc32(&self) -> u32 {
+        self.crc32
+    }
+
+    /// Lowercase hex SHA-1 digest of PRG-ROM followed by CHR-ROM.
+    #[inline]
+    #[must_use]
+    pub fn sha1(&self) -> &str {
+        &self.sha1
+    }
+
+    /// CRC32 of the unpatched PRG-ROM followed by CHR-ROM, if this Cart was soft-patched.
+    #[inline]
+    #[must_use]
+    pub const fn original_crc32(&self) -> Option<u32> {
+        self.original_crc32
+    }
+
+    /// Returns the game database entry for this Cart, if one was found.
+    #[inline]
+    #[must_use]
+    pub const fn game_info(&self) -> Option<&GameInfo> {
+        self.game_info.as_ref()
+    }
+
+    /// Whether the game database lists the given chip for this Cart.
+    #[inline]
+    #[must_use]
+    pub fn has_chip(&self, name: &str) -> bool {
+        self.game_info
+            .as_ref()
+            .is_some_and(|info| info.has_chip(name))
+    }
+
     #[inline]
     #[must_use]
     pub fn chr_rom(&self) -> &[u8] {
@@ -248,6 +628,26 @@ impl Cart {
         }
     }
 
+    /// Returns whether this cartridge is for the `VS System` arcade hardware.
+    #[inline]
+    #[must_use]
+    pub const fn is_vs_system(&self) -> bool {
+        self.header.flags & 0x30 == 0x10 || self.header.mapper_num == 99
+    }
+
+    /// Returns the PPU model required by this cartridge. `VS System` games without NES 2.0 PPU
+    /// information default to the `2C03`.
+    #[inline]
+    pub const fn ppu_model(&self) -> PpuModel {
+        if !self.is_vs_system() {
+            PpuModel::Rp2c02
+        } else if self.header.version == 2 {
+            PpuModel::from_vs_data(self.header.vs_data)
+        } else {
+            PpuModel::Rp2c03
+        }
+    }
+
     /// Returns the Mapper number for this Cart.
     #[inline]
     #[must_use]
@@ -266,7 +666,11 @@ impl Cart {
     #[inline]
     #[must_use]
     pub const fn mapper_board(&self) -> &'static str {
-        self.header.mapper_board()
+        if matches!(self.mapper, Mapper::Nsf(_)) {
+            "NSF"
+        } else {
+            self.header.mapper_board()
+        }
     }
 
     /// Allows mappers to add PRG-RAM.
@@ -287,6 +691,16 @@ impl Cart {
         RamState::fill(&mut self.ex_ram, self.ram_state);
     }
 
+    /// Copies a trainer into PRG-RAM at $7000-$71FF, adding PRG-RAM if needed. Only mappers
+    /// with PRG-RAM mapped at $6000-$7FFF can access it.
+    fn load_trainer(&mut self, trainer: &[u8]) {
+        let end = TRAINER_OFFSET + trainer.len();
+        if self.prg_ram.len() < end {
+            self.add_prg_ram(0x2000);
+        }
+        self.prg_ram[TRAINER_OFFSET..end].copy_from_slice(trainer);
+    }
+
     fn calculate_ram_size(value: u8) -> NesResult<usize> {
         if value > 0 {
             64usize
@@ -297,26 +711,17 @@ impl Cart {
         }
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    fn lookup_region(lookup_hash: u64) -> NesRegion {
-        use std::io::BufRead;
-
-        let db = BufReader::new(GAME_DB);
-        let lines: Vec<String> = db.lines().map_while(Result::ok).collect();
-        if let Ok(line) = lines.binary_search_by(|line| {
-            let hash = line
-                .split(',')
-                .next()
-                .map(|hash| hash.parse::<u64>().unwrap_or_default())
-                .unwrap_or_default();
-            hash.cmp(&lookup_hash)
-        }) {
-            let mut fields = lines[line].split(',').skip(1);
-            if let Some(region) = fields.next() {
-                return NesRegion::try_from(region).unwrap_or_default();
-            }
-        }
-        NesRegion::default()
+    /// Computes the CRC32 and SHA-1 ROM identity over PRG-ROM followed by CHR-ROM, matching the
+    /// hashes used by common ROM databases.
+    #[must_use]
+    pub fn rom_hash(prg_rom: &[u8], chr_rom: &[u8]) -> (u32, String) {
+        let mut crc32 = crc32fast::Hasher::new();
+        crc32.update(prg_rom);
+        crc32.update(chr_rom);
+        let mut sha1 = sha1_smol::Sha1::new();
+        sha1.update(prg_rom);
+        sha1.update(chr_rom);
+        (crc32.finalize(), sha1.digest().to_string())
     }
 }
 
@@ -354,6 +759,13 @@ impl std::fmt::Debug for Cart {
         f.debug_struct("Cart")
             .field("name", &self.name)
             .field("header", &self.header)
+            .field("crc32", &format_args!("{:08X}", self.crc32))
+            .field("sha1", &self.sha1)
+            .field(
+                "original_crc32",
+                &self.original_crc32.map(|crc32| format!("{crc32:08X}")),
+            )
+            .field("game_info", &self.game_info)
             .field("region", &self.region)
             .field("ram_state", &self.ram_state)
             .field("mapper", &self.mapper)
@@ -471,11 +883,6 @@ impl NesHeader {
             }
         }
 
-        // Trainer
-        if flags & 0x04 == 0x04 {
-            bail!("trained roms are currently not supported.");
-        }
-
         Ok(Self {
             version,
             mapper_num,
@@ -490,6 +897,76 @@ impl NesHeader {
         })
     }
 
+    /// Applies game database corrections to a potentially bad or incomplete header.
+    pub fn apply_game_info(&mut self, info: &GameInfo) {
+        let board_mapper_num = info.board.as_deref().and_then(game_db::board_mapper_num);
+        if let Some(mapper_num) = info.mapper_num.or(board_mapper_num) {
+            self.mapper_num = mapper_num;
+        }
+        if let Some(submapper_num) = info.submapper_num {
+            self.submapper_num = submapper_num;
+        }
+        if let Some(battery) = info.battery {
+            self.flags = (self.flags & !0x02) | if battery { 0x02 } else { 0x00 };
+        }
+        if let Some(mirroring) = info.mirroring {
+            self.flags &= !0x09;
+            match mirroring {
+                Mirroring::Vertical => self.flags |= 0x01,
+                Mirroring::FourScreen => self.flags |= 0x08,
+                _ => (),
+            }
+        }
+        if let Some(size) = info.prg_ram_size {
+            match Self::ram_shift(size) {
+                Some(shift) => self.prg_ram_shift = shift,
+                None => log::warn!("ignoring unsupported game database prg_ram size: {size}"),
+            }
+        }
+        if let Some(size) = info.chr_ram_size {
+            match Self::ram_shift(size) {
+                Some(shift) => self.chr_ram_shift = shift,
+                None => log::warn!("ignoring unsupported game database chr_ram size: {size}"),
+            }
+        }
+    }
+
+    /// Whether a 512-byte trainer precedes PRG-ROM.
+    #[inline]
+    #[must_use]
+    pub const fn has_trainer(&self) -> bool {
+        self.flags & 0x04 == 0x04
+    }
+
+    /// Returns the region given by the NES 2.0 CPU/PPU timing, or `None` for `iNES` headers and
+    /// multi-region ROMs.
+    ///
+    /// <https://www.nesdev.org/wiki/NES_2.0#Byte_12_(CPU/PPU_Timing)>
+    #[must_use]
+    pub const fn region(&self) -> Option<NesRegion> {
+        if self.version != 2 {
+            return None;
+        }
+        match self.tv_mode & 0x03 {
+            0 => Some(NesRegion::Ntsc),
+            1 => Some(NesRegion::Pal),
+            3 => Some(NesRegion::Dendy),
+            _ => None,
+        }
+    }
+
+    /// Inverse of `Cart::calculate_ram_size`. Returns `None` for sizes a header can't represent,
+    /// which is anything other than zero or a power of two of at least 128 bytes.
+    const fn ram_shift(size: usize) -> Option<u8> {
+        if size == 0 {
+            Some(0)
+        } else if size >= 128 && size.is_power_of_two() {
+            Some((size / 64).trailing_zeros() as u8)
+        } else {
+            None
+        }
+    }
+
     #[must_use]
     pub const fn mapper_board(&self) -> &'static str {
         match self.mapper_num {
@@ -501,11 +978,47 @@ impl NesHeader {
             5 => "Mapper 005 - ExROM/MMC5",
             7 => "Mapper 007 - AxROM",
             9 => "Mapper 009 - PxROM",
+            10 => "Mapper 010 - FxROM/MMC4",
+            11 => "Mapper 011 - Color Dreams",
+            13 => "Mapper 013 - CPROM",
+            15 => "Mapper 015 - K-1029/100-in-1",
+            16 => "Mapper 016 - Bandai FCG",
+            18 => "Mapper 018 - Jaleco SS88006",
+            19 => "Mapper 019 - Namco 163",
+            20 => "Mapper 020 - Famicom Disk System",
+            21 => "Mapper 021 - Vrc4a/Vrc4c",
+            22 => "Mapper 022 - Vrc2a",
+            23 => "Mapper 023 - Vrc2b/Vrc4e/Vrc4f",
             24 => "Mapper 024 - Vrc6a",
+            25 => "Mapper 025 - Vrc2c/Vrc4b/Vrc4d",
             26 => "Mapper 026 - Vrc6b",
+            30 => "Mapper 030 - UNROM 512",
+            32 => "Mapper 032 - Irem G-101",
+            33 => "Mapper 033 - Taito TC0190",
+            34 => "Mapper 034 - BNROM/NINA-001",
+            41 => "Mapper 041 - Caltron 6-in-1",
+            48 => "Mapper 048 - Taito TC0690",
+            64 => "Mapper 064 - Tengen RAMBO-1",
             66 => "Mapper 066 - GxROM/MxROM",
+            69 => "Mapper 069 - Sunsoft FME-7",
             71 => "Mapper 071 - Camerica/Codemasters/BF909x",
+            79 => "Mapper 079 - NINA-003/NINA-006",
+            85 => "Mapper 085 - Vrc7",
+            87 => "Mapper 087 - J87",
+            99 => "Mapper 099 - VS System",
+            118 => "Mapper 118 - TxSROM/MMC3",
+            119 => "Mapper 119 - TQROM/MMC3",
+            140 => "Mapper 140 - Jaleco JF-11/JF-14",
+            153 => "Mapper 153 - Bandai LZ93D50 with SRAM",
             155 => "Mapper 155 - SxROM/MMC1A",
+            157 => "Mapper 157 - Bandai Datach",
+            159 => "Mapper 159 - Bandai LZ93D50 with 24C01",
+            180 => "Mapper 180 - UNROM (Reverse)",
+            185 => "Mapper 185 - CNROM (Copy Protection)",
+            206 => "Mapper 206 - DxROM/Namco 108",
+            225 => "Mapper 225 - ET-4310/K-1010",
+            228 => "Mapper 228 - Action 52",
+            232 => "Mapper 232 - Camerica BF9096/Quattro",
             _ => "Unimplemented Mapper",
         }
     }
@@ -532,6 +1045,150 @@ impl std::fmt::Debug for NesHeader {
 mod tests {
     use super::*;
 
+    #[test]
+    fn apply_game_info() {
+        let mut header = NesHeader {
+            version: 1,
+            mapper_num: 4,
+            flags: 0b0000_1001,
+            prg_rom_banks: 8,
+            ..NesHeader::default()
+        };
+        header.apply_game_info(&GameInfo {
+            board: Some("NES-SNROM".to_string()),
+            prg_ram_size: Some(0x2000),
+            battery: Some(true),
+            mirroring: Some(Mirroring::Horizontal),
+            ..GameInfo::default()
+        });
+        assert_eq!(
+            header,
+            NesHeader {
+                version: 1,
+                mapper_num: 1,
+                flags: 0b0000_0010,
+                prg_rom_banks: 8,
+                prg_ram_shift: 7,
+                ..NesHeader::default()
+            }
+        );
+        assert_eq!(
+            Cart::calculate_ram_size(header.prg_ram_shift).expect("valid size"),
+            0x2000
+        );
+
+        header.apply_game_info(&GameInfo {
+            prg_ram_size: Some(64),
+            chr_ram_size: Some(0x2800),
+            ..GameInfo::default()
+        });
+        assert_eq!(header.prg_ram_shift, 7, "unsupported prg_ram size ignored");
+        assert_eq!(header.chr_ram_shift, 0, "unsupported chr_ram size ignored");
+
+        header.apply_game_info(&GameInfo {
+            prg_ram_size: Some(0),
+            chr_ram_size: Some(128),
+            ..GameInfo::default()
+        });
+        assert_eq!(header.prg_ram_shift, 0, "no prg_ram");
+        assert_eq!(header.chr_ram_shift, 1, "minimum chr_ram size");
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn apply_game_db_entry() {
+        // M.U.L.E. (USA) with a bad header claiming NROM
+        let mut header = NesHeader {
+            version: 1,
+            mapper_num: 0,
+            prg_rom_banks: 8,
+            ..NesHeader::default()
+        };
+        let info = GameInfo::lookup(0x0939_852F, "").expect("valid entry");
+        header.apply_game_info(&info);
+        assert_eq!(header.mapper_num, 1);
+        assert_eq!(header.mapper_board(), "Mapper 001 - SxROM/MMC1B/C");
+    }
+
+    #[test]
+    fn header_region() {
+        let header = |version, tv_mode| NesHeader {
+            version,
+            tv_mode,
+            ..NesHeader::default()
+        };
+        assert_eq!(header(1, 0x01).region(), None, "iNES");
+        assert_eq!(header(2, 0x00).region(), Some(NesRegion::Ntsc));
+        assert_eq!(header(2, 0x01).region(), Some(NesRegion::Pal));
+        assert_eq!(header(2, 0x02).region(), None, "multi-region");
+        assert_eq!(header(2, 0x03).region(), Some(NesRegion::Dendy));
+    }
+
+    #[test]
+    fn patch_changes_rom_hash() {
+        let mut rom = b"NES\x1a\x01\x01".to_vec();
+        rom.resize(16 + PRG_ROM_BANK_SIZE + CHR_ROM_BANK_SIZE, 0x00);
+        let mut patch = b"PATCH".to_vec();
+        patch.extend([0x00, 0x00, 0x10, 0x00, 0x01, 0xEA]);
+        patch.extend(b"EOF");
+
+        let cart =
+            Cart::from_rom("rom", &mut rom.as_slice(), RamState::AllZeros).expect("valid rom");
+        let patched =
+            Cart::from_rom_with_patch("rom", &mut rom.as_slice(), &patch, RamState::AllZeros)
+                .expect("valid patched rom");
+        assert_eq!(patched.prg_rom[0x0000], 0xEA, "patched prg_rom");
+        assert_ne!(patched.crc32(), cart.crc32(), "crc32");
+        assert_ne!(patched.sha1(), cart.sha1(), "sha1");
+        assert_eq!(
+            patched.original_crc32(),
+            Some(cart.crc32()),
+            "original crc32"
+        );
+        assert_eq!(cart.original_crc32(), None, "unpatched");
+    }
+
+    #[test]
+    fn vs_system_ppu_model() {
+        // NES 2.0 VS System header with an RP2C04-0003 PPU
+        let mut rom = b"NES\x1a\x02\x01\x01\x09\x00\x00\x00\x00\x00\x04".to_vec();
+        rom.resize(16, 0x00);
+        rom.resize(rom.len() + 2 * PRG_ROM_BANK_SIZE + CHR_ROM_BANK_SIZE, 0x00);
+
+        let cart =
+            Cart::from_rom("vs", &mut rom.as_slice(), RamState::AllZeros).expect("valid rom");
+        assert!(cart.is_vs_system(), "vs system");
+        assert_eq!(cart.ppu_model(), PpuModel::Rp2c04_0003);
+
+        let cart = Cart::empty();
+        assert!(!cart.is_vs_system(), "nes");
+        assert_eq!(cart.ppu_model(), PpuModel::Rp2c02);
+    }
+
+    #[test]
+    fn load_trainer() {
+        let mut rom = b"NES\x1a\x01\x01\x04\x00".to_vec();
+        rom.resize(16, 0x00);
+        rom.extend((0..TRAINER_SIZE).map(|i| i as u8));
+        rom.resize(rom.len() + PRG_ROM_BANK_SIZE + CHR_ROM_BANK_SIZE, 0xEA);
+
+        let cart =
+            Cart::from_rom("trainer", &mut rom.as_slice(), RamState::AllZeros).expect("valid rom");
+        assert!(cart.header.has_trainer(), "has trainer");
+        assert_eq!(cart.prg_rom(), [0xEA; PRG_ROM_BANK_SIZE], "prg-rom");
+        assert_eq!(cart.prg_ram().len(), 0x2000, "prg-ram");
+        assert_eq!(cart.prg_ram()[TRAINER_OFFSET - 1], 0x00, "$6FFF");
+        assert_eq!(cart.prg_ram()[TRAINER_OFFSET + 0x01], 0x01, "$7001");
+        assert_eq!(cart.prg_ram()[TRAINER_OFFSET + 0x01FF], 0xFF, "$71FF");
+    }
+
+    #[test]
+    fn rom_hash() {
+        let (crc32, sha1) = Cart::rom_hash(b"ab", b"c");
+        assert_eq!(crc32, 0x3524_41C2, "crc32");
+        assert_eq!(sha1, "a9993e364706816aba3e25717850c26c9cd0d89d", "sha1");
+    }
+
     macro_rules! test_headers {
         ($(($test:ident, $data:expr, $header:expr$(,)?)),*$(,)?) => {$(
             #[test]
@@ -0,0 +1,125 @@
+//! Compressed ROM support. ROMs stored in `.zip` or gzip archives are extracted in memory.
+//!
+//! `.zip` archives may contain `.nes`, `.unf`, `.unif`, `.fds`, `.nsf` or `.nsfe` entries.
+
+use crate::NesResult;
+use anyhow::{bail, Context};
+use flate2::read::GzDecoder;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+const ZIP_MAGIC: [u8; 4] = *b"PK\x03\x04";
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const ROM_EXTENSIONS: [&str; 6] = ["nes", "unf", "unif", "fds", "nsf", "nsfe"];
+// Largest uncompressed ROM size allowed, guarding against decompression bombs
+const MAX_ROM_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Whether ROM data starting with `magic` is a supported archive.
+pub(super) fn is_archive(magic: &[u8]) -> bool {
+    magic.starts_with(&ZIP_MAGIC) || magic.starts_with(&GZIP_MAGIC)
+}
+
+/// Extracts ROM data from a `.zip` or gzip archive. For `.zip` archives, the first entry with a
+/// supported ROM extension in archive order is used.
+///
+/// # Errors
+///
+/// If the archive is invalid, doesn't contain a ROM, or the ROM is larger than `MAX_ROM_SIZE`
+/// when uncompressed, then an error is returned.
+pub(super) fn extract_rom<F: Read>(magic: &[u8], archive: &mut F) -> NesResult<Vec<u8>> {
+    if magic.starts_with(&ZIP_MAGIC) {
+        let mut data = vec![];
+        archive.read_to_end(&mut data)?;
+        let mut zip = ZipArchive::new(Cursor::new(data)).context("invalid zip archive")?;
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i).context("invalid zip archive entry")?;
+            let is_rom = file.name().rsplit_once('.').is_some_and(|(_, ext)| {
+                ROM_EXTENSIONS
+                    .iter()
+                    .any(|rom_ext| ext.eq_ignore_ascii_case(rom_ext))
+            });
+            if is_rom {
+                log::debug!("extracting `{}` from zip archive", file.name());
+                let name = file.name().to_string();
+                return read_limited(&mut file)
+                    .with_context(|| format!("failed to extract `{name}`"));
+            }
+        }
+        bail!("no rom found in zip archive. expected one of: {ROM_EXTENSIONS:?}")
+    } else {
+        read_limited(&mut GzDecoder::new(archive)).context("invalid gzip archive")
+    }
+}
+
+/// Reads decompressed data, erroring if it exceeds `MAX_ROM_SIZE`.
+fn read_limited<F: Read>(reader: &mut F) -> NesResult<Vec<u8>> {
+    let mut data = vec![];
+    reader.take(MAX_ROM_SIZE + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_ROM_SIZE {
+        bail!("uncompressed rom exceeds the maximum size of {MAX_ROM_SIZE} bytes");
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+    use zip::{write::FileOptions, ZipWriter};
+
+    fn read_magic(data: &[u8]) -> [u8; 4] {
+        let mut magic = [0x00; 4];
+        magic.copy_from_slice(&data[..4]);
+        magic
+    }
+
+    #[test]
+    fn extract_zip() {
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        for (name, data) in [
+            ("readme.txt", b"readme".as_slice()),
+            ("b.nes", b"NES\x1ab"),
+            ("a.NES", b"NES\x1aa"),
+        ] {
+            zip.start_file(name, FileOptions::default())
+                .expect("valid zip entry");
+            zip.write_all(data).expect("valid zip write");
+        }
+        let data = zip.finish().expect("valid zip").into_inner();
+
+        let magic = read_magic(&data);
+        assert!(is_archive(&magic), "is archive");
+        let rom = extract_rom(&magic, &mut data.as_slice()).expect("valid rom");
+        assert_eq!(rom, b"NES\x1ab");
+    }
+
+    #[test]
+    fn extract_gzip() {
+        let mut gzip = GzEncoder::new(vec![], Compression::default());
+        gzip.write_all(b"NES\x1a").expect("valid gzip write");
+        let data = gzip.finish().expect("valid gzip");
+
+        let magic = read_magic(&data);
+        assert!(is_archive(&magic), "is archive");
+        let rom = extract_rom(&magic, &mut data.as_slice()).expect("valid rom");
+        assert_eq!(rom, b"NES\x1a");
+    }
+
+    #[test]
+    fn extract_too_large() {
+        let mut gzip = GzEncoder::new(vec![], Compression::default());
+        gzip.write_all(&vec![0x00; MAX_ROM_SIZE as usize + 1])
+            .expect("valid gzip write");
+        let data = gzip.finish().expect("valid gzip");
+
+        let magic = read_magic(&data);
+        assert!(extract_rom(&magic, &mut data.as_slice()).is_err());
+    }
+
+    #[test]
+    fn not_archive() {
+        assert!(!is_archive(b"NES\x1a"));
+        assert!(!is_archive(b"UNIF"));
+    }
+}
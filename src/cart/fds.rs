@@ -0,0 +1,187 @@
+//! Famicom Disk System (FDS) disk image loader.
+//!
+//! Disk images are either raw `.fds` dumps or prefixed with a 16-byte `fwNES` header containing
+//! the number of disk sides. Each side is 65500 bytes of block data without gaps or CRCs, which
+//! are added back so the disk drive can be emulated at the bit level.
+//!
+//! <https://www.nesdev.org/wiki/FDS_file_format>
+
+use crate::NesResult;
+use anyhow::{bail, Context};
+use std::io::Read;
+
+pub const MAGIC: [u8; 4] = *b"FDS\x1a";
+/// Raw disk images start with the disk info block, which is identified by `*NINTENDO-HVC*`.
+pub const RAW_MAGIC: [u8; 4] = *b"\x01*NI";
+/// FDS BIOS filename, loaded from the configuration directory.
+pub const BIOS_NAME: &str = "disksys.rom";
+pub const BIOS_SIZE: usize = 0x2000;
+const HEADER_SIZE: usize = 16;
+const SIDE_SIZE: usize = 65500;
+// Gaps are measured in bits on the disk surface
+const LEADING_GAP: usize = 28300 / 8;
+const BLOCK_GAP: usize = 976 / 8;
+
+/// Whether disk data starting with `magic` is an FDS disk image.
+#[must_use]
+pub fn is_fds(magic: &[u8]) -> bool {
+    magic.starts_with(&MAGIC) || magic.starts_with(&RAW_MAGIC)
+}
+
+/// An FDS disk image split into sides.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct FdsDisk {
+    pub sides: Vec<Vec<u8>>,
+}
+
+impl FdsDisk {
+    /// Load an FDS disk image, with or without an `fwNES` header.
+    ///
+    /// # Errors
+    ///
+    /// If the disk image is empty or truncated, then an error is returned.
+    pub fn load<F: Read>(disk_data: &mut F) -> NesResult<Self> {
+        let mut data = vec![];
+        disk_data.read_to_end(&mut data)?;
+        let data = if data.starts_with(&MAGIC) {
+            if data.len() < HEADER_SIZE {
+                bail!("truncated fds header");
+            }
+            let side_count = data[4] as usize;
+            let data = &data[HEADER_SIZE..];
+            if data.len() < side_count * SIDE_SIZE {
+                bail!(
+                    "truncated fds disk. sides: {side_count}, expected: {}, remaining: {}",
+                    side_count * SIDE_SIZE,
+                    data.len()
+                );
+            }
+            &data[..side_count * SIDE_SIZE]
+        } else {
+            // Some dumps omit trailing zeroes from the last side
+            data.as_slice()
+        };
+        if data.is_empty() {
+            bail!("fds disk has no sides");
+        }
+
+        let sides = data
+            .chunks(SIDE_SIZE)
+            .map(|side| {
+                if !side.starts_with(&RAW_MAGIC) {
+                    bail!("fds disk info block not found");
+                }
+                Ok(side.to_vec())
+            })
+            .collect::<NesResult<Vec<_>>>()
+            .context("invalid fds disk side")?;
+        Ok(Self { sides })
+    }
+
+    /// Returns each side with gaps, block start marks and CRCs added, as read by the disk drive.
+    #[must_use]
+    pub fn gapped_sides(&self) -> Vec<Vec<u8>> {
+        self.sides.iter().map(|side| Self::add_gaps(side)).collect()
+    }
+
+    fn add_gaps(side: &[u8]) -> Vec<u8> {
+        let mut disk = vec![0x00; LEADING_GAP];
+        let mut pos = 0;
+        while pos < side.len() {
+            let len = match side[pos] {
+                1 => 56, // Disk info
+                2 => 2,  // File amount
+                3 => 16, // File header
+                // File data, sized by the preceding file header
+                4 if pos >= 16 => {
+                    1 + usize::from(u16::from_le_bytes([side[pos - 3], side[pos - 2]]))
+                }
+                _ => break,
+            };
+            if pos + len > side.len() {
+                break;
+            }
+            disk.push(0x80); // Block start mark
+            disk.extend_from_slice(&side[pos..pos + len]);
+            // CRCs aren't stored in disk images, so a placeholder is used
+            disk.extend([0x4D, 0x62]);
+            disk.extend([0x00; BLOCK_GAP]);
+            pos += len;
+        }
+        // Unused space is read as gap until the end of the disk
+        let len = disk.len().max(LEADING_GAP + SIDE_SIZE);
+        disk.resize(len, 0x00);
+        disk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn side() -> Vec<u8> {
+        let mut side = vec![0x01];
+        side.extend(b"*NINTENDO-HVC*");
+        side.resize(56, 0x00);
+        side.extend([0x02, 0x01]);
+        let mut file_header = [0x00; 16];
+        file_header[0] = 0x03;
+        file_header[13..15].copy_from_slice(&4u16.to_le_bytes());
+        side.extend(file_header);
+        side.extend([0x04, 0xAA, 0xBB, 0xCC, 0xDD]);
+        side.resize(SIDE_SIZE, 0x00);
+        side
+    }
+
+    #[test]
+    fn load_fds() {
+        let mut disk = MAGIC.to_vec();
+        disk.push(2);
+        disk.resize(HEADER_SIZE, 0x00);
+        disk.extend(side());
+        disk.extend(side());
+        assert!(is_fds(&disk));
+
+        let fds = FdsDisk::load(&mut disk.as_slice()).expect("valid fds");
+        assert_eq!(fds.sides.len(), 2);
+        assert_eq!(fds.sides[1], side());
+
+        disk.truncate(disk.len() - 1);
+        assert!(
+            FdsDisk::load(&mut disk.as_slice()).is_err(),
+            "truncated side"
+        );
+    }
+
+    #[test]
+    fn load_raw_fds() {
+        let disk = side();
+        assert!(is_fds(&disk));
+        let fds = FdsDisk::load(&mut disk.as_slice()).expect("valid fds");
+        assert_eq!(fds.sides.len(), 1);
+        assert!(!is_fds(b"NES\x1a"));
+    }
+
+    #[test]
+    fn gapped_sides() {
+        let fds = FdsDisk {
+            sides: vec![side()],
+        };
+        let disk = &fds.gapped_sides()[0];
+
+        let mut pos = LEADING_GAP;
+        for block in [
+            &side()[0..56],
+            &[0x02, 0x01],
+            &side()[58..74],
+            &side()[74..79],
+        ] {
+            assert_eq!(disk[pos], 0x80, "block start mark");
+            assert_eq!(&disk[pos + 1..=pos + block.len()], block);
+            pos += 1 + block.len() + 2 + BLOCK_GAP;
+        }
+        assert!(disk[pos..].iter().all(|&b| b == 0x00), "trailing gap");
+        assert_eq!(disk.len(), LEADING_GAP + SIDE_SIZE);
+    }
+}
@@ -0,0 +1,221 @@
+//! Game database used to identify ROMs and correct bad or missing `NesHeader` data.
+
+use crate::{common::NesRegion, ppu::Mirroring};
+use std::str::FromStr;
+
+#[cfg(not(target_arch = "wasm32"))]
+const GAME_DB: &str = include_str!("../../config/game_database.txt");
+
+/// A game database entry. Fields left empty in the database are `None`.
+///
+/// Sizes are in bytes. `PrgRomSize` and `ChrRomSize` are part of the ROM identity and are not
+/// stored.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct GameInfo {
+    pub region: Option<NesRegion>,
+    pub board: Option<String>,
+    pub pcb: Option<String>,
+    pub chip: Option<String>,
+    pub mapper_num: Option<u16>,
+    pub chr_ram_size: Option<usize>,
+    pub prg_ram_size: Option<usize>,
+    pub battery: Option<bool>,
+    pub mirroring: Option<Mirroring>,
+    pub submapper_num: Option<u8>,
+    pub title: Option<String>,
+}
+
+impl GameInfo {
+    /// Looks up the game database entry for a ROM by its CRC32 and SHA-1.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn lookup(crc32: u32, sha1: &str) -> Option<Self> {
+        lookup_game(crc32, sha1).map(Self::parse)
+    }
+
+    /// Parses the fields following the hash columns of a game database entry.
+    pub fn parse(fields: &str) -> Self {
+        // Title is last and may contain commas
+        let mut fields = fields.splitn(13, ',');
+        let mut next = || {
+            fields
+                .next()
+                .map(str::trim)
+                .filter(|field| !field.is_empty())
+        };
+        let region = next().and_then(|region| NesRegion::try_from(region).ok());
+        let board = next().map(ToString::to_string);
+        let pcb = next().map(ToString::to_string);
+        let chip = next().map(ToString::to_string);
+        let mapper_num = next().and_then(parse);
+        let _prg_rom_size = next();
+        let _chr_rom_size = next();
+        let chr_ram_size = next().and_then(parse);
+        let prg_ram_size = next().and_then(parse);
+        let battery = next().and_then(parse);
+        let mirroring = next().and_then(parse_mirroring);
+        let submapper_num = next().and_then(parse);
+        let title = next().map(|title| {
+            title
+                .strip_prefix('"')
+                .and_then(|title| title.strip_suffix('"'))
+                .unwrap_or(title)
+                .to_string()
+        });
+        Self {
+            region,
+            board,
+            pcb,
+            chip,
+            mapper_num,
+            chr_ram_size,
+            prg_ram_size,
+            battery,
+            mirroring,
+            submapper_num,
+            title,
+        }
+    }
+
+    /// Whether the `Chip` field contains the given chip name, ignoring case.
+    #[must_use]
+    pub fn has_chip(&self, name: &str) -> bool {
+        self.chip
+            .as_ref()
+            .is_some_and(|chip| chip.to_ascii_uppercase().contains(name))
+    }
+}
+
+/// Returns the mapper number for a cartridge board name, e.g. `NES-SNROM` or `SNROM`.
+///
+/// <https://www.nesdev.org/wiki/Cartridge_board_reference>
+#[must_use]
+pub fn board_mapper_num(board: &str) -> Option<u16> {
+    let board = board.trim().to_ascii_uppercase();
+    let board = ["NES-", "HVC-", "FC-", "UNL-", "BMC-"]
+        .iter()
+        .find_map(|prefix| board.strip_prefix(prefix))
+        .unwrap_or(&board);
+    let mapper_num = match board {
+        "NROM" | "NROM-128" | "NROM-256" | "HROM" | "RROM" | "RTROM" | "SROM" | "STROM" => 0,
+        "SAROM" | "SBROM" | "SCROM" | "SC1ROM" | "SEROM" | "SFROM" | "SF1ROM" | "SGROM"
+        | "SHROM" | "SH1ROM" | "SIROM" | "SJROM" | "SKROM" | "SLROM" | "SL1ROM" | "SL2ROM"
+        | "SL3ROM" | "SLRROM" | "SMROM" | "SNROM" | "SOROM" | "SUROM" | "SXROM" => 1,
+        "UNROM" | "UN1ROM" | "UOROM" => 2,
+        "CNROM" => 3,
+        "HKROM" | "TBROM" | "TEROM" | "TFROM" | "TGROM" | "TKROM" | "TLROM" | "TL1ROM"
+        | "TL2ROM" | "TNROM" | "TR1ROM" | "TSROM" | "TVROM" => 4,
+        "EKROM" | "ELROM" | "ETROM" | "EWROM" => 5,
+        "AMROM" | "ANROM" | "AN1ROM" | "AOROM" => 7,
+        "PNROM" | "PEEOROM" => 9,
+        "FJROM" | "FKROM" => 10,
+        "CPROM" => 13,
+        "JALECO-JF-23" | "JALECO-JF-24" | "JALECO-JF-25" | "JALECO-JF-27" | "JALECO-JF-29"
+        | "JALECO-JF-37" | "JALECO-JF-40" => 18,
+        "UNROM-512-8" | "UNROM-512-16" | "UNROM-512-32" => 30,
+        "IREM-G101" => 32,
+        "TAITO-TC0190FMC" | "TAITO-TC0350FMR" => 33,
+        "BNROM" | "NINA-001" => 34,
+        "TAITO-TC0190FMC+PAL16R4" => 48,
+        "TENGEN-800032" => 64,
+        "GNROM" | "MHROM" => 66,
+        "CAMERICA-BF9093" | "CAMERICA-BF9097" | "CAMERICA-ALGNV11" => 71,
+        "NINA-03" | "NINA-06" => 79,
+        "TKSROM" | "TLSROM" => 118,
+        "TQROM" => 119,
+        "DEROM" | "DE1ROM" | "DRROM" => 206,
+        _ => return None,
+    };
+    Some(mapper_num)
+}
+
+fn parse<T: FromStr>(field: &str) -> Option<T> {
+    field.parse().ok()
+}
+
+/// Only hardwired mirroring is kept, as single-screen mirroring is controlled by mappers.
+fn parse_mirroring(field: &str) -> Option<Mirroring> {
+    match field {
+        "Horizontal" => Some(Mirroring::Horizontal),
+        "Vertical" => Some(Mirroring::Vertical),
+        "FourScreen" => Some(Mirroring::FourScreen),
+        _ => None,
+    }
+}
+
+/// Returns the game database fields following the `Crc32` and `Sha1` columns for a ROM. The
+/// database is sorted by `Crc32`, and `Sha1` disambiguates collisions when present.
+#[cfg(not(target_arch = "wasm32"))]
+fn lookup_game(crc32: u32, sha1: &str) -> Option<&'static str> {
+    let parse_crc32 = |line: &str| {
+        line.split(',')
+            .next()
+            .and_then(|crc32| u32::from_str_radix(crc32, 16).ok())
+            .unwrap_or_default()
+    };
+    let lines: Vec<&str> = GAME_DB
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect();
+    let start = lines.partition_point(|line| parse_crc32(line) < crc32);
+    lines[start..]
+        .iter()
+        .take_while(|line| parse_crc32(line) == crc32)
+        .find_map(|line| {
+            let mut fields = line.splitn(3, ',').skip(1);
+            let (hash, fields) = (fields.next()?, fields.next()?);
+            (hash.is_empty() || hash.eq_ignore_ascii_case(sha1)).then_some(fields)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_game_info() {
+        let info = GameInfo::parse(
+            r#"PAL,NES-SNROM,,MMC1B2,1,,,,8192,true,SingleScreenA,,"Zelda, The Legend of (Europe).nes""#,
+        );
+        assert_eq!(
+            info,
+            GameInfo {
+                region: Some(NesRegion::Pal),
+                board: Some("NES-SNROM".to_string()),
+                chip: Some("MMC1B2".to_string()),
+                mapper_num: Some(1),
+                prg_ram_size: Some(8192),
+                battery: Some(true),
+                title: Some("Zelda, The Legend of (Europe).nes".to_string()),
+                ..GameInfo::default()
+            }
+        );
+        assert!(info.has_chip("MMC1B"), "has chip");
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn lookup_game_info() {
+        let info = GameInfo::lookup(0x0939_852F, "").expect("valid entry");
+        assert_eq!(info.title.as_deref(), Some("M.U.L.E. (USA).nes"));
+        assert_eq!(info.region, Some(NesRegion::Ntsc));
+        assert_eq!(info.mapper_num, Some(1));
+
+        let info = GameInfo::lookup(0x0013_88B3, "").expect("valid entry");
+        assert_eq!(
+            info.title.as_deref(),
+            Some("Mega Man 3 (Europe) (Rev A).nes")
+        );
+        assert_eq!(info.region, Some(NesRegion::Pal));
+
+        assert_eq!(GameInfo::lookup(0x0939_852E, ""), None, "unknown crc32");
+    }
+
+    #[test]
+    fn board_mapper() {
+        assert_eq!(board_mapper_num("NES-SNROM"), Some(1));
+        assert_eq!(board_mapper_num("tlrom"), Some(4));
+        assert_eq!(board_mapper_num("HVC-UNROM"), Some(2));
+        assert_eq!(board_mapper_num("UNKNOWN"), None);
+    }
+}
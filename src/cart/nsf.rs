@@ -0,0 +1,350 @@
+//! NES Sound Format (NSF) and extended NSFe loader.
+//!
+//! NSF files are a 128-byte header followed by program data. NSFe files are a 4-byte signature
+//! followed by chunks, each consisting of a little-endian 32-bit length, a 4-byte ID and the
+//! chunk data, and may additionally store track labels, lengths and fades.
+//!
+//! <https://www.nesdev.org/wiki/NSF>
+//! <https://www.nesdev.org/wiki/NSFe>
+
+use crate::{common::NesRegion, NesResult};
+use anyhow::bail;
+use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+
+pub const MAGIC: [u8; 4] = *b"NESM";
+pub const NSFE_MAGIC: [u8; 4] = *b"NSFE";
+const HEADER_SIZE: usize = 0x80;
+const DEFAULT_NTSC_SPEED: u16 = 16639;
+const DEFAULT_PAL_SPEED: u16 = 19997;
+
+/// Whether ROM data starting with `magic` is an NSF or NSFe file.
+#[must_use]
+pub fn is_nsf(magic: &[u8]) -> bool {
+    magic.starts_with(&MAGIC) || magic.starts_with(&NSFE_MAGIC)
+}
+
+bitflags! {
+    /// Expansion audio chips used by an NSF.
+    #[derive(Default, Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+    #[must_use]
+    pub struct NsfChips: u8 {
+        const VRC6 = 0x01;
+        const VRC7 = 0x02;
+        const FDS = 0x04;
+        const MMC5 = 0x08;
+        const N163 = 0x10;
+        const S5B = 0x20;
+    }
+}
+
+/// Per-track metadata. Lengths and fades are in milliseconds and are only provided by NSFe files.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[must_use]
+pub struct NsfTrack {
+    pub label: Option<String>,
+    pub length: Option<u32>,
+    pub fade: Option<u32>,
+}
+
+/// A parsed NSF or NSFe file.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct NsfFile {
+    pub title: String,
+    pub artist: String,
+    pub copyright: String,
+    pub load_addr: u16,
+    pub init_addr: u16,
+    pub play_addr: u16,
+    /// Play routine period in microseconds.
+    pub ntsc_speed: u16,
+    pub pal_speed: u16,
+    /// Initial 4K banks for $8000-$FFFF, if the file uses bankswitching.
+    pub banks: Option<[u8; 8]>,
+    /// `None` if the file supports both NTSC and PAL.
+    pub region: Option<NesRegion>,
+    pub chips: NsfChips,
+    /// Zero-based.
+    pub starting_track: usize,
+    pub tracks: Vec<NsfTrack>,
+    pub data: Vec<u8>,
+}
+
+impl NsfFile {
+    /// Load an NSF or NSFe file, including the signature.
+    ///
+    /// # Errors
+    ///
+    /// If the file is invalid or truncated, then an error is returned.
+    pub fn load<F: Read>(rom_data: &mut F) -> NesResult<Self> {
+        let mut data = vec![];
+        rom_data.read_to_end(&mut data)?;
+        let nsf = if data.starts_with(&NSFE_MAGIC) {
+            Self::load_nsfe(&data[NSFE_MAGIC.len()..])?
+        } else if data.starts_with(&MAGIC) {
+            Self::load_nsf(&data)?
+        } else {
+            bail!("nsf header signature not found");
+        };
+        if nsf.load_addr < 0x8000 {
+            bail!("invalid nsf load address: ${:04X}", nsf.load_addr);
+        }
+        if nsf.data.is_empty() {
+            bail!("nsf program data not found");
+        }
+        if nsf.tracks.is_empty() {
+            bail!("nsf has no tracks");
+        }
+        Ok(nsf)
+    }
+
+    fn load_nsf(data: &[u8]) -> NesResult<Self> {
+        if data.len() < HEADER_SIZE {
+            bail!("truncated nsf header");
+        }
+        let (header, data) = data.split_at(HEADER_SIZE);
+        let word = |offset: usize| u16::from_le_bytes([header[offset], header[offset + 1]]);
+        let mut banks = [0x00; 8];
+        banks.copy_from_slice(&header[0x70..0x78]);
+        let total_songs = header[0x06].max(1);
+        Ok(Self {
+            title: string(&header[0x0E..0x2E]),
+            artist: string(&header[0x2E..0x4E]),
+            copyright: string(&header[0x4E..0x6E]),
+            load_addr: word(0x08),
+            init_addr: word(0x0A),
+            play_addr: word(0x0C),
+            ntsc_speed: word(0x6E),
+            pal_speed: word(0x78),
+            banks: banks.iter().any(|&bank| bank > 0).then_some(banks),
+            region: region(header[0x7A]),
+            chips: NsfChips::from_bits_truncate(header[0x7B]),
+            starting_track: usize::from(header[0x07].saturating_sub(1)),
+            tracks: vec![NsfTrack::default(); total_songs.into()],
+            data: data.to_vec(),
+        })
+    }
+
+    fn load_nsfe(mut chunks: &[u8]) -> NesResult<Self> {
+        let mut nsf = Self {
+            ntsc_speed: DEFAULT_NTSC_SPEED,
+            pal_speed: DEFAULT_PAL_SPEED,
+            ..Self::default()
+        };
+        let mut has_info = false;
+        let mut lengths = vec![];
+        let mut fades = vec![];
+        let mut labels = vec![];
+        while !chunks.is_empty() {
+            if chunks.len() < 8 {
+                bail!("truncated nsfe chunk header");
+            }
+            let (len, rest) = chunks.split_at(4);
+            let (id, rest) = rest.split_at(4);
+            let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+            if rest.len() < len {
+                let id = String::from_utf8_lossy(id);
+                bail!(
+                    "truncated nsfe chunk {id}. expected: {len}, remaining: {}",
+                    rest.len()
+                );
+            }
+            let (chunk, rest) = rest.split_at(len);
+            chunks = rest;
+
+            match id {
+                b"INFO" => {
+                    if chunk.len() < 8 {
+                        bail!("truncated nsfe INFO chunk");
+                    }
+                    let word =
+                        |offset: usize| u16::from_le_bytes([chunk[offset], chunk[offset + 1]]);
+                    nsf.load_addr = word(0);
+                    nsf.init_addr = word(2);
+                    nsf.play_addr = word(4);
+                    nsf.region = region(chunk[6]);
+                    nsf.chips = NsfChips::from_bits_truncate(chunk[7]);
+                    let total_songs = chunk.get(8).copied().unwrap_or(1).max(1);
+                    nsf.tracks = vec![NsfTrack::default(); total_songs.into()];
+                    nsf.starting_track = chunk.get(9).copied().unwrap_or(0).into();
+                    has_info = true;
+                }
+                b"DATA" => nsf.data = chunk.to_vec(),
+                b"BANK" => {
+                    let mut banks = [0x00; 8];
+                    let len = chunk.len().min(8);
+                    banks[..len].copy_from_slice(&chunk[..len]);
+                    nsf.banks = Some(banks);
+                }
+                b"RATE" => {
+                    if chunk.len() >= 2 {
+                        nsf.ntsc_speed = u16::from_le_bytes([chunk[0], chunk[1]]);
+                    }
+                    if chunk.len() >= 4 {
+                        nsf.pal_speed = u16::from_le_bytes([chunk[2], chunk[3]]);
+                    }
+                }
+                b"auth" => {
+                    let mut strings = chunk.split(|&b| b == 0x00).map(string);
+                    nsf.title = strings.next().unwrap_or_default();
+                    nsf.artist = strings.next().unwrap_or_default();
+                    nsf.copyright = strings.next().unwrap_or_default();
+                }
+                b"time" => lengths = milliseconds(chunk),
+                b"fade" => fades = milliseconds(chunk),
+                b"tlbl" => labels = chunk.split(|&b| b == 0x00).map(string).collect(),
+                b"NEND" => break,
+                // Chunks starting with an uppercase letter are required to play correctly
+                [b'A'..=b'Z', ..] => {
+                    bail!("unsupported nsfe chunk {}", String::from_utf8_lossy(id))
+                }
+                _ => log::debug!("skipping nsfe chunk {}", String::from_utf8_lossy(id)),
+            }
+        }
+        if !has_info {
+            bail!("nsfe INFO chunk not found");
+        }
+
+        for (i, track) in nsf.tracks.iter_mut().enumerate() {
+            track.length = lengths.get(i).copied().flatten();
+            track.fade = fades.get(i).copied().flatten();
+            track.label = labels.get(i).filter(|label| !label.is_empty()).cloned();
+        }
+        Ok(nsf)
+    }
+}
+
+/// Decodes a null-terminated string.
+fn string(data: &[u8]) -> String {
+    let data = data.split(|&b| b == 0x00).next().unwrap_or_default();
+    String::from_utf8_lossy(data).trim().to_string()
+}
+
+/// Decodes NSFe track times, where negative values mean the default should be used.
+fn milliseconds(chunk: &[u8]) -> Vec<Option<u32>> {
+    chunk
+        .chunks_exact(4)
+        .map(|ms| u32::try_from(i32::from_le_bytes([ms[0], ms[1], ms[2], ms[3]])).ok())
+        .collect()
+}
+
+/// Decodes the region byte, where bit 0 selects PAL and bit 1 indicates dual-region support.
+const fn region(val: u8) -> Option<NesRegion> {
+    if val & 0x02 == 0x02 {
+        None
+    } else if val & 0x01 == 0x01 {
+        Some(NesRegion::Pal)
+    } else {
+        Some(NesRegion::Ntsc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_le_bytes().to_vec();
+        chunk.extend(id);
+        chunk.extend(data);
+        chunk
+    }
+
+    #[test]
+    fn load_nsf() {
+        let mut nsf = MAGIC.to_vec();
+        nsf.extend([0x1A, 0x01, 3, 2]);
+        nsf.extend([0x00, 0x80, 0x03, 0x80, 0x06, 0x80]);
+        nsf.extend(b"Title\0");
+        nsf.resize(0x2E, 0x00);
+        nsf.extend(b"Artist\0");
+        nsf.resize(0x4E, 0x00);
+        nsf.extend(b"2023 Copyright\0");
+        nsf.resize(0x6E, 0x00);
+        nsf.extend(16639u16.to_le_bytes());
+        nsf.extend([0, 1, 0, 0, 0, 0, 0, 0]);
+        nsf.extend(19997u16.to_le_bytes());
+        nsf.extend([0x02, 0x09]);
+        nsf.resize(HEADER_SIZE, 0x00);
+        nsf.extend([0x60; 0x10]);
+
+        let nsf = NsfFile::load(&mut nsf.as_slice()).expect("valid nsf");
+        assert_eq!(nsf.title, "Title");
+        assert_eq!(nsf.artist, "Artist");
+        assert_eq!(nsf.copyright, "2023 Copyright");
+        assert_eq!(
+            (nsf.load_addr, nsf.init_addr, nsf.play_addr),
+            (0x8000, 0x8003, 0x8006)
+        );
+        assert_eq!(nsf.banks, Some([0, 1, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(nsf.region, None, "dual region");
+        assert_eq!(nsf.chips, NsfChips::VRC6 | NsfChips::MMC5);
+        assert_eq!(nsf.starting_track, 1);
+        assert_eq!(nsf.tracks.len(), 3);
+        assert_eq!(nsf.data, [0x60; 0x10]);
+    }
+
+    #[test]
+    fn load_nsfe() {
+        let mut nsf = NSFE_MAGIC.to_vec();
+        nsf.extend(chunk(
+            b"INFO",
+            &[0x00, 0x80, 0x03, 0x80, 0x06, 0x80, 0x01, 0x00, 2, 0],
+        ));
+        nsf.extend(chunk(b"DATA", &[0x60; 0x10]));
+        nsf.extend(chunk(b"auth", b"Title\0Artist\0Copyright\0Ripper\0"));
+        let mut time = 90_000i32.to_le_bytes().to_vec();
+        time.extend((-1i32).to_le_bytes());
+        nsf.extend(chunk(b"time", &time));
+        nsf.extend(chunk(b"tlbl", b"Intro\0Theme\0"));
+        nsf.extend(chunk(b"plst", &[1, 0]));
+        nsf.extend(chunk(b"NEND", &[]));
+
+        let nsf = NsfFile::load(&mut nsf.as_slice()).expect("valid nsfe");
+        assert_eq!(nsf.title, "Title");
+        assert_eq!(nsf.copyright, "Copyright");
+        assert_eq!(nsf.region, Some(NesRegion::Pal));
+        assert_eq!(nsf.banks, None);
+        assert_eq!(nsf.ntsc_speed, DEFAULT_NTSC_SPEED);
+        assert_eq!(
+            nsf.tracks,
+            [
+                NsfTrack {
+                    label: Some("Intro".to_string()),
+                    length: Some(90_000),
+                    fade: None,
+                },
+                NsfTrack {
+                    label: Some("Theme".to_string()),
+                    length: None,
+                    fade: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn load_nsfe_without_song_count() {
+        let mut nsf = NSFE_MAGIC.to_vec();
+        nsf.extend(chunk(b"INFO", &[0x00, 0x80, 0x03, 0x80, 0x06, 0x80, 0, 0]));
+        nsf.extend(chunk(b"DATA", &[0x60]));
+        nsf.extend(chunk(b"NEND", &[]));
+
+        let nsf = NsfFile::load(&mut nsf.as_slice()).expect("valid nsfe");
+        assert_eq!(nsf.tracks.len(), 1);
+    }
+
+    #[test]
+    fn unsupported_nsfe_chunk() {
+        let mut nsf = NSFE_MAGIC.to_vec();
+        nsf.extend(chunk(
+            b"INFO",
+            &[0x00, 0x80, 0x03, 0x80, 0x06, 0x80, 0, 0, 1],
+        ));
+        nsf.extend(chunk(b"DATA", &[0x60]));
+        nsf.extend(chunk(b"XTRA", &[0x00]));
+        assert!(NsfFile::load(&mut nsf.as_slice()).is_err());
+    }
+}
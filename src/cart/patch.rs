@@ -0,0 +1,438 @@
+//! ROM soft-patching with `IPS`, `UPS` and `BPS` patches.
+//!
+//! Patches are applied in memory to the full ROM file, including the header, so the original ROM
+//! is never modified.
+//!
+//! <https://zerosoft.zophar.net/ips.php>
+//! <https://www.romhacking.net/documents/392/>
+//! <https://www.romhacking.net/documents/746/>
+
+use crate::NesResult;
+use anyhow::{anyhow, bail, Context};
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: usize = 0x0045_4F46; // "EOF"
+const UPS_MAGIC: &[u8] = b"UPS1";
+const BPS_MAGIC: &[u8] = b"BPS1";
+// Source, target and patch CRC32 checksums
+const FOOTER_SIZE: usize = 12;
+// Largest patched ROM size allowed, guarding against malformed sizes
+const MAX_TARGET_SIZE: usize = 16 * 1024 * 1024;
+
+/// Supported patch formats.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[must_use]
+pub enum PatchFormat {
+    Ips,
+    Ups,
+    Bps,
+}
+
+impl PatchFormat {
+    /// File extensions for each patch format, in order of preference.
+    pub const EXTENSIONS: [&'static str; 3] = ["ips", "ups", "bps"];
+
+    /// Detects the patch format from its signature.
+    #[must_use]
+    pub fn detect(patch: &[u8]) -> Option<Self> {
+        if patch.starts_with(IPS_MAGIC) {
+            Some(Self::Ips)
+        } else if patch.starts_with(UPS_MAGIC) {
+            Some(Self::Ups)
+        } else if patch.starts_with(BPS_MAGIC) {
+            Some(Self::Bps)
+        } else {
+            None
+        }
+    }
+}
+
+/// Applies a patch to ROM data, returning the patched ROM.
+///
+/// # Errors
+///
+/// If the patch format is not recognized, the patch is malformed, or `UPS`/`BPS` checksums don't
+/// match, then an error is returned.
+pub fn apply(rom: &[u8], patch: &[u8]) -> NesResult<Vec<u8>> {
+    match PatchFormat::detect(patch) {
+        Some(PatchFormat::Ips) => apply_ips(rom, patch).context("invalid ips patch"),
+        Some(PatchFormat::Ups) => apply_ups(rom, patch).context("invalid ups patch"),
+        Some(PatchFormat::Bps) => apply_bps(rom, patch).context("invalid bps patch"),
+        None => bail!("unrecognized patch format"),
+    }
+}
+
+/// Reads bytes from a patch, erroring at the end of the patch.
+struct PatchReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    const fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn bytes(&mut self, len: usize) -> NesResult<&'a [u8]> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or_else(|| anyhow!("unexpected end of patch at offset {}", self.pos))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> NesResult<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn be(&mut self, len: usize) -> NesResult<usize> {
+        Ok(self
+            .bytes(len)?
+            .iter()
+            .fold(0, |val, &byte| (val << 8) | usize::from(byte)))
+    }
+
+    /// Reads a `UPS`/`BPS` variable-length number.
+    fn varint(&mut self) -> NesResult<usize> {
+        let mut val = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let byte = self.byte()?;
+            let pos = self.pos;
+            let invalid = || anyhow!("invalid patch number at offset {pos}");
+            val = usize::from(byte & 0x7F)
+                .checked_mul(shift)
+                .and_then(|n| n.checked_add(val))
+                .ok_or_else(invalid)?;
+            if byte & 0x80 == 0x80 {
+                return Ok(val);
+            }
+            shift = shift.checked_mul(0x80).ok_or_else(invalid)?;
+            val = val.checked_add(shift).ok_or_else(invalid)?;
+        }
+    }
+}
+
+fn apply_ips(rom: &[u8], patch: &[u8]) -> NesResult<Vec<u8>> {
+    let mut patched = rom.to_vec();
+    let mut reader = PatchReader::new(patch, IPS_MAGIC.len());
+    loop {
+        let offset = reader.be(3)?;
+        if offset == IPS_EOF {
+            break;
+        }
+        let len = reader.be(2)?;
+        let (len, data) = if len == 0 {
+            // Run-length encoded record
+            let len = reader.be(2)?;
+            (len, vec![reader.byte()?; len])
+        } else {
+            (len, reader.bytes(len)?.to_vec())
+        };
+        if patched.len() < offset + len {
+            patched.resize(offset + len, 0x00);
+        }
+        patched[offset..offset + len].copy_from_slice(&data);
+    }
+    // Optional truncation extension
+    if let Ok(len) = reader.be(3) {
+        patched.truncate(len);
+    }
+    Ok(patched)
+}
+
+/// Validates the `UPS`/`BPS` patch checksum and returns the source and target checksums.
+fn checksums(patch: &[u8]) -> NesResult<(u32, u32)> {
+    if patch.len() < FOOTER_SIZE {
+        bail!("missing checksums");
+    }
+    // The patch checksum covers everything but itself, including the source and target checksums
+    let body = &patch[..patch.len() - 4];
+    let footer = &patch[patch.len() - FOOTER_SIZE..];
+    let checksum =
+        |n: usize| u32::from_le_bytes([footer[n], footer[n + 1], footer[n + 2], footer[n + 3]]);
+    let (source, target, patch_crc32) = (checksum(0), checksum(4), checksum(8));
+    if crc32fast::hash(body) != patch_crc32 {
+        bail!("patch checksum mismatch");
+    }
+    Ok((source, target))
+}
+
+fn validate_source(rom: &[u8], source_size: usize, source_crc32: u32) -> NesResult<()> {
+    if rom.len() != source_size || crc32fast::hash(rom) != source_crc32 {
+        bail!("patch does not match rom. expected size: {source_size}, crc32: {source_crc32:08X}");
+    }
+    Ok(())
+}
+
+fn validate_target_size(target_size: usize) -> NesResult<()> {
+    if target_size > MAX_TARGET_SIZE {
+        bail!("patched rom size {target_size} exceeds the maximum of {MAX_TARGET_SIZE}");
+    }
+    Ok(())
+}
+
+fn validate_target(patched: &[u8], target_crc32: u32) -> NesResult<()> {
+    if crc32fast::hash(patched) != target_crc32 {
+        bail!("patched rom checksum mismatch");
+    }
+    Ok(())
+}
+
+fn apply_ups(rom: &[u8], patch: &[u8]) -> NesResult<Vec<u8>> {
+    let (source_crc32, target_crc32) = checksums(patch)?;
+    let end = patch.len() - FOOTER_SIZE;
+    let mut reader = PatchReader::new(&patch[..end], UPS_MAGIC.len());
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    validate_source(rom, source_size, source_crc32)?;
+    validate_target_size(target_size)?;
+
+    let mut patched = rom.to_vec();
+    patched.resize(target_size, 0x00);
+    let mut pos = 0usize;
+    while reader.pos < end {
+        pos = pos
+            .checked_add(reader.varint()?)
+            .filter(|&pos| pos <= target_size)
+            .ok_or_else(|| anyhow!("patch offset out of bounds"))?;
+        loop {
+            let byte = reader.byte()?;
+            if byte == 0x00 {
+                pos += 1;
+                break;
+            }
+            *patched
+                .get_mut(pos)
+                .ok_or_else(|| anyhow!("patch offset {pos} out of bounds"))? ^= byte;
+            pos += 1;
+        }
+    }
+    validate_target(&patched, target_crc32)?;
+    Ok(patched)
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> NesResult<Vec<u8>> {
+    let (source_crc32, target_crc32) = checksums(patch)?;
+    let end = patch.len() - FOOTER_SIZE;
+    let mut reader = PatchReader::new(&patch[..end], BPS_MAGIC.len());
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    let metadata_size = reader.varint()?;
+    reader.bytes(metadata_size)?;
+    validate_source(rom, source_size, source_crc32)?;
+    validate_target_size(target_size)?;
+
+    let out_of_bounds = || anyhow!("patch copy out of bounds");
+    let relative_offset = |offset: usize, data: usize| {
+        let delta = data >> 1;
+        if data & 0x01 == 0x01 {
+            offset.checked_sub(delta)
+        } else {
+            offset.checked_add(delta)
+        }
+        .ok_or_else(out_of_bounds)
+    };
+    // Bounds of `len` bytes starting at `offset` within `size`
+    let range = |offset: usize, len: usize, size: usize| {
+        offset
+            .checked_add(len)
+            .filter(|&end| end <= size)
+            .map(|end| offset..end)
+            .ok_or_else(out_of_bounds)
+    };
+    let mut patched = vec![0x00; target_size];
+    let mut output_offset = 0;
+    let mut source_offset = 0;
+    let mut target_offset = 0;
+    while reader.pos < end {
+        let data = reader.varint()?;
+        let len = (data >> 2) + 1;
+        let output =
+            range(output_offset, len, target_size).context("patch writes past the target size")?;
+        match data & 0x03 {
+            // SourceRead
+            0 => {
+                let source = range(output_offset, len, rom.len())?;
+                patched[output.clone()].copy_from_slice(&rom[source]);
+            }
+            // TargetRead
+            1 => patched[output.clone()].copy_from_slice(reader.bytes(len)?),
+            // SourceCopy
+            2 => {
+                source_offset = relative_offset(source_offset, reader.varint()?)?;
+                let source = range(source_offset, len, rom.len())?;
+                patched[output.clone()].copy_from_slice(&rom[source]);
+                source_offset += len;
+            }
+            // TargetCopy, which may overlap with the bytes being written
+            _ => {
+                target_offset = relative_offset(target_offset, reader.varint()?)?;
+                for offset in output.clone() {
+                    if target_offset >= offset {
+                        return Err(out_of_bounds());
+                    }
+                    patched[offset] = patched[target_offset];
+                    target_offset += 1;
+                }
+            }
+        }
+        output_offset = output.end;
+    }
+    if output_offset != target_size {
+        bail!("patched rom size mismatch. expected: {target_size}, patched: {output_offset}");
+    }
+    validate_target(&patched, target_crc32)?;
+    Ok(patched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(mut val: usize) -> Vec<u8> {
+        let mut bytes = vec![];
+        loop {
+            let byte = (val & 0x7F) as u8;
+            val >>= 7;
+            if val == 0 {
+                bytes.push(byte | 0x80);
+                return bytes;
+            }
+            bytes.push(byte);
+            val -= 1;
+        }
+    }
+
+    fn footer(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        patch.extend(crc32fast::hash(source).to_le_bytes());
+        patch.extend(crc32fast::hash(target).to_le_bytes());
+        patch.extend(crc32fast::hash(&patch).to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn ips() {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend([0x00, 0x00, 0x01, 0x00, 0x02, 0xAA, 0xBB]);
+        // RLE record extending the ROM
+        patch.extend([0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x03, 0xCC]);
+        patch.extend(b"EOF");
+        assert_eq!(
+            apply(&[0x00; 4], &patch).expect("valid patch"),
+            [0x00, 0xAA, 0xBB, 0x00, 0x00, 0xCC, 0xCC, 0xCC]
+        );
+
+        patch.extend([0x00, 0x00, 0x02]);
+        assert_eq!(
+            apply(&[0x00; 4], &patch).expect("valid patch"),
+            [0x00, 0xAA]
+        );
+    }
+
+    #[test]
+    fn ups() {
+        let source = [0x01, 0x02, 0x03, 0x04];
+        let target = [0x01, 0x12, 0x03, 0x04, 0x05];
+        let mut patch = b"UPS1".to_vec();
+        patch.extend(varint(source.len()));
+        patch.extend(varint(target.len()));
+        patch.extend(varint(1));
+        patch.extend([0x10, 0x00]);
+        patch.extend(varint(1));
+        patch.extend([0x05, 0x00]);
+        let patch = footer(patch, &source, &target);
+
+        assert_eq!(apply(&source, &patch).expect("valid patch"), target);
+        assert!(apply(&target, &patch).is_err(), "source mismatch");
+        let mut corrupt = patch.clone();
+        corrupt[5] ^= 0xFF;
+        assert!(apply(&source, &corrupt).is_err(), "patch checksum");
+
+        let mut patch = b"UPS1".to_vec();
+        patch.extend(varint(source.len()));
+        patch.extend(varint(MAX_TARGET_SIZE + 1));
+        let patch = footer(patch, &source, &target);
+        assert!(apply(&source, &patch).is_err(), "target too large");
+
+        let mut patch = b"UPS1".to_vec();
+        patch.extend(varint(source.len()));
+        patch.extend(varint(target.len()));
+        patch.extend(varint(usize::MAX));
+        patch.extend([0x00]);
+        let patch = footer(patch, &source, &target);
+        assert!(apply(&source, &patch).is_err(), "offset overflow");
+    }
+
+    #[test]
+    fn bps() {
+        let source = b"ABCDEFGH";
+        let target = b"ABCDxyxyxyGH";
+        let mut patch = b"BPS1".to_vec();
+        patch.extend(varint(source.len()));
+        patch.extend(varint(target.len()));
+        patch.extend(varint(0));
+        // SourceRead 4
+        patch.extend(varint(3 << 2));
+        // TargetRead 2
+        patch.extend(varint((1 << 2) | 1));
+        patch.extend(b"xy");
+        // TargetCopy 4 from target offset 4
+        patch.extend(varint((3 << 2) | 3));
+        patch.extend(varint(4 << 1));
+        // SourceCopy 2 from source offset 6
+        patch.extend(varint((1 << 2) | 2));
+        patch.extend(varint(6 << 1));
+        let patch = footer(patch, source, target);
+
+        assert_eq!(apply(source, &patch).expect("valid patch"), target);
+        assert!(apply(target, &patch).is_err(), "source mismatch");
+    }
+
+    #[test]
+    fn bps_out_of_bounds() {
+        let source = b"ABCD";
+        let target = b"ABCDABCD";
+        let bps = |metadata_size: usize, actions: &[usize]| {
+            let mut patch = b"BPS1".to_vec();
+            patch.extend(varint(source.len()));
+            patch.extend(varint(target.len()));
+            patch.extend(varint(metadata_size));
+            for &action in actions {
+                patch.extend(varint(action));
+            }
+            footer(patch, source, target)
+        };
+
+        let patch = bps(usize::MAX, &[]);
+        assert!(apply(source, &patch).is_err(), "metadata past end of patch");
+        // SourceRead 4, then TargetCopy 1M from target offset 0
+        let patch = bps(0, &[3 << 2, ((0x0010_0000 - 1) << 2) | 3, 0]);
+        assert!(
+            apply(source, &patch).is_err(),
+            "target copy past target size"
+        );
+        // TargetCopy 4 from target offset 0 before any bytes are written
+        let patch = bps(0, &[(3 << 2) | 3, 0]);
+        assert!(
+            apply(source, &patch).is_err(),
+            "target copy of unwritten bytes"
+        );
+        // SourceCopy 4 from past the end of the source
+        let patch = bps(0, &[(3 << 2) | 2, usize::MAX & !0x01]);
+        assert!(apply(source, &patch).is_err(), "source copy out of bounds");
+    }
+
+    #[test]
+    fn varint_overflow() {
+        let patch = [0x7F; 16];
+        assert!(PatchReader::new(&patch, 0).varint().is_err());
+    }
+
+    #[test]
+    fn unrecognized() {
+        assert!(apply(&[0x00], b"NOPE").is_err());
+    }
+}
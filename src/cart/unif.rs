@@ -0,0 +1,204 @@
+//! Universal NES Image Format (UNIF) loader.
+//!
+//! UNIF ROMs are a 32-byte header followed by chunks, each consisting of a 4-byte ID, a
+//! little-endian 32-bit length and the chunk data. Boards are identified by name instead of mapper
+//! number, and are converted to an equivalent `NesHeader`.
+//!
+//! <https://www.nesdev.org/wiki/UNIF>
+
+use super::{game_db, NesHeader, CHR_ROM_BANK_SIZE, PRG_ROM_BANK_SIZE};
+use crate::NesResult;
+use anyhow::{anyhow, bail, Context};
+use std::io::Read;
+
+pub const MAGIC: [u8; 4] = *b"UNIF";
+const HEADER_SIZE: usize = 32;
+
+/// A UNIF ROM converted to a `NesHeader`, with PRG-ROM and CHR-ROM concatenated in chunk order.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct Unif {
+    pub board: String,
+    pub header: NesHeader,
+    pub prg_rom: Vec<u8>,
+    pub chr_rom: Vec<u8>,
+}
+
+impl Unif {
+    /// Load a UNIF ROM, including the `UNIF` signature.
+    ///
+    /// # Errors
+    ///
+    /// If the UNIF data is invalid or the board is not supported, then an error is returned.
+    pub fn load<F: Read>(rom_data: &mut F) -> NesResult<Self> {
+        let mut header = [0u8; HEADER_SIZE];
+        rom_data.read_exact(&mut header)?;
+        if header[0..4] != MAGIC {
+            bail!("unif header signature not found");
+        }
+
+        let mut data = vec![];
+        rom_data.read_to_end(&mut data)?;
+        let mut board = None;
+        let mut prg_chunks: [Option<&[u8]>; 16] = Default::default();
+        let mut chr_chunks: [Option<&[u8]>; 16] = Default::default();
+        let mut mirroring = None;
+        let mut battery = false;
+        let mut tv_mode = None;
+
+        let mut chunks = data.as_slice();
+        while !chunks.is_empty() {
+            if chunks.len() < 8 {
+                bail!("truncated unif chunk header");
+            }
+            let (id, rest) = chunks.split_at(4);
+            let (len, rest) = rest.split_at(4);
+            let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+            if rest.len() < len {
+                let id = String::from_utf8_lossy(id);
+                bail!(
+                    "truncated unif chunk {id}. expected: {len}, remaining: {}",
+                    rest.len()
+                );
+            }
+            let (chunk, rest) = rest.split_at(len);
+            chunks = rest;
+
+            match id {
+                b"MAPR" => {
+                    let name = chunk.split(|&b| b == 0x00).next().unwrap_or_default();
+                    board = Some(String::from_utf8_lossy(name).trim().to_string());
+                }
+                b"MIRR" => mirroring = chunk.first().copied(),
+                b"BATR" => battery = chunk.first() != Some(&0x00),
+                b"TVCI" => tv_mode = chunk.first().copied(),
+                [b'P', b'R', b'G', n] => prg_chunks[chunk_index(*n)?] = Some(chunk),
+                [b'C', b'H', b'R', n] => chr_chunks[chunk_index(*n)?] = Some(chunk),
+                _ => log::debug!("skipping unif chunk {}", String::from_utf8_lossy(id)),
+            }
+        }
+
+        let board = board.ok_or_else(|| anyhow!("unif board name not found"))?;
+        let mapper_num = game_db::board_mapper_num(&board)
+            .with_context(|| format!("unsupported unif board: {board}"))?;
+        let prg_rom: Vec<u8> = prg_chunks
+            .iter()
+            .flatten()
+            .flat_map(|c| c.iter())
+            .copied()
+            .collect();
+        let chr_rom: Vec<u8> = chr_chunks
+            .iter()
+            .flatten()
+            .flat_map(|c| c.iter())
+            .copied()
+            .collect();
+        if prg_rom.is_empty() {
+            bail!("unif prg-rom not found");
+        }
+
+        // Mirroring: 0 = Horizontal, 1 = Vertical, 4 = Four-Screen, others are mapper-controlled
+        let mut flags = match mirroring {
+            Some(1) => 0x01,
+            Some(4) => 0x08,
+            _ => 0x00,
+        };
+        if battery {
+            flags |= 0x02;
+        }
+
+        Ok(Self {
+            board,
+            header: NesHeader {
+                version: 2,
+                mapper_num,
+                flags,
+                prg_rom_banks: banks(prg_rom.len(), PRG_ROM_BANK_SIZE)?,
+                chr_rom_banks: banks(chr_rom.len(), CHR_ROM_BANK_SIZE)?,
+                // TVCI: 0 = NTSC, 1 = PAL, 2 = Both. Unspecified is treated as multi-region.
+                tv_mode: match tv_mode {
+                    Some(0) => 0x00,
+                    Some(1) => 0x01,
+                    _ => 0x02,
+                },
+                ..NesHeader::default()
+            },
+            prg_rom,
+            chr_rom,
+        })
+    }
+}
+
+/// Returns the index of a `PRG0`-`PRGF` or `CHR0`-`CHRF` chunk.
+fn chunk_index(n: u8) -> NesResult<usize> {
+    char::from(n)
+        .to_digit(16)
+        .map(|n| n as usize)
+        .ok_or_else(|| anyhow!("invalid unif rom chunk number: {}", char::from(n)))
+}
+
+fn banks(len: usize, bank_size: usize) -> NesResult<u16> {
+    // Partial banks are rounded up
+    let mut banks = len / bank_size;
+    if banks * bank_size < len {
+        banks += 1;
+    }
+    u16::try_from(banks).context("unif rom too large")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend((data.len() as u32).to_le_bytes());
+        chunk.extend(data);
+        chunk
+    }
+
+    #[test]
+    fn load_unif() {
+        let mut rom = MAGIC.to_vec();
+        rom.extend(7u32.to_le_bytes());
+        rom.resize(HEADER_SIZE, 0x00);
+        rom.extend(chunk(b"MAPR", b"NES-SNROM\0"));
+        rom.extend(chunk(b"PRG1", &[0x02; 0x4000]));
+        rom.extend(chunk(b"PRG0", &[0x01; 0x4000]));
+        rom.extend(chunk(b"CHR0", &[0x03; 0x2000]));
+        rom.extend(chunk(b"NAME", b"Test\0"));
+        rom.extend(chunk(b"MIRR", &[0x01]));
+        rom.extend(chunk(b"BATR", &[0x01]));
+        rom.extend(chunk(b"TVCI", &[0x01]));
+
+        let unif = Unif::load(&mut rom.as_slice()).expect("valid unif");
+        assert_eq!(unif.board, "NES-SNROM");
+        assert_eq!(
+            unif.header,
+            NesHeader {
+                version: 2,
+                mapper_num: 1,
+                flags: 0x03,
+                prg_rom_banks: 2,
+                chr_rom_banks: 1,
+                tv_mode: 0x01,
+                ..NesHeader::default()
+            }
+        );
+        assert_eq!(unif.prg_rom[0x0000], 0x01, "PRG0 first");
+        assert_eq!(unif.prg_rom[0x4000], 0x02, "PRG1 second");
+        assert_eq!(unif.chr_rom, [0x03; 0x2000]);
+
+        rom.truncate(rom.len() - 1);
+        assert!(Unif::load(&mut rom.as_slice()).is_err(), "truncated chunk");
+    }
+
+    #[test]
+    fn unsupported_board() {
+        let mut rom = MAGIC.to_vec();
+        rom.resize(HEADER_SIZE, 0x00);
+        rom.extend(chunk(b"MAPR", b"UNL-UNKNOWN\0"));
+        rom.extend(chunk(b"PRG0", &[0x00; 0x4000]));
+        assert!(Unif::load(&mut rom.as_slice()).is_err());
+    }
+}
@@ -173,9 +173,10 @@ pub fn hexdump(data: &[u8], addr_offset: usize) -> Vec<String> {
 pub(crate) mod tests {
     use super::*;
     use crate::{
+        cart::Cart,
         control_deck::ControlDeck,
         input::Slot,
-        mapper::{Mapper, MapperRevision},
+        mapper::{Mapped, Mapper, MapperRevision},
         nes::event::{Action, NesState, Setting},
         ppu::Ppu,
         video::VideoFilter,
@@ -278,6 +279,9 @@ pub(crate) mod tests {
                 Action::Nes(state) => match state {
                     NesState::SoftReset => deck.reset(Kind::Soft),
                     NesState::HardReset => deck.reset(Kind::Hard),
+                    NesState::SwitchDiskSide => {
+                        deck.switch_disk_side();
+                    }
                     NesState::MapperRevision(board) => match board {
                         MapperRevision::Mmc3(revision) => {
                             if let Mapper::Txrom(ref mut mapper) = deck.mapper_mut() {
@@ -344,6 +348,55 @@ pub(crate) mod tests {
         })
     }
 
+    /// Loads a mapper onto a cartridge with zeroed PRG-ROM and CHR-ROM of the given sizes and no
+    /// PRG-RAM, returning both. An empty CHR-ROM leaves the mapper to add CHR-RAM.
+    pub(crate) fn load_mapper<M>(
+        prg_rom_len: usize,
+        chr_rom_len: usize,
+        load: impl FnOnce(&mut Cart) -> Mapper,
+    ) -> (Cart, M)
+    where
+        Mapper: TryInto<M>,
+    {
+        let mut cart = Cart::empty();
+        cart.prg_ram.clear();
+        cart.prg_rom = vec![0x00; prg_rom_len];
+        cart.chr_rom = vec![0x00; chr_rom_len];
+        match load(&mut cart).try_into() {
+            Ok(mapper) => (cart, mapper),
+            Err(_) => panic!("loaded an unexpected mapper"),
+        }
+    }
+
+    /// Runs a test ROM built by `test_roms/mapper/src/build.py` for a number of frames and
+    /// returns the results it recorded in CPU RAM.
+    pub(crate) fn test_rom_results(path: &str, frames: u32) -> Vec<u8> {
+        let mut deck = load_control_deck(path);
+        while deck.frame_number() < frames {
+            let _ = deck.clock_frame().expect("valid frame clock");
+        }
+        let wram = deck.wram();
+        let count = usize::from(wram[0x0000]);
+        wram[0x0300..0x0300 + count].to_vec()
+    }
+
+    /// Runs a test ROM built by `test_roms/mapper/src/build.py` for a number of frames and
+    /// returns the PPU scanline each time the mapper raised its IRQ.
+    pub(crate) fn test_rom_irq_scanlines(path: &str, frames: u32) -> Vec<u32> {
+        let mut deck = load_control_deck(path);
+        let mut scanlines = vec![];
+        let mut irq_pending = false;
+        while deck.frame_number() < frames {
+            let _ = deck.clock_instr().expect("valid instruction clock");
+            let pending = deck.mapper().irq_pending();
+            if pending && !irq_pending {
+                scanlines.push(deck.ppu().scanline());
+            }
+            irq_pending = pending;
+        }
+        scanlines
+    }
+
     pub(crate) fn test_rom(directory: &str, test_name: &str) {
         if !&*INIT_TESTS {
             log::debug!("Initialized tests");
@@ -5,14 +5,15 @@ use crate::{
     common::{Clock, Kind, NesRegion, Regional, Reset},
     cpu::Cpu,
     input::{FourPlayer, Joypad, Slot},
-    mapper::Mapper,
+    mapper::{Mapper, Nsf},
     mem::RamState,
     ppu::Ppu,
+    save_state::{SaveState, Section},
     video::{Video, VideoFilter},
     NesResult,
 };
-use anyhow::anyhow;
-use std::{io::Read, ops::ControlFlow};
+use anyhow::{anyhow, bail};
+use std::{borrow::Cow, io::Read, ops::ControlFlow};
 
 /// Represents an NES Control Deck
 #[derive(Debug, Clone)]
@@ -20,9 +21,11 @@ use std::{io::Read, ops::ControlFlow};
 pub struct ControlDeck {
     running: bool,
     ram_state: RamState,
+    // Configured region, used when the loaded ROM doesn't specify one
     region: NesRegion,
     video: Video,
     loaded_rom: Option<String>,
+    rom_hash: u32,
     cycles_remaining: f32,
     cpu: Cpu,
 }
@@ -43,12 +46,16 @@ impl ControlDeck {
             region: NesRegion::default(),
             video: Video::default(),
             loaded_rom: None,
+            rom_hash: 0,
             cycles_remaining: 0.0,
             cpu,
         }
     }
 
-    /// Loads a ROM cartridge into memory
+    /// Loads a ROM cartridge into memory.
+    ///
+    /// The region is taken from the NES 2.0 header if specified, then the game database, and
+    /// otherwise the configured region set with `set_region` is used.
     ///
     /// # Errors
     ///
@@ -56,17 +63,90 @@ impl ControlDeck {
     pub fn load_rom<S: ToString, F: Read>(&mut self, name: S, rom: &mut F) -> NesResult<()> {
         self.loaded_rom = Some(name.to_string());
         let cart = Cart::from_rom(name, rom, self.ram_state)?;
-        self.set_region(cart.region());
+        self.load_cart(cart);
+        Ok(())
+    }
+
+    /// Loads a ROM cartridge into memory with an `IPS`, `UPS` or `BPS` patch applied. The ROM data
+    /// itself is left unmodified.
+    ///
+    /// # Errors
+    ///
+    /// If the patch fails to apply or there is any issue loading the patched ROM, then an error is
+    /// returned.
+    pub fn load_rom_with_patch<S: ToString, F: Read>(
+        &mut self,
+        name: S,
+        rom: &mut F,
+        patch: &[u8],
+    ) -> NesResult<()> {
+        self.loaded_rom = Some(name.to_string());
+        let cart = Cart::from_rom_with_patch(name, rom, patch, self.ram_state)?;
+        self.load_cart(cart);
+        Ok(())
+    }
+
+    fn load_cart(&mut self, mut cart: Cart) {
+        self.rom_hash = cart.crc32();
+        let region = cart.rom_region().unwrap_or(self.region);
+        cart.set_region(region);
         self.cpu.load_cart(cart);
+        self.cpu.set_region(region);
         self.reset(Kind::Hard);
-        Ok(())
     }
 
+    /// Loads a CPU save state. Save states exclude cartridge ROM data, so the ROM of the currently
+    /// loaded cartridge is carried over.
     #[inline]
-    pub fn load_cpu(&mut self, cpu: Cpu) {
+    pub fn load_cpu(&mut self, mut cpu: Cpu) {
+        cpu.load_rom_from(&mut self.cpu);
         self.cpu = cpu;
     }
 
+    /// Serializes the current state of the console into a versioned [`SaveState`]. Cartridge ROM
+    /// is excluded.
+    ///
+    /// # Errors
+    ///
+    /// If any section of the console state fails to serialize, then an error is returned.
+    pub fn save_state(&self) -> NesResult<Vec<u8>> {
+        let mut state = SaveState::new(self.rom_hash);
+        state.set_section(Section::Cpu, &self.cpu)?;
+        state.set_section(Section::Ppu, self.cpu.ppu())?;
+        state.set_section(Section::Apu, self.cpu.apu())?;
+        state.set_section(Section::Mapper, self.cpu.mapper())?;
+        state.set_section(Section::Input, self.cpu.input())?;
+        state.to_bytes()
+    }
+
+    /// Restores the state of the console from a [`SaveState`] created by [`ControlDeck::save_state`],
+    /// migrating it from older versions if needed.
+    ///
+    /// # Errors
+    ///
+    /// If the save state is invalid, fails to migrate, or was created with a different ROM, then
+    /// an error is returned.
+    pub fn load_state(&mut self, data: &[u8]) -> NesResult<()> {
+        let state = SaveState::from_bytes(data)?;
+        if state.rom_hash() != self.rom_hash {
+            bail!(
+                "save state was created with a different rom. expected: {:08x}, save state: {:08x}",
+                self.rom_hash,
+                state.rom_hash()
+            );
+        }
+        // DIP switches are a user setting rather than console state
+        let dip_switches = self.dip_switches();
+        let mut cpu: Cpu = state.section(Section::Cpu)?;
+        *cpu.ppu_mut() = state.section(Section::Ppu)?;
+        *cpu.apu_mut() = state.section(Section::Apu)?;
+        *cpu.input_mut() = state.section(Section::Input)?;
+        cpu.ppu_mut().load_mapper(state.section(Section::Mapper)?);
+        self.load_cpu(cpu);
+        self.set_dip_switches(dip_switches);
+        Ok(())
+    }
+
     #[inline]
     #[must_use]
     pub const fn loaded_rom(&self) -> &Option<String> {
@@ -75,13 +155,13 @@ impl ControlDeck {
 
     #[inline]
     #[must_use]
-    pub const fn cart_battery_backed(&self) -> bool {
+    pub fn cart_battery_backed(&self) -> bool {
         self.cpu.cart_battery_backed()
     }
 
     #[inline]
     #[must_use]
-    pub fn sram(&self) -> &[u8] {
+    pub fn sram(&self) -> Cow<'_, [u8]> {
         self.cpu.sram()
     }
 
@@ -100,8 +180,13 @@ impl ControlDeck {
     #[inline]
     #[must_use]
     pub fn frame_buffer(&mut self) -> &[u8] {
-        self.video
-            .apply_filter(self.cpu.frame_buffer(), self.cpu.frame_number());
+        if self.cpu.ppu().model().is_rgb() {
+            // RGB PPUs output colors directly instead of composite video
+            self.video.decode_rgb_buffer(self.cpu.frame_buffer());
+        } else {
+            self.video
+                .apply_filter(self.cpu.frame_buffer(), self.cpu.frame_number());
+        }
         self.video.output()
     }
 
@@ -285,6 +370,35 @@ impl ControlDeck {
         self.cpu.mapper_mut()
     }
 
+    /// Ejects the current Famicom Disk System disk side and inserts the next one. Returns the
+    /// side being inserted, or `None` if the loaded cartridge is not an FDS disk.
+    #[inline]
+    pub fn switch_disk_side(&mut self) -> Option<usize> {
+        match self.mapper_mut() {
+            Mapper::Fds(ref mut fds) => Some(fds.switch_side()),
+            _ => None,
+        }
+    }
+
+    /// Returns the loaded `NSF` player, or `None` if the loaded cartridge is not an `NSF` file.
+    #[inline]
+    #[must_use]
+    pub const fn nsf(&self) -> Option<&Nsf> {
+        match self.mapper() {
+            Mapper::Nsf(ref nsf) => Some(nsf),
+            _ => None,
+        }
+    }
+
+    /// Starts playing an `NSF` track from the beginning. Does nothing if the loaded cartridge is
+    /// not an `NSF` file.
+    pub fn play_nsf_track(&mut self, track: usize) {
+        if let Mapper::Nsf(ref mut nsf) = self.mapper_mut() {
+            nsf.set_track(track);
+            self.reset(Kind::Soft);
+        }
+    }
+
     /// Returns whether Four Score is enabled.
     #[inline]
     pub const fn four_player(&self) -> FourPlayer {
@@ -329,6 +443,32 @@ impl ControlDeck {
         self.cpu.zapper_mut().aim(x, y);
     }
 
+    /// Returns the `VS System` DIP switch settings.
+    #[inline]
+    #[must_use]
+    pub const fn dip_switches(&self) -> u8 {
+        self.cpu.vs_input().dip_switches
+    }
+
+    /// Set the `VS System` DIP switches, with switch 1 in the lowest bit.
+    #[inline]
+    pub const fn set_dip_switches(&mut self, dip_switches: u8) {
+        self.cpu.vs_input_mut().dip_switches = dip_switches;
+    }
+
+    /// Insert a coin into a `VS System` coin slot for a given controller slot.
+    #[inline]
+    pub fn insert_coin(&mut self, slot: Slot) {
+        let region = self.cpu.region();
+        self.cpu.vs_input_mut().insert_coin(slot, region);
+    }
+
+    /// Press or release the `VS System` service button.
+    #[inline]
+    pub const fn set_service_button(&mut self, pressed: bool) {
+        self.cpu.vs_input_mut().service = pressed;
+    }
+
     /// Set the image filter for video output.
     #[inline]
     pub fn set_filter(&mut self, filter: VideoFilter) {
@@ -385,13 +525,14 @@ impl Clock for ControlDeck {
 }
 
 impl Regional for ControlDeck {
-    /// Get the NES format for the emulation.
+    /// Get the NES format for the emulation, which is the loaded ROM's region if it specifies one.
     #[inline]
     fn region(&self) -> NesRegion {
-        self.region
+        self.cpu.region()
     }
 
-    /// Set the NES format for the emulation.
+    /// Set the NES format for the emulation. It's also kept as the configured region for any ROMs
+    /// loaded later that don't specify one.
     #[inline]
     fn set_region(&mut self, region: NesRegion) {
         self.region = region;
@@ -406,3 +547,33 @@ impl Reset for ControlDeck {
         self.running = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom(nes2_tv_mode: Option<u8>) -> Vec<u8> {
+        let mut rom = b"NES\x1a\x01\x01".to_vec();
+        rom.resize(16, 0x00);
+        if let Some(tv_mode) = nes2_tv_mode {
+            rom[7] = 0x08;
+            rom[12] = tv_mode;
+        }
+        rom.resize(rom.len() + 0x4000 + 0x2000, 0x00);
+        rom
+    }
+
+    #[test]
+    fn load_rom_region() {
+        let mut deck = ControlDeck::default();
+        deck.set_region(NesRegion::Dendy);
+
+        deck.load_rom("pal", &mut rom(Some(0x01)).as_slice())
+            .expect("valid rom");
+        assert_eq!(deck.region(), NesRegion::Pal, "rom region");
+
+        deck.load_rom("unknown", &mut rom(None).as_slice())
+            .expect("valid rom");
+        assert_eq!(deck.region(), NesRegion::Dendy, "configured region");
+    }
+}
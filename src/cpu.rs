@@ -7,7 +7,7 @@ use crate::{
     bus::CpuBus,
     cart::Cart,
     common::{Clock, Kind, NesRegion, Regional, Reset},
-    input::{FourPlayer, Joypad, Slot, Zapper},
+    input::{FourPlayer, Input, Joypad, Slot, VsInput, Zapper},
     mapper::Mapper,
     mem::{Access, Mem},
     ppu::Ppu,
@@ -26,7 +26,10 @@ use instr::{
     },
 };
 use serde::{Deserialize, Serialize};
-use std::fmt::{self, Write};
+use std::{
+    borrow::Cow,
+    fmt::{self, Write},
+};
 
 pub mod instr;
 
@@ -266,6 +269,16 @@ impl Cpu {
         self.bus.mapper_mut()
     }
 
+    #[inline]
+    pub const fn input(&self) -> &Input {
+        self.bus.input()
+    }
+
+    #[inline]
+    pub const fn input_mut(&mut self) -> &mut Input {
+        self.bus.input_mut()
+    }
+
     #[inline]
     pub const fn joypad(&self, slot: Slot) -> &Joypad {
         self.bus.joypad(slot)
@@ -291,20 +304,35 @@ impl Cpu {
         self.bus.zapper_mut()
     }
 
+    #[inline]
+    pub const fn vs_input(&self) -> &VsInput {
+        self.bus.vs_input()
+    }
+
+    #[inline]
+    pub const fn vs_input_mut(&mut self) -> &mut VsInput {
+        self.bus.vs_input_mut()
+    }
+
     #[inline]
     pub fn load_cart(&mut self, cart: Cart) {
         self.bus.load_cart(cart);
     }
 
+    #[inline]
+    pub fn load_rom_from(&mut self, other: &mut Self) {
+        self.bus.load_rom_from(&mut other.bus);
+    }
+
     #[inline]
     #[must_use]
-    pub const fn cart_battery_backed(&self) -> bool {
+    pub fn cart_battery_backed(&self) -> bool {
         self.bus.cart_battery_backed()
     }
 
     #[inline]
     #[must_use]
-    pub fn sram(&self) -> &[u8] {
+    pub fn sram(&self) -> Cow<'_, [u8]> {
         self.bus.sram()
     }
 
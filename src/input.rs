@@ -83,6 +83,7 @@ pub struct Input {
     joypads: [Joypad; 4],
     signatures: [Joypad; 2],
     zapper: Zapper,
+    vs: VsInput,
     turbo_timer: u32,
     four_player: FourPlayer,
 }
@@ -97,6 +98,7 @@ impl Input {
                 Joypad::signature(0b0000_0100),
             ],
             zapper: Zapper::new(),
+            vs: VsInput::new(),
             turbo_timer: 30,
             four_player: FourPlayer::default(),
         }
@@ -127,6 +129,16 @@ impl Input {
         &mut self.zapper
     }
 
+    #[inline]
+    pub const fn vs_input(&self) -> &VsInput {
+        &self.vs
+    }
+
+    #[inline]
+    pub const fn vs_input_mut(&mut self) -> &mut VsInput {
+        &mut self.vs
+    }
+
     #[inline]
     pub const fn four_player(&self) -> FourPlayer {
         self.four_player
@@ -150,6 +162,13 @@ impl InputRegisters for Input {
             0x00
         };
 
+        // VS System cabinets replace open bus with coin, service and DIP switch inputs
+        let vs = if self.vs.enabled {
+            self.vs.read(slot)
+        } else {
+            0x40
+        };
+
         let slot = slot as usize;
         let val = match self.four_player {
             FourPlayer::Disabled => self.joypads[slot].read(),
@@ -169,7 +188,7 @@ impl InputRegisters for Input {
             }
         };
 
-        zapper | val | 0x40
+        zapper | val | vs
     }
 
     fn peek(&self, slot: Slot, ppu: &Ppu) -> u8 {
@@ -182,6 +201,13 @@ impl InputRegisters for Input {
             0x00
         };
 
+        // VS System cabinets replace open bus with coin, service and DIP switch inputs
+        let vs = if self.vs.enabled {
+            self.vs.read(slot)
+        } else {
+            0x40
+        };
+
         let slot = slot as usize;
         let val = match self.four_player {
             FourPlayer::Disabled => self.joypads[slot].peek(),
@@ -201,7 +227,7 @@ impl InputRegisters for Input {
             }
         };
 
-        zapper | val | 0x40
+        zapper | val | vs
     }
 
     fn write(&mut self, val: u8) {
@@ -217,6 +243,7 @@ impl InputRegisters for Input {
 impl Clock for Input {
     fn clock(&mut self) -> usize {
         self.zapper.clock();
+        self.vs.clock();
         self.turbo_timer -= 1;
         if self.turbo_timer == 0 {
             // Roughly 20Hz
@@ -245,6 +272,7 @@ impl Reset for Input {
             sig.reset(kind);
         }
         self.zapper.reset(kind);
+        self.vs.reset(kind);
     }
 }
 
@@ -512,10 +540,117 @@ impl Reset for Zapper {
     }
 }
 
+/// `VS System` cabinet inputs.
+///
+/// <https://www.nesdev.org/wiki/VS_System#Ports>
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct VsInput {
+    pub enabled: bool,
+    pub dip_switches: u8, // Switch 1 in bit 0
+    pub coins: [f32; 2],
+    pub service: bool,
+}
+
+impl VsInput {
+    const fn new() -> Self {
+        Self {
+            enabled: false,
+            dip_switches: 0x00,
+            coins: [0.0; 2],
+            service: false,
+        }
+    }
+
+    /// Inserts a coin into the coin slot for `Slot::One` or `Slot::Two`, timing the coin pulse
+    /// for the given `region`.
+    #[inline]
+    pub fn insert_coin(&mut self, slot: Slot, region: NesRegion) {
+        let coin = usize::from(slot == Slot::Two);
+        if self.coins[coin] <= 0.0 {
+            // Coin switches stay closed for ~50ms so games polling once a frame see them
+            self.coins[coin] = Cpu::region_clock_rate(region) / 20.0;
+        }
+    }
+
+    // $4016 read
+    // 7  bit  0
+    // ---- ----
+    // xCCD DSxx
+    //  ||| ||
+    //  ||| |+--- Service button
+    //  ||+-+---- DIP switches 1-2
+    //  |+------- Coin slot 1
+    //  +-------- Coin slot 2
+    //
+    // $4017 read
+    // 7  bit  0
+    // ---- ----
+    // DDDD DDxx
+    // |||| ||
+    // ++++-++--- DIP switches 3-8
+    #[must_use]
+    fn read(&self, slot: Slot) -> u8 {
+        if slot == Slot::One {
+            let coin = |coin: f32| u8::from(coin > 0.0);
+            (u8::from(self.service) << 2)
+                | ((self.dip_switches & 0x03) << 3)
+                | (coin(self.coins[0]) << 5)
+                | (coin(self.coins[1]) << 6)
+        } else {
+            self.dip_switches & 0xFC
+        }
+    }
+}
+
+impl Clock for VsInput {
+    fn clock(&mut self) -> usize {
+        for coin in &mut self.coins {
+            if *coin > 0.0 {
+                *coin -= 1.0;
+            }
+        }
+        1
+    }
+}
+
+impl Reset for VsInput {
+    fn reset(&mut self, _kind: Kind) {
+        self.coins = [0.0; 2];
+        self.service = false;
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::test_roms;
 
+    #[test]
+    fn vs_system_ports() {
+        let ppu = Ppu::default();
+        let mut input = Input::new();
+        input.vs_input_mut().dip_switches = 0b1010_0110;
+        assert_eq!(input.peek(Slot::One, &ppu) & 0xFE, 0x40, "disabled");
+
+        input.vs_input_mut().enabled = true;
+        assert_eq!(input.peek(Slot::One, &ppu) & 0xFE, 0x10, "dip switches 1-2");
+        assert_eq!(input.peek(Slot::Two, &ppu) & 0xFE, 0xA4, "dip switches 3-8");
+
+        input.vs_input_mut().insert_coin(Slot::Two, NesRegion::Pal);
+        input.vs_input_mut().service = true;
+        assert_eq!(input.peek(Slot::One, &ppu) & 0xFE, 0x54);
+        assert_eq!(
+            input.vs_input().coins[1],
+            Cpu::region_clock_rate(NesRegion::Pal) / 20.0,
+            "coin pulse length"
+        );
+
+        input.vs_input_mut().coins[1] = 1.0;
+        input.clock();
+        assert_eq!(input.peek(Slot::One, &ppu) & 0x40, 0x00, "coin released");
+    }
+
     test_roms!(
         "test_roms/input",
         #[ignore = "todo"]
@@ -58,6 +58,7 @@ pub mod mem;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod nes;
 pub mod ppu;
+pub mod save_state;
 pub mod video;
 
 pub type NesError = anyhow::Error;
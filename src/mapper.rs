@@ -8,30 +8,74 @@ use crate::{
 };
 use enum_dispatch::enum_dispatch;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 pub use m000_nrom::Nrom;
 pub use m001_sxrom::{Mmc1Revision, Sxrom};
-pub use m002_uxrom::Uxrom;
-pub use m003_cnrom::Cnrom;
-pub use m004_txrom::{Mmc3Revision, Txrom};
+pub use m002_m180_uxrom::{Uxrom, UxromRevision};
+pub use m003_m013_m087_m185_cnrom::{Cnrom, CnromRevision};
+pub use m004_txrom::{Mmc3Revision, Txrom, TxromBoard};
 pub use m005_exrom::Exrom;
 pub use m007_axrom::Axrom;
 pub use m009_pxrom::Pxrom;
+pub use m010_fxrom::Fxrom;
+pub use m011_m066_m079_m140_gxrom::{Gxrom, GxromRevision};
+pub use m015_k1029::K1029;
+pub use m016_m153_m157_m159_bandai_fcg::{BandaiFcg, BandaiFcgRevision};
+pub use m018_jaleco_ss88006::JalecoSs88006;
+pub use m019_namco163::Namco163;
+pub use m020_fds::Fds;
+pub use m021_m022_m023_m025_vrc4::{Vrc4, Vrc4Revision, Vrc4Wiring};
 pub use m024_m026_vrc6::Vrc6;
-pub use m066_gxrom::Gxrom;
+pub use m030_unrom512::Unrom512;
+pub use m032_irem_g101::{IremG101, IremG101Revision};
+pub use m033_m048_taito_tc0190::{TaitoTc0190, TaitoTc0190Revision};
+pub use m034_bnrom_nina001::{Bnrom, BnromRevision};
+pub use m041_caltron::Caltron;
+pub use m064_rambo1::Rambo1;
+pub use m069_fme7::Fme7;
 pub use m071_bf909x::{Bf909Revision, Bf909x};
+pub use m085_vrc7::Vrc7;
+pub use m099_vs_system::VsSystem;
+pub use m225_et4310::Et4310;
+pub use m228_action52::Action52;
+pub use m232_bf9096::Bf9096;
+pub use nsf::Nsf;
 
+pub mod eeprom;
+pub mod flash;
 pub mod m000_nrom;
 pub mod m001_sxrom;
-pub mod m002_uxrom;
-pub mod m003_cnrom;
+pub mod m002_m180_uxrom;
+pub mod m003_m013_m087_m185_cnrom;
 pub mod m004_txrom;
 pub mod m005_exrom;
 pub mod m007_axrom;
 pub mod m009_pxrom;
+pub mod m010_fxrom;
+pub mod m011_m066_m079_m140_gxrom;
+pub mod m015_k1029;
+pub mod m016_m153_m157_m159_bandai_fcg;
+pub mod m018_jaleco_ss88006;
+pub mod m019_namco163;
+pub mod m020_fds;
+pub mod m021_m022_m023_m025_vrc4;
 pub mod m024_m026_vrc6;
-pub mod m066_gxrom;
+pub mod m030_unrom512;
+pub mod m032_irem_g101;
+pub mod m033_m048_taito_tc0190;
+pub mod m034_bnrom_nina001;
+pub mod m041_caltron;
+pub mod m064_rambo1;
+pub mod m069_fme7;
 pub mod m071_bf909x;
+pub mod m085_vrc7;
+pub mod m099_vs_system;
+pub mod m225_et4310;
+pub mod m228_action52;
+pub mod m232_bf9096;
+pub mod mmc2_latch;
+pub mod nsf;
 pub mod vrc_irq;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -42,6 +86,7 @@ pub enum MapperRevision {
     Bf909(Bf909Revision),
 }
 
+/// Save states serialize the mapper by variant index, so new variants must be added at the end.
 #[enum_dispatch]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(clippy::large_enum_variant)]
@@ -59,6 +104,26 @@ pub enum Mapper {
     Vrc6,
     Gxrom,
     Bf909x,
+    Fds,
+    Nsf,
+    Vrc7,
+    Namco163,
+    Fme7,
+    Vrc4,
+    Fxrom,
+    BandaiFcg,
+    Bnrom,
+    Unrom512,
+    K1029,
+    Caltron,
+    Et4310,
+    Action52,
+    Bf9096,
+    IremG101,
+    TaitoTc0190,
+    JalecoSs88006,
+    Rambo1,
+    VsSystem,
 }
 
 impl Mapper {
@@ -78,6 +143,7 @@ impl Default for Mapper {
 pub enum MappedRead {
     None,
     Chr(usize),
+    ChrRam(usize),
     CIRam(usize),
     ExRam(usize),
     PrgRom(usize),
@@ -90,6 +156,7 @@ pub enum MappedRead {
 pub enum MappedWrite {
     None,
     Chr(usize, u8),
+    ChrRam(usize, u8),
     CIRam(usize, u8),
     ExRam(usize, u8),
     PrgRam(usize, u8),
@@ -125,6 +192,19 @@ pub trait Mapped {
     fn ppu_bus_write(&mut self, _addr: u16, _val: u8) {}
     fn cpu_bus_read(&mut self, _addr: u16) {}
     fn cpu_bus_write(&mut self, _addr: u16, _val: u8) {}
+    /// Whether the mapper has battery-backed memory, saved even if the cartridge has no
+    /// battery-backed PRG-RAM.
+    fn battery_backed(&self) -> bool {
+        false
+    }
+    /// Battery-backed memory internal to the mapper, saved to Save RAM after PRG-RAM.
+    fn battery_ram(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&[])
+    }
+    fn load_battery_ram(&mut self, _ram: &[u8]) {}
+    /// Moves mapper-owned ROM data excluded from save states from the mapper of the currently
+    /// loaded cartridge.
+    fn load_rom_from(&mut self, _other: &mut Mapper) {}
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
@@ -0,0 +1,325 @@
+//! `Eeprom`
+//!
+//! 24C01 and 24C02 serial EEPROMs used by Bandai boards to store save data, accessed by
+//! bit-banging an I2C bus.
+//!
+//! <https://www.nesdev.org/wiki/Bandai_FCG_board#Serial_EEPROM>
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[must_use]
+pub enum EepromKind {
+    /// 128 bytes. Transfers start with a 7-bit word address instead of a device address, and
+    /// bits are sent least significant first.
+    Eeprom24C01,
+    /// 256 bytes. Standard I2C device and word addressing, with bits sent most significant first.
+    Eeprom24C02,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[must_use]
+enum Mode {
+    #[default]
+    Idle,
+    DeviceAddress,
+    Address,
+    Read,
+    Write,
+    SendAck,
+    WaitAck,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Eeprom {
+    kind: EepromKind,
+    data: Vec<u8>,
+    mode: Mode,
+    next_mode: Mode,
+    device_address: u8,
+    address: u8,
+    buffer: u8,
+    bit_counter: u8,
+    output: bool,
+    scl: bool,
+    sda: bool,
+}
+
+impl Eeprom {
+    pub fn new(kind: EepromKind) -> Self {
+        let size = match kind {
+            EepromKind::Eeprom24C01 => 128,
+            EepromKind::Eeprom24C02 => 256,
+        };
+        Self {
+            kind,
+            data: vec![0x00; size],
+            mode: Mode::Idle,
+            next_mode: Mode::Idle,
+            device_address: 0x00,
+            address: 0x00,
+            buffer: 0x00,
+            bit_counter: 0,
+            output: true,
+            scl: false,
+            sda: false,
+        }
+    }
+
+    /// Contents of the EEPROM.
+    #[inline]
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn load_data(&mut self, data: &[u8]) {
+        let len = data.len().min(self.data.len());
+        self.data[..len].copy_from_slice(&data[..len]);
+    }
+
+    /// Level of the data line driven by the EEPROM.
+    #[inline]
+    #[must_use]
+    pub const fn output(&self) -> bool {
+        self.output
+    }
+
+    #[inline]
+    #[must_use]
+    const fn address_mask(&self) -> u8 {
+        match self.kind {
+            EepromKind::Eeprom24C01 => 0x7F,
+            EepromKind::Eeprom24C02 => 0xFF,
+        }
+    }
+
+    /// Bit position of the current bit, in transfer order for the EEPROM kind.
+    #[inline]
+    #[must_use]
+    const fn bit_shift(&self) -> u8 {
+        match self.kind {
+            EepromKind::Eeprom24C01 => self.bit_counter,
+            EepromKind::Eeprom24C02 => 7 - self.bit_counter,
+        }
+    }
+
+    /// Shifts a bit into `val`.
+    fn shift_in(&mut self, val: u8, bit: bool) -> u8 {
+        if self.bit_counter >= 8 {
+            return val;
+        }
+        let shift = self.bit_shift();
+        self.bit_counter += 1;
+        (val & !(1 << shift)) | (u8::from(bit) << shift)
+    }
+
+    fn start_read(&mut self) {
+        self.next_mode = Mode::Read;
+        self.buffer = self.data[usize::from(self.address & self.address_mask())];
+    }
+
+    /// Updates the clock and data lines driven by the CPU.
+    pub fn write(&mut self, scl: bool, sda: bool) {
+        if self.scl && scl && self.sda && !sda {
+            // Start condition
+            self.mode = match self.kind {
+                EepromKind::Eeprom24C01 => Mode::Address,
+                EepromKind::Eeprom24C02 => Mode::DeviceAddress,
+            };
+            self.bit_counter = 0;
+            self.output = true;
+        } else if self.scl && scl && !self.sda && sda {
+            // Stop condition
+            self.mode = Mode::Idle;
+            self.output = true;
+        } else if !self.scl && scl {
+            self.clock_rising(sda);
+        } else if self.scl && !scl {
+            self.clock_falling();
+        }
+        self.scl = scl;
+        self.sda = sda;
+    }
+
+    fn clock_rising(&mut self, sda: bool) {
+        match self.mode {
+            Mode::DeviceAddress => self.device_address = self.shift_in(self.device_address, sda),
+            Mode::Address if self.kind == EepromKind::Eeprom24C01 => {
+                // The 8th bit after the 7-bit address selects read or write
+                if self.bit_counter < 7 {
+                    self.address = self.shift_in(self.address, sda) & 0x7F;
+                } else if self.bit_counter == 7 {
+                    self.bit_counter = 8;
+                    if sda {
+                        self.start_read();
+                    } else {
+                        self.next_mode = Mode::Write;
+                    }
+                }
+            }
+            Mode::Address => self.address = self.shift_in(self.address, sda),
+            Mode::Read if self.bit_counter < 8 => {
+                // Shift the next bit of the read buffer out onto the data line
+                self.output = (self.buffer >> self.bit_shift()) & 0x01 == 0x01;
+                self.bit_counter += 1;
+            }
+            Mode::Write => self.buffer = self.shift_in(self.buffer, sda),
+            Mode::SendAck => self.output = false,
+            Mode::WaitAck => {
+                // Reads continue sequentially while the CPU acknowledges each byte
+                if sda {
+                    self.next_mode = Mode::Idle;
+                } else {
+                    self.start_read();
+                }
+            }
+            Mode::Read | Mode::Idle => (),
+        }
+    }
+
+    fn clock_falling(&mut self) {
+        match self.mode {
+            Mode::DeviceAddress if self.bit_counter == 8 => {
+                // Devices respond to %1010xxxR
+                if self.device_address & 0xF0 == 0xA0 {
+                    self.mode = Mode::SendAck;
+                    self.output = true;
+                    if self.device_address & 0x01 == 0x01 {
+                        self.start_read();
+                    } else {
+                        self.next_mode = Mode::Address;
+                    }
+                } else {
+                    self.mode = Mode::Idle;
+                    self.output = true;
+                }
+            }
+            Mode::Address if self.bit_counter == 8 => {
+                self.mode = Mode::SendAck;
+                self.output = true;
+                if self.kind == EepromKind::Eeprom24C02 {
+                    self.next_mode = Mode::Write;
+                }
+            }
+            Mode::Read if self.bit_counter == 8 => {
+                self.mode = Mode::WaitAck;
+                self.address = self.address.wrapping_add(1) & self.address_mask();
+            }
+            Mode::Write if self.bit_counter == 8 => {
+                let address = self.address & self.address_mask();
+                self.data[usize::from(address)] = self.buffer;
+                self.address = address.wrapping_add(1) & self.address_mask();
+                self.mode = Mode::SendAck;
+                self.next_mode = Mode::Write;
+                self.output = true;
+            }
+            Mode::SendAck | Mode::WaitAck => {
+                self.mode = self.next_mode;
+                self.bit_counter = 0;
+                self.output = true;
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Bus(Eeprom);
+
+    impl Bus {
+        fn start(&mut self) {
+            self.0.write(false, true);
+            self.0.write(true, true);
+            self.0.write(true, false);
+            self.0.write(false, false);
+        }
+
+        fn stop(&mut self) {
+            self.0.write(false, false);
+            self.0.write(true, false);
+            self.0.write(true, true);
+        }
+
+        fn bit(&mut self, bit: bool) -> bool {
+            self.0.write(false, bit);
+            self.0.write(true, bit);
+            let out = self.0.output();
+            self.0.write(false, bit);
+            out
+        }
+
+        fn send(&mut self, val: u8, lsb_first: bool) -> bool {
+            for i in 0..8 {
+                let shift = if lsb_first { i } else { 7 - i };
+                self.bit((val >> shift) & 0x01 == 0x01);
+            }
+            self.bit(true)
+        }
+
+        fn recv(&mut self, lsb_first: bool, ack: bool) -> u8 {
+            let mut val = 0;
+            for i in 0..8 {
+                let shift = if lsb_first { i } else { 7 - i };
+                val |= u8::from(self.bit(true)) << shift;
+            }
+            self.bit(!ack);
+            val
+        }
+    }
+
+    #[test]
+    fn eeprom_24c02() {
+        let mut bus = Bus(Eeprom::new(EepromKind::Eeprom24C02));
+        bus.start();
+        assert!(!bus.send(0xA0, false), "device ack");
+        assert!(!bus.send(0x10, false), "address ack");
+        assert!(!bus.send(0x12, false), "data ack");
+        assert!(!bus.send(0x34, false), "data ack");
+        bus.stop();
+        assert_eq!(bus.0.data()[0x10..0x12], [0x12, 0x34]);
+
+        // Random read: set the address with a dummy write, then restart as a read
+        bus.start();
+        bus.send(0xA0, false);
+        bus.send(0x10, false);
+        bus.start();
+        assert!(!bus.send(0xA1, false));
+        assert_eq!(bus.recv(false, true), 0x12);
+        assert_eq!(bus.recv(false, false), 0x34);
+        bus.stop();
+
+        // Other devices are ignored
+        bus.start();
+        assert!(bus.send(0x50, false), "no ack");
+        bus.stop();
+    }
+
+    #[test]
+    fn eeprom_24c01() {
+        let mut bus = Bus(Eeprom::new(EepromKind::Eeprom24C01));
+        bus.start();
+        // 7-bit address followed by the write bit
+        assert!(!bus.send(0x05, true), "address ack");
+        assert!(!bus.send(0xAB, true), "data ack");
+        bus.stop();
+        assert_eq!(bus.0.data()[0x05], 0xAB);
+
+        bus.start();
+        assert!(!bus.send(0x85, true));
+        assert_eq!(bus.recv(true, false), 0xAB);
+        bus.stop();
+
+        let mut data = vec![0x00; 128];
+        data[0x7F] = 0x42;
+        bus.0.load_data(&data);
+        bus.start();
+        bus.send(0xFF, true);
+        assert_eq!(bus.recv(true, false), 0x42);
+        bus.stop();
+    }
+}
@@ -0,0 +1,196 @@
+//! `Flash`
+//!
+//! SST39SF040 flash memory used as self-writable PRG-ROM by homebrew boards, programmed through
+//! JEDEC command sequences.
+//!
+//! <https://www.nesdev.org/wiki/UNROM_512#Flash_save>
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[must_use]
+enum Mode {
+    #[default]
+    Read,
+    Unlock1,
+    Unlock2,
+    Program,
+    Erase,
+    EraseUnlock1,
+    EraseUnlock2,
+    SoftwareId,
+}
+
+/// Flash memory, stored as the original ROM overlaid with any modified sectors.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Flash {
+    #[serde(skip)] // Excluded from save states, see `Flash::load_rom_from`
+    rom: Vec<u8>,
+    mode: Mode,
+    sectors: BTreeMap<u8, Vec<u8>>, // Modified sectors keyed by sector index
+}
+
+impl Flash {
+    const SECTOR_SIZE: usize = 4 * 1024;
+    const MANUFACTURER_ID: u8 = 0xBF;
+    const DEVICE_ID: u8 = 0xB7;
+
+    pub const fn new(rom: Vec<u8>) -> Self {
+        Self {
+            rom,
+            mode: Mode::Read,
+            sectors: BTreeMap::new(),
+        }
+    }
+
+    /// Moves the original ROM from another `Flash`. ROM is not serialized, so restoring a save
+    /// state requires reattaching the ROM of the currently loaded cartridge.
+    #[inline]
+    pub fn load_rom_from(&mut self, other: &mut Self) {
+        self.rom = std::mem::take(&mut other.rom);
+    }
+
+    #[must_use]
+    pub fn read(&self, addr: usize) -> u8 {
+        if self.mode == Mode::SoftwareId {
+            if addr & 0x01 == 0x00 {
+                Self::MANUFACTURER_ID
+            } else {
+                Self::DEVICE_ID
+            }
+        } else {
+            match self.sectors.get(&Self::sector(addr)) {
+                Some(sector) => sector[addr % Self::SECTOR_SIZE],
+                None => self.rom[addr],
+            }
+        }
+    }
+
+    pub fn write(&mut self, addr: usize, val: u8) {
+        // Commands only decode A0-A14
+        self.mode = match (self.mode, addr & 0x7FFF, val) {
+            (Mode::Program, ..) => {
+                // Programming can only clear bits
+                self.sector_mut(addr)[addr % Self::SECTOR_SIZE] &= val;
+                Mode::Read
+            }
+            (Mode::SoftwareId, _, 0xF0) => Mode::Read,
+            (Mode::SoftwareId, ..) => Mode::SoftwareId,
+            (Mode::Read, 0x5555, 0xAA) => Mode::Unlock1,
+            (Mode::Unlock1, 0x2AAA, 0x55) => Mode::Unlock2,
+            (Mode::Unlock2, 0x5555, 0xA0) => Mode::Program,
+            (Mode::Unlock2, 0x5555, 0x80) => Mode::Erase,
+            (Mode::Unlock2, 0x5555, 0x90) => Mode::SoftwareId,
+            (Mode::Erase, 0x5555, 0xAA) => Mode::EraseUnlock1,
+            (Mode::EraseUnlock1, 0x2AAA, 0x55) => Mode::EraseUnlock2,
+            (Mode::EraseUnlock2, _, 0x30) => {
+                self.sector_mut(addr).fill(0xFF);
+                Mode::Read
+            }
+            (Mode::EraseUnlock2, 0x5555, 0x10) => {
+                for addr in (0..self.rom.len()).step_by(Self::SECTOR_SIZE) {
+                    self.sector_mut(addr).fill(0xFF);
+                }
+                Mode::Read
+            }
+            _ => Mode::Read,
+        };
+    }
+
+    /// Sectors changed since power on, stored as a sector index byte followed by the sector
+    /// contents.
+    #[must_use]
+    pub fn modified_sectors(&self) -> Vec<u8> {
+        let mut modified_sectors = Vec::with_capacity(self.sectors.len() * (Self::SECTOR_SIZE + 1));
+        for (index, sector) in &self.sectors {
+            modified_sectors.push(*index);
+            modified_sectors.extend_from_slice(sector);
+        }
+        modified_sectors
+    }
+
+    pub fn load_modified_sectors(&mut self, data: &[u8]) {
+        for sector in data.chunks_exact(Self::SECTOR_SIZE + 1) {
+            let start = usize::from(sector[0]) * Self::SECTOR_SIZE;
+            if start < self.rom.len() {
+                self.sectors.insert(sector[0], sector[1..].to_vec());
+            }
+        }
+    }
+
+    // Flash chips are at most 512K, so sector indexes fit in a byte
+    const fn sector(addr: usize) -> u8 {
+        (addr / Self::SECTOR_SIZE) as u8
+    }
+
+    /// Returns a modified sector, copying it from ROM on first write.
+    fn sector_mut(&mut self, addr: usize) -> &mut [u8] {
+        let start = addr & !(Self::SECTOR_SIZE - 1);
+        let rom = &self.rom;
+        self.sectors
+            .entry(Self::sector(addr))
+            .or_insert_with(|| rom[start..start + Self::SECTOR_SIZE].to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(flash: &mut Flash, cmd: u8) {
+        flash.write(0x5555, 0xAA);
+        flash.write(0x2AAA, 0x55);
+        flash.write(0x5555, cmd);
+    }
+
+    #[test]
+    fn program_and_erase() {
+        let mut flash = Flash::new(vec![0xFF; 0x80000]);
+        command(&mut flash, 0xA0);
+        flash.write(0x12345, 0x5A);
+        assert_eq!(flash.read(0x12345), 0x5A);
+
+        // Writes without a command are ignored
+        flash.write(0x12346, 0x00);
+        assert_eq!(flash.read(0x12346), 0xFF);
+
+        // Programming can't set bits
+        command(&mut flash, 0xA0);
+        flash.write(0x12345, 0xF0);
+        assert_eq!(flash.read(0x12345), 0x50);
+
+        command(&mut flash, 0x80);
+        flash.write(0x5555, 0xAA);
+        flash.write(0x2AAA, 0x55);
+        flash.write(0x12000, 0x30);
+        assert_eq!(flash.read(0x12345), 0xFF);
+    }
+
+    #[test]
+    fn software_id() {
+        let mut flash = Flash::new(vec![0x00; 0x80000]);
+        command(&mut flash, 0x90);
+        assert_eq!(flash.read(0x0000), 0xBF);
+        assert_eq!(flash.read(0x0001), 0xB7);
+        flash.write(0x0000, 0xF0);
+        assert_eq!(flash.read(0x0001), 0x00);
+    }
+
+    #[test]
+    fn modified_sectors() {
+        let mut flash = Flash::new(vec![0xFF; 0x80000]);
+        assert!(flash.modified_sectors().is_empty());
+        command(&mut flash, 0xA0);
+        flash.write(0x7F001, 0x12);
+        let sectors = flash.modified_sectors();
+        assert_eq!(sectors.len(), 0x1001);
+        assert_eq!(sectors[..3], [0x7F, 0xFF, 0x12]);
+
+        let mut flash = Flash::new(vec![0xFF; 0x80000]);
+        flash.load_modified_sectors(&sectors);
+        assert_eq!(flash.read(0x7F001), 0x12);
+        assert_eq!(flash.modified_sectors(), sectors);
+    }
+}
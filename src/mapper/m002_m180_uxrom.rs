@@ -0,0 +1,126 @@
+//! `UxROM` (Mapper 002)
+//! `UNROM` reverse (Mapper 180)
+//!
+//! <https://wiki.nesdev.com/w/index.php/UxROM>
+//! <https://wiki.nesdev.org/w/index.php?title=INES_Mapper_180>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[must_use]
+pub enum UxromRevision {
+    /// `UxROM` - Switchable bank at $8000-$BFFF with the last bank fixed at $C000-$FFFF.
+    Uxrom,
+    /// `UNROM` wired with an AND gate instead of an OR gate - The first bank fixed at
+    /// $8000-$BFFF with a switchable bank at $C000-$FFFF.
+    UnromReverse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Uxrom {
+    revision: UxromRevision,
+    mirroring: Mirroring,
+    prg_rom_banks: MemBanks,
+}
+
+impl Uxrom {
+    const PRG_ROM_WINDOW: usize = 16 * 1024;
+    const CHR_RAM_SIZE: usize = 8 * 1024;
+
+    pub fn load(cart: &mut Cart, revision: UxromRevision) -> Mapper {
+        if !cart.has_chr() {
+            cart.add_chr_ram(Self::CHR_RAM_SIZE);
+        };
+        let mut uxrom = Self {
+            revision,
+            mirroring: cart.mirroring(),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_ROM_WINDOW),
+        };
+        if revision == UxromRevision::Uxrom {
+            let last_bank = uxrom.prg_rom_banks.last();
+            uxrom.prg_rom_banks.set(1, last_bank);
+        }
+        uxrom.into()
+    }
+}
+
+impl MemMap for Uxrom {
+    // UxROM
+    // PPU $0000..=$1FFF 8K Fixed CHR-ROM/CHR-RAM Bank
+    // CPU $8000..=$BFFF 16K PRG-ROM Bank Switchable
+    // CPU $C000..=$FFFF 16K PRG-ROM Fixed to Last Bank
+
+    // UNROM reverse
+    // PPU $0000..=$1FFF 8K Fixed CHR-ROM/CHR-RAM Bank
+    // CPU $8000..=$BFFF 16K PRG-ROM Fixed to First Bank
+    // CPU $C000..=$FFFF 16K PRG-ROM Bank Switchable
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(addr.into()),
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr {
+            0x0000..=0x1FFF => MappedWrite::Chr(addr.into(), val),
+            0x8000..=0xFFFF => {
+                let slot = match self.revision {
+                    UxromRevision::Uxrom => 0,
+                    UxromRevision::UnromReverse => 1,
+                };
+                self.prg_rom_banks.set(slot, val.into());
+                MappedWrite::None
+            }
+            _ => MappedWrite::None,
+        }
+    }
+}
+
+impl Mapped for Uxrom {
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+}
+
+impl Clock for Uxrom {}
+impl Regional for Uxrom {}
+impl Reset for Uxrom {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_mapper;
+
+    #[test]
+    fn unrom_reverse_banks() {
+        let (cart, mut unrom): (_, Uxrom) = load_mapper(0x20000, 0, |cart| {
+            Uxrom::load(cart, UxromRevision::UnromReverse)
+        });
+        assert_eq!(cart.chr_ram.len(), 0x2000);
+
+        let _ = unrom.map_write(0x8000, 0x05);
+        assert_eq!(unrom.map_peek(0x8001), MappedRead::PrgRom(0x00001));
+        assert_eq!(unrom.map_peek(0xC001), MappedRead::PrgRom(0x14001));
+
+        let _ = unrom.map_write(0xFFFF, 0x07);
+        assert_eq!(unrom.map_peek(0xBFFF), MappedRead::PrgRom(0x03FFF));
+        assert_eq!(unrom.map_peek(0xFFFF), MappedRead::PrgRom(0x1FFFF));
+    }
+}
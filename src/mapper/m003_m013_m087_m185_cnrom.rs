@@ -0,0 +1,225 @@
+//! `CNROM` (Mapper 003)
+//! `CPROM` (Mapper 013)
+//! `J87` (Mapper 087)
+//! `CNROM` with copy protection (Mapper 185)
+//!
+//! <https://wiki.nesdev.com/w/index.php/CNROM>
+//! <https://wiki.nesdev.com/w/index.php/INES_Mapper_003>
+//! <https://wiki.nesdev.org/w/index.php?title=CPROM>
+//! <https://wiki.nesdev.org/w/index.php?title=INES_Mapper_087>
+//! <https://wiki.nesdev.org/w/index.php?title=INES_Mapper_185>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[must_use]
+pub enum CnromRevision {
+    /// `CNROM` - 8K CHR-ROM bank select at $8000-$FFFF.
+    Cnrom,
+    /// `CPROM` - 4K CHR-RAM bank select for $1000-$1FFF at $8000-$FFFF.
+    Cprom,
+    /// `J87` - Jaleco, Konami and Taito boards with an 8K CHR-ROM bank select at $6000-$7FFF.
+    J87,
+    /// `CNROM` boards with diodes on the CHR-ROM enable lines instead of a CHR bank select. Games
+    /// check that CHR-ROM reads fail with the wrong value latched.
+    ///
+    /// `chr_key` is the value of the low two latch bits that enables CHR-ROM, set by NES 2.0
+    /// submappers 4-7. Without it, CHR-ROM is enabled by any value with low bits set other than
+    /// $13.
+    CopyProtect { chr_key: Option<u8> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Cnrom {
+    revision: CnromRevision,
+    mirroring: Mirroring,
+    chr_banks: MemBanks,
+    chr_enabled: bool,
+    mirror_prg_rom: bool,
+}
+
+impl Cnrom {
+    const CHR_WINDOW: usize = 8 * 1024;
+    const CPROM_CHR_WINDOW: usize = 4 * 1024;
+    const CPROM_CHR_RAM_SIZE: usize = 16 * 1024;
+
+    const DISABLED_CHR: u8 = 0xFF;
+
+    pub fn load(cart: &mut Cart, revision: CnromRevision) -> Mapper {
+        let chr_window = if revision == CnromRevision::Cprom {
+            if !cart.has_chr() {
+                cart.add_chr_ram(Self::CPROM_CHR_RAM_SIZE);
+            }
+            Self::CPROM_CHR_WINDOW
+        } else {
+            Self::CHR_WINDOW
+        };
+        let cnrom = Self {
+            revision,
+            mirroring: cart.mirroring(),
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_len(), chr_window),
+            chr_enabled: true,
+            mirror_prg_rom: cart.prg_rom.len() <= 0x4000,
+        };
+        cnrom.into()
+    }
+}
+
+impl MemMap for Cnrom {
+    // CNROM/J87
+    // PPU $0000..=$1FFF 8K CHR-ROM Banks Switchable
+    // CPU $8000..=$BFFF 16K PRG-ROM Bank Fixed
+    // CPU $C000..=$FFFF 16K PRG-ROM Bank Fixed or Bank 1 Mirror if only 16 KB PRG-ROM
+
+    // CPROM
+    // PPU $0000..=$0FFF 4K CHR-RAM Bank Fixed to First Bank
+    // PPU $1000..=$1FFF 4K CHR-RAM Bank Switchable
+    // CPU $8000..=$FFFF 32K PRG-ROM Bank Fixed
+
+    // CNROM with copy protection
+    // PPU $0000..=$1FFF 8K CHR-ROM Bank Fixed, or disabled
+    // CPU $8000..=$BFFF 16K PRG-ROM Bank Fixed
+    // CPU $C000..=$FFFF 16K PRG-ROM Bank Fixed or Bank 1 Mirror if only 16 KB PRG-ROM
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF if self.chr_enabled => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x0000..=0x1FFF => MappedRead::Data(Self::DISABLED_CHR),
+            0x8000..=0xBFFF => MappedRead::PrgRom((addr & 0x3FFF).into()),
+            0xC000..=0xFFFF => {
+                let mirror = if self.mirror_prg_rom { 0x3FFF } else { 0x7FFF };
+                MappedRead::PrgRom((addr & mirror).into())
+            }
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match (self.revision, addr) {
+            (_, 0x0000..=0x1FFF) => {
+                return MappedWrite::Chr(self.chr_banks.translate(addr), val);
+            }
+            (CnromRevision::Cnrom, 0x8000..=0xFFFF) => self.chr_banks.set(0, val.into()),
+            (CnromRevision::Cprom, 0x8000..=0xFFFF) => self.chr_banks.set(1, (val & 0x03).into()),
+            (CnromRevision::J87, 0x6000..=0x7FFF) => {
+                // The two CHR bank bits are wired in reverse order
+                let bank = ((val & 0x01) << 1) | ((val & 0x02) >> 1);
+                self.chr_banks.set(0, bank.into());
+            }
+            (CnromRevision::CopyProtect { chr_key }, 0x8000..=0xFFFF) => {
+                self.chr_enabled = match chr_key {
+                    Some(key) => val & 0x03 == key,
+                    None => val & 0x0F != 0x00 && val != 0x13,
+                };
+            }
+            _ => (),
+        }
+        MappedWrite::None
+    }
+}
+
+impl Mapped for Cnrom {
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+}
+
+impl Clock for Cnrom {}
+impl Regional for Cnrom {}
+impl Reset for Cnrom {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_mapper;
+
+    fn load(prg_rom_len: usize, chr_rom_len: usize, revision: CnromRevision) -> (Cart, Cnrom) {
+        load_mapper(prg_rom_len, chr_rom_len, |cart| Cnrom::load(cart, revision))
+    }
+
+    #[test]
+    fn cprom_banks() {
+        let (cart, mut cprom) = load(0x8000, 0, CnromRevision::Cprom);
+        assert_eq!(cart.chr_ram.len(), 0x4000);
+        assert_eq!(cprom.map_peek(0xC001), MappedRead::PrgRom(0x4001));
+
+        let _ = cprom.map_write(0x8000, 0xFE);
+        assert_eq!(cprom.map_peek(0x0001), MappedRead::Chr(0x0001));
+        assert_eq!(cprom.map_peek(0x1001), MappedRead::Chr(0x2001));
+        assert_eq!(
+            cprom.map_write(0x1FFF, 0x01),
+            MappedWrite::Chr(0x2FFF, 0x01)
+        );
+
+        let _ = cprom.map_write(0xFFFF, 0x03);
+        assert_eq!(cprom.map_peek(0x1001), MappedRead::Chr(0x3001));
+        assert_eq!(cprom.map_peek(0x0FFF), MappedRead::Chr(0x0FFF));
+    }
+
+    #[test]
+    fn j87_chr_bank_bits_swapped() {
+        let (_, mut j87) = load(0x8000, 0x8000, CnromRevision::J87);
+        for (val, bank) in [(0x00, 0), (0x01, 2), (0x02, 1), (0xFF, 3)] {
+            let _ = j87.map_write(0x6000, val);
+            assert_eq!(
+                j87.map_peek(0x0001),
+                MappedRead::Chr(bank * 0x2000 + 0x0001),
+                "${val:02X}"
+            );
+        }
+        let _ = j87.map_write(0x8000, 0x01);
+        assert_eq!(j87.map_peek(0x0001), MappedRead::Chr(0x6001));
+    }
+
+    #[test]
+    fn prg_rom_mirror() {
+        let (_, cnrom) = load(0x8000, 0x8000, CnromRevision::J87);
+        assert_eq!(cnrom.map_peek(0xC001), MappedRead::PrgRom(0x4001));
+        let (_, cnrom) = load(0x4000, 0x8000, CnromRevision::J87);
+        assert_eq!(cnrom.map_peek(0xC001), MappedRead::PrgRom(0x0001));
+    }
+
+    #[test]
+    fn copy_protect_chr_enable_heuristic() {
+        let revision = CnromRevision::CopyProtect { chr_key: None };
+        let (_, mut cnrom) = load(0x4000, 0x2000, revision);
+        assert_eq!(cnrom.map_peek(0x0010), MappedRead::Chr(0x0010));
+        assert_eq!(cnrom.map_peek(0xC010), MappedRead::PrgRom(0x0010));
+        for (val, enabled) in [(0x00, false), (0x01, true), (0x13, false), (0x20, false)] {
+            let _ = cnrom.map_write(0x8000, val);
+            assert_eq!(
+                cnrom.map_peek(0x0010) != MappedRead::Data(0xFF),
+                enabled,
+                "${val:02X}"
+            );
+        }
+    }
+
+    #[test]
+    fn copy_protect_chr_enable_submapper() {
+        let revision = CnromRevision::CopyProtect {
+            chr_key: Some(0x02),
+        };
+        let (_, mut cnrom) = load(0x4000, 0x2000, revision);
+        let _ = cnrom.map_write(0x8000, 0x13);
+        assert_eq!(cnrom.map_peek(0x0010), MappedRead::Data(0xFF));
+        let _ = cnrom.map_write(0x8000, 0x22);
+        assert_eq!(cnrom.map_peek(0x0010), MappedRead::Chr(0x0010));
+        let _ = cnrom.map_write(0x8000, 0x01);
+        assert_eq!(cnrom.map_peek(0x0010), MappedRead::Data(0xFF));
+    }
+}
@@ -1,7 +1,13 @@
 //! `TxROM`/`MMC3` (Mapper 004)
+//! `TxSROM` (Mapper 118)
+//! `TQROM` (Mapper 119)
+//! `DxROM`/`Namco 108` (Mapper 206)
 //!
 //! <https://wiki.nesdev.com/w/index.php/TxROM>
 //! <https://wiki.nesdev.com/w/index.php/MMC3>
+//! <https://wiki.nesdev.com/w/index.php/INES_Mapper_118>
+//! <https://wiki.nesdev.com/w/index.php/INES_Mapper_119>
+//! <https://wiki.nesdev.com/w/index.php/INES_Mapper_206>
 
 use crate::{
     cart::Cart,
@@ -35,6 +41,22 @@ pub enum Mmc3Revision {
     Acc,
 }
 
+/// Boards built around the `MMC3` bank registers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[must_use]
+pub enum TxromBoard {
+    /// Standard `MMC3` wiring.
+    Txrom,
+    /// `TKSROM`/`TLSROM` - bit 7 of the CHR banks for PPU $0000-$0FFF selects the CIRAM page
+    /// of the matching nametable instead of mirroring control.
+    TxSrom,
+    /// `TQROM` - bit 6 of a CHR bank selects 8K CHR-RAM instead of 64K CHR-ROM.
+    Tqrom,
+    /// `Namco 108` - the `MMC3` predecessor without IRQs, mirroring control, PRG-RAM or bank
+    /// modes.
+    Namco108,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[must_use]
 struct TxRegs {
@@ -68,7 +90,10 @@ pub struct Txrom {
     mirroring: Mirroring,
     irq_pending: bool,
     revision: Mmc3Revision,
+    board: TxromBoard,
     chr_banks: MemBanks,
+    chr_ram_banks: MemBanks,
+    chr_ram_slots: u8,
     prg_ram_banks: MemBanks,
     prg_rom_banks: MemBanks,
 }
@@ -83,13 +108,16 @@ impl Txrom {
 
     const PRG_MODE_MASK: u8 = 0x40; // Bit 6 of bank select
     const CHR_INVERSION_MASK: u8 = 0x80; // Bit 7 of bank select
+    const TQROM_CHR_RAM_MASK: u8 = 0x40; // Bit 6 of CHR banks on TQROM
 
-    pub fn load(cart: &mut Cart) -> Mapper {
-        cart.add_prg_ram(Self::PRG_RAM_SIZE);
+    pub fn load(cart: &mut Cart, board: TxromBoard) -> Mapper {
+        if board != TxromBoard::Namco108 {
+            cart.add_prg_ram(Self::PRG_RAM_SIZE);
+        }
         if cart.mirroring() == Mirroring::FourScreen {
             cart.add_ex_ram(Self::FOUR_SCREEN_RAM_SIZE);
         }
-        if !cart.has_chr() {
+        if !cart.has_chr() || (board == TxromBoard::Tqrom && cart.chr_ram.is_empty()) {
             cart.add_chr_ram(Self::CHR_RAM_SIZE);
         };
         let mut txrom = Self {
@@ -97,7 +125,10 @@ impl Txrom {
             mirroring: cart.mirroring(),
             irq_pending: false,
             revision: Mmc3Revision::BC, // TODO compare to known games
+            board,
             chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_len(), Self::CHR_WINDOW),
+            chr_ram_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_ram.len(), Self::CHR_WINDOW),
+            chr_ram_slots: 0x00,
             prg_ram_banks: MemBanks::new(0x6000, 0x7FFF, cart.prg_ram.len(), Self::PRG_WINDOW),
             prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_WINDOW),
         };
@@ -129,26 +160,41 @@ impl Txrom {
 
         // 1: two 2K banks at $1000-$1FFF, four 1 KB banks at $0000-$0FFF
         // 0: two 2K banks at $0000-$0FFF, four 1 KB banks at $1000-$1FFF
+        self.chr_ram_slots = 0x00;
+        for slot in 0..8 {
+            let bank = self.chr_bank(slot);
+            if self.board == TxromBoard::Tqrom && bank & Self::TQROM_CHR_RAM_MASK != 0 {
+                self.chr_ram_banks.set(slot, bank as usize);
+                self.chr_ram_slots |= 1 << slot;
+            } else {
+                self.chr_banks.set(slot, bank as usize);
+            }
+        }
+    }
+
+    /// Returns the CHR bank register value mapped to a 1K slot of PPU $0000-$1FFF.
+    const fn chr_bank(&self, slot: usize) -> u8 {
         let chr = self.regs.bank_values;
-        if self.regs.bank_select & Self::CHR_INVERSION_MASK == Self::CHR_INVERSION_MASK {
-            self.chr_banks.set(0, chr[2] as usize);
-            self.chr_banks.set(1, chr[3] as usize);
-            self.chr_banks.set(2, chr[4] as usize);
-            self.chr_banks.set(3, chr[5] as usize);
-            self.chr_banks.set_range(4, 5, (chr[0] & 0xFE) as usize);
-            self.chr_banks.set_range(6, 7, (chr[1] & 0xFE) as usize);
+        let slot = if self.regs.bank_select & Self::CHR_INVERSION_MASK == Self::CHR_INVERSION_MASK {
+            slot ^ 0x04
         } else {
-            self.chr_banks.set_range(0, 1, (chr[0] & 0xFE) as usize);
-            self.chr_banks.set_range(2, 3, (chr[1] & 0xFE) as usize);
-            self.chr_banks.set(4, chr[2] as usize);
-            self.chr_banks.set(5, chr[3] as usize);
-            self.chr_banks.set(6, chr[4] as usize);
-            self.chr_banks.set(7, chr[5] as usize);
+            slot
+        };
+        match slot {
+            0..=3 => (chr[slot >> 1] & 0xFE) | (slot & 0x01) as u8,
+            _ => chr[slot - 2],
         }
     }
 
+    /// Returns the CIRAM address for a nametable address on `TxSROM`, selected by bit 7 of the
+    /// CHR bank mapped to the matching 1K of PPU $0000-$0FFF.
+    const fn txsrom_ciram_addr(&self, addr: u16) -> usize {
+        let page = (self.chr_bank(((addr >> 10) & 0x03) as usize) >> 7) as usize;
+        (page << 10) | (addr & 0x03FF) as usize
+    }
+
     fn clock_irq(&mut self, addr: u16) {
-        if addr < 0x2000 {
+        if addr < 0x2000 && self.board != TxromBoard::Namco108 {
             let next_clock = (addr >> 12) & 1;
             let (last, next) = if self.revision == Mmc3Revision::Acc {
                 (1, 0)
@@ -227,10 +273,17 @@ impl MemMap for Txrom {
 
     fn map_peek(&self, addr: u16) -> MappedRead {
         match addr {
+            0x0000..=0x1FFF if self.chr_ram_slots & (1 << (addr >> 10)) != 0 => {
+                MappedRead::ChrRam(self.chr_ram_banks.translate(addr))
+            }
             0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
             0x2000..=0x3EFF if self.mirroring == Mirroring::FourScreen => {
-                MappedRead::ExRam((addr & 0x1FFF) as usize)
+                MappedRead::ExRam((addr & 0x0FFF) as usize)
             }
+            0x2000..=0x3EFF if self.board == TxromBoard::TxSrom => {
+                MappedRead::CIRam(self.txsrom_ciram_addr(addr))
+            }
+            0x6000..=0x7FFF if self.board == TxromBoard::Namco108 => MappedRead::None,
             0x6000..=0x7FFF => MappedRead::PrgRam(self.prg_ram_banks.translate(addr)),
             0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
             _ => MappedRead::None,
@@ -239,11 +292,30 @@ impl MemMap for Txrom {
 
     fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
         match addr {
+            0x0000..=0x1FFF if self.chr_ram_slots & (1 << (addr >> 10)) != 0 => {
+                MappedWrite::ChrRam(self.chr_ram_banks.translate(addr), val)
+            }
             0x0000..=0x1FFF => MappedWrite::Chr(self.chr_banks.translate(addr), val),
             0x2000..=0x3EFF if self.mirroring == Mirroring::FourScreen => {
-                MappedWrite::ExRam((addr & 0x1FFF) as usize, val)
+                MappedWrite::ExRam((addr & 0x0FFF) as usize, val)
             }
+            0x2000..=0x3EFF if self.board == TxromBoard::TxSrom => {
+                MappedWrite::CIRam(self.txsrom_ciram_addr(addr), val)
+            }
+            0x6000..=0x7FFF if self.board == TxromBoard::Namco108 => MappedWrite::None,
             0x6000..=0x7FFF => MappedWrite::PrgRam(self.prg_ram_banks.translate(addr), val),
+            // Namco 108 only decodes the bank select and bank data registers, without bank modes
+            0x8000..=0x9FFF if self.board == TxromBoard::Namco108 => {
+                if addr & 0x01 == 0x00 {
+                    self.regs.bank_select = val & 0x07;
+                } else {
+                    let bank = self.regs.bank_select & 0x07;
+                    self.regs.bank_values[bank as usize] = val;
+                }
+                self.update_banks();
+                MappedWrite::None
+            }
+            0xA000..=0xFFFF if self.board == TxromBoard::Namco108 => MappedWrite::None,
             0x8000..=0xFFFF => {
                 //  7654 3210
                 // `CPMx xRRR`
@@ -278,7 +350,10 @@ impl MemMap for Txrom {
                         self.update_banks();
                     }
                     0xA000 => {
-                        if self.mirroring != Mirroring::FourScreen {
+                        // TxSROM nametables are selected by CHR banks instead
+                        if self.mirroring != Mirroring::FourScreen
+                            && self.board != TxromBoard::TxSrom
+                        {
                             self.mirroring = match val & 0x01 {
                                 0 => Mirroring::Vertical,
                                 1 => Mirroring::Horizontal,
@@ -320,7 +395,88 @@ impl Regional for Txrom {}
 
 #[cfg(test)]
 mod tests {
-    use crate::test_roms;
+    use super::*;
+    use crate::{common::tests::load_mapper, test_roms};
+
+    fn load(board: TxromBoard, chr_len: usize) -> (Cart, Txrom) {
+        load_mapper(0x20000, chr_len, |cart| Txrom::load(cart, board))
+    }
+
+    #[test]
+    fn txsrom_nametables() {
+        let (_, mut txrom) = load(TxromBoard::TxSrom, 0x20000);
+        // R0 selects the CIRAM page for nametables 0-1, R1 for nametables 2-3
+        let _ = txrom.map_write(0x8000, 0x00);
+        let _ = txrom.map_write(0x8001, 0x82);
+        let _ = txrom.map_write(0x8000, 0x01);
+        let _ = txrom.map_write(0x8001, 0x04);
+        assert_eq!(txrom.map_peek(0x2005), MappedRead::CIRam(0x0405));
+        assert_eq!(txrom.map_peek(0x2405), MappedRead::CIRam(0x0405));
+        assert_eq!(txrom.map_peek(0x2805), MappedRead::CIRam(0x0005));
+        assert_eq!(txrom.map_peek(0x0400), MappedRead::Chr(0x0C00));
+
+        // Mirroring writes are ignored
+        let _ = txrom.map_write(0xA000, 0x01);
+        assert_eq!(txrom.map_peek(0x2805), MappedRead::CIRam(0x0005));
+
+        // With CHR inversion, R2-R5 select the CIRAM page for each nametable
+        let _ = txrom.map_write(0x8000, 0x85);
+        let _ = txrom.map_write(0x8001, 0x80);
+        assert_eq!(txrom.map_peek(0x2C01), MappedRead::CIRam(0x0401));
+        assert_eq!(txrom.map_peek(0x2001), MappedRead::CIRam(0x0001));
+        assert_eq!(
+            txrom.map_write(0x3C01, 0x11),
+            MappedWrite::CIRam(0x0401, 0x11)
+        );
+    }
+
+    #[test]
+    fn tqrom_chr_ram() {
+        let (cart, mut txrom) = load(TxromBoard::Tqrom, 0x10000);
+        assert_eq!(cart.chr_ram.len(), 0x2000);
+
+        let _ = txrom.map_write(0x8000, 0x02);
+        let _ = txrom.map_write(0x8001, 0x43);
+        let _ = txrom.map_write(0x8000, 0x03);
+        let _ = txrom.map_write(0x8001, 0x05);
+        assert_eq!(txrom.map_peek(0x1010), MappedRead::ChrRam(0x0C10));
+        assert_eq!(
+            txrom.map_write(0x1010, 0x22),
+            MappedWrite::ChrRam(0x0C10, 0x22)
+        );
+        assert_eq!(txrom.map_peek(0x1410), MappedRead::Chr(0x1410));
+
+        let _ = txrom.map_write(0x8000, 0x02);
+        let _ = txrom.map_write(0x8001, 0x3F);
+        assert_eq!(txrom.map_peek(0x1010), MappedRead::Chr(0xFC10));
+    }
+
+    #[test]
+    fn namco108() {
+        let (cart, mut txrom) = load(TxromBoard::Namco108, 0x10000);
+        assert!(cart.prg_ram.is_empty());
+        assert_eq!(txrom.map_peek(0x6000), MappedRead::None);
+
+        // PRG and CHR modes are ignored
+        let _ = txrom.map_write(0x8000, 0xC6);
+        let _ = txrom.map_write(0x8001, 0x02);
+        assert_eq!(txrom.map_peek(0x8000), MappedRead::PrgRom(0x4000));
+        assert_eq!(txrom.map_peek(0xC000), MappedRead::PrgRom(0x1C000));
+        let _ = txrom.map_write(0x9000, 0x00);
+        let _ = txrom.map_write(0x9001, 0x05);
+        assert_eq!(txrom.map_peek(0x0400), MappedRead::Chr(0x1400));
+
+        // Registers are only decoded at $8000-$9FFF
+        let _ = txrom.map_write(0xA000, 0x01);
+        assert_eq!(txrom.mirroring(), Mirroring::Horizontal);
+        let _ = txrom.map_write(0xC000, 0x00);
+        let _ = txrom.map_write(0xE001, 0x00);
+        for _ in 0..4 {
+            txrom.clock_irq(0x0000);
+            txrom.clock_irq(0x1000);
+        }
+        assert!(!txrom.irq_pending());
+    }
 
     test_roms!(
         "test_roms/mapper/m004_txrom",
@@ -5,7 +5,10 @@
 use crate::{
     cart::Cart,
     common::{Clock, Kind, Regional, Reset},
-    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap, Mirroring},
+    mapper::{
+        mmc2_latch::{Mmc2Latch, Mmc2LatchRevision},
+        Mapped, MappedRead, MappedWrite, Mapper, MemMap, Mirroring,
+    },
     mem::MemBanks,
 };
 use serde::{Deserialize, Serialize};
@@ -14,25 +17,12 @@ use serde::{Deserialize, Serialize};
 #[must_use]
 pub struct Pxrom {
     mirroring: Mirroring,
-    // CHR-ROM $FD/0000 bank select ($B000-$BFFF)
-    // CHR-ROM $FE/0000 bank select ($C000-$CFFF)
-    // CHR-ROM $FD/1000 bank select ($D000-$DFFF)
-    // CHR-ROM $FE/1000 bank select ($E000-$EFFF)
-    // 7  bit  0
-    // ---- ----
-    // xxxC CCCC
-    //    | ||||
-    //    +-++++- Select 4K CHR-ROM bank for PPU $0000/$1000-$0FFF/$1FFF
-    //            used when latch 0/1 = $FD/$FE
-    latch: [usize; 2],
-    latch_banks: [u8; 4],
-    chr_banks: MemBanks,
+    latch: Mmc2Latch,
     prg_rom_banks: MemBanks,
 }
 
 impl Pxrom {
     const PRG_WINDOW: usize = 8 * 1024;
-    const CHR_ROM_WINDOW: usize = 4 * 1024;
     const PRG_RAM_SIZE: usize = 8 * 1024;
 
     const MIRRORING_MASK: u8 = 0x01;
@@ -41,9 +31,7 @@ impl Pxrom {
         cart.add_prg_ram(Self::PRG_RAM_SIZE);
         let mut pxrom = Self {
             mirroring: cart.mirroring(),
-            latch: [0x00; 2],
-            latch_banks: [0x00; 4],
-            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_rom.len(), Self::CHR_ROM_WINDOW),
+            latch: Mmc2Latch::new(Mmc2LatchRevision::Mmc2, cart.chr_rom.len()),
             prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_WINDOW),
         };
         let last_bank = pxrom.prg_rom_banks.last();
@@ -52,13 +40,6 @@ impl Pxrom {
         pxrom.prg_rom_banks.set(3, last_bank);
         pxrom.into()
     }
-
-    fn update_banks(&mut self) {
-        let bank0 = self.latch_banks[self.latch[0]] as usize;
-        let bank1 = self.latch_banks[self.latch[1] + 2] as usize;
-        self.chr_banks.set(0, bank0);
-        self.chr_banks.set(1, bank1);
-    }
 }
 
 impl Mapped for Pxrom {
@@ -83,20 +64,13 @@ impl MemMap for Pxrom {
     fn map_read(&mut self, addr: u16) -> MappedRead {
         let val = self.map_peek(addr);
         // Update latch after read
-        match addr {
-            0x0FD8 | 0x0FE8 | 0x1FD8..=0x1FDF | 0x1FE8..=0x1FEF => {
-                let addr = addr as usize;
-                self.latch[addr >> 12] = ((addr >> 4) & 0xFF) - 0xFD;
-                self.update_banks();
-            }
-            _ => (),
-        }
+        self.latch.read(addr);
         val
     }
 
     fn map_peek(&self, addr: u16) -> MappedRead {
         match addr {
-            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x0000..=0x1FFF => MappedRead::Chr(self.latch.translate(addr)),
             0x6000..=0x7FFF => MappedRead::PrgRam((addr & 0x1FFF).into()),
             0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
             _ => MappedRead::None,
@@ -111,8 +85,7 @@ impl MemMap for Pxrom {
                 MappedWrite::None
             }
             0xB000..=0xEFFF => {
-                self.latch_banks[((addr - 0xB000) >> 12) as usize] = val & 0x1F;
-                self.update_banks();
+                self.latch.write_bank(addr, val);
                 MappedWrite::None
             }
             0xF000..=0xFFFF => {
@@ -129,12 +102,35 @@ impl MemMap for Pxrom {
 }
 
 impl Reset for Pxrom {
-    fn reset(&mut self, _kind: Kind) {
-        self.latch = [0x00; 2];
-        self.latch_banks = [0x00; 4];
-        self.update_banks();
+    fn reset(&mut self, kind: Kind) {
+        self.latch.reset(kind);
     }
 }
 
 impl Clock for Pxrom {}
 impl Regional for Pxrom {}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::tests::test_rom_results;
+
+    #[test]
+    fn mmc2_latch() {
+        // See `test_roms/mapper/src/mmc2_latch.s`
+        let results = test_rom_results("test_roms/mapper/m009_pxrom/mmc2_latch.nes", 3);
+        assert_eq!(results[0..4], [3, 13, 14, 15], "prg banks");
+        assert_eq!(results[4..7], [8, 11, 4], "latch 0 trigger timing");
+        assert_eq!(
+            results[7..10],
+            [7, 4, 8],
+            "latch 0 only triggers on $0FD8 and $0FE8"
+        );
+        assert_eq!(results[10..13], [16, 19, 12], "latch 1 trigger range");
+        assert_eq!(results[13], 20, "bank write to the selected latch");
+        assert_eq!(
+            results[14..],
+            [0xC3, 0xB2, 0xC3, 0xB2, 0xB2, 0xB2, 0xC3, 0xC3],
+            "vertical, then horizontal mirroring"
+        );
+    }
+}
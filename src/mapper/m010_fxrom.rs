@@ -0,0 +1,154 @@
+//! `FxROM`/`MMC4` (Mapper 010)
+//!
+//! <https://www.nesdev.org/wiki/MMC4>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Kind, Regional, Reset},
+    mapper::{
+        mmc2_latch::{Mmc2Latch, Mmc2LatchRevision},
+        Mapped, MappedRead, MappedWrite, Mapper, MemMap, Mirroring,
+    },
+    mem::MemBanks,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Fxrom {
+    mirroring: Mirroring,
+    latch: Mmc2Latch,
+    prg_rom_banks: MemBanks,
+}
+
+impl Fxrom {
+    const PRG_WINDOW: usize = 16 * 1024;
+    const PRG_RAM_SIZE: usize = 8 * 1024;
+
+    const MIRRORING_MASK: u8 = 0x01;
+
+    pub fn load(cart: &mut Cart) -> Mapper {
+        if !cart.has_prg_ram() {
+            cart.add_prg_ram(Self::PRG_RAM_SIZE);
+        }
+        let mut fxrom = Self {
+            mirroring: cart.mirroring(),
+            latch: Mmc2Latch::new(Mmc2LatchRevision::Mmc4, cart.chr_rom.len()),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_WINDOW),
+        };
+        let last_bank = fxrom.prg_rom_banks.last();
+        fxrom.prg_rom_banks.set(1, last_bank);
+        fxrom.into()
+    }
+}
+
+impl Mapped for Fxrom {
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+}
+
+impl MemMap for Fxrom {
+    // PPU $0000..=$0FFF Two 4K switchable CHR-ROM banks
+    // PPU $1000..=$1FFF Two 4K switchable CHR-ROM banks
+    // CPU $6000..=$7FFF 8K PRG-RAM bank, battery-backed
+    // CPU $8000..=$BFFF 16K switchable PRG-ROM bank
+    // CPU $C000..=$FFFF 16K PRG-ROM bank, fixed to the last bank
+
+    fn map_read(&mut self, addr: u16) -> MappedRead {
+        let val = self.map_peek(addr);
+        // Update latch after read
+        self.latch.read(addr);
+        val
+    }
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.latch.translate(addr)),
+            0x6000..=0x7FFF => MappedRead::PrgRam((addr & 0x1FFF).into()),
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr {
+            0x6000..=0x7FFF => MappedWrite::PrgRam((addr & 0x1FFF).into(), val),
+            0xA000..=0xAFFF => {
+                self.prg_rom_banks.set(0, (val & 0x0F).into());
+                MappedWrite::None
+            }
+            0xB000..=0xEFFF => {
+                self.latch.write_bank(addr, val);
+                MappedWrite::None
+            }
+            0xF000..=0xFFFF => {
+                self.mirroring = match val & Self::MIRRORING_MASK {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    _ => unreachable!("impossible mirroring mode"),
+                };
+                MappedWrite::None
+            }
+            _ => MappedWrite::None,
+        }
+    }
+}
+
+impl Reset for Fxrom {
+    fn reset(&mut self, kind: Kind) {
+        self.latch.reset(kind);
+    }
+}
+
+impl Clock for Fxrom {}
+impl Regional for Fxrom {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::{load_mapper, test_rom_results};
+
+    #[test]
+    fn banks() {
+        let (cart, mut fxrom): (_, Fxrom) = load_mapper(0x20000, 0x20000, Fxrom::load);
+        assert_eq!(cart.prg_ram.len(), 0x2000);
+        assert!(matches!(
+            fxrom.map_peek(0xC000),
+            MappedRead::PrgRom(0x1C000)
+        ));
+        let _ = fxrom.map_write(0xA000, 0x03);
+        assert!(matches!(fxrom.map_peek(0x8001), MappedRead::PrgRom(0xC001)));
+
+        let _ = fxrom.map_write(0xB000, 0x01);
+        let _ = fxrom.map_write(0xC000, 0x02);
+        assert!(matches!(fxrom.map_read(0x0FEA), MappedRead::Chr(0x1FEA)));
+        assert!(matches!(fxrom.map_read(0x0000), MappedRead::Chr(0x2000)));
+    }
+
+    #[test]
+    fn mmc4_latch() {
+        // See `test_roms/mapper/src/mmc2_latch.s`
+        let results = test_rom_results("test_roms/mapper/m010_fxrom/mmc4_latch.nes", 3);
+        assert_eq!(results[0..4], [6, 7, 14, 15], "prg banks");
+        assert_eq!(results[4..7], [8, 11, 4], "latch 0 trigger timing");
+        assert_eq!(
+            results[7..10],
+            [7, 8, 4],
+            "latch 0 triggers on $0FD8-$0FDF and $0FE8-$0FEF"
+        );
+        assert_eq!(results[10..13], [16, 19, 12], "latch 1 trigger range");
+        assert_eq!(results[13], 20, "bank write to the selected latch");
+        assert_eq!(
+            results[14..],
+            [0xC3, 0xB2, 0xC3, 0xB2, 0xB2, 0xB2, 0xC3, 0xC3],
+            "vertical, then horizontal mirroring"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! `Color Dreams` (Mapper 011)
+//! `GxROM` (Mapper 066)
+//! `NINA-003`/`NINA-006` (Mapper 079)
+//! `Jaleco JF-11`/`JF-14` (Mapper 140)
+//!
+//! <https://wiki.nesdev.org/w/index.php?title=Color_Dreams>
+//! <https://wiki.nesdev.org/w/index.php?title=GxROM>
+//! <https://wiki.nesdev.org/w/index.php?title=NINA-003-006>
+//! <https://wiki.nesdev.org/w/index.php?title=INES_Mapper_140>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[must_use]
+pub enum GxromRevision {
+    /// `GxROM` - PRG-ROM bank in bits 4-5 and CHR-ROM bank in bits 0-3 at $8000-$FFFF.
+    Gxrom,
+    /// `Color Dreams` - PRG-ROM bank in bits 0-1 and CHR-ROM bank in bits 4-7 at $8000-$FFFF.
+    ColorDreams,
+    /// `NINA-003`/`NINA-006` - PRG-ROM bank in bit 3 and CHR-ROM bank in bits 0-2 at
+    /// $4100-$5FFF.
+    Nina003006,
+    /// `Jaleco JF-11`/`JF-14` - The `GxROM` register at $6000-$7FFF.
+    Jf11Jf14,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Gxrom {
+    revision: GxromRevision,
+    mirroring: Mirroring,
+    chr_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+}
+
+impl Gxrom {
+    const PRG_ROM_WINDOW: usize = 32 * 1024;
+    const CHR_WINDOW: usize = 8 * 1024;
+
+    pub fn load(cart: &mut Cart, revision: GxromRevision) -> Mapper {
+        let gxrom = Self {
+            revision,
+            mirroring: cart.mirroring(),
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_rom.len(), Self::CHR_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_ROM_WINDOW),
+        };
+        gxrom.into()
+    }
+}
+
+impl MemMap for Gxrom {
+    // PPU $0000..=$1FFF 8K CHR-ROM Bank Switchable
+    // CPU $8000..=$FFFF 32K PRG-ROM Bank Switchable
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        let (prg_bank, chr_bank) = match (self.revision, addr) {
+            (GxromRevision::Gxrom, 0x8000..=0xFFFF)
+            | (GxromRevision::Jf11Jf14, 0x6000..=0x7FFF) => ((val & 0x30) >> 4, val & 0x0F),
+            (GxromRevision::ColorDreams, 0x8000..=0xFFFF) => (val & 0x03, (val & 0xF0) >> 4),
+            // Registers are mirrored at $4100-$41FF, $4300-$43FF, ..., $5F00-$5FFF
+            (GxromRevision::Nina003006, 0x4100..=0x5FFF) if addr & 0xE100 == 0x4100 => {
+                ((val & 0x08) >> 3, val & 0x07)
+            }
+            _ => return MappedWrite::None,
+        };
+        self.prg_rom_banks.set(0, prg_bank.into());
+        self.chr_banks.set(0, chr_bank.into());
+        MappedWrite::None
+    }
+}
+
+impl Mapped for Gxrom {
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+}
+
+impl Clock for Gxrom {}
+impl Regional for Gxrom {}
+impl Reset for Gxrom {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_mapper;
+
+    fn load(prg_rom_len: usize, chr_rom_len: usize, revision: GxromRevision) -> Gxrom {
+        let (_, gxrom) = load_mapper(prg_rom_len, chr_rom_len, |cart| Gxrom::load(cart, revision));
+        gxrom
+    }
+
+    #[test]
+    fn color_dreams_banks() {
+        let mut color_dreams = load(0x20000, 0x20000, GxromRevision::ColorDreams);
+        assert_eq!(color_dreams.map_peek(0x8001), MappedRead::PrgRom(0x0001));
+
+        let _ = color_dreams.map_write(0xC000, 0xF3);
+        assert_eq!(color_dreams.map_peek(0x8001), MappedRead::PrgRom(0x18001));
+        assert_eq!(color_dreams.map_peek(0x0001), MappedRead::Chr(0x1E001));
+
+        let _ = color_dreams.map_write(0x8000, 0x21);
+        assert_eq!(color_dreams.map_peek(0xFFFF), MappedRead::PrgRom(0x0FFFF));
+        assert_eq!(color_dreams.map_peek(0x1FFF), MappedRead::Chr(0x05FFF));
+
+        // Writes below $8000 are ignored
+        let _ = color_dreams.map_write(0x6000, 0x00);
+        assert_eq!(color_dreams.map_peek(0x8001), MappedRead::PrgRom(0x08001));
+    }
+
+    #[test]
+    fn nina003_006_banks() {
+        let mut nina = load(0x10000, 0x10000, GxromRevision::Nina003006);
+        let _ = nina.map_write(0x4100, 0x0D);
+        assert_eq!(nina.map_peek(0x8001), MappedRead::PrgRom(0x8001));
+        assert_eq!(nina.map_peek(0x0001), MappedRead::Chr(0xA001));
+
+        // Mirrored registers
+        let _ = nina.map_write(0x5FFF, 0x03);
+        assert_eq!(nina.map_peek(0x8001), MappedRead::PrgRom(0x0001));
+        assert_eq!(nina.map_peek(0x0001), MappedRead::Chr(0x6001));
+
+        // Addresses with A8 clear or outside $4100-$5FFF are ignored
+        for addr in [0x4000, 0x4200, 0x5EFF, 0x6100, 0x8100] {
+            let _ = nina.map_write(addr, 0x0F);
+            assert_eq!(
+                nina.map_peek(0x0001),
+                MappedRead::Chr(0x6001),
+                "${addr:04X}"
+            );
+        }
+    }
+
+    #[test]
+    fn jf11_jf14_banks() {
+        let mut jf11 = load(0x20000, 0x20000, GxromRevision::Jf11Jf14);
+        let _ = jf11.map_write(0x6000, 0x2F);
+        assert_eq!(jf11.map_peek(0x8001), MappedRead::PrgRom(0x10001));
+        assert_eq!(jf11.map_peek(0x0001), MappedRead::Chr(0x1E001));
+
+        let _ = jf11.map_write(0x7FFF, 0x13);
+        assert_eq!(jf11.map_peek(0xFFFF), MappedRead::PrgRom(0x0FFFF));
+        assert_eq!(jf11.map_peek(0x1FFF), MappedRead::Chr(0x07FFF));
+
+        // Writes to $8000-$FFFF are ignored
+        let _ = jf11.map_write(0x8000, 0x00);
+        assert_eq!(jf11.map_peek(0x0001), MappedRead::Chr(0x06001));
+    }
+}
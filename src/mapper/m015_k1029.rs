@@ -0,0 +1,182 @@
+//! `K-1029`/`K-1030P` 100-in-1 multicart (Mapper 015)
+//!
+//! <https://wiki.nesdev.org/w/index.php?title=INES_Mapper_015>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Kind, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct K1029 {
+    mode: u8,
+    bank: u8,
+    mirroring: Mirroring,
+    prg_rom_banks: MemBanks,
+}
+
+impl K1029 {
+    const PRG_WINDOW: usize = 8 * 1024;
+    const PRG_RAM_SIZE: usize = 8 * 1024;
+    const CHR_RAM_SIZE: usize = 8 * 1024;
+
+    const PRG_BANK_MASK: u8 = 0x3F; // 0b111111
+    const MIRRORING_MASK: u8 = 0x40; // 0b1000000
+    const PRG_SUBBANK_MASK: u8 = 0x80; // 0b10000000
+
+    pub fn load(cart: &mut Cart) -> Mapper {
+        cart.add_prg_ram(Self::PRG_RAM_SIZE);
+        if !cart.has_chr() {
+            cart.add_chr_ram(Self::CHR_RAM_SIZE);
+        };
+        let mut k1029 = Self {
+            mode: 0x00,
+            bank: 0x00,
+            mirroring: Mirroring::Vertical,
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_WINDOW),
+        };
+        k1029.update_banks();
+        k1029.into()
+    }
+
+    fn update_banks(&mut self) {
+        // 16K bank in 8K units
+        let bank = usize::from(self.bank & Self::PRG_BANK_MASK) << 1;
+        let banks = match self.mode {
+            // NROM-256: $8000-$FFFF 32K
+            0 => {
+                let bank = bank & !0x03;
+                [bank, bank | 1, bank | 2, bank | 3]
+            }
+            // UNROM: $8000-$BFFF switchable, $C000-$FFFF fixed to the last bank of the 128K block
+            1 => {
+                let last = bank | 0x0E;
+                [bank, bank | 1, last, last | 1]
+            }
+            // NROM-64: 8K mirrored across $8000-$FFFF
+            2 => {
+                let bank = bank | usize::from(self.bank >> 7);
+                [bank; 4]
+            }
+            // NROM-128: 16K mirrored at $8000-$BFFF and $C000-$FFFF
+            _ => [bank, bank | 1, bank, bank | 1],
+        };
+        for (slot, bank) in banks.into_iter().enumerate() {
+            self.prg_rom_banks.set(slot, bank);
+        }
+    }
+
+    /// CHR-RAM is write-protected in the NROM-256 and NROM-128 modes.
+    #[inline]
+    const fn chr_writable(&self) -> bool {
+        matches!(self.mode, 1 | 2)
+    }
+}
+
+impl MemMap for K1029 {
+    // PPU $0000..=$1FFF 8K CHR-RAM Bank Fixed
+    // CPU $6000..=$7FFF 8K PRG-RAM Bank Fixed
+    // CPU $8000..=$FFFF 32K, 16K or 8K PRG-ROM Banks Switchable, depending on mode
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(addr.into()),
+            0x6000..=0x7FFF => MappedRead::PrgRam((addr & 0x1FFF).into()),
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr {
+            0x0000..=0x1FFF if self.chr_writable() => MappedWrite::Chr(addr.into(), val),
+            0x6000..=0x7FFF => MappedWrite::PrgRam((addr & 0x1FFF).into(), val),
+            0x8000..=0xFFFF => {
+                // $8000-$FFFF: Mode selected by A0-A1
+                // 7654 3210
+                // pMBB BBBB
+                // |||| ||||
+                // ||++-++++- Select 16K PRG-ROM bank
+                // |+-------- Mirroring (0: Vertical; 1: Horizontal)
+                // +--------- Select 8K half of the PRG-ROM bank in NROM-64 mode
+                self.mode = (addr & 0x03) as u8;
+                self.bank = val & (Self::PRG_BANK_MASK | Self::PRG_SUBBANK_MASK);
+                self.mirroring = if val & Self::MIRRORING_MASK == Self::MIRRORING_MASK {
+                    Mirroring::Horizontal
+                } else {
+                    Mirroring::Vertical
+                };
+                self.update_banks();
+                MappedWrite::None
+            }
+            _ => MappedWrite::None,
+        }
+    }
+}
+
+impl Mapped for K1029 {
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+}
+
+impl Reset for K1029 {
+    // The menu runs from the first 32K bank, so clearing the register returns to it
+    fn reset(&mut self, _kind: Kind) {
+        self.mode = 0x00;
+        self.bank = 0x00;
+        self.mirroring = Mirroring::Vertical;
+        self.update_banks();
+    }
+}
+
+impl Clock for K1029 {}
+impl Regional for K1029 {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_mapper;
+
+    #[test]
+    fn banks() {
+        let (_, mut k1029): (_, K1029) = load_mapper(0x0010_0000, 0x2000, K1029::load);
+
+        let _ = k1029.map_write(0x8000, 0x43);
+        assert_eq!(k1029.map_peek(0x8000), MappedRead::PrgRom(0x8000));
+        assert_eq!(k1029.map_peek(0xC000), MappedRead::PrgRom(0xC000));
+        assert_eq!(k1029.mirroring(), Mirroring::Horizontal);
+        assert_eq!(k1029.map_write(0x0000, 0x00), MappedWrite::None);
+
+        let _ = k1029.map_write(0x8001, 0x09);
+        assert_eq!(k1029.map_peek(0x8000), MappedRead::PrgRom(0x24000));
+        assert_eq!(k1029.map_peek(0xC000), MappedRead::PrgRom(0x3C000));
+        assert_eq!(
+            k1029.map_write(0x0000, 0x00),
+            MappedWrite::Chr(0x0000, 0x00)
+        );
+
+        let _ = k1029.map_write(0x8002, 0x85);
+        assert_eq!(k1029.map_peek(0xE001), MappedRead::PrgRom(0x16001));
+
+        let _ = k1029.map_write(0x8003, 0x05);
+        assert_eq!(k1029.map_peek(0xC001), MappedRead::PrgRom(0x14001));
+
+        // Reset returns to the menu
+        k1029.reset(Kind::Soft);
+        assert_eq!(k1029.map_peek(0x8001), MappedRead::PrgRom(0x0001));
+        assert_eq!(k1029.map_peek(0xC001), MappedRead::PrgRom(0x4001));
+        assert_eq!(k1029.mirroring(), Mirroring::Vertical);
+    }
+}
@@ -0,0 +1,402 @@
+//! `Bandai FCG` / `LZ93D50` (Mappers 016, 153, 157 and 159)
+//!
+//! <https://www.nesdev.org/wiki/Bandai_FCG_board>
+//! <https://www.nesdev.org/wiki/INES_Mapper_153>
+//! <https://www.nesdev.org/wiki/INES_Mapper_157>
+//! <https://www.nesdev.org/wiki/INES_Mapper_159>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Kind, Regional, Reset},
+    mapper::{
+        eeprom::{Eeprom, EepromKind},
+        Mapped, MappedRead, MappedWrite, Mapper, MemMap,
+    },
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[must_use]
+pub enum BandaiFcgRevision {
+    /// FCG-1/FCG-2: registers at $6000-$7FFF, and IRQ counter writes take effect immediately.
+    Fcg,
+    /// LZ93D50: registers at $8000-$FFFF, and IRQ counter writes go to a latch copied into the
+    /// counter when IRQs are enabled.
+    Lz93d50,
+    /// Mapper 016 without a submapper: registers at both ranges, with the LZ93D50 IRQ latch.
+    Unknown,
+}
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct BandaiFcgRegs {
+    prg_bank: u8,
+    outer_prg_bank: u8,
+    prg_ram_enabled: bool,
+    eeprom_read_enabled: bool,
+}
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct BandaiFcgIrq {
+    counter: u16,
+    latch: u16,
+    enabled: bool,
+    pending: bool,
+}
+
+impl Clock for BandaiFcgIrq {
+    fn clock(&mut self) -> usize {
+        if self.enabled {
+            // The counter is checked before decrementing
+            if self.counter == 0 {
+                self.pending = true;
+            }
+            self.counter = self.counter.wrapping_sub(1);
+            1
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct BandaiFcg {
+    regs: BandaiFcgRegs,
+    revision: BandaiFcgRevision,
+    irq: BandaiFcgIrq,
+    eeprom: Option<Eeprom>,
+    mirroring: Mirroring,
+    /// Mapper 153 uses CHR registers to select a 256K outer PRG-ROM bank, with fixed CHR-RAM and
+    /// 8K PRG-RAM.
+    outer_prg_banking: bool,
+    chr_banking: bool,
+    has_prg_ram: bool,
+    chr_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+}
+
+impl BandaiFcg {
+    const PRG_RAM_SIZE: usize = 8 * 1024;
+    const CHR_RAM_SIZE: usize = 8 * 1024;
+    const PRG_WINDOW: usize = 16 * 1024;
+    const CHR_WINDOW: usize = 1024;
+    const OUTER_PRG_SIZE: usize = 256 * 1024;
+
+    pub fn load(
+        cart: &mut Cart,
+        revision: BandaiFcgRevision,
+        eeprom: Option<EepromKind>,
+    ) -> Mapper {
+        let outer_prg_banking = cart.prg_rom.len() > Self::OUTER_PRG_SIZE;
+        if outer_prg_banking && !cart.has_prg_ram() {
+            cart.add_prg_ram(Self::PRG_RAM_SIZE);
+        }
+        let chr_banking = !cart.chr_rom.is_empty();
+        if !cart.has_chr() {
+            cart.add_chr_ram(Self::CHR_RAM_SIZE);
+        }
+        let mut bandai_fcg = Self {
+            regs: BandaiFcgRegs::default(),
+            revision,
+            irq: BandaiFcgIrq::default(),
+            eeprom: eeprom.map(Eeprom::new),
+            mirroring: cart.mirroring(),
+            outer_prg_banking,
+            chr_banking,
+            has_prg_ram: cart.has_prg_ram(),
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_len(), Self::CHR_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_WINDOW),
+        };
+        bandai_fcg.update_prg_banks();
+        bandai_fcg.into()
+    }
+
+    fn update_prg_banks(&mut self) {
+        let outer_bank = usize::from(self.regs.outer_prg_bank);
+        self.prg_rom_banks
+            .set(0, outer_bank | usize::from(self.regs.prg_bank));
+        self.prg_rom_banks.set(1, outer_bank | 0x0F);
+    }
+
+    #[inline]
+    #[must_use]
+    const fn registers_at(&self, addr: u16) -> bool {
+        match self.revision {
+            BandaiFcgRevision::Fcg => matches!(addr, 0x6000..=0x7FFF),
+            BandaiFcgRevision::Lz93d50 => matches!(addr, 0x8000..=0xFFFF),
+            BandaiFcgRevision::Unknown => matches!(addr, 0x6000..=0xFFFF),
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, val: u8) {
+        let latched = self.revision != BandaiFcgRevision::Fcg;
+        match addr & 0x0F {
+            reg @ 0x00..=0x07 => {
+                if self.outer_prg_banking {
+                    self.regs.outer_prg_bank = (val & 0x01) << 4;
+                    self.update_prg_banks();
+                } else if self.chr_banking {
+                    self.chr_banks.set(reg.into(), val.into());
+                }
+            }
+            0x08 => {
+                self.regs.prg_bank = val & 0x0F;
+                self.update_prg_banks();
+            }
+            0x09 => {
+                self.mirroring = match val & 0x03 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenA,
+                    _ => Mirroring::SingleScreenB,
+                };
+            }
+            0x0A => {
+                self.irq.enabled = val & 0x01 == 0x01;
+                if latched {
+                    self.irq.counter = self.irq.latch;
+                }
+                self.irq.pending = false;
+            }
+            0x0B => {
+                if latched {
+                    self.irq.latch = (self.irq.latch & 0xFF00) | u16::from(val);
+                } else {
+                    self.irq.counter = (self.irq.counter & 0xFF00) | u16::from(val);
+                }
+            }
+            0x0C => {
+                if latched {
+                    self.irq.latch = (u16::from(val) << 8) | (self.irq.latch & 0x00FF);
+                } else {
+                    self.irq.counter = (u16::from(val) << 8) | (self.irq.counter & 0x00FF);
+                }
+            }
+            0x0D => {
+                // [RDC. ....]
+                //  |||
+                //  ||+------- EEPROM clock (SCL), or PRG-RAM enable on mapper 153
+                //  |+-------- EEPROM data (SDA)
+                //  +--------- EEPROM read enable
+                self.regs.prg_ram_enabled = val & 0x20 == 0x20;
+                self.regs.eeprom_read_enabled = val & 0x80 == 0x80;
+                if let Some(eeprom) = &mut self.eeprom {
+                    eeprom.write(val & 0x20 == 0x20, val & 0x40 == 0x40);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Mapped for BandaiFcg {
+    #[inline]
+    fn irq_pending(&self) -> bool {
+        self.irq.pending
+    }
+
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+
+    fn battery_backed(&self) -> bool {
+        self.eeprom.is_some()
+    }
+
+    #[inline]
+    fn battery_ram(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.eeprom.as_ref().map_or(&[], Eeprom::data))
+    }
+
+    fn load_battery_ram(&mut self, ram: &[u8]) {
+        if let Some(eeprom) = &mut self.eeprom {
+            eeprom.load_data(ram);
+        }
+    }
+}
+
+impl MemMap for BandaiFcg {
+    // PPU $0000..=$1FFF Eight 1K switchable CHR-ROM banks, or 8K CHR-RAM
+    //
+    // CPU $6000..=$7FFF EEPROM data, or 8K PRG-RAM on mapper 153
+    // CPU $8000..=$BFFF 16K switchable PRG-ROM bank
+    // CPU $C000..=$FFFF 16K PRG-ROM bank, fixed to the last bank
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x6000..=0x7FFF if self.has_prg_ram => {
+                if self.regs.prg_ram_enabled {
+                    MappedRead::PrgRam((addr & 0x1FFF).into())
+                } else {
+                    MappedRead::None
+                }
+            }
+            0x6000..=0x7FFF => match &self.eeprom {
+                // EEPROM data is returned in bit 4 when enabled, the rest is open bus
+                Some(eeprom) if self.regs.eeprom_read_enabled => {
+                    let open_bus = (addr >> 8) as u8 & 0xEF;
+                    MappedRead::Data(open_bus | (u8::from(eeprom.output()) << 4))
+                }
+                _ => MappedRead::None,
+            },
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr {
+            0x0000..=0x1FFF if !self.chr_banking => {
+                MappedWrite::Chr(self.chr_banks.translate(addr), val)
+            }
+            0x6000..=0x7FFF if self.has_prg_ram && self.regs.prg_ram_enabled => {
+                MappedWrite::PrgRam((addr & 0x1FFF).into(), val)
+            }
+            _ if self.registers_at(addr) => {
+                self.write_register(addr, val);
+                MappedWrite::None
+            }
+            _ => MappedWrite::None,
+        }
+    }
+}
+
+impl Clock for BandaiFcg {
+    fn clock(&mut self) -> usize {
+        self.irq.clock()
+    }
+}
+
+impl Reset for BandaiFcg {
+    fn reset(&mut self, _kind: Kind) {
+        self.irq = BandaiFcgIrq::default();
+    }
+}
+
+impl Regional for BandaiFcg {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_mapper;
+
+    fn load(
+        prg_rom_len: usize,
+        chr_rom_len: usize,
+        revision: BandaiFcgRevision,
+        eeprom: Option<EepromKind>,
+    ) -> BandaiFcg {
+        let (_, bandai_fcg) = load_mapper(prg_rom_len, chr_rom_len, |cart| {
+            BandaiFcg::load(cart, revision, eeprom)
+        });
+        bandai_fcg
+    }
+
+    #[test]
+    fn banks() {
+        let mut fcg = load(0x40000, 0x40000, BandaiFcgRevision::Fcg, None);
+        assert!(matches!(fcg.map_peek(0xC000), MappedRead::PrgRom(0x3C000)));
+        let _ = fcg.map_write(0x8008, 0x02);
+        assert!(
+            matches!(fcg.map_peek(0x8000), MappedRead::PrgRom(0x0000)),
+            "FCG ignores $8000"
+        );
+        let _ = fcg.map_write(0x6008, 0x02);
+        assert!(matches!(fcg.map_peek(0x8000), MappedRead::PrgRom(0x8000)));
+        let _ = fcg.map_write(0x6007, 0xFF);
+        assert!(matches!(fcg.map_peek(0x1C01), MappedRead::Chr(0x3FC01)));
+        let _ = fcg.map_write(0x6009, 0x01);
+        assert_eq!(fcg.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn outer_prg_bank() {
+        let mut lz93d50 = load(0x80000, 0x00, BandaiFcgRevision::Lz93d50, None);
+        let _ = lz93d50.map_write(0x8008, 0x01);
+        let _ = lz93d50.map_write(0x8000, 0x01);
+        assert!(matches!(
+            lz93d50.map_peek(0x8000),
+            MappedRead::PrgRom(0x44000)
+        ));
+        assert!(matches!(
+            lz93d50.map_peek(0xC000),
+            MappedRead::PrgRom(0x7C000)
+        ));
+        assert!(matches!(
+            lz93d50.map_write(0x0001, 0x01),
+            MappedWrite::Chr(0x0001, 0x01)
+        ));
+    }
+
+    #[test]
+    fn irq() {
+        let mut fcg = load(0x40000, 0x40000, BandaiFcgRevision::Fcg, None);
+        let _ = fcg.map_write(0x600B, 0x01);
+        let _ = fcg.map_write(0x600C, 0x00);
+        let _ = fcg.map_write(0x600A, 0x01);
+        fcg.clock();
+        assert!(!fcg.irq_pending());
+        fcg.clock();
+        assert!(fcg.irq_pending());
+        let _ = fcg.map_write(0x600A, 0x00);
+        assert!(!fcg.irq_pending());
+
+        // LZ93D50 only loads the counter when IRQs are enabled
+        let mut lz93d50 = load(0x40000, 0x40000, BandaiFcgRevision::Lz93d50, None);
+        let _ = lz93d50.map_write(0x800B, 0x01);
+        let _ = lz93d50.map_write(0x800C, 0x00);
+        assert_eq!(lz93d50.irq.counter, 0x0000);
+        let _ = lz93d50.map_write(0x800A, 0x01);
+        assert_eq!(lz93d50.irq.counter, 0x0001);
+    }
+
+    #[test]
+    fn eeprom() {
+        let mut lz93d50 = load(
+            0x40000,
+            0x40000,
+            BandaiFcgRevision::Lz93d50,
+            Some(EepromKind::Eeprom24C02),
+        );
+        assert_eq!(lz93d50.battery_ram().len(), 256);
+        let mut ram = vec![0x00; 256];
+        ram[0x00] = 0x80;
+        lz93d50.load_battery_ram(&ram);
+
+        let mut write = |scl: bool, sda: bool| {
+            let val = 0x80 | (u8::from(sda) << 6) | (u8::from(scl) << 5);
+            let _ = lz93d50.map_write(0x800D, val);
+        };
+        // Start, then select the device for reading
+        write(false, true);
+        write(true, true);
+        write(true, false);
+        for bit in [true, false, true, false, false, false, false, true, true] {
+            write(false, bit);
+            write(true, bit);
+            write(false, bit);
+        }
+        // The first data bit is shifted out on the next clock
+        write(false, true);
+        write(true, true);
+        assert!(matches!(lz93d50.map_peek(0x6000), MappedRead::Data(0x70)));
+
+        let _ = lz93d50.map_write(0x800D, 0x60);
+        assert!(
+            matches!(lz93d50.map_peek(0x6000), MappedRead::None),
+            "read disabled"
+        );
+    }
+}
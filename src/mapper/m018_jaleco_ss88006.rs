@@ -0,0 +1,337 @@
+//! `Jaleco SS88006` (Mapper 018)
+//!
+//! <https://wiki.nesdev.org/w/index.php?title=INES_Mapper_018>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Kind, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct JalecoSs88006Regs {
+    prg_banks: [u8; 3],
+    chr_banks: [u8; 8],
+    prg_ram_enabled: bool,
+    prg_ram_writable: bool,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct JalecoSs88006Irq {
+    reload: u16,
+    counter: u16,
+    // Bits of the counter that decrement, based on the selected counter width
+    mask: u16,
+    enabled: bool,
+    pending: bool,
+}
+
+impl Default for JalecoSs88006Irq {
+    fn default() -> Self {
+        Self {
+            reload: 0x0000,
+            counter: 0x0000,
+            mask: 0xFFFF,
+            enabled: false,
+            pending: false,
+        }
+    }
+}
+
+impl Clock for JalecoSs88006Irq {
+    fn clock(&mut self) -> usize {
+        if self.enabled {
+            // Only the low bits selected by the width decrement, the rest are left unchanged
+            let counter = (self.counter & self.mask).wrapping_sub(1) & self.mask;
+            if counter == 0 {
+                self.pending = true;
+            }
+            self.counter = (self.counter & !self.mask) | counter;
+            1
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct JalecoSs88006 {
+    regs: JalecoSs88006Regs,
+    irq: JalecoSs88006Irq,
+    mirroring: Mirroring,
+    chr_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+}
+
+impl JalecoSs88006 {
+    const PRG_WINDOW: usize = 8 * 1024;
+    const CHR_WINDOW: usize = 1024;
+    const PRG_RAM_SIZE: usize = 8 * 1024;
+    const CHR_RAM_SIZE: usize = 8 * 1024;
+
+    pub fn load(cart: &mut Cart) -> Mapper {
+        if !cart.has_prg_ram() {
+            cart.add_prg_ram(Self::PRG_RAM_SIZE);
+        }
+        if !cart.has_chr() {
+            cart.add_chr_ram(Self::CHR_RAM_SIZE);
+        }
+        let mut jaleco = Self {
+            regs: JalecoSs88006Regs::default(),
+            irq: JalecoSs88006Irq::default(),
+            mirroring: cart.mirroring(),
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_len(), Self::CHR_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_WINDOW),
+        };
+        let last_bank = jaleco.prg_rom_banks.last();
+        jaleco.prg_rom_banks.set(3, last_bank);
+        jaleco.into()
+    }
+
+    /// Replaces the low or high nibble of a bank register, selected by A0.
+    #[inline]
+    #[must_use]
+    const fn set_nibble(reg: u8, addr: u16, val: u8) -> u8 {
+        if addr & 0x01 == 0x00 {
+            (reg & 0xF0) | (val & 0x0F)
+        } else {
+            (reg & 0x0F) | ((val & 0x0F) << 4)
+        }
+    }
+
+    fn set_irq_reload_nibble(&mut self, addr: u16, val: u8) {
+        let shift = (addr & 0x03) << 2;
+        self.irq.reload = (self.irq.reload & !(0x0F << shift)) | (u16::from(val & 0x0F) << shift);
+    }
+}
+
+impl Mapped for JalecoSs88006 {
+    #[inline]
+    fn irq_pending(&self) -> bool {
+        self.irq.pending
+    }
+
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+}
+
+impl MemMap for JalecoSs88006 {
+    // PPU $0000..=$1FFF Eight 1K CHR-ROM Banks Switchable
+    // CPU $6000..=$7FFF 8K PRG-RAM Bank, optionally write protected
+    // CPU $8000..=$9FFF 8K PRG-ROM Bank Switchable
+    // CPU $A000..=$BFFF 8K PRG-ROM Bank Switchable
+    // CPU $C000..=$DFFF 8K PRG-ROM Bank Switchable
+    // CPU $E000..=$FFFF 8K PRG-ROM Bank Fixed to Last
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x6000..=0x7FFF if self.regs.prg_ram_enabled => {
+                MappedRead::PrgRam((addr & 0x1FFF).into())
+            }
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr & 0xF003 {
+            0x0000..=0x1FFF => return MappedWrite::Chr(self.chr_banks.translate(addr), val),
+            0x6000..=0x7FFF if self.regs.prg_ram_enabled && self.regs.prg_ram_writable => {
+                return MappedWrite::PrgRam((addr & 0x1FFF).into(), val);
+            }
+            // Each bank register is written one nibble at a time through a pair of addresses
+            // $8000/$8001: PRG-ROM $8000, $8002/$8003: PRG-ROM $A000, $9000/$9001: PRG-ROM $C000
+            reg @ (0x8000..=0x8003 | 0x9000..=0x9001) => {
+                let slot = match reg {
+                    0x8000 | 0x8001 => 0,
+                    0x8002 | 0x8003 => 1,
+                    _ => 2,
+                };
+                let bank = Self::set_nibble(self.regs.prg_banks[slot], addr, val) & 0x3F;
+                self.regs.prg_banks[slot] = bank;
+                self.prg_rom_banks.set(slot, bank.into());
+            }
+            0x9002 => {
+                self.regs.prg_ram_enabled = val & 0x01 == 0x01;
+                self.regs.prg_ram_writable = val & 0x02 == 0x02;
+            }
+            // $A000-$D003: CHR-ROM 1K banks, two registers per $1000 range
+            reg @ 0xA000..=0xDFFF => {
+                let slot = (((reg - 0xA000) >> 11) | ((reg >> 1) & 0x01)) as usize;
+                let bank = Self::set_nibble(self.regs.chr_banks[slot], addr, val);
+                self.regs.chr_banks[slot] = bank;
+                self.chr_banks.set(slot, bank.into());
+            }
+            0xE000..=0xE003 => self.set_irq_reload_nibble(addr, val),
+            0xF000 => {
+                self.irq.counter = self.irq.reload;
+                self.irq.pending = false;
+            }
+            0xF001 => {
+                // 7654 3210
+                // .... SSSE
+                //      |||+- IRQ enable
+                //      +++-- Counter width: 1xx 4-bit, 01x 8-bit, 001 12-bit, 000 16-bit
+                self.irq.enabled = val & 0x01 == 0x01;
+                self.irq.mask = if val & 0x08 == 0x08 {
+                    0x000F
+                } else if val & 0x04 == 0x04 {
+                    0x00FF
+                } else if val & 0x02 == 0x02 {
+                    0x0FFF
+                } else {
+                    0xFFFF
+                };
+                self.irq.pending = false;
+            }
+            0xF002 => {
+                self.mirroring = match val & 0x03 {
+                    0 => Mirroring::Horizontal,
+                    1 => Mirroring::Vertical,
+                    2 => Mirroring::SingleScreenA,
+                    _ => Mirroring::SingleScreenB,
+                };
+            }
+            // $F003 controls the uPD7756C ADPCM chip, which isn't emulated
+            _ => (),
+        }
+        MappedWrite::None
+    }
+}
+
+impl Clock for JalecoSs88006 {
+    fn clock(&mut self) -> usize {
+        self.irq.clock()
+    }
+}
+
+impl Reset for JalecoSs88006 {
+    fn reset(&mut self, _kind: Kind) {
+        self.irq = JalecoSs88006Irq::default();
+    }
+}
+
+impl Regional for JalecoSs88006 {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::{load_mapper, test_rom_results};
+
+    fn load() -> JalecoSs88006 {
+        let (_, jaleco) = load_mapper(0x40000, 0x40000, JalecoSs88006::load);
+        jaleco
+    }
+
+    #[test]
+    fn banks() {
+        let mut jaleco = load();
+        assert_eq!(jaleco.map_peek(0xE000), MappedRead::PrgRom(0x3E000));
+
+        let _ = jaleco.map_write(0x8000, 0x03);
+        let _ = jaleco.map_write(0x8001, 0x01);
+        let _ = jaleco.map_write(0x8003, 0x01);
+        let _ = jaleco.map_write(0x9000, 0x05);
+        assert_eq!(jaleco.map_peek(0x8000), MappedRead::PrgRom(0x26000));
+        assert_eq!(jaleco.map_peek(0xA001), MappedRead::PrgRom(0x2_0001));
+        assert_eq!(jaleco.map_peek(0xC000), MappedRead::PrgRom(0xA000));
+
+        // Only the written nibble changes
+        let _ = jaleco.map_write(0x8002, 0x04);
+        assert_eq!(jaleco.map_peek(0xA000), MappedRead::PrgRom(0x28000));
+
+        let _ = jaleco.map_write(0xA000, 0x0F);
+        let _ = jaleco.map_write(0xA001, 0x0A);
+        let _ = jaleco.map_write(0xB002, 0x01);
+        let _ = jaleco.map_write(0xD003, 0x08);
+        assert_eq!(jaleco.map_peek(0x0000), MappedRead::Chr(0x2BC00));
+        assert_eq!(jaleco.map_peek(0x0C00), MappedRead::Chr(0x0400));
+        assert_eq!(jaleco.map_peek(0x1C00), MappedRead::Chr(0x2_0000));
+
+        let _ = jaleco.map_write(0xF002, 0x02);
+        assert_eq!(jaleco.mirroring(), Mirroring::SingleScreenA);
+    }
+
+    #[test]
+    fn prg_ram() {
+        let mut jaleco = load();
+        assert_eq!(jaleco.map_peek(0x6000), MappedRead::None);
+        let _ = jaleco.map_write(0x9002, 0x01);
+        assert_eq!(jaleco.map_peek(0x6001), MappedRead::PrgRam(0x0001));
+        assert_eq!(jaleco.map_write(0x6001, 0x12), MappedWrite::None);
+        let _ = jaleco.map_write(0x9002, 0x03);
+        assert_eq!(
+            jaleco.map_write(0x6001, 0x12),
+            MappedWrite::PrgRam(0x0001, 0x12)
+        );
+    }
+
+    #[test]
+    fn irq_width() {
+        let mut jaleco = load();
+        for (addr, val) in [
+            (0xE000, 0x02),
+            (0xE001, 0x01),
+            (0xE002, 0x00),
+            (0xE003, 0x01),
+        ] {
+            let _ = jaleco.map_write(addr, val);
+        }
+        // 4-bit counter: only the low nibble of $1012 decrements
+        let _ = jaleco.map_write(0xF000, 0x00);
+        let _ = jaleco.map_write(0xF001, 0x09);
+        jaleco.clock();
+        assert!(!jaleco.irq_pending());
+        jaleco.clock();
+        assert!(jaleco.irq_pending());
+        assert_eq!(jaleco.irq.counter, 0x1010);
+
+        let _ = jaleco.map_write(0xF001, 0x09);
+        assert!(!jaleco.irq_pending());
+        // Wraps within the low nibble
+        jaleco.clock();
+        assert_eq!(jaleco.irq.counter, 0x101F);
+
+        // 16-bit counter
+        let _ = jaleco.map_write(0xF000, 0x00);
+        let _ = jaleco.map_write(0xF001, 0x01);
+        for _ in 0..0x1011 {
+            jaleco.clock();
+        }
+        assert!(!jaleco.irq_pending());
+        jaleco.clock();
+        assert!(jaleco.irq_pending());
+    }
+
+    #[test]
+    fn ss88006() {
+        // See `test_roms/mapper/src/ss88006.s`
+        let results = test_rom_results("test_roms/mapper/m018_jaleco_ss88006/ss88006.nes", 3);
+        assert_eq!(results[0..4], [3, 5, 9, 15], "prg banks");
+        assert_eq!(
+            results[4..12],
+            [10, 21, 32, 43, 54, 65, 76, 87],
+            "chr banks"
+        );
+        assert_eq!(
+            results[12..24],
+            [0xB2, 0xB2, 0xC3, 0xC3, 0xC3, 0xB2, 0xC3, 0xB2, 0xC3, 0xC3, 0xC3, 0xC3],
+            "horizontal, vertical, then single screen mirroring"
+        );
+        assert_eq!(results[24..], [126], "irq cpu cycle count");
+    }
+}
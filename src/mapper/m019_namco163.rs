@@ -0,0 +1,543 @@
+//! `Namco 163` (Mapper 019)
+//!
+//! <https://www.nesdev.org/wiki/INES_Mapper_019>
+//! <https://www.nesdev.org/wiki/Namco_163_audio>
+
+use crate::{
+    apu::PULSE_TABLE,
+    audio::Audio,
+    cart::Cart,
+    common::{Clock, Kind, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Namco163Regs {
+    chr: [u8; 8],
+    nametables: [u8; 4],
+    ciram_disabled: [bool; 2],
+    sound_disabled: bool,
+    write_protect: u8,
+}
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Namco163Irq {
+    counter: u16,
+    enabled: bool,
+    pending: bool,
+}
+
+impl Clock for Namco163Irq {
+    fn clock(&mut self) -> usize {
+        // The 15-bit counter stops once it reaches $7FFF
+        if self.enabled && self.counter < 0x7FFF {
+            self.counter += 1;
+            if self.counter == 0x7FFF {
+                self.pending = true;
+            }
+            1
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Namco163 {
+    regs: Namco163Regs,
+    irq: Namco163Irq,
+    audio: Namco163Audio,
+    mirroring: Mirroring,
+    chr_banks: MemBanks,
+    nt_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+}
+
+impl Namco163 {
+    const PRG_RAM_SIZE: usize = 8 * 1024;
+    const CHR_RAM_SIZE: usize = 8 * 1024;
+    const PRG_WINDOW: usize = 8 * 1024;
+    const CHR_WINDOW: usize = 1024;
+    /// CHR and nametable bank values at or above this select CIRAM pages instead of CHR-ROM.
+    const CIRAM_SELECT: u8 = 0xE0;
+
+    pub fn load(cart: &mut Cart) -> Mapper {
+        if !cart.has_prg_ram() {
+            cart.add_prg_ram(Self::PRG_RAM_SIZE);
+        }
+        if !cart.has_chr() {
+            cart.add_chr_ram(Self::CHR_RAM_SIZE);
+        }
+        let mut namco163 = Self {
+            regs: Namco163Regs::default(),
+            irq: Namco163Irq::default(),
+            audio: Namco163Audio::new(),
+            mirroring: cart.mirroring(),
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_len(), Self::CHR_WINDOW),
+            nt_banks: MemBanks::new(0x2000, 0x2FFF, cart.chr_len(), Self::CHR_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_WINDOW),
+        };
+        let last_bank = namco163.prg_rom_banks.last();
+        namco163.prg_rom_banks.set(3, last_bank);
+        namco163.into()
+    }
+
+    /// Returns the CIRAM address for a 1K bank value, if it selects CIRAM.
+    #[inline]
+    #[must_use]
+    const fn ciram_addr(bank: u8, addr: u16) -> Option<usize> {
+        if bank >= Self::CIRAM_SELECT {
+            Some((((bank & 0x01) as usize) << 10) | (addr & 0x03FF) as usize)
+        } else {
+            None
+        }
+    }
+
+    #[must_use]
+    const fn ppu_ciram_addr(&self, addr: u16) -> Option<usize> {
+        match addr {
+            0x0000..=0x1FFF => {
+                let bank = self.regs.chr[(addr >> 10) as usize];
+                if self.regs.ciram_disabled[(addr >> 12) as usize] {
+                    None
+                } else {
+                    Self::ciram_addr(bank, addr)
+                }
+            }
+            _ => Self::ciram_addr(self.regs.nametables[((addr >> 10) & 0x03) as usize], addr),
+        }
+    }
+
+    #[must_use]
+    const fn prg_ram_writable(&self, addr: u16) -> bool {
+        // [KKKK DCBA]
+        //  |||| ||||
+        //  |||| |||+- 1: Write-protect 2K window of external RAM from $6000-$67FF
+        //  |||| ||+-- 1: Write-protect 2K window of external RAM from $6800-$6FFF
+        //  |||| |+--- 1: Write-protect 2K window of external RAM from $7000-$77FF
+        //  |||| +---- 1: Write-protect 2K window of external RAM from $7800-$7FFF
+        //  ++++------ Additionally the upper nibble must be equal to b0100 to allow writes
+        let window = (addr >> 11) & 0x03;
+        self.regs.write_protect & 0xF0 == 0x40 && (self.regs.write_protect >> window) & 0x01 == 0
+    }
+}
+
+impl Mapped for Namco163 {
+    #[inline]
+    fn irq_pending(&self) -> bool {
+        self.irq.pending
+    }
+
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+
+    #[inline]
+    fn battery_ram(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.audio.ram)
+    }
+
+    fn load_battery_ram(&mut self, ram: &[u8]) {
+        let len = ram.len().min(self.audio.ram.len());
+        self.audio.ram[..len].copy_from_slice(&ram[..len]);
+    }
+}
+
+impl MemMap for Namco163 {
+    // PPU $0000..=$1FFF Eight 1K switchable CHR-ROM or CIRAM banks
+    // PPU $2000..=$2FFF Four 1K switchable CHR-ROM or CIRAM nametables
+    //
+    // CPU $4800..=$4FFF Internal RAM data port
+    // CPU $5000..=$5FFF IRQ counter
+    // CPU $6000..=$7FFF 8K PRG-RAM
+    // CPU $8000..=$9FFF 8K switchable PRG-ROM bank
+    // CPU $A000..=$BFFF 8K switchable PRG-ROM bank
+    // CPU $C000..=$DFFF 8K switchable PRG-ROM bank
+    // CPU $E000..=$FFFF 8K PRG-ROM bank, fixed to the last bank
+
+    fn map_read(&mut self, addr: u16) -> MappedRead {
+        match addr {
+            0x4800..=0x4FFF => MappedRead::Data(self.audio.read_data()),
+            _ => self.map_peek(addr),
+        }
+    }
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x3EFF => match self.ppu_ciram_addr(addr) {
+                Some(addr) => MappedRead::CIRam(addr),
+                None if addr < 0x2000 => MappedRead::Chr(self.chr_banks.translate(addr)),
+                None => MappedRead::Chr(self.nt_banks.translate(addr & 0x2FFF)),
+            },
+            0x4800..=0x4FFF => MappedRead::Data(self.audio.peek_data()),
+            0x5000..=0x57FF => MappedRead::Data((self.irq.counter & 0xFF) as u8),
+            0x5800..=0x5FFF => {
+                let enabled = u8::from(self.irq.enabled) << 7;
+                MappedRead::Data(enabled | (self.irq.counter >> 8) as u8)
+            }
+            0x6000..=0x7FFF => MappedRead::PrgRam((addr & 0x1FFF).into()),
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr {
+            0x0000..=0x3EFF => {
+                return match self.ppu_ciram_addr(addr) {
+                    Some(addr) => MappedWrite::CIRam(addr, val),
+                    None if addr < 0x2000 => MappedWrite::Chr(self.chr_banks.translate(addr), val),
+                    None => MappedWrite::None,
+                };
+            }
+            0x4800..=0x4FFF => self.audio.write_data(val),
+            0x5000..=0x57FF => {
+                self.irq.counter = (self.irq.counter & 0x7F00) | u16::from(val);
+                self.irq.pending = false;
+            }
+            0x5800..=0x5FFF => {
+                // [EHHH HHHH] IRQ enable and counter high 7 bits
+                self.irq.counter = (u16::from(val & 0x7F) << 8) | (self.irq.counter & 0xFF);
+                self.irq.enabled = val & 0x80 == 0x80;
+                self.irq.pending = false;
+            }
+            0x6000..=0x7FFF if self.prg_ram_writable(addr) => {
+                return MappedWrite::PrgRam((addr & 0x1FFF).into(), val);
+            }
+            0x8000..=0xBFFF => {
+                let bank = ((addr - 0x8000) >> 11) as usize;
+                self.regs.chr[bank] = val;
+                self.chr_banks.set(bank, val.into());
+            }
+            0xC000..=0xDFFF => {
+                let bank = ((addr - 0xC000) >> 11) as usize;
+                self.regs.nametables[bank] = val;
+                self.nt_banks.set(bank, val.into());
+            }
+            0xE000..=0xE7FF => {
+                // [.SPP PPPP] Sound disable and PRG-ROM bank at $8000-$9FFF
+                self.regs.sound_disabled = val & 0x40 == 0x40;
+                self.prg_rom_banks.set(0, (val & 0x3F).into());
+            }
+            0xE800..=0xEFFF => {
+                // [HLPP PPPP]
+                //  |||| ||||
+                //  ||++-++++- PRG-ROM bank at $A000-$BFFF
+                //  |+-------- 1: Disable CIRAM for $0000-$0FFF
+                //  +--------- 1: Disable CIRAM for $1000-$1FFF
+                self.regs.ciram_disabled = [val & 0x40 == 0x40, val & 0x80 == 0x80];
+                self.prg_rom_banks.set(1, (val & 0x3F).into());
+            }
+            0xF000..=0xF7FF => self.prg_rom_banks.set(2, (val & 0x3F).into()),
+            0xF800..=0xFFFF => {
+                self.regs.write_protect = val;
+                // [IAAA AAAA] Auto-increment and internal RAM address
+                self.audio.addr = val & 0x7F;
+                self.audio.auto_increment = val & 0x80 == 0x80;
+            }
+            _ => (),
+        }
+        MappedWrite::None
+    }
+}
+
+impl Audio for Namco163 {
+    fn output(&self) -> f32 {
+        if self.regs.sound_disabled {
+            0.0
+        } else {
+            self.audio.output()
+        }
+    }
+}
+
+impl Clock for Namco163 {
+    fn clock(&mut self) -> usize {
+        self.irq.clock();
+        if !self.regs.sound_disabled {
+            self.audio.clock();
+        }
+        1
+    }
+}
+
+impl Reset for Namco163 {
+    fn reset(&mut self, _kind: Kind) {
+        self.irq = Namco163Irq::default();
+        self.audio.channel = 7;
+        self.audio.divider = 0;
+        self.audio.out = 0.0;
+    }
+}
+
+impl Regional for Namco163 {}
+
+/// Wavetable audio with up to eight channels, each stored in internal RAM. Channels are updated
+/// one at a time every 15 CPU cycles and the output switches to the channel being updated, so
+/// using more channels lowers the multiplexing rate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Namco163Audio {
+    ram: Vec<u8>,
+    addr: u8,
+    auto_increment: bool,
+    channel: usize,
+    divider: u8,
+    out: f32,
+}
+
+impl Default for Namco163Audio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Namco163Audio {
+    const RAM_SIZE: usize = 128;
+    const CPU_DIVIDER: u8 = 15;
+    /// Channel registers occupy the top of internal RAM, eight bytes per channel.
+    const CHANNEL_REGS: usize = 0x40;
+
+    fn new() -> Self {
+        Self {
+            ram: vec![0x00; Self::RAM_SIZE],
+            addr: 0x00,
+            auto_increment: false,
+            channel: 7,
+            divider: 0,
+            out: 0.0,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    fn peek_data(&self) -> u8 {
+        self.ram[self.addr as usize]
+    }
+
+    fn read_data(&mut self) -> u8 {
+        let val = self.peek_data();
+        self.addr = self.next_addr();
+        val
+    }
+
+    fn write_data(&mut self, val: u8) {
+        self.ram[self.addr as usize] = val;
+        self.addr = self.next_addr();
+    }
+
+    #[inline]
+    #[must_use]
+    const fn next_addr(&self) -> u8 {
+        if self.auto_increment {
+            (self.addr + 1) & 0x7F
+        } else {
+            self.addr
+        }
+    }
+
+    /// Number of enabled channels, from 1 to 8, stored in the upper bits of the last channel's
+    /// volume register.
+    #[inline]
+    #[must_use]
+    fn channel_count(&self) -> usize {
+        usize::from((self.ram[0x7F] >> 4) & 0x07) + 1
+    }
+
+    /// Advances a channel's phase and returns its output.
+    fn update_channel(&mut self, channel: usize) -> f32 {
+        let regs = Self::CHANNEL_REGS + 8 * channel;
+        let reg = |offset: usize| u32::from(self.ram[regs + offset]);
+        let freq = reg(0) | (reg(2) << 8) | ((reg(4) & 0x03) << 16);
+        let phase = reg(1) | (reg(3) << 8) | (reg(5) << 16);
+        let length = (256 - (reg(4) & 0xFC)) << 16;
+        let offset = reg(6);
+        let volume = reg(7) & 0x0F;
+
+        let phase = (phase + freq) % length;
+        self.ram[regs + 1] = phase as u8;
+        self.ram[regs + 3] = (phase >> 8) as u8;
+        self.ram[regs + 5] = (phase >> 16) as u8;
+
+        // Samples are 4-bit, stored low nibble first
+        let sample_addr = ((phase >> 16) + offset) & 0xFF;
+        let sample = (self.ram[(sample_addr >> 1) as usize] >> ((sample_addr & 0x01) << 2)) & 0x0F;
+        (f32::from(sample) - 8.0) * volume as f32
+    }
+
+    #[inline]
+    #[must_use]
+    fn output(&self) -> f32 {
+        // A full-volume channel is about as loud as a full-volume pulse channel
+        PULSE_TABLE[PULSE_TABLE.len() - 1] / 120.0 * self.out
+    }
+}
+
+impl Clock for Namco163Audio {
+    fn clock(&mut self) -> usize {
+        self.divider += 1;
+        if self.divider < Self::CPU_DIVIDER {
+            return 0;
+        }
+        self.divider = 0;
+
+        // Channels are updated from 7 down to the lowest enabled channel
+        let lowest_channel = 8 - self.channel_count();
+        self.channel = if self.channel <= lowest_channel {
+            7
+        } else {
+            self.channel - 1
+        };
+        self.out = self.update_channel(self.channel);
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_mapper;
+
+    fn namco163() -> Namco163 {
+        let (_, namco163) = load_mapper(0x20000, 0x40000, Namco163::load);
+        namco163
+    }
+
+    #[test]
+    fn banks() {
+        let mut namco163 = namco163();
+        assert!(matches!(
+            namco163.map_peek(0xE000),
+            MappedRead::PrgRom(0x1E000)
+        ));
+        let _ = namco163.map_write(0xE000, 0x41);
+        let _ = namco163.map_write(0xE800, 0xC2);
+        let _ = namco163.map_write(0xF000, 0x03);
+        assert!(matches!(
+            namco163.map_peek(0x8000),
+            MappedRead::PrgRom(0x2000)
+        ));
+        assert!(matches!(
+            namco163.map_peek(0xA000),
+            MappedRead::PrgRom(0x4000)
+        ));
+        assert!(matches!(
+            namco163.map_peek(0xC000),
+            MappedRead::PrgRom(0x6000)
+        ));
+        assert!(namco163.output().abs() < f32::EPSILON, "sound disabled");
+
+        // CIRAM is disabled for pattern tables by $E800
+        let _ = namco163.map_write(0x8800, 0xE1);
+        assert!(matches!(
+            namco163.map_peek(0x0401),
+            MappedRead::Chr(0x38401)
+        ));
+        let _ = namco163.map_write(0xE800, 0x00);
+        assert!(matches!(
+            namco163.map_peek(0x0401),
+            MappedRead::CIRam(0x0401)
+        ));
+
+        let _ = namco163.map_write(0xC000, 0xE0);
+        let _ = namco163.map_write(0xC800, 0xE1);
+        let _ = namco163.map_write(0xD000, 0x05);
+        assert!(matches!(
+            namco163.map_peek(0x2005),
+            MappedRead::CIRam(0x0005)
+        ));
+        assert!(matches!(
+            namco163.map_peek(0x2405),
+            MappedRead::CIRam(0x0405)
+        ));
+        assert!(matches!(namco163.map_peek(0x2805), MappedRead::Chr(0x1405)));
+    }
+
+    #[test]
+    fn prg_ram_write_protect() {
+        let mut namco163 = namco163();
+        assert!(matches!(
+            namco163.map_write(0x6000, 0x01),
+            MappedWrite::None
+        ));
+        let _ = namco163.map_write(0xF800, 0x42);
+        assert!(matches!(
+            namco163.map_write(0x6000, 0x01),
+            MappedWrite::PrgRam(0x0000, 0x01)
+        ));
+        assert!(matches!(
+            namco163.map_write(0x6800, 0x01),
+            MappedWrite::None
+        ));
+    }
+
+    #[test]
+    fn irq() {
+        let mut namco163 = namco163();
+        let _ = namco163.map_write(0x5000, 0xFD);
+        let _ = namco163.map_write(0x5800, 0xFF);
+        assert!(matches!(namco163.map_peek(0x5800), MappedRead::Data(0xFF)));
+        namco163.clock();
+        assert!(!namco163.irq_pending());
+        namco163.clock();
+        assert!(namco163.irq_pending());
+        namco163.clock();
+        assert!(
+            matches!(namco163.map_peek(0x5000), MappedRead::Data(0xFF)),
+            "stopped"
+        );
+        let _ = namco163.map_write(0x5000, 0x00);
+        assert!(!namco163.irq_pending());
+    }
+
+    #[test]
+    fn internal_ram() {
+        let mut namco163 = namco163();
+        let _ = namco163.map_write(0xF800, 0xFE);
+        let _ = namco163.map_write(0x4800, 0x12);
+        let _ = namco163.map_write(0x4800, 0x34);
+        let _ = namco163.map_write(0x4800, 0x34);
+        assert_eq!(namco163.battery_ram()[0x7E..], [0x12, 0x34]);
+        assert_eq!(namco163.battery_ram()[0x00], 0x34, "address wraps");
+
+        let mut ram = vec![0xAA; Namco163Audio::RAM_SIZE];
+        ram[0x10] = 0x55;
+        namco163.load_battery_ram(&ram);
+        let _ = namco163.map_write(0xF800, 0x10);
+        assert!(matches!(namco163.map_read(0x4800), MappedRead::Data(0x55)));
+    }
+
+    #[test]
+    fn audio_output() {
+        let mut namco163 = namco163();
+        // Square wave of length 4 at address 0
+        let _ = namco163.map_write(0xF800, 0x80);
+        let _ = namco163.map_write(0x4800, 0xFF);
+        let _ = namco163.map_write(0x4800, 0x00);
+        // Channel 7: one sample per update, length 4, full volume
+        let _ = namco163.map_write(0xF800, 0xF8);
+        for val in [0x00, 0x00, 0x00, 0x00, 0xFD, 0x00, 0x00, 0x0F] {
+            let _ = namco163.map_write(0x4800, val);
+        }
+        let mut outputs = vec![];
+        for _ in 0..15 * 8 {
+            namco163.clock();
+            outputs.push(namco163.output());
+        }
+        assert!(outputs.iter().any(|&out| out > 0.0));
+        assert!(outputs.iter().any(|&out| out < 0.0));
+    }
+}
@@ -0,0 +1,995 @@
+//! `Famicom Disk System` (Mapper 020)
+//!
+//! <https://www.nesdev.org/wiki/Family_Computer_Disk_System>
+//! <https://www.nesdev.org/wiki/FDS_audio>
+
+use crate::{
+    apu::PULSE_TABLE,
+    audio::Audio,
+    cart::Cart,
+    common::{Clock, Kind, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::BTreeMap, ops::Range};
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct FdsRegs {
+    disk_regs_enabled: bool,
+    sound_regs_enabled: bool,
+    motor_on: bool,
+    reset_transfer: bool,
+    read_mode: bool,
+    crc_control: bool,
+    disk_ready: bool,
+    disk_irq_enabled: bool,
+    write_data: u8,
+    read_data: u8,
+}
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct FdsIrq {
+    reload: u16,
+    counter: u16,
+    enabled: bool,
+    repeat: bool,
+    pending: bool,
+}
+
+impl Clock for FdsIrq {
+    fn clock(&mut self) -> usize {
+        if self.enabled {
+            if self.counter == 0 {
+                self.pending = true;
+                self.counter = self.reload;
+                if !self.repeat {
+                    self.enabled = false;
+                }
+            } else {
+                self.counter -= 1;
+            }
+            1
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Fds {
+    regs: FdsRegs,
+    irq: FdsIrq,
+    audio: FdsAudio,
+    mirroring: Mirroring,
+    #[serde(skip)] // Excluded from save states, see `Fds::load_rom_from`
+    sides: Vec<Vec<u8>>,
+    sectors: BTreeMap<(u8, u8), Vec<u8>>, // Written sectors keyed by side and sector index
+    side: Option<usize>,
+    next_side: Option<usize>,
+    insert_delay: u32,
+    position: usize,
+    delay: u32,
+    end_of_head: bool,
+    scanning: bool,
+    gap_ended: bool,
+    transfer_complete: bool,
+    disk_irq_pending: bool,
+    crc: u16,
+    last_crc_control: bool,
+}
+
+impl Fds {
+    const PRG_RAM_SIZE: usize = 32 * 1024;
+    const CHR_RAM_SIZE: usize = 8 * 1024;
+    // CPU cycles the disk is left ejected when switching sides, so games notice the change
+    const INSERT_DELAY: u32 = 1_000_000;
+    // CPU cycles for the drive head to return to the start of the disk
+    const REWIND_DELAY: u32 = 50_000;
+    // CPU cycles to transfer a byte, ~96.4 kbit/s
+    const BYTE_DELAY: u32 = 150;
+    // Granularity of disk writes kept in save states and Save RAM
+    const SECTOR_SIZE: usize = 1024;
+
+    /// Load the FDS RAM adapter with the BIOS loaded as PRG-ROM and disk sides with gaps added.
+    pub fn load(cart: &mut Cart, sides: Vec<Vec<u8>>) -> Mapper {
+        cart.add_prg_ram(Self::PRG_RAM_SIZE);
+        cart.add_chr_ram(Self::CHR_RAM_SIZE);
+        Self {
+            regs: FdsRegs::default(),
+            irq: FdsIrq::default(),
+            audio: FdsAudio::new(),
+            mirroring: Mirroring::Vertical,
+            side: (!sides.is_empty()).then_some(0),
+            sides,
+            sectors: BTreeMap::new(),
+            next_side: None,
+            insert_delay: 0,
+            position: 0,
+            delay: 0,
+            end_of_head: true,
+            scanning: false,
+            gap_ended: false,
+            transfer_complete: false,
+            disk_irq_pending: false,
+            crc: 0,
+            last_crc_control: false,
+        }
+        .into()
+    }
+
+    /// Ejects the current disk side and inserts the next one after a delay, wrapping around to
+    /// the first side. Returns the side being inserted.
+    pub fn switch_side(&mut self) -> usize {
+        let next_side = self
+            .side
+            .or(self.next_side)
+            .map_or(0, |side| (side + 1) % self.sides.len().max(1));
+        self.side = None;
+        self.next_side = Some(next_side);
+        self.insert_delay = Self::INSERT_DELAY;
+        next_side
+    }
+
+    #[inline]
+    #[must_use]
+    const fn disk_inserted(&self) -> bool {
+        self.side.is_some()
+    }
+
+    // Disk sides are under 256 sectors, see `Fds::SECTOR_SIZE`
+    const fn sector(position: usize) -> u8 {
+        (position / Self::SECTOR_SIZE) as u8
+    }
+
+    /// Returns the byte range of a sector within a side, or `None` if it's past the end of the
+    /// disk.
+    fn sector_range(&self, side: u8, sector: u8) -> Option<Range<usize>> {
+        let len = self.sides.get(usize::from(side))?.len();
+        let start = usize::from(sector) * Self::SECTOR_SIZE;
+        (start < len).then(|| start..(start + Self::SECTOR_SIZE).min(len))
+    }
+
+    fn read_disk(&self, side: usize, position: usize) -> u8 {
+        match self.sectors.get(&(side as u8, Self::sector(position))) {
+            Some(sector) => sector[position % Self::SECTOR_SIZE],
+            None => self.sides[side][position],
+        }
+    }
+
+    /// Writes to a sector, copying it from the disk image on first write.
+    fn write_disk(&mut self, side: usize, position: usize, val: u8) {
+        let key = (side as u8, Self::sector(position));
+        let sides = &self.sides;
+        let sector = self.sectors.entry(key).or_insert_with(|| {
+            let start = position & !(Self::SECTOR_SIZE - 1);
+            sides[side][start..(start + Self::SECTOR_SIZE).min(sides[side].len())].to_vec()
+        });
+        sector[position % Self::SECTOR_SIZE] = val;
+    }
+
+    fn update_crc(&mut self, val: u8) {
+        for bit in 0..8 {
+            let carry = self.crc & 0x01 == 0x01;
+            self.crc >>= 1;
+            if carry {
+                self.crc ^= 0x8408;
+            }
+            if val & (1 << bit) != 0x00 {
+                self.crc ^= 0x8408;
+            }
+        }
+    }
+
+    fn clock_disk(&mut self) {
+        if self.insert_delay > 0 {
+            self.insert_delay -= 1;
+            if self.insert_delay == 0 {
+                self.side = self.next_side.take();
+            }
+        }
+
+        let Some(side) = self.side else {
+            self.end_of_head = true;
+            self.scanning = false;
+            return;
+        };
+        if !self.regs.motor_on {
+            self.end_of_head = true;
+            self.scanning = false;
+            return;
+        }
+        if self.regs.reset_transfer && !self.scanning {
+            return;
+        }
+        if self.end_of_head {
+            self.delay = Self::REWIND_DELAY;
+            self.end_of_head = false;
+            self.position = 0;
+            self.gap_ended = false;
+            return;
+        }
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+
+        self.scanning = true;
+        let mut need_irq = self.regs.disk_irq_enabled;
+        if self.regs.read_mode {
+            let val = self.read_disk(side, self.position);
+            if !self.last_crc_control {
+                self.update_crc(val);
+            }
+            if !self.regs.disk_ready {
+                self.gap_ended = false;
+                self.crc = 0;
+            } else if val > 0x00 && !self.gap_ended {
+                // The block start mark ends the gap and isn't transferred
+                self.gap_ended = true;
+                need_irq = false;
+            }
+            if self.gap_ended {
+                self.transfer_complete = true;
+                self.regs.read_data = val;
+                if need_irq {
+                    self.disk_irq_pending = true;
+                }
+            }
+        } else {
+            let mut val = 0x00;
+            if !self.regs.crc_control {
+                self.transfer_complete = true;
+                val = self.regs.write_data;
+                if need_irq {
+                    self.disk_irq_pending = true;
+                }
+            }
+            if !self.regs.disk_ready {
+                val = 0x00;
+            }
+            if self.regs.crc_control {
+                if !self.last_crc_control {
+                    self.update_crc(0x00);
+                    self.update_crc(0x00);
+                }
+                val = (self.crc & 0xFF) as u8;
+                self.crc >>= 8;
+            } else {
+                self.update_crc(val);
+            }
+            // Writes land behind the read head
+            if self.position >= 2 {
+                self.write_disk(side, self.position - 2, val);
+            }
+            self.gap_ended = false;
+        }
+        self.last_crc_control = self.regs.crc_control;
+
+        self.position += 1;
+        if self.position >= self.sides[side].len() {
+            self.regs.motor_on = false;
+            self.end_of_head = true;
+        } else {
+            self.delay = Self::BYTE_DELAY;
+        }
+    }
+
+    fn peek_register(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x4030 if self.regs.disk_regs_enabled => {
+                // [E.B. ..DT]
+                //  | |    ||
+                //  | |    |+- Timer IRQ occurred
+                //  | |    +-- Byte transfer complete
+                //  | +------- End of disk head
+                //  +--------- Disk registers enabled
+                //
+                // CRC errors are never reported since disk images don't store CRCs
+                let mut val = 0x80;
+                if self.irq.pending {
+                    val |= 0x01;
+                }
+                if self.transfer_complete {
+                    val |= 0x02;
+                }
+                if self.end_of_head {
+                    val |= 0x40;
+                }
+                MappedRead::Data(val)
+            }
+            0x4031 if self.regs.disk_regs_enabled => MappedRead::Data(self.regs.read_data),
+            0x4032 if self.regs.disk_regs_enabled => {
+                // [.... .PRS]
+                //        |||
+                //        ||+- Disk not inserted
+                //        |+-- Disk not ready
+                //        +--- Disk write protected
+                let mut val = 0x40;
+                if !self.disk_inserted() {
+                    val |= 0x05;
+                }
+                if !self.disk_inserted() || !self.scanning {
+                    val |= 0x02;
+                }
+                MappedRead::Data(val)
+            }
+            // Expansion port with battery good bit set
+            0x4033 if self.regs.disk_regs_enabled => MappedRead::Data(0x80),
+            0x4040..=0x407F | 0x4090 | 0x4092 if self.regs.sound_regs_enabled => {
+                MappedRead::Data(self.audio.read_register(addr))
+            }
+            _ => MappedRead::None,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, val: u8) {
+        if addr == 0x4023 {
+            // [.... ..SD]
+            //         ||
+            //         |+- Disk registers enabled
+            //         +-- Sound registers enabled
+            self.regs.disk_regs_enabled = val & 0x01 == 0x01;
+            self.regs.sound_regs_enabled = val & 0x02 == 0x02;
+            if !self.regs.disk_regs_enabled {
+                self.irq.enabled = false;
+                self.irq.pending = false;
+                self.disk_irq_pending = false;
+            }
+            return;
+        }
+        match addr {
+            0x4020..=0x4026 if !self.regs.disk_regs_enabled => (),
+            0x4020 => self.irq.reload = (self.irq.reload & 0xFF00) | u16::from(val),
+            0x4021 => self.irq.reload = (self.irq.reload & 0x00FF) | (u16::from(val) << 8),
+            0x4022 => {
+                // [.... ..ER]
+                //         ||
+                //         |+- IRQ repeat
+                //         +-- IRQ enabled
+                self.irq.repeat = val & 0x01 == 0x01;
+                self.irq.enabled = val & 0x02 == 0x02;
+                if self.irq.enabled {
+                    self.irq.counter = self.irq.reload;
+                } else {
+                    self.irq.pending = false;
+                }
+            }
+            0x4024 => {
+                self.regs.write_data = val;
+                self.transfer_complete = false;
+                self.disk_irq_pending = false;
+            }
+            0x4025 => {
+                // [IS.C MRTD]
+                //  || | ||||
+                //  || | |||+- Drive motor on
+                //  || | ||+-- Reset transfer timing
+                //  || | |+--- Transfer mode: 1: Read, 0: Write
+                //  || | +---- Mirroring: 1: Horizontal, 0: Vertical
+                //  || +------ Transfer CRC
+                //  |+-------- Start reading/writing data
+                //  +--------- Disk IRQ enabled
+                self.regs.motor_on = val & 0x01 == 0x01;
+                self.regs.reset_transfer = val & 0x02 == 0x02;
+                self.regs.read_mode = val & 0x04 == 0x04;
+                self.mirroring = if val & 0x08 == 0x08 {
+                    Mirroring::Horizontal
+                } else {
+                    Mirroring::Vertical
+                };
+                self.regs.crc_control = val & 0x10 == 0x10;
+                self.regs.disk_ready = val & 0x40 == 0x40;
+                self.regs.disk_irq_enabled = val & 0x80 == 0x80;
+                self.disk_irq_pending = false;
+            }
+            0x4040..=0x408A if self.regs.sound_regs_enabled => {
+                self.audio.write_register(addr, val);
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Mapped for Fds {
+    #[inline]
+    fn irq_pending(&self) -> bool {
+        self.irq.pending || self.disk_irq_pending
+    }
+
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+
+    fn battery_backed(&self) -> bool {
+        !self.sides.is_empty()
+    }
+
+    /// Sectors written since the disk was loaded, stored as a side index byte and a sector index
+    /// byte followed by the sector contents.
+    fn battery_ram(&self) -> Cow<'_, [u8]> {
+        let mut ram = Vec::with_capacity(self.sectors.len() * (Self::SECTOR_SIZE + 2));
+        for (&(side, sector), data) in &self.sectors {
+            ram.extend([side, sector]);
+            ram.extend_from_slice(data);
+        }
+        Cow::Owned(ram)
+    }
+
+    fn load_battery_ram(&mut self, mut ram: &[u8]) {
+        while let [side, sector, data @ ..] = ram {
+            let Some(range) = self.sector_range(*side, *sector) else {
+                break;
+            };
+            if data.len() < range.len() {
+                break;
+            }
+            let (data, rest) = data.split_at(range.len());
+            self.sectors.insert((*side, *sector), data.to_vec());
+            ram = rest;
+        }
+    }
+
+    fn load_rom_from(&mut self, other: &mut Mapper) {
+        if let Mapper::Fds(other) = other {
+            self.sides = std::mem::take(&mut other.sides);
+        }
+    }
+}
+
+impl MemMap for Fds {
+    // PPU $0000..=$1FFF 8K CHR-RAM
+    //
+    // CPU $4020..=$409F RAM adapter and audio registers
+    // CPU $6000..=$DFFF 32K PRG-RAM
+    // CPU $E000..=$FFFF 8K BIOS PRG-ROM
+
+    fn map_read(&mut self, addr: u16) -> MappedRead {
+        let val = self.map_peek(addr);
+        if self.regs.disk_regs_enabled && matches!(addr, 0x4030 | 0x4031) {
+            // Reading status or data acknowledges the transfer
+            self.transfer_complete = false;
+            self.disk_irq_pending = false;
+            if addr == 0x4030 {
+                self.irq.pending = false;
+            }
+        }
+        val
+    }
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(addr.into()),
+            0x4020..=0x409F => self.peek_register(addr),
+            0x6000..=0xDFFF => MappedRead::PrgRam((addr - 0x6000).into()),
+            0xE000..=0xFFFF => MappedRead::PrgRom((addr - 0xE000).into()),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr {
+            0x0000..=0x1FFF => MappedWrite::Chr(addr.into(), val),
+            0x4020..=0x409F => {
+                self.write_register(addr, val);
+                MappedWrite::None
+            }
+            0x6000..=0xDFFF => MappedWrite::PrgRam((addr - 0x6000).into(), val),
+            _ => MappedWrite::None,
+        }
+    }
+}
+
+impl Audio for Fds {
+    #[inline]
+    fn output(&self) -> f32 {
+        self.audio.output()
+    }
+}
+
+impl Clock for Fds {
+    fn clock(&mut self) -> usize {
+        self.irq.clock();
+        self.audio.clock();
+        self.clock_disk();
+        1
+    }
+}
+
+impl Reset for Fds {
+    fn reset(&mut self, kind: Kind) {
+        self.regs = FdsRegs::default();
+        self.irq = FdsIrq::default();
+        self.audio.reset(kind);
+        self.end_of_head = true;
+        self.scanning = false;
+        self.transfer_complete = false;
+        self.disk_irq_pending = false;
+        if kind == Kind::Hard && !self.sides.is_empty() {
+            self.side = Some(0);
+            self.next_side = None;
+            self.insert_delay = 0;
+        }
+    }
+}
+
+impl Regional for Fds {}
+
+impl std::fmt::Debug for Fds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Fds")
+            .field("regs", &self.regs)
+            .field("irq", &self.irq)
+            .field("audio", &self.audio)
+            .field("mirroring", &self.mirroring)
+            .field("side_count", &self.sides.len())
+            .field("written_sectors", &self.sectors.len())
+            .field("side", &self.side)
+            .field("next_side", &self.next_side)
+            .field("insert_delay", &self.insert_delay)
+            .field("position", &self.position)
+            .field("delay", &self.delay)
+            .field("end_of_head", &self.end_of_head)
+            .field("scanning", &self.scanning)
+            .field("gap_ended", &self.gap_ended)
+            .field("transfer_complete", &self.transfer_complete)
+            .field("disk_irq_pending", &self.disk_irq_pending)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct FdsEnvelope {
+    speed: u8,
+    gain: u8,
+    disabled: bool,
+    increase: bool,
+    frequency: u16,
+    timer: u32,
+    master_speed: u8,
+}
+
+impl FdsEnvelope {
+    const fn new() -> Self {
+        Self {
+            speed: 0,
+            gain: 0,
+            disabled: false,
+            increase: false,
+            frequency: 0,
+            timer: 0,
+            master_speed: 0xE8,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, val: u8) {
+        match addr & 0x03 {
+            0 => {
+                // [MDSS SSSS]
+                //  ||++-++++- Envelope speed, or gain if the envelope is disabled
+                //  |+-------- Direction: 1: Increase, 0: Decrease
+                //  +--------- Envelope disabled
+                self.speed = val & 0x3F;
+                self.increase = val & 0x40 == 0x40;
+                self.disabled = val & 0x80 == 0x80;
+                self.reset_timer();
+                if self.disabled {
+                    self.gain = self.speed;
+                }
+            }
+            2 => self.frequency = (self.frequency & 0x0F00) | u16::from(val),
+            3 => self.frequency = (u16::from(val & 0x0F) << 8) | (self.frequency & 0xFF),
+            _ => (),
+        }
+    }
+
+    #[inline]
+    fn reset_timer(&mut self) {
+        self.timer = 8 * (u32::from(self.speed) + 1) * u32::from(self.master_speed);
+    }
+
+    fn tick(&mut self) -> bool {
+        if !self.disabled && self.master_speed > 0 {
+            self.timer = self.timer.saturating_sub(1);
+            if self.timer == 0 {
+                self.reset_timer();
+                if self.increase && self.gain < 32 {
+                    self.gain += 1;
+                } else if !self.increase && self.gain > 0 {
+                    self.gain -= 1;
+                }
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct FdsModulator {
+    envelope: FdsEnvelope,
+    counter: i8,
+    disabled: bool,
+    table: Vec<u8>,
+    table_pos: usize,
+    overflow: u16,
+    output: i32,
+}
+
+impl FdsModulator {
+    const RESET: i8 = i8::MIN;
+    const TABLE: [i8; 8] = [0, 1, 2, 4, Self::RESET, -4, -2, -1];
+
+    fn new() -> Self {
+        Self {
+            envelope: FdsEnvelope::new(),
+            counter: 0,
+            disabled: false,
+            table: vec![0x00; 64],
+            table_pos: 0,
+            overflow: 0,
+            output: 0,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x4084 | 0x4086 => self.envelope.write_register(addr, val),
+            0x4085 => self.counter = Self::wrap_counter((val & 0x7F) as i8),
+            0x4087 => {
+                self.envelope.write_register(addr, val);
+                self.disabled = val & 0x80 == 0x80;
+                if self.disabled {
+                    self.overflow = 0;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn write_table(&mut self, val: u8) {
+        // The mod table is only writable while modulation is disabled
+        if self.disabled {
+            self.table[self.table_pos] = val & 0x07;
+            self.table[(self.table_pos + 1) & 0x3F] = val & 0x07;
+            self.table_pos = (self.table_pos + 2) & 0x3F;
+        }
+    }
+
+    /// Wraps the 7-bit signed mod counter.
+    #[inline]
+    const fn wrap_counter(val: i8) -> i8 {
+        if val >= 64 {
+            val - 64 - 64
+        } else if val < -64 {
+            val + 64 + 64
+        } else {
+            val
+        }
+    }
+
+    #[inline]
+    const fn enabled(&self) -> bool {
+        !self.disabled && self.envelope.frequency > 0
+    }
+
+    fn tick(&mut self) -> bool {
+        if self.enabled() {
+            let (overflow, carry) = self.overflow.overflowing_add(self.envelope.frequency);
+            self.overflow = overflow;
+            if carry {
+                let offset = Self::TABLE[self.table[self.table_pos] as usize];
+                self.counter = if offset == Self::RESET {
+                    0
+                } else {
+                    Self::wrap_counter(self.counter.wrapping_add(offset))
+                };
+                self.table_pos = (self.table_pos + 1) & 0x3F;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn update_output(&mut self, pitch: u16) {
+        // Multiply counter by gain, dropping the lowest 4 bits with rounding
+        let mut temp = i32::from(self.counter) * i32::from(self.envelope.gain);
+        let remainder = temp & 0x0F;
+        temp >>= 4;
+        if remainder > 0 && temp & 0x80 == 0x00 {
+            temp += if self.counter < 0 { -1 } else { 2 };
+        }
+        // Wrap to -64..=191
+        if temp >= 192 {
+            temp -= 256;
+        } else if temp < -64 {
+            temp += 256;
+        }
+        // Multiply by pitch, rounding to nearest while dropping 6 bits
+        temp *= i32::from(pitch);
+        let remainder = temp & 0x3F;
+        temp >>= 6;
+        if remainder >= 32 {
+            temp += 1;
+        }
+        self.output = temp;
+    }
+
+    #[inline]
+    const fn output(&self) -> i32 {
+        if self.enabled() {
+            self.output
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct FdsAudio {
+    wave_table: Vec<u8>,
+    wave_write: bool,
+    volume: FdsEnvelope,
+    modulator: FdsModulator,
+    envelopes_disabled: bool,
+    halt: bool,
+    master_volume: u8,
+    overflow: u16,
+    wave_pos: usize,
+    out: u8,
+}
+
+impl Default for FdsAudio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FdsAudio {
+    const WAVE_VOLUME_TABLE: [u32; 4] = [36, 24, 17, 14];
+
+    fn new() -> Self {
+        Self {
+            wave_table: vec![0x00; 64],
+            wave_write: false,
+            volume: FdsEnvelope::new(),
+            modulator: FdsModulator::new(),
+            envelopes_disabled: false,
+            halt: false,
+            master_volume: 0,
+            overflow: 0,
+            wave_pos: 0,
+            out: 0,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    fn output(&self) -> f32 {
+        // At full volume the FDS is ~2.4 times louder than a single APU pulse channel
+        let scale = 2.4 * PULSE_TABLE[PULSE_TABLE.len() - 1] / 63.0;
+        scale * f32::from(self.out)
+    }
+
+    fn read_register(&self, addr: u16) -> u8 {
+        match addr {
+            0x4040..=0x407F => 0x40 | self.wave_table[(addr & 0x3F) as usize],
+            0x4090 => 0x40 | self.volume.gain,
+            0x4092 => 0x40 | self.modulator.envelope.gain,
+            _ => 0x40,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x4040..=0x407F if self.wave_write => {
+                self.wave_table[(addr & 0x3F) as usize] = val & 0x3F;
+            }
+            0x4080 | 0x4082 => self.volume.write_register(addr, val),
+            0x4083 => {
+                // [HE.. FFFF]
+                //  ||   ++++- Wave frequency high
+                //  |+-------- Disable volume and modulator envelopes
+                //  +--------- Halt waveform and reset phase
+                self.envelopes_disabled = val & 0x40 == 0x40;
+                self.halt = val & 0x80 == 0x80;
+                if self.envelopes_disabled {
+                    self.volume.reset_timer();
+                    self.modulator.envelope.reset_timer();
+                }
+                self.volume.write_register(addr, val);
+            }
+            0x4084..=0x4087 => self.modulator.write_register(addr, val),
+            0x4088 => self.modulator.write_table(val),
+            0x4089 => {
+                // [W... ..VV]
+                //  |      ++- Master volume
+                //  +--------- Wave table write enabled, holding the current output
+                self.master_volume = val & 0x03;
+                self.wave_write = val & 0x80 == 0x80;
+            }
+            0x408A => {
+                self.volume.master_speed = val;
+                self.modulator.envelope.master_speed = val;
+            }
+            _ => (),
+        }
+    }
+
+    fn update_output(&mut self) {
+        let gain = u32::from(self.volume.gain.min(32));
+        let level = gain * Self::WAVE_VOLUME_TABLE[self.master_volume as usize];
+        self.out = (u32::from(self.wave_table[self.wave_pos]) * level / 1152) as u8;
+    }
+}
+
+impl Clock for FdsAudio {
+    fn clock(&mut self) -> usize {
+        let frequency = self.volume.frequency;
+        if !self.halt && !self.envelopes_disabled {
+            self.volume.tick();
+            if self.modulator.envelope.tick() {
+                self.modulator.update_output(frequency);
+            }
+        }
+        if self.modulator.tick() {
+            self.modulator.update_output(frequency);
+        }
+
+        if self.halt {
+            self.wave_pos = 0;
+            self.update_output();
+        } else {
+            self.update_output();
+            let pitch = i32::from(frequency) + self.modulator.output();
+            if pitch > 0 && !self.wave_write {
+                let pitch = pitch as u16;
+                let (overflow, carry) = self.overflow.overflowing_add(pitch);
+                self.overflow = overflow;
+                if carry {
+                    self.wave_pos = (self.wave_pos + 1) & 0x3F;
+                }
+            }
+        }
+        1
+    }
+}
+
+impl Reset for FdsAudio {
+    fn reset(&mut self, _kind: Kind) {
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fds() -> Fds {
+        let mut cart = Cart::empty();
+        let side = vec![0x00, 0x00, 0x80, 0x01, 0x2A];
+        match Fds::load(&mut cart, vec![side.clone(), side]) {
+            Mapper::Fds(fds) => fds,
+            _ => unreachable!("fds mapper"),
+        }
+    }
+
+    #[test]
+    fn timer_irq() {
+        let mut fds = fds();
+        let _ = fds.map_write(0x4022, 0x02);
+        assert!(!fds.irq.enabled, "disk registers disabled");
+
+        let _ = fds.map_write(0x4023, 0x01);
+        let _ = fds.map_write(0x4020, 0x02);
+        let _ = fds.map_write(0x4021, 0x00);
+        let _ = fds.map_write(0x4022, 0x02);
+        for _ in 0..2 {
+            fds.clock();
+            assert!(!fds.irq_pending());
+        }
+        fds.clock();
+        assert!(fds.irq_pending(), "irq after reload + 1 cycles");
+        assert_eq!(fds.map_read(0x4030), MappedRead::Data(0xC1));
+        assert!(!fds.irq_pending(), "acknowledged by $4030");
+        assert!(!fds.irq.enabled, "one-shot timer");
+    }
+
+    #[test]
+    fn read_disk() {
+        let mut fds = fds();
+        let _ = fds.map_write(0x4023, 0x01);
+        // Motor on, read mode, disk ready, IRQ enabled
+        let _ = fds.map_write(0x4025, 0xC5);
+        fds.clock();
+        assert_eq!(fds.delay, Fds::REWIND_DELAY);
+
+        for _ in 0..=Fds::REWIND_DELAY {
+            fds.clock();
+        }
+        // Leading gap is skipped until the block start mark
+        let mut bytes = vec![];
+        while bytes.len() < 2 {
+            fds.clock();
+            if fds.irq_pending() {
+                let MappedRead::Data(val) = fds.map_read(0x4031) else {
+                    unreachable!("disk data");
+                };
+                bytes.push(val);
+            }
+        }
+        assert_eq!(bytes, [0x01, 0x2A]);
+    }
+
+    #[test]
+    fn switch_side() {
+        let mut fds = fds();
+        let _ = fds.map_write(0x4023, 0x01);
+        assert_eq!(fds.map_peek(0x4032), MappedRead::Data(0x42));
+
+        assert_eq!(fds.switch_side(), 1);
+        assert_eq!(fds.map_peek(0x4032), MappedRead::Data(0x47), "ejected");
+        for _ in 0..Fds::INSERT_DELAY {
+            fds.clock();
+        }
+        assert_eq!(fds.side, Some(1));
+        assert_eq!(fds.switch_side(), 0, "wraps to first side");
+    }
+
+    #[test]
+    fn disk_writes_saved() {
+        let mut written = fds();
+        written.write_disk(1, 3, 0x55);
+        assert_eq!(written.read_disk(1, 3), 0x55);
+        assert_eq!(written.read_disk(0, 3), 0x01, "other side unchanged");
+        assert_eq!(
+            written.battery_ram(),
+            [0x01, 0x00, 0x00, 0x00, 0x80, 0x55, 0x2A].as_slice()
+        );
+
+        let mut loaded = fds();
+        loaded.load_battery_ram(&written.battery_ram());
+        assert_eq!(loaded.read_disk(1, 3), 0x55, "restored from save ram");
+        let mut loaded = fds();
+        loaded.load_battery_ram(&[0x01, 0x00, 0x00]);
+        assert!(loaded.sectors.is_empty(), "truncated sector ignored");
+
+        let state = bincode::serialize(&written).expect("valid serialize");
+        let mut loaded: Fds = bincode::deserialize(&state).expect("valid deserialize");
+        assert!(
+            loaded.sides.is_empty(),
+            "disk image excluded from save state"
+        );
+        loaded.load_rom_from(&mut fds().into());
+        assert_eq!(loaded.read_disk(1, 3), 0x55, "restored from save state");
+        assert_eq!(loaded.read_disk(1, 4), 0x2A);
+    }
+
+    #[test]
+    fn audio_output() {
+        let mut audio = FdsAudio::new();
+        audio.write_register(0x4089, 0x80);
+        for addr in 0x4040..=0x407F {
+            audio.write_register(addr, 0x3F);
+        }
+        audio.write_register(0x4089, 0x00);
+        audio.write_register(0x4080, 0xA0);
+        audio.write_register(0x4082, 0x00);
+        audio.write_register(0x4083, 0x01);
+        audio.clock();
+        assert_eq!(audio.out, 63, "full volume");
+        assert_eq!(audio.read_register(0x4090), 0x60);
+    }
+}
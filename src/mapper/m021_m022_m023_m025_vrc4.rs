@@ -0,0 +1,399 @@
+//! `VRC2` / `VRC4` (Mappers 021, 022, 023 and 025)
+//!
+//! <https://www.nesdev.org/wiki/VRC2_and_VRC4>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Kind, Regional, Reset},
+    mapper::{vrc_irq::VrcIrq, Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[must_use]
+pub enum Vrc4Revision {
+    /// VRC2a/b/c: no IRQ, PRG swap mode or 2-bit mirroring, and a one-bit microwire latch at
+    /// $6000-$6FFF in place of PRG-RAM.
+    Vrc2,
+    /// VRC4a/b/c/d/e/f
+    Vrc4,
+}
+
+/// Which CPU address lines are wired to the chip's two register select pins, and whether the
+/// lowest CHR bank bit is left unconnected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[must_use]
+pub struct Vrc4Wiring {
+    a0: u16,
+    a1: u16,
+    chr_shift: u8,
+}
+
+impl Vrc4Wiring {
+    /// VRC4a: A1, A2
+    pub const VRC4A: Self = Self::new(0x02, 0x04);
+    /// VRC4b and VRC2c: A1, A0
+    pub const VRC4B: Self = Self::new(0x02, 0x01);
+    /// VRC4c: A6, A7
+    pub const VRC4C: Self = Self::new(0x40, 0x80);
+    /// VRC4d: A3, A2
+    pub const VRC4D: Self = Self::new(0x08, 0x04);
+    /// VRC4e: A2, A3
+    pub const VRC4E: Self = Self::new(0x04, 0x08);
+    /// VRC4f and VRC2b: A0, A1
+    pub const VRC4F: Self = Self::new(0x01, 0x02);
+    /// VRC2a: A1, A0, with CHR A10 driven by the chip's CHR A11 output
+    pub const VRC2A: Self = Self {
+        chr_shift: 1,
+        ..Self::VRC4B
+    };
+
+    pub const fn new(a0: u16, a1: u16) -> Self {
+        Self {
+            a0,
+            a1,
+            chr_shift: 0,
+        }
+    }
+
+    /// Combines two wirings, for when the variant sharing a mapper number is unknown. Each pair
+    /// uses different address lines, so games written for either still select the right
+    /// registers.
+    pub const fn or(self, other: Self) -> Self {
+        Self::new(self.a0 | other.a0, self.a1 | other.a1)
+    }
+
+    /// Returns the wiring and revision for a mapper and NES 2.0 submapper number. `is_vrc2`
+    /// distinguishes VRC2 and VRC4 boards that share a mapper number without a submapper.
+    ///
+    /// <https://www.nesdev.org/wiki/NES_2.0_submappers#021,_023,_025:_Konami_VRC2/VRC4>
+    pub const fn from_mapper(
+        mapper_num: u16,
+        submapper_num: u8,
+        is_vrc2: bool,
+    ) -> (Self, Vrc4Revision) {
+        let revision = if is_vrc2 {
+            Vrc4Revision::Vrc2
+        } else {
+            Vrc4Revision::Vrc4
+        };
+        match (mapper_num, submapper_num) {
+            (21, 1) => (Self::VRC4A, Vrc4Revision::Vrc4),
+            (21, 2) => (Self::VRC4C, Vrc4Revision::Vrc4),
+            (21, _) => (Self::VRC4A.or(Self::VRC4C), Vrc4Revision::Vrc4),
+            (22, _) => (Self::VRC2A, Vrc4Revision::Vrc2),
+            (23, 1) => (Self::VRC4F, Vrc4Revision::Vrc4),
+            (23, 2) => (Self::VRC4E, Vrc4Revision::Vrc4),
+            (23, 3) => (Self::VRC4F, Vrc4Revision::Vrc2),
+            (23, _) => (Self::VRC4F.or(Self::VRC4E), revision),
+            (25, 1) => (Self::VRC4B, Vrc4Revision::Vrc4),
+            (25, 2) => (Self::VRC4D, Vrc4Revision::Vrc4),
+            (25, 3) => (Self::VRC4B, Vrc4Revision::Vrc2),
+            (_, _) => (Self::VRC4B.or(Self::VRC4D), revision),
+        }
+    }
+
+    /// Translates a CPU address into a canonical register address from $x000 to $x003.
+    #[must_use]
+    const fn register(self, addr: u16) -> u16 {
+        let mut reg = addr & 0xF000;
+        if addr & self.a0 != 0 {
+            reg |= 0x01;
+        }
+        if addr & self.a1 != 0 {
+            reg |= 0x02;
+        }
+        reg
+    }
+}
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Vrc4Regs {
+    prg: [u8; 2],
+    chr: [u16; 8],
+    prg_swap: bool,
+    latch: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Vrc4 {
+    regs: Vrc4Regs,
+    revision: Vrc4Revision,
+    wiring: Vrc4Wiring,
+    has_prg_ram: bool,
+    mirroring: Mirroring,
+    irq: VrcIrq,
+    chr_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+}
+
+impl Vrc4 {
+    const PRG_RAM_SIZE: usize = 8 * 1024;
+    const CHR_RAM_SIZE: usize = 8 * 1024;
+    const PRG_WINDOW: usize = 8 * 1024;
+    const CHR_WINDOW: usize = 1024;
+
+    pub fn load(cart: &mut Cart, revision: Vrc4Revision, wiring: Vrc4Wiring) -> Mapper {
+        if revision == Vrc4Revision::Vrc4 && !cart.has_prg_ram() {
+            cart.add_prg_ram(Self::PRG_RAM_SIZE);
+        }
+        if !cart.has_chr() {
+            cart.add_chr_ram(Self::CHR_RAM_SIZE);
+        }
+        let mut vrc4 = Self {
+            regs: Vrc4Regs::default(),
+            revision,
+            wiring,
+            has_prg_ram: cart.has_prg_ram(),
+            mirroring: cart.mirroring(),
+            irq: VrcIrq::default(),
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_len(), Self::CHR_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_WINDOW),
+        };
+        vrc4.update_prg_banks();
+        vrc4.into()
+    }
+
+    fn update_prg_banks(&mut self) {
+        let second_last_bank = self.prg_rom_banks.last().saturating_sub(1);
+        let last_bank = self.prg_rom_banks.last();
+        let prg0 = self.regs.prg[0].into();
+        if self.regs.prg_swap {
+            self.prg_rom_banks.set(0, second_last_bank);
+            self.prg_rom_banks.set(2, prg0);
+        } else {
+            self.prg_rom_banks.set(0, prg0);
+            self.prg_rom_banks.set(2, second_last_bank);
+        }
+        self.prg_rom_banks.set(1, self.regs.prg[1].into());
+        self.prg_rom_banks.set(3, last_bank);
+    }
+
+    fn write_chr(&mut self, reg: u16, val: u8) {
+        // $B000-$E003: Low and high nibbles of each 1K CHR bank, two banks per $1000 range
+        let bank = ((((reg >> 12) - 0x0B) << 1) | ((reg & 0x02) >> 1)) as usize;
+        let chr = &mut self.regs.chr[bank];
+        *chr = if reg & 0x01 == 0x01 {
+            (u16::from(val & 0x1F) << 4) | (*chr & 0x0F)
+        } else {
+            (*chr & 0x1F0) | u16::from(val & 0x0F)
+        };
+        let chr = *chr >> self.wiring.chr_shift;
+        self.chr_banks.set(bank, chr.into());
+    }
+}
+
+impl Mapped for Vrc4 {
+    #[inline]
+    fn irq_pending(&self) -> bool {
+        self.irq.pending()
+    }
+
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+}
+
+impl MemMap for Vrc4 {
+    // PPU $0000..=$1FFF Eight 1K switchable CHR banks
+    //
+    // CPU $6000..=$7FFF 8K PRG-RAM bank, fixed, or VRC2 microwire latch at $6000..=$6FFF
+    // CPU $8000..=$9FFF 8K switchable PRG-ROM bank or fixed to the second-last bank
+    // CPU $A000..=$BFFF 8K switchable PRG-ROM bank
+    // CPU $C000..=$DFFF 8K PRG-ROM bank, fixed to the second-last bank or switchable
+    // CPU $E000..=$FFFF 8K PRG-ROM bank, fixed to the last bank
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x6000..=0x7FFF if self.has_prg_ram => MappedRead::PrgRam((addr & 0x1FFF).into()),
+            // Only the lowest bit is driven, the rest is open bus
+            0x6000..=0x6FFF if self.revision == Vrc4Revision::Vrc2 => {
+                MappedRead::Data(((addr >> 8) as u8 & 0xFE) | self.regs.latch)
+            }
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr {
+            0x0000..=0x1FFF => return MappedWrite::Chr(self.chr_banks.translate(addr), val),
+            0x6000..=0x7FFF if self.has_prg_ram => {
+                return MappedWrite::PrgRam((addr & 0x1FFF).into(), val);
+            }
+            0x6000..=0x6FFF if self.revision == Vrc4Revision::Vrc2 => {
+                self.regs.latch = val & 0x01;
+                return MappedWrite::None;
+            }
+            0x8000..=0xFFFF => (),
+            _ => return MappedWrite::None,
+        }
+
+        let vrc4 = self.revision == Vrc4Revision::Vrc4;
+        match self.wiring.register(addr) {
+            0x8000..=0x8003 => {
+                self.regs.prg[0] = val & 0x1F;
+                self.update_prg_banks();
+            }
+            0x9000..=0x9001 if vrc4 => {
+                self.mirroring = match val & 0x03 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenA,
+                    _ => Mirroring::SingleScreenB,
+                };
+            }
+            0x9002..=0x9003 if vrc4 => {
+                // [.... ..M.] PRG swap mode
+                self.regs.prg_swap = val & 0x02 == 0x02;
+                self.update_prg_banks();
+            }
+            0x9000..=0x9003 => {
+                self.mirroring = if val & 0x01 == 0x01 {
+                    Mirroring::Horizontal
+                } else {
+                    Mirroring::Vertical
+                };
+            }
+            0xA000..=0xA003 => {
+                self.regs.prg[1] = val & 0x1F;
+                self.update_prg_banks();
+            }
+            reg @ 0xB000..=0xE003 => self.write_chr(reg, val),
+            0xF000 if vrc4 => {
+                let reload = (self.irq.reload() & 0xF0) | (val & 0x0F);
+                self.irq.write_reload(reload);
+            }
+            0xF001 if vrc4 => {
+                let reload = (val << 4) | (self.irq.reload() & 0x0F);
+                self.irq.write_reload(reload);
+            }
+            0xF002 if vrc4 => self.irq.write_control(val),
+            0xF003 if vrc4 => self.irq.acknowledge(),
+            _ => (),
+        }
+        MappedWrite::None
+    }
+}
+
+impl Clock for Vrc4 {
+    fn clock(&mut self) -> usize {
+        if self.revision == Vrc4Revision::Vrc4 {
+            self.irq.clock()
+        } else {
+            0
+        }
+    }
+}
+
+impl Reset for Vrc4 {
+    fn reset(&mut self, kind: Kind) {
+        self.irq.reset(kind);
+    }
+}
+
+impl Regional for Vrc4 {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_mapper;
+
+    fn load(mapper_num: u16, submapper_num: u8) -> Vrc4 {
+        let (wiring, revision) = Vrc4Wiring::from_mapper(mapper_num, submapper_num, false);
+        let (_, vrc4) = load_mapper(0x40000, 0x80000, |cart| Vrc4::load(cart, revision, wiring));
+        vrc4
+    }
+
+    #[test]
+    fn wiring() {
+        assert_eq!(Vrc4Wiring::VRC4A.register(0xB004), 0xB002);
+        assert_eq!(Vrc4Wiring::VRC4C.register(0xB040), 0xB001);
+        assert_eq!(Vrc4Wiring::VRC4B.register(0xB001), 0xB002);
+        assert_eq!(Vrc4Wiring::VRC4D.register(0xB00C), 0xB003);
+        assert_eq!(Vrc4Wiring::VRC4E.register(0xB004), 0xB001);
+        assert_eq!(Vrc4Wiring::VRC4F.register(0xB002), 0xB002);
+
+        let combined = Vrc4Wiring::VRC4B.or(Vrc4Wiring::VRC4D);
+        assert_eq!(combined.register(0xB002), 0xB001);
+        assert_eq!(combined.register(0xB008), 0xB001);
+        assert_eq!(combined.register(0xB004), 0xB002);
+
+        assert_eq!(
+            Vrc4Wiring::from_mapper(23, 3, false),
+            (Vrc4Wiring::VRC4F, Vrc4Revision::Vrc2)
+        );
+        assert_eq!(Vrc4Wiring::from_mapper(25, 0, true).1, Vrc4Revision::Vrc2);
+        assert_eq!(Vrc4Wiring::from_mapper(22, 0, false).1, Vrc4Revision::Vrc2);
+    }
+
+    #[test]
+    fn prg_banks() {
+        let mut vrc4 = load(21, 1);
+        let _ = vrc4.map_write(0x8000, 0x01);
+        let _ = vrc4.map_write(0xA000, 0x02);
+        assert!(matches!(vrc4.map_peek(0x8000), MappedRead::PrgRom(0x2000)));
+        assert!(matches!(vrc4.map_peek(0xA000), MappedRead::PrgRom(0x4000)));
+        assert!(matches!(vrc4.map_peek(0xC000), MappedRead::PrgRom(0x3C000)));
+        assert!(matches!(vrc4.map_peek(0xE000), MappedRead::PrgRom(0x3E000)));
+
+        let _ = vrc4.map_write(0x9004, 0x02);
+        assert!(matches!(vrc4.map_peek(0x8000), MappedRead::PrgRom(0x3C000)));
+        assert!(matches!(vrc4.map_peek(0xC000), MappedRead::PrgRom(0x2000)));
+
+        let _ = vrc4.map_write(0x9000, 0x03);
+        assert_eq!(vrc4.mirroring(), Mirroring::SingleScreenB);
+    }
+
+    #[test]
+    fn chr_banks() {
+        let mut vrc4 = load(25, 2);
+        let _ = vrc4.map_write(0xE004, 0x0F);
+        let _ = vrc4.map_write(0xE00C, 0x1F);
+        assert!(matches!(vrc4.map_peek(0x1C01), MappedRead::Chr(0x7FC01)));
+
+        let mut vrc2 = load(22, 0);
+        let _ = vrc2.map_write(0xB000, 0x03);
+        assert!(matches!(vrc2.map_peek(0x0001), MappedRead::Chr(0x0401)));
+    }
+
+    #[test]
+    fn vrc2_latch() {
+        let mut vrc2 = load(22, 0);
+        let _ = vrc2.map_write(0x6000, 0xFF);
+        assert!(matches!(vrc2.map_peek(0x6000), MappedRead::Data(0x61)));
+        assert!(matches!(vrc2.map_peek(0x7000), MappedRead::None));
+
+        // VRC2 only has 1-bit mirroring and ignores swap mode
+        let _ = vrc2.map_write(0x9002, 0x03);
+        assert_eq!(vrc2.mirroring(), Mirroring::Horizontal);
+        assert!(matches!(vrc2.map_peek(0xC000), MappedRead::PrgRom(0x3C000)));
+    }
+
+    #[test]
+    fn irq() {
+        let mut vrc4 = load(23, 2);
+        let _ = vrc4.map_write(0xF000, 0x0E);
+        let _ = vrc4.map_write(0xF004, 0x0F);
+        assert_eq!(vrc4.irq.reload(), 0xFE);
+        // Cycle mode, enabled
+        let _ = vrc4.map_write(0xF008, 0x06);
+        vrc4.clock();
+        assert!(!vrc4.irq_pending());
+        vrc4.clock();
+        assert!(vrc4.irq_pending());
+        let _ = vrc4.map_write(0xF00C, 0x00);
+        assert!(!vrc4.irq_pending());
+    }
+}
@@ -0,0 +1,232 @@
+//! `UNROM 512` (Mapper 030)
+//!
+//! <https://www.nesdev.org/wiki/UNROM_512>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Regional, Reset},
+    mapper::{flash::Flash, Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Unrom512 {
+    mirroring: Mirroring,
+    one_screen: bool,
+    chr_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+    flash: Option<Flash>,
+}
+
+impl Unrom512 {
+    const PRG_ROM_WINDOW: usize = 16 * 1024;
+    const CHR_WINDOW: usize = 8 * 1024;
+    const CHR_RAM_SIZE: usize = 32 * 1024;
+
+    const PRG_BANK_MASK: u8 = 0x1F; // 0b11111
+    const CHR_BANK_MASK: u8 = 0x60; // 0b1100000
+    const SINGLE_SCREEN_B: u8 = 0x80; // 0b10000000
+
+    // Four-screen nametables use the last 8K of CHR-RAM
+    const FOUR_SCREEN_OFFSET: usize = 0x6000;
+
+    /// `SingleScreenA` mirroring selects switchable one-screen mirroring. Boards with flash
+    /// save PRG-ROM writes as Save RAM.
+    pub fn load(cart: &mut Cart, mirroring: Mirroring, flash: bool) -> Mapper {
+        if !cart.has_chr() {
+            cart.add_chr_ram(Self::CHR_RAM_SIZE);
+        };
+        let mut unrom = Self {
+            mirroring,
+            one_screen: mirroring == Mirroring::SingleScreenA,
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_len(), Self::CHR_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_ROM_WINDOW),
+            flash: flash.then(|| Flash::new(cart.prg_rom.clone())),
+        };
+        let last_bank = unrom.prg_rom_banks.last();
+        unrom.prg_rom_banks.set(1, last_bank);
+        unrom.into()
+    }
+}
+
+impl Mapped for Unrom512 {
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+
+    fn battery_ram(&self) -> Cow<'_, [u8]> {
+        self.flash.as_ref().map_or(Cow::Borrowed(&[]), |flash| {
+            Cow::Owned(flash.modified_sectors())
+        })
+    }
+
+    fn load_battery_ram(&mut self, ram: &[u8]) {
+        if let Some(flash) = &mut self.flash {
+            flash.load_modified_sectors(ram);
+        }
+    }
+
+    fn load_rom_from(&mut self, other: &mut Mapper) {
+        if let (
+            Some(flash),
+            Mapper::Unrom512(Self {
+                flash: Some(other), ..
+            }),
+        ) = (&mut self.flash, other)
+        {
+            flash.load_rom_from(other);
+        }
+    }
+}
+
+impl MemMap for Unrom512 {
+    // PPU $0000..=$1FFF 8K CHR-RAM Bank Switchable
+    // PPU $2000..=$3EFF Four-screen nametables in CHR-RAM (optional)
+    // CPU $8000..=$BFFF 16K PRG-ROM Bank Switchable
+    // CPU $C000..=$FFFF 16K PRG-ROM Fixed to Last Bank
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x2000..=0x3EFF if self.mirroring == Mirroring::FourScreen => {
+                MappedRead::ChrRam(Self::FOUR_SCREEN_OFFSET | (addr & 0x1FFF) as usize)
+            }
+            0x8000..=0xFFFF => {
+                let addr = self.prg_rom_banks.translate(addr);
+                match &self.flash {
+                    Some(flash) => MappedRead::Data(flash.read(addr)),
+                    None => MappedRead::PrgRom(addr),
+                }
+            }
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr {
+            0x0000..=0x1FFF => MappedWrite::Chr(self.chr_banks.translate(addr), val),
+            0x2000..=0x3EFF if self.mirroring == Mirroring::FourScreen => {
+                MappedWrite::ChrRam(Self::FOUR_SCREEN_OFFSET | (addr & 0x1FFF) as usize, val)
+            }
+            // Flash boards only decode the bank register at $C000-$FFFF
+            0x8000..=0xBFFF if self.flash.is_some() => {
+                let addr = self.prg_rom_banks.translate(addr);
+                if let Some(flash) = &mut self.flash {
+                    flash.write(addr, val);
+                }
+                MappedWrite::None
+            }
+            0x8000..=0xFFFF => {
+                // 7654 3210
+                // MCCP PPPP
+                // |||+-++++- Select 16K PRG-ROM bank at $8000-$BFFF
+                // |++------- Select 8K CHR-RAM bank at $0000-$1FFF
+                // +--------- Select one-screen nametable, if enabled
+                self.prg_rom_banks
+                    .set(0, (val & Self::PRG_BANK_MASK).into());
+                self.chr_banks
+                    .set(0, ((val & Self::CHR_BANK_MASK) >> 5).into());
+                if self.one_screen {
+                    self.mirroring = if val & Self::SINGLE_SCREEN_B == Self::SINGLE_SCREEN_B {
+                        Mirroring::SingleScreenB
+                    } else {
+                        Mirroring::SingleScreenA
+                    };
+                }
+                MappedWrite::None
+            }
+            _ => MappedWrite::None,
+        }
+    }
+}
+
+impl Clock for Unrom512 {}
+impl Regional for Unrom512 {}
+impl Reset for Unrom512 {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_mapper;
+
+    fn load(mirroring: Mirroring, flash: bool) -> (Cart, Unrom512) {
+        load_mapper(0x80000, 0, |cart| {
+            // Erased flash reads as $FF
+            cart.prg_rom.fill(0xFF);
+            Unrom512::load(cart, mirroring, flash)
+        })
+    }
+
+    #[test]
+    fn banks() {
+        let (cart, mut unrom) = load(Mirroring::SingleScreenA, false);
+        assert_eq!(cart.chr_ram.len(), 0x8000);
+        assert_eq!(unrom.map_peek(0xC000), MappedRead::PrgRom(0x7C000));
+
+        let _ = unrom.map_write(0x8000, 0xE3);
+        assert_eq!(unrom.map_peek(0x8001), MappedRead::PrgRom(0xC001));
+        assert_eq!(unrom.map_peek(0x0001), MappedRead::Chr(0x6001));
+        assert_eq!(unrom.mirroring(), Mirroring::SingleScreenB);
+
+        // Fixed mirroring ignores the one-screen select
+        let (_, mut unrom) = load(Mirroring::Vertical, false);
+        let _ = unrom.map_write(0xC000, 0x80);
+        assert_eq!(unrom.mirroring(), Mirroring::Vertical);
+
+        let (_, mut unrom) = load(Mirroring::FourScreen, false);
+        assert_eq!(
+            unrom.map_write(0x2C01, 0x12),
+            MappedWrite::ChrRam(0x6C01, 0x12)
+        );
+    }
+
+    #[test]
+    fn flash() {
+        let (_, mut unrom) = load(Mirroring::Vertical, true);
+        assert!(unrom.battery_ram().is_empty());
+
+        // $5555 and $2AAA are mapped through the PRG-ROM bank at $8000-$BFFF
+        let mut write = |bank: u8, addr: u16, val: u8| {
+            let _ = unrom.map_write(0xC000, bank);
+            let _ = unrom.map_write(addr, val);
+        };
+        write(0x01, 0x9555, 0xAA);
+        write(0x00, 0xAAAA, 0x55);
+        write(0x01, 0x9555, 0xA0);
+        write(0x1E, 0x8010, 0x42);
+        assert_eq!(unrom.map_peek(0x8010), MappedRead::Data(0x42));
+        assert_eq!(unrom.battery_ram()[..1], [0x78]);
+
+        let sectors = unrom.battery_ram().to_vec();
+        let (_, mut unrom) = load(Mirroring::Vertical, true);
+        unrom.load_battery_ram(&sectors);
+        let _ = unrom.map_write(0xC000, 0x1E);
+        assert_eq!(unrom.map_peek(0x8010), MappedRead::Data(0x42));
+    }
+
+    #[test]
+    fn flash_save_state_excludes_rom() {
+        let (_, mut unrom) = load(Mirroring::Vertical, true);
+        let sectors = [vec![0x7F, 0x00], vec![0xFF; 0x0FFF]].concat();
+        unrom.load_battery_ram(&sectors);
+
+        let state = bincode::serialize(&unrom).expect("valid serialize");
+        assert!(state.len() < 0x2000, "state size: {}", state.len());
+        let mut loaded: Unrom512 = bincode::deserialize(&state).expect("valid deserialize");
+        loaded.load_rom_from(&mut unrom.into());
+        let _ = loaded.map_write(0xC000, 0x1F);
+        assert_eq!(loaded.map_peek(0xB000), MappedRead::Data(0x00));
+        assert_eq!(loaded.map_peek(0xB001), MappedRead::Data(0xFF));
+        assert_eq!(loaded.map_peek(0xA000), MappedRead::Data(0xFF));
+    }
+}
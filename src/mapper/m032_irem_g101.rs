@@ -0,0 +1,186 @@
+//! `Irem G-101` (Mapper 032)
+//!
+//! <https://wiki.nesdev.org/w/index.php?title=INES_Mapper_032>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[must_use]
+pub enum IremG101Revision {
+    /// PRG mode and mirroring selected through $9000.
+    G101,
+    /// Major League - fixed PRG mode and one-screen mirroring.
+    MajorLeague,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct IremG101 {
+    revision: IremG101Revision,
+    prg_mode: bool,
+    prg_banks: [u8; 2],
+    mirroring: Mirroring,
+    chr_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+}
+
+impl IremG101 {
+    const PRG_WINDOW: usize = 8 * 1024;
+    const CHR_WINDOW: usize = 1024;
+
+    const PRG_MODE_MASK: u8 = 0x02; // 0b10
+    const MIRRORING_MASK: u8 = 0x01; // 0b1
+
+    pub fn load(cart: &mut Cart, revision: IremG101Revision) -> Mapper {
+        let mut g101 = Self {
+            revision,
+            prg_mode: false,
+            prg_banks: [0x00; 2],
+            mirroring: match revision {
+                IremG101Revision::G101 => cart.mirroring(),
+                IremG101Revision::MajorLeague => Mirroring::SingleScreenA,
+            },
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_rom.len(), Self::CHR_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_WINDOW),
+        };
+        g101.update_prg_banks();
+        g101.into()
+    }
+
+    fn update_prg_banks(&mut self) {
+        let second_last = self.prg_rom_banks.last() - 1;
+        let [prg0, prg1] = self.prg_banks.map(usize::from);
+        if self.prg_mode {
+            self.prg_rom_banks.set(0, second_last);
+            self.prg_rom_banks.set(2, prg0);
+        } else {
+            self.prg_rom_banks.set(0, prg0);
+            self.prg_rom_banks.set(2, second_last);
+        }
+        self.prg_rom_banks.set(1, prg1);
+        self.prg_rom_banks.set(3, second_last + 1);
+    }
+}
+
+impl MemMap for IremG101 {
+    // PPU $0000..=$1FFF Eight 1K CHR-ROM Banks Switchable
+    // CPU $8000..=$9FFF (or $C000..=$DFFF) 8K PRG-ROM Bank Switchable
+    // CPU $A000..=$BFFF 8K PRG-ROM Bank Switchable
+    // CPU $C000..=$DFFF (or $8000..=$9FFF) 8K PRG-ROM Bank Fixed to second-to-last Bank
+    // CPU $E000..=$FFFF 8K PRG-ROM Bank Fixed to Last
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr & 0xF007 {
+            0x8000..=0x8007 => {
+                self.prg_banks[0] = val & 0x1F;
+                self.update_prg_banks();
+            }
+            0x9000..=0x9007 if self.revision == IremG101Revision::G101 => {
+                // 7654 3210
+                // .... ..PM
+                //        |+- Mirroring (0: Vertical; 1: Horizontal)
+                //        +-- PRG-ROM mode (0: $8000 switchable; 1: $C000 switchable)
+                self.prg_mode = val & Self::PRG_MODE_MASK == Self::PRG_MODE_MASK;
+                self.mirroring = if val & Self::MIRRORING_MASK == Self::MIRRORING_MASK {
+                    Mirroring::Horizontal
+                } else {
+                    Mirroring::Vertical
+                };
+                self.update_prg_banks();
+            }
+            0xA000..=0xA007 => {
+                self.prg_banks[1] = val & 0x1F;
+                self.update_prg_banks();
+            }
+            0xB000..=0xB007 => self.chr_banks.set((addr & 0x07).into(), val.into()),
+            _ => (),
+        }
+        MappedWrite::None
+    }
+}
+
+impl Mapped for IremG101 {
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+}
+
+impl Clock for IremG101 {}
+impl Regional for IremG101 {}
+impl Reset for IremG101 {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::{load_mapper, test_rom_results};
+
+    fn load(revision: IremG101Revision) -> IremG101 {
+        let (_, g101) = load_mapper(0x40000, 0x20000, |cart| IremG101::load(cart, revision));
+        g101
+    }
+
+    #[test]
+    fn banks() {
+        let mut g101 = load(IremG101Revision::G101);
+        let _ = g101.map_write(0x8000, 0x02);
+        let _ = g101.map_write(0xA007, 0x05);
+        let _ = g101.map_write(0xB003, 0x7F);
+        assert_eq!(g101.map_peek(0x8000), MappedRead::PrgRom(0x4000));
+        assert_eq!(g101.map_peek(0xA000), MappedRead::PrgRom(0xA000));
+        assert_eq!(g101.map_peek(0xC000), MappedRead::PrgRom(0x3C000));
+        assert_eq!(g101.map_peek(0xE000), MappedRead::PrgRom(0x3E000));
+        assert_eq!(g101.map_peek(0x0C01), MappedRead::Chr(0x1FC01));
+
+        let _ = g101.map_write(0x9000, 0x03);
+        assert_eq!(g101.map_peek(0x8000), MappedRead::PrgRom(0x3C000));
+        assert_eq!(g101.map_peek(0xC000), MappedRead::PrgRom(0x4000));
+        assert_eq!(g101.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn major_league() {
+        let mut g101 = load(IremG101Revision::MajorLeague);
+        let _ = g101.map_write(0x9000, 0x03);
+        assert_eq!(g101.map_peek(0x8000), MappedRead::PrgRom(0x0000));
+        assert_eq!(g101.mirroring(), Mirroring::SingleScreenA);
+    }
+
+    #[test]
+    fn g101() {
+        // See `test_roms/mapper/src/g101.s`
+        let results = test_rom_results("test_roms/mapper/m032_irem_g101/g101.nes", 3);
+        assert_eq!(results[0..4], [4, 6, 14, 15], "prg mode 0");
+        assert_eq!(results[4..8], [14, 6, 4, 15], "prg mode 1");
+        assert_eq!(
+            results[8..16],
+            [17, 34, 51, 68, 85, 102, 119, 127],
+            "chr banks"
+        );
+        assert_eq!(
+            results[16..],
+            [0xC3, 0xB2, 0xC3, 0xB2, 0xB2, 0xB2, 0xC3, 0xC3],
+            "vertical, then horizontal mirroring"
+        );
+    }
+}
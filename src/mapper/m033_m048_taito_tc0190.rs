@@ -0,0 +1,282 @@
+//! `Taito TC0190` (Mapper 033)
+//! `Taito TC0690` (Mapper 048)
+//!
+//! <https://wiki.nesdev.org/w/index.php?title=INES_Mapper_033>
+//! <https://wiki.nesdev.org/w/index.php?title=INES_Mapper_048>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Kind, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[must_use]
+pub enum TaitoTc0190Revision {
+    /// `TC0190` - mirroring in $8000 bit 6, without IRQs.
+    Tc0190,
+    /// `TC0690` - mirroring moved to $E000 and a scanline IRQ counter like `MMC3`.
+    Tc0690,
+}
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct TaitoTc0690Irq {
+    latch: u8,
+    counter: u8,
+    reload: bool,
+    enabled: bool,
+    pending: bool,
+    last_a12: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct TaitoTc0190 {
+    revision: TaitoTc0190Revision,
+    irq: TaitoTc0690Irq,
+    mirroring: Mirroring,
+    chr_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+}
+
+impl TaitoTc0190 {
+    const PRG_WINDOW: usize = 8 * 1024;
+    const CHR_WINDOW: usize = 1024;
+
+    const MIRRORING_MASK: u8 = 0x40; // 0b1000000
+
+    pub fn load(cart: &mut Cart, revision: TaitoTc0190Revision) -> Mapper {
+        let mut taito = Self {
+            revision,
+            irq: TaitoTc0690Irq::default(),
+            mirroring: cart.mirroring(),
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_rom.len(), Self::CHR_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_WINDOW),
+        };
+        let last_bank = taito.prg_rom_banks.last();
+        taito.prg_rom_banks.set(2, last_bank - 1);
+        taito.prg_rom_banks.set(3, last_bank);
+        taito.into()
+    }
+
+    #[inline]
+    const fn mirroring_bit(val: u8) -> Mirroring {
+        if val & Self::MIRRORING_MASK == Self::MIRRORING_MASK {
+            Mirroring::Horizontal
+        } else {
+            Mirroring::Vertical
+        }
+    }
+
+    /// Clocks the `TC0690` IRQ counter on rising edges of PPU A12, once per scanline during
+    /// rendering.
+    fn clock_irq(&mut self, addr: u16) {
+        if addr < 0x2000 && self.revision == TaitoTc0190Revision::Tc0690 {
+            let a12 = (addr >> 12) & 0x01;
+            if self.irq.last_a12 == 0 && a12 == 1 {
+                if self.irq.counter == 0 || self.irq.reload {
+                    self.irq.counter = self.irq.latch;
+                } else {
+                    self.irq.counter -= 1;
+                }
+                if self.irq.counter == 0 && self.irq.enabled {
+                    self.irq.pending = true;
+                }
+                self.irq.reload = false;
+            }
+            self.irq.last_a12 = a12;
+        }
+    }
+}
+
+impl Mapped for TaitoTc0190 {
+    #[inline]
+    fn irq_pending(&self) -> bool {
+        self.irq.pending
+    }
+
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+
+    #[inline]
+    fn ppu_bus_read(&mut self, addr: u16) {
+        self.clock_irq(addr);
+    }
+
+    #[inline]
+    fn ppu_bus_write(&mut self, addr: u16, _val: u8) {
+        self.clock_irq(addr);
+    }
+}
+
+impl MemMap for TaitoTc0190 {
+    // PPU $0000..=$07FF 2K CHR-ROM Bank Switchable
+    // PPU $0800..=$0FFF 2K CHR-ROM Bank Switchable
+    // PPU $1000..=$1FFF Four 1K CHR-ROM Banks Switchable
+    // CPU $8000..=$9FFF 8K PRG-ROM Bank Switchable
+    // CPU $A000..=$BFFF 8K PRG-ROM Bank Switchable
+    // CPU $C000..=$DFFF 8K PRG-ROM Bank Fixed to second-to-last Bank
+    // CPU $E000..=$FFFF 8K PRG-ROM Bank Fixed to Last
+
+    #[inline]
+    fn map_read(&mut self, addr: u16) -> MappedRead {
+        self.clock_irq(addr);
+        self.map_peek(addr)
+    }
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        let tc0690 = self.revision == TaitoTc0190Revision::Tc0690;
+        match addr & 0xE003 {
+            0x8000 => {
+                // TC0190 shares $8000 between mirroring and the PRG-ROM bank
+                if tc0690 {
+                    self.prg_rom_banks.set(0, val.into());
+                } else {
+                    self.mirroring = Self::mirroring_bit(val);
+                    self.prg_rom_banks.set(0, (val & 0x3F).into());
+                }
+            }
+            0x8001 => self.prg_rom_banks.set(1, (val & 0x3F).into()),
+            0x8002 => self.chr_banks.set_range(0, 1, usize::from(val) << 1),
+            0x8003 => self.chr_banks.set_range(2, 3, usize::from(val) << 1),
+            0xA000..=0xA003 => self.chr_banks.set(4 + usize::from(addr & 0x03), val.into()),
+            // The IRQ latch is written inverted
+            0xC000 if tc0690 => self.irq.latch = val ^ 0xFF,
+            0xC001 if tc0690 => {
+                self.irq.counter = 0;
+                self.irq.reload = true;
+            }
+            0xC002 if tc0690 => self.irq.enabled = true,
+            0xC003 if tc0690 => {
+                self.irq.enabled = false;
+                self.irq.pending = false;
+            }
+            0xE000 if tc0690 => self.mirroring = Self::mirroring_bit(val),
+            _ => (),
+        }
+        MappedWrite::None
+    }
+}
+
+impl Reset for TaitoTc0190 {
+    fn reset(&mut self, _kind: Kind) {
+        self.irq = TaitoTc0690Irq::default();
+    }
+}
+
+impl Clock for TaitoTc0190 {}
+impl Regional for TaitoTc0190 {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::{load_mapper, test_rom_irq_scanlines, test_rom_results};
+
+    fn load(revision: TaitoTc0190Revision) -> TaitoTc0190 {
+        let (_, taito) = load_mapper(0x40000, 0x40000, |cart| TaitoTc0190::load(cart, revision));
+        taito
+    }
+
+    #[test]
+    fn banks() {
+        let mut tc0190 = load(TaitoTc0190Revision::Tc0190);
+        let _ = tc0190.map_write(0x8000, 0x45);
+        let _ = tc0190.map_write(0x8001, 0x06);
+        let _ = tc0190.map_write(0x8003, 0x03);
+        let _ = tc0190.map_write(0xA002, 0x11);
+        assert_eq!(tc0190.map_peek(0x8000), MappedRead::PrgRom(0xA000));
+        assert_eq!(tc0190.map_peek(0xA000), MappedRead::PrgRom(0xC000));
+        assert_eq!(tc0190.map_peek(0xC000), MappedRead::PrgRom(0x3C000));
+        assert_eq!(tc0190.map_peek(0x0C01), MappedRead::Chr(0x1C01));
+        assert_eq!(tc0190.map_peek(0x1801), MappedRead::Chr(0x4401));
+        assert_eq!(tc0190.mirroring(), Mirroring::Horizontal);
+
+        // TC0690 uses all of $8000 for the PRG-ROM bank
+        let mut tc0690 = load(TaitoTc0190Revision::Tc0690);
+        let _ = tc0690.map_write(0x8000, 0x45);
+        assert_eq!(tc0690.map_peek(0x8000), MappedRead::PrgRom(0xA000));
+        assert_eq!(tc0690.mirroring(), Mirroring::Horizontal);
+        let _ = tc0690.map_write(0xE000, 0x40);
+        assert_eq!(tc0690.mirroring(), Mirroring::Horizontal);
+        let _ = tc0690.map_write(0xE000, 0x00);
+        assert_eq!(tc0690.mirroring(), Mirroring::Vertical);
+    }
+
+    fn scanline(taito: &mut TaitoTc0190) {
+        taito.ppu_bus_read(0x0000);
+        taito.ppu_bus_read(0x1000);
+    }
+
+    #[test]
+    fn irq() {
+        let mut tc0690 = load(TaitoTc0190Revision::Tc0690);
+        let _ = tc0690.map_write(0xC000, 0xFD);
+        let _ = tc0690.map_write(0xC001, 0x00);
+        let _ = tc0690.map_write(0xC002, 0x00);
+        scanline(&mut tc0690);
+        scanline(&mut tc0690);
+        assert!(!tc0690.irq_pending());
+        scanline(&mut tc0690);
+        assert!(tc0690.irq_pending());
+
+        let _ = tc0690.map_write(0xC003, 0x00);
+        assert!(!tc0690.irq_pending());
+
+        // TC0190 has no IRQ counter
+        let mut tc0190 = load(TaitoTc0190Revision::Tc0190);
+        let _ = tc0190.map_write(0xC000, 0xFF);
+        let _ = tc0190.map_write(0xC002, 0x00);
+        scanline(&mut tc0190);
+        assert!(!tc0190.irq_pending());
+    }
+
+    fn assert_banks(results: &[u8]) {
+        assert_eq!(results[0..4], [5, 7, 14, 15], "prg banks");
+        assert_eq!(results[4..12], [8, 9, 20, 21, 40, 50, 60, 70], "chr banks");
+        assert_eq!(
+            results[12..],
+            [0xC3, 0xB2, 0xC3, 0xB2, 0xB2, 0xB2, 0xC3, 0xC3],
+            "vertical, then horizontal mirroring"
+        );
+    }
+
+    #[test]
+    fn tc0190() {
+        // See `test_roms/mapper/src/tc0190.s`
+        assert_banks(&test_rom_results(
+            "test_roms/mapper/m033_m048_taito_tc0190/tc0190.nes",
+            3,
+        ));
+    }
+
+    #[test]
+    fn tc0690() {
+        // See `test_roms/mapper/src/tc0690.s`
+        let path = "test_roms/mapper/m033_m048_taito_tc0190/tc0690.nes";
+        assert_banks(&test_rom_results(path, 3));
+        assert_eq!(
+            test_rom_irq_scanlines(path, 6),
+            [99, 150, 99, 150],
+            "irq scanlines"
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! `BNROM`/`NINA-001` (Mapper 034)
+//!
+//! <https://wiki.nesdev.org/w/index.php?title=INES_Mapper_034>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[must_use]
+pub enum BnromRevision {
+    /// `BNROM` - 32K PRG-ROM bank select at $8000-$FFFF with fixed CHR-RAM.
+    Bnrom,
+    /// `NINA-001` - PRG-RAM with PRG-ROM and 4K CHR-ROM bank selects at $7FFD-$7FFF.
+    Nina001,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Bnrom {
+    revision: BnromRevision,
+    mirroring: Mirroring,
+    chr_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+}
+
+impl Bnrom {
+    const PRG_ROM_WINDOW: usize = 32 * 1024;
+    const PRG_RAM_SIZE: usize = 8 * 1024;
+    const CHR_WINDOW: usize = 4 * 1024;
+    const CHR_RAM_SIZE: usize = 8 * 1024;
+
+    pub fn load(cart: &mut Cart, revision: BnromRevision) -> Mapper {
+        if revision == BnromRevision::Nina001 {
+            cart.add_prg_ram(Self::PRG_RAM_SIZE);
+        }
+        if !cart.has_chr() {
+            cart.add_chr_ram(Self::CHR_RAM_SIZE);
+        };
+        let bnrom = Self {
+            revision,
+            mirroring: cart.mirroring(),
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_len(), Self::CHR_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_ROM_WINDOW),
+        };
+        bnrom.into()
+    }
+}
+
+impl MemMap for Bnrom {
+    // BNROM
+    // PPU $0000..=$1FFF 8K CHR-RAM Bank Fixed
+    // CPU $8000..=$FFFF 32K PRG-ROM Bank Switchable
+
+    // NINA-001
+    // PPU $0000..=$0FFF 4K CHR-ROM Bank Switchable
+    // PPU $1000..=$1FFF 4K CHR-ROM Bank Switchable
+    // CPU $6000..=$7FFF 8K PRG-RAM Bank Fixed
+    // CPU $8000..=$FFFF 32K PRG-ROM Bank Switchable
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x6000..=0x7FFF if self.revision == BnromRevision::Nina001 => {
+                MappedRead::PrgRam((addr & 0x1FFF).into())
+            }
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match (self.revision, addr) {
+            (_, 0x0000..=0x1FFF) => MappedWrite::Chr(self.chr_banks.translate(addr), val),
+            (BnromRevision::Nina001, 0x6000..=0x7FFF) => {
+                // Bank registers also write through to PRG-RAM
+                match addr {
+                    0x7FFD => self.prg_rom_banks.set(0, (val & 0x01).into()),
+                    0x7FFE => self.chr_banks.set(0, (val & 0x0F).into()),
+                    0x7FFF => self.chr_banks.set(1, (val & 0x0F).into()),
+                    _ => (),
+                }
+                MappedWrite::PrgRam((addr & 0x1FFF).into(), val)
+            }
+            (BnromRevision::Bnrom, 0x8000..=0xFFFF) => {
+                self.prg_rom_banks.set(0, val.into());
+                MappedWrite::None
+            }
+            _ => MappedWrite::None,
+        }
+    }
+}
+
+impl Mapped for Bnrom {
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+}
+
+impl Clock for Bnrom {}
+impl Regional for Bnrom {}
+impl Reset for Bnrom {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_mapper;
+
+    fn load(revision: BnromRevision, chr_len: usize) -> (Cart, Bnrom) {
+        load_mapper(0x20000, chr_len, |cart| Bnrom::load(cart, revision))
+    }
+
+    #[test]
+    fn bnrom() {
+        let (cart, mut bnrom) = load(BnromRevision::Bnrom, 0);
+        assert!(cart.prg_ram.is_empty());
+        assert_eq!(cart.chr_ram.len(), 0x2000);
+
+        let _ = bnrom.map_write(0x8000, 0x03);
+        assert_eq!(bnrom.map_peek(0x8001), MappedRead::PrgRom(0x18001));
+        assert_eq!(bnrom.map_peek(0x1001), MappedRead::Chr(0x1001));
+        assert_eq!(bnrom.map_write(0x7FFD, 0x00), MappedWrite::None);
+        assert_eq!(bnrom.map_peek(0x8001), MappedRead::PrgRom(0x18001));
+    }
+
+    #[test]
+    fn nina001() {
+        let (cart, mut nina) = load(BnromRevision::Nina001, 0x10000);
+        assert_eq!(cart.prg_ram.len(), 0x2000);
+
+        assert_eq!(
+            nina.map_write(0x7FFD, 0x01),
+            MappedWrite::PrgRam(0x1FFD, 0x01)
+        );
+        let _ = nina.map_write(0x7FFE, 0x02);
+        let _ = nina.map_write(0x7FFF, 0x05);
+        assert_eq!(nina.map_peek(0x8001), MappedRead::PrgRom(0x8001));
+        assert_eq!(nina.map_peek(0x0001), MappedRead::Chr(0x2001));
+        assert_eq!(nina.map_peek(0x1001), MappedRead::Chr(0x5001));
+
+        // BNROM register writes are ignored
+        let _ = nina.map_write(0x8000, 0x00);
+        assert_eq!(nina.map_peek(0x8001), MappedRead::PrgRom(0x8001));
+    }
+}
@@ -0,0 +1,145 @@
+//! `Caltron 6-in-1` multicart (Mapper 041)
+//!
+//! <https://wiki.nesdev.org/w/index.php?title=INES_Mapper_041>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Kind, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Caltron {
+    outer_bank: u8,
+    inner_bank: u8,
+    mirroring: Mirroring,
+    chr_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+}
+
+impl Caltron {
+    const PRG_ROM_WINDOW: usize = 32 * 1024;
+    const CHR_WINDOW: usize = 8 * 1024;
+
+    const PRG_BANK_MASK: u8 = 0x07; // 0b111
+    const INNER_ENABLE_MASK: u8 = 0x04; // 0b100
+    const CHR_OUTER_BANK_MASK: u8 = 0x18; // 0b11000
+    const MIRRORING_MASK: u8 = 0x20; // 0b100000
+    const CHR_INNER_BANK_MASK: u8 = 0x03; // 0b11
+
+    pub fn load(cart: &mut Cart) -> Mapper {
+        let mut caltron = Self {
+            outer_bank: 0x00,
+            inner_bank: 0x00,
+            mirroring: Mirroring::Vertical,
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_rom.len(), Self::CHR_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_ROM_WINDOW),
+        };
+        caltron.update_banks();
+        caltron.into()
+    }
+
+    fn update_banks(&mut self) {
+        self.prg_rom_banks
+            .set(0, (self.outer_bank & Self::PRG_BANK_MASK).into());
+        let chr_bank = ((self.outer_bank & Self::CHR_OUTER_BANK_MASK) >> 1) | self.inner_bank;
+        self.chr_banks.set(0, chr_bank.into());
+        self.mirroring = if self.outer_bank & Self::MIRRORING_MASK == Self::MIRRORING_MASK {
+            Mirroring::Horizontal
+        } else {
+            Mirroring::Vertical
+        };
+    }
+}
+
+impl MemMap for Caltron {
+    // PPU $0000..=$1FFF 8K CHR-ROM Bank Switchable
+    // CPU $8000..=$FFFF 32K PRG-ROM Bank Switchable
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr {
+            // $6000-$67FF: Outer bank select, latched from the address
+            // 7654 3210
+            // ..MC CPPP
+            //   || ||||
+            //   || |+++- Select 32K PRG-ROM bank, bit 2 also enables the inner CHR-ROM bank
+            //   || |     select
+            //   |+-+---- Select bits 2-3 of the 8K CHR-ROM bank
+            //   +------- Mirroring (0: Vertical; 1: Horizontal)
+            0x6000..=0x67FF => {
+                self.outer_bank = (addr & 0x3F) as u8;
+                self.update_banks();
+            }
+            // $8000-$FFFF: Bits 0-1 of the 8K CHR-ROM bank, only while enabled by the outer bank
+            0x8000..=0xFFFF if self.outer_bank & Self::INNER_ENABLE_MASK != 0 => {
+                self.inner_bank = val & Self::CHR_INNER_BANK_MASK;
+                self.update_banks();
+            }
+            _ => (),
+        }
+        MappedWrite::None
+    }
+}
+
+impl Mapped for Caltron {
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+}
+
+impl Reset for Caltron {
+    // The board clears both registers on reset to return to the menu
+    fn reset(&mut self, _kind: Kind) {
+        self.outer_bank = 0x00;
+        self.inner_bank = 0x00;
+        self.update_banks();
+    }
+}
+
+impl Clock for Caltron {}
+impl Regional for Caltron {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_mapper;
+
+    #[test]
+    fn banks() {
+        let (_, mut caltron): (_, Caltron) = load_mapper(0x40000, 0x20000, Caltron::load);
+
+        // Inner bank writes are ignored until enabled
+        let _ = caltron.map_write(0x8000, 0x03);
+        assert_eq!(caltron.map_peek(0x0000), MappedRead::Chr(0x0000));
+
+        let _ = caltron.map_write(0x6000 | 0x2E, 0x00);
+        assert_eq!(caltron.map_peek(0x8000), MappedRead::PrgRom(0x30000));
+        assert_eq!(caltron.map_peek(0x0000), MappedRead::Chr(0x8000));
+        assert_eq!(caltron.mirroring(), Mirroring::Horizontal);
+
+        let _ = caltron.map_write(0x8000, 0x03);
+        assert_eq!(caltron.map_peek(0x0001), MappedRead::Chr(0xE001));
+
+        caltron.reset(Kind::Soft);
+        assert_eq!(caltron.map_peek(0x8000), MappedRead::PrgRom(0x0000));
+        assert_eq!(caltron.map_peek(0x0001), MappedRead::Chr(0x0001));
+    }
+}
@@ -0,0 +1,365 @@
+//! `Tengen RAMBO-1` (Mapper 064)
+//!
+//! <https://wiki.nesdev.org/w/index.php?title=RAMBO-1>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Kind, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Rambo1Regs {
+    bank_select: u8,
+    bank_values: [u8; 16],
+}
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Rambo1Irq {
+    latch: u8,
+    counter: u8,
+    reload: bool,
+    enabled: bool,
+    pending: bool,
+    /// Counts CPU cycles instead of scanlines, clocking the counter every 4 cycles.
+    cycle_mode: bool,
+    prescaler: u8,
+    last_a12: u16,
+}
+
+impl Rambo1Irq {
+    fn clock_a12(&mut self, addr: u16) {
+        if addr < 0x2000 {
+            let a12 = (addr >> 12) & 0x01;
+            if !self.cycle_mode && self.last_a12 == 0 && a12 == 1 {
+                self.clock();
+            }
+            self.last_a12 = a12;
+        }
+    }
+}
+
+impl Clock for Rambo1Irq {
+    fn clock(&mut self) -> usize {
+        if self.reload {
+            // Reloads count one extra clock compared to `MMC3`
+            self.counter = if self.latch <= 1 {
+                self.latch.wrapping_add(1)
+            } else {
+                self.latch.wrapping_add(2)
+            };
+            self.reload = false;
+        } else if self.counter == 0 {
+            self.counter = self.latch.wrapping_add(1);
+        }
+        self.counter = self.counter.wrapping_sub(1);
+        if self.counter == 0 && self.enabled {
+            self.pending = true;
+        }
+        1
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Rambo1 {
+    regs: Rambo1Regs,
+    irq: Rambo1Irq,
+    mirroring: Mirroring,
+    chr_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+}
+
+impl Rambo1 {
+    const PRG_WINDOW: usize = 8 * 1024;
+    const CHR_WINDOW: usize = 1024;
+
+    const CHR_INVERSION_MASK: u8 = 0x80; // 0b10000000
+    const PRG_MODE_MASK: u8 = 0x40; // 0b1000000
+    const CHR_1K_MODE_MASK: u8 = 0x20; // 0b100000
+
+    pub fn load(cart: &mut Cart) -> Mapper {
+        let mut rambo1 = Self {
+            regs: Rambo1Regs::default(),
+            irq: Rambo1Irq::default(),
+            mirroring: cart.mirroring(),
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_len(), Self::CHR_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_WINDOW),
+        };
+        rambo1.update_banks();
+        rambo1.into()
+    }
+
+    fn update_banks(&mut self) {
+        let regs = self.regs.bank_values.map(usize::from);
+
+        // R6, R7 and RF rotate through $8000-$DFFF depending on the PRG mode
+        let prg_banks = if self.regs.bank_select & Self::PRG_MODE_MASK == Self::PRG_MODE_MASK {
+            [regs[15], regs[6], regs[7]]
+        } else {
+            [regs[6], regs[7], regs[15]]
+        };
+        for (slot, bank) in prg_banks.into_iter().enumerate() {
+            self.prg_rom_banks.set(slot, bank);
+        }
+        let last_bank = self.prg_rom_banks.last();
+        self.prg_rom_banks.set(3, last_bank);
+
+        // R0 and R1 select 2K banks, unless R8 and R9 are enabled to select the odd 1K halves
+        let chr_banks = if self.regs.bank_select & Self::CHR_1K_MODE_MASK == Self::CHR_1K_MODE_MASK
+        {
+            [
+                regs[0], regs[8], regs[1], regs[9], regs[2], regs[3], regs[4], regs[5],
+            ]
+        } else {
+            [
+                regs[0] & !0x01,
+                regs[0] | 0x01,
+                regs[1] & !0x01,
+                regs[1] | 0x01,
+                regs[2],
+                regs[3],
+                regs[4],
+                regs[5],
+            ]
+        };
+        let inversion =
+            if self.regs.bank_select & Self::CHR_INVERSION_MASK == Self::CHR_INVERSION_MASK {
+                4
+            } else {
+                0
+            };
+        for (slot, bank) in chr_banks.into_iter().enumerate() {
+            self.chr_banks.set(slot ^ inversion, bank);
+        }
+    }
+}
+
+impl Mapped for Rambo1 {
+    #[inline]
+    fn irq_pending(&self) -> bool {
+        self.irq.pending
+    }
+
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+
+    #[inline]
+    fn ppu_bus_read(&mut self, addr: u16) {
+        self.irq.clock_a12(addr);
+    }
+
+    #[inline]
+    fn ppu_bus_write(&mut self, addr: u16, _val: u8) {
+        self.irq.clock_a12(addr);
+    }
+}
+
+impl MemMap for Rambo1 {
+    // PPU $0000..=$1FFF Two 2K and four 1K, or eight 1K CHR-ROM Banks Switchable
+    // CPU $8000..=$DFFF Three 8K PRG-ROM Banks Switchable
+    // CPU $E000..=$FFFF 8K PRG-ROM Bank Fixed to Last
+
+    #[inline]
+    fn map_read(&mut self, addr: u16) -> MappedRead {
+        self.irq.clock_a12(addr);
+        self.map_peek(addr)
+    }
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr & 0xE001 {
+            0x0000..=0x1FFF => return MappedWrite::Chr(self.chr_banks.translate(addr), val),
+            0x8000 => {
+                // 7654 3210
+                // CPK. RRRR
+                // |||  ++++- Select register for the next write to $8001
+                // ||+------- Enable 1K CHR banks through R8 and R9
+                // |+-------- PRG-ROM bank mode
+                // +--------- CHR A12 inversion
+                self.regs.bank_select = val;
+                self.update_banks();
+            }
+            0x8001 => {
+                self.regs.bank_values[usize::from(self.regs.bank_select & 0x0F)] = val;
+                self.update_banks();
+            }
+            0xA000 => {
+                self.mirroring = if val & 0x01 == 0x01 {
+                    Mirroring::Horizontal
+                } else {
+                    Mirroring::Vertical
+                };
+            }
+            0xC000 => self.irq.latch = val,
+            0xC001 => {
+                self.irq.cycle_mode = val & 0x01 == 0x01;
+                self.irq.prescaler = 0;
+                self.irq.reload = true;
+            }
+            0xE000 => {
+                self.irq.enabled = false;
+                self.irq.pending = false;
+            }
+            0xE001 => self.irq.enabled = true,
+            _ => (),
+        }
+        MappedWrite::None
+    }
+}
+
+impl Clock for Rambo1 {
+    fn clock(&mut self) -> usize {
+        if self.irq.cycle_mode {
+            self.irq.prescaler = (self.irq.prescaler + 1) & 0x03;
+            if self.irq.prescaler == 0 {
+                self.irq.clock();
+            }
+            1
+        } else {
+            0
+        }
+    }
+}
+
+impl Reset for Rambo1 {
+    fn reset(&mut self, _kind: Kind) {
+        self.irq = Rambo1Irq::default();
+    }
+}
+
+impl Regional for Rambo1 {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::{load_mapper, test_rom_irq_scanlines, test_rom_results};
+
+    fn load() -> Rambo1 {
+        let (_, rambo1) = load_mapper(0x20000, 0x40000, Rambo1::load);
+        rambo1
+    }
+
+    fn set_bank(rambo1: &mut Rambo1, bank_select: u8, val: u8) {
+        let _ = rambo1.map_write(0x8000, bank_select);
+        let _ = rambo1.map_write(0x8001, val);
+    }
+
+    #[test]
+    fn banks() {
+        let mut rambo1 = load();
+        set_bank(&mut rambo1, 0x06, 0x02);
+        set_bank(&mut rambo1, 0x07, 0x03);
+        set_bank(&mut rambo1, 0x0F, 0x04);
+        assert_eq!(rambo1.map_peek(0x8000), MappedRead::PrgRom(0x4000));
+        assert_eq!(rambo1.map_peek(0xA000), MappedRead::PrgRom(0x6000));
+        assert_eq!(rambo1.map_peek(0xC000), MappedRead::PrgRom(0x8000));
+        assert_eq!(rambo1.map_peek(0xE000), MappedRead::PrgRom(0x1E000));
+
+        let _ = rambo1.map_write(0x8000, 0x40);
+        assert_eq!(rambo1.map_peek(0x8000), MappedRead::PrgRom(0x8000));
+        assert_eq!(rambo1.map_peek(0xA000), MappedRead::PrgRom(0x4000));
+        assert_eq!(rambo1.map_peek(0xC000), MappedRead::PrgRom(0x6000));
+
+        set_bank(&mut rambo1, 0x00, 0x11);
+        set_bank(&mut rambo1, 0x08, 0x20);
+        set_bank(&mut rambo1, 0x02, 0x30);
+        assert_eq!(rambo1.map_peek(0x0000), MappedRead::Chr(0x4000));
+        assert_eq!(rambo1.map_peek(0x0400), MappedRead::Chr(0x4400));
+        assert_eq!(rambo1.map_peek(0x1000), MappedRead::Chr(0xC000));
+
+        // 1K mode uses R8 for $0400, and inversion swaps the pattern table halves
+        let _ = rambo1.map_write(0x8000, 0xA0);
+        assert_eq!(rambo1.map_peek(0x1000), MappedRead::Chr(0x4400));
+        assert_eq!(rambo1.map_peek(0x1400), MappedRead::Chr(0x8000));
+        assert_eq!(rambo1.map_peek(0x0000), MappedRead::Chr(0xC000));
+    }
+
+    #[test]
+    fn scanline_irq() {
+        let mut rambo1 = load();
+        let _ = rambo1.map_write(0xC000, 0x02);
+        let _ = rambo1.map_write(0xC001, 0x00);
+        let _ = rambo1.map_write(0xE001, 0x00);
+        // The first IRQ after a reload takes two extra scanlines for latch values above 1
+        for _ in 0..4 {
+            assert!(!rambo1.irq_pending());
+            rambo1.ppu_bus_read(0x0000);
+            rambo1.ppu_bus_read(0x1000);
+        }
+        assert!(rambo1.irq_pending());
+
+        let _ = rambo1.map_write(0xE000, 0x00);
+        assert!(!rambo1.irq_pending());
+    }
+
+    #[test]
+    fn cycle_irq() {
+        let mut rambo1 = load();
+        let _ = rambo1.map_write(0xC000, 0x01);
+        let _ = rambo1.map_write(0xC001, 0x01);
+        let _ = rambo1.map_write(0xE001, 0x00);
+        // Scanlines don't clock the counter in cycle mode
+        rambo1.ppu_bus_read(0x0000);
+        rambo1.ppu_bus_read(0x1000);
+        for _ in 0..7 {
+            rambo1.clock();
+        }
+        assert!(!rambo1.irq_pending());
+        rambo1.clock();
+        assert!(rambo1.irq_pending());
+    }
+
+    #[test]
+    fn rambo1() {
+        // See `test_roms/mapper/src/rambo1.s`
+        let path = "test_roms/mapper/m064_rambo1/rambo1.nes";
+        let results = test_rom_results(path, 3);
+        assert_eq!(results[0..4], [2, 3, 4, 15], "prg mode 0");
+        assert_eq!(results[4..8], [4, 2, 3, 15], "prg mode 1");
+        assert_eq!(
+            results[8..16],
+            [10, 11, 20, 21, 30, 40, 50, 60],
+            "2k chr banks"
+        );
+        assert_eq!(
+            results[16..24],
+            [10, 13, 20, 23, 30, 40, 50, 60],
+            "1k chr banks"
+        );
+        assert_eq!(
+            results[24..32],
+            [30, 40, 50, 60, 10, 13, 20, 23],
+            "chr inversion"
+        );
+        assert_eq!(
+            results[32..],
+            [0xC3, 0xB2, 0xC3, 0xB2, 0xB2, 0xB2, 0xC3, 0xC3],
+            "vertical, then horizontal mirroring"
+        );
+        assert_eq!(
+            test_rom_irq_scanlines(path, 6),
+            [100, 152, 100, 152],
+            "irq scanlines"
+        );
+    }
+}
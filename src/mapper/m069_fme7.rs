@@ -0,0 +1,558 @@
+//! `Sunsoft FME-7` / `Sunsoft 5B` (Mapper 069)
+//!
+//! <https://www.nesdev.org/wiki/Sunsoft_FME-7>
+//! <https://www.nesdev.org/wiki/Sunsoft_5B_audio>
+
+use crate::{
+    apu::PULSE_TABLE,
+    audio::Audio,
+    cart::Cart,
+    common::{Clock, Kind, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Fme7Regs {
+    command: u8,
+    prg_ram_select: bool,
+    prg_ram_enabled: bool,
+    audio_select: u8,
+}
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Fme7Irq {
+    counter: u16,
+    enabled: bool,
+    counter_enabled: bool,
+    pending: bool,
+}
+
+impl Clock for Fme7Irq {
+    fn clock(&mut self) -> usize {
+        if self.counter_enabled {
+            self.counter = self.counter.wrapping_sub(1);
+            if self.counter == 0xFFFF && self.enabled {
+                self.pending = true;
+            }
+            1
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Fme7 {
+    regs: Fme7Regs,
+    irq: Fme7Irq,
+    audio: Sunsoft5bAudio,
+    mirroring: Mirroring,
+    chr_banks: MemBanks,
+    prg_ram_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+    prg_rom_6000_banks: MemBanks,
+}
+
+impl Fme7 {
+    const PRG_RAM_SIZE: usize = 8 * 1024;
+    const CHR_RAM_SIZE: usize = 8 * 1024;
+    const PRG_WINDOW: usize = 8 * 1024;
+    const CHR_WINDOW: usize = 1024;
+
+    pub fn load(cart: &mut Cart) -> Mapper {
+        if !cart.has_prg_ram() {
+            cart.add_prg_ram(Self::PRG_RAM_SIZE);
+        }
+        if !cart.has_chr() {
+            cart.add_chr_ram(Self::CHR_RAM_SIZE);
+        }
+        let mut fme7 = Self {
+            regs: Fme7Regs::default(),
+            irq: Fme7Irq::default(),
+            audio: Sunsoft5bAudio::new(),
+            mirroring: cart.mirroring(),
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_len(), Self::CHR_WINDOW),
+            prg_ram_banks: MemBanks::new(0x6000, 0x7FFF, cart.prg_ram.len(), Self::PRG_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_WINDOW),
+            prg_rom_6000_banks: MemBanks::new(0x6000, 0x7FFF, cart.prg_rom.len(), Self::PRG_WINDOW),
+        };
+        let last_bank = fme7.prg_rom_banks.last();
+        fme7.prg_rom_banks.set(3, last_bank);
+        fme7.into()
+    }
+
+    fn write_parameter(&mut self, val: u8) {
+        match self.regs.command {
+            0x00..=0x07 => self.chr_banks.set(self.regs.command.into(), val.into()),
+            0x08 => {
+                // [ERbB BBBB]
+                //  |||| ||||
+                //  ||++-++++- PRG bank at $6000-$7FFF
+                //  |+-------- RAM/ROM select (0: PRG-ROM, 1: PRG-RAM)
+                //  +--------- RAM enable (0: disabled, 1: enabled)
+                self.regs.prg_ram_select = val & 0x40 == 0x40;
+                self.regs.prg_ram_enabled = val & 0x80 == 0x80;
+                self.prg_ram_banks.set(0, (val & 0x3F).into());
+                self.prg_rom_6000_banks.set(0, (val & 0x3F).into());
+            }
+            0x09..=0x0B => {
+                let slot = usize::from(self.regs.command - 0x09);
+                self.prg_rom_banks.set(slot, (val & 0x3F).into());
+            }
+            0x0C => {
+                self.mirroring = match val & 0x03 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenA,
+                    _ => Mirroring::SingleScreenB,
+                };
+            }
+            0x0D => {
+                // [C... ...T]
+                //  |       |
+                //  |       +- IRQ enable
+                //  +--------- IRQ counter enable
+                self.irq.enabled = val & 0x01 == 0x01;
+                self.irq.counter_enabled = val & 0x80 == 0x80;
+                self.irq.pending = false;
+            }
+            0x0E => self.irq.counter = (self.irq.counter & 0xFF00) | u16::from(val),
+            0x0F => self.irq.counter = (u16::from(val) << 8) | (self.irq.counter & 0x00FF),
+            _ => unreachable!("command is 4 bits"),
+        }
+    }
+}
+
+impl Mapped for Fme7 {
+    #[inline]
+    fn irq_pending(&self) -> bool {
+        self.irq.pending
+    }
+
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+}
+
+impl MemMap for Fme7 {
+    // PPU $0000..=$1FFF Eight 1K switchable CHR banks
+    //
+    // CPU $6000..=$7FFF 8K switchable PRG-ROM or PRG-RAM bank
+    // CPU $8000..=$9FFF 8K switchable PRG-ROM bank
+    // CPU $A000..=$BFFF 8K switchable PRG-ROM bank
+    // CPU $C000..=$DFFF 8K switchable PRG-ROM bank
+    // CPU $E000..=$FFFF 8K PRG-ROM bank, fixed to the last bank
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x6000..=0x7FFF if !self.regs.prg_ram_select => {
+                MappedRead::PrgRom(self.prg_rom_6000_banks.translate(addr))
+            }
+            0x6000..=0x7FFF if self.regs.prg_ram_enabled => {
+                MappedRead::PrgRam(self.prg_ram_banks.translate(addr))
+            }
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr {
+            0x0000..=0x1FFF => return MappedWrite::Chr(self.chr_banks.translate(addr), val),
+            0x6000..=0x7FFF if self.regs.prg_ram_select && self.regs.prg_ram_enabled => {
+                return MappedWrite::PrgRam(self.prg_ram_banks.translate(addr), val);
+            }
+            0x8000..=0x9FFF => self.regs.command = val & 0x0F,
+            0xA000..=0xBFFF => self.write_parameter(val),
+            0xC000..=0xDFFF => self.regs.audio_select = val,
+            // Writes are ignored unless the upper nibble of the select register is clear
+            0xE000..=0xFFFF if self.regs.audio_select & 0xF0 == 0x00 => {
+                self.audio.write_register(self.regs.audio_select, val);
+            }
+            _ => (),
+        }
+        MappedWrite::None
+    }
+}
+
+impl Audio for Fme7 {
+    fn output(&self) -> f32 {
+        self.audio.output()
+    }
+}
+
+impl Clock for Fme7 {
+    fn clock(&mut self) -> usize {
+        self.irq.clock();
+        self.audio.clock();
+        1
+    }
+}
+
+impl Reset for Fme7 {
+    fn reset(&mut self, kind: Kind) {
+        self.irq = Fme7Irq::default();
+        self.audio.reset(kind);
+    }
+}
+
+impl Regional for Fme7 {}
+
+/// Output level for each 5-bit volume, 1.5dB apart. Volume 0 is silent.
+static VOLUME_TABLE: Lazy<[f32; 32]> = Lazy::new(|| {
+    let mut volume_table = [0.0; 32];
+    for (i, val) in volume_table.iter_mut().enumerate().skip(1) {
+        *val = 10f32.powf(-1.5 * (31 - i) as f32 / 20.0);
+    }
+    volume_table
+});
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Sunsoft5bChannel {
+    period: u16,
+    counter: u16,
+    tone: bool,
+    tone_disabled: bool,
+    noise_disabled: bool,
+    volume: u8,
+    envelope_enabled: bool,
+}
+
+/// A YM2149F derived sound generator with three square channels, a shared noise generator and
+/// a shared envelope generator.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Sunsoft5bAudio {
+    channels: [Sunsoft5bChannel; 3],
+    divider: u8,
+    noise_period: u8,
+    noise_counter: u8,
+    noise_shift: u32,
+    envelope_period: u16,
+    envelope_counter: u16,
+    envelope_shape: u8,
+    envelope_step: u8,
+    envelope_attack: bool,
+    envelope_holding: bool,
+}
+
+impl Default for Sunsoft5bAudio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sunsoft5bAudio {
+    /// The generators are clocked at half the CPU rate, with a further divide by 8 before the
+    /// tone, noise and envelope counters.
+    const CPU_DIVIDER: u8 = 16;
+
+    pub(crate) const fn new() -> Self {
+        Self {
+            channels: [Sunsoft5bChannel {
+                period: 0,
+                counter: 0,
+                tone: false,
+                tone_disabled: false,
+                noise_disabled: false,
+                volume: 0,
+                envelope_enabled: false,
+            }; 3],
+            divider: 0,
+            noise_period: 0,
+            noise_counter: 0,
+            noise_shift: 1,
+            envelope_period: 0,
+            envelope_counter: 0,
+            envelope_shape: 0,
+            envelope_step: 0,
+            envelope_attack: false,
+            envelope_holding: false,
+        }
+    }
+
+    pub(crate) fn write_register(&mut self, reg: u8, val: u8) {
+        match reg {
+            0x00 | 0x02 | 0x04 => {
+                let channel = &mut self.channels[usize::from(reg >> 1)];
+                channel.period = (channel.period & 0x0F00) | u16::from(val);
+            }
+            0x01 | 0x03 | 0x05 => {
+                let channel = &mut self.channels[usize::from(reg >> 1)];
+                channel.period = (u16::from(val & 0x0F) << 8) | (channel.period & 0x00FF);
+            }
+            0x06 => self.noise_period = val & 0x1F,
+            0x07 => {
+                // [..CB Acba] Noise disable for channels C-A, tone disable for channels c-a
+                for (i, channel) in self.channels.iter_mut().enumerate() {
+                    channel.tone_disabled = (val >> i) & 0x01 == 0x01;
+                    channel.noise_disabled = (val >> (i + 3)) & 0x01 == 0x01;
+                }
+            }
+            0x08..=0x0A => {
+                // [...E VVVV] Envelope enable and channel volume
+                let channel = &mut self.channels[usize::from(reg - 0x08)];
+                channel.volume = val & 0x0F;
+                channel.envelope_enabled = val & 0x10 == 0x10;
+            }
+            0x0B => self.envelope_period = (self.envelope_period & 0xFF00) | u16::from(val),
+            0x0C => self.envelope_period = (u16::from(val) << 8) | (self.envelope_period & 0x00FF),
+            0x0D => {
+                // [CAaH] Continue, attack, alternate and hold
+                self.envelope_shape = val & 0x0F;
+                self.envelope_attack = val & 0x04 == 0x04;
+                self.envelope_step = 0;
+                self.envelope_counter = 0;
+                self.envelope_holding = false;
+            }
+            _ => (),
+        }
+    }
+
+    /// The current 5-bit envelope volume.
+    #[must_use]
+    const fn envelope_volume(&self) -> u8 {
+        if self.envelope_holding && self.envelope_shape & 0x08 == 0x00 {
+            0
+        } else if self.envelope_attack {
+            self.envelope_step
+        } else {
+            31 - self.envelope_step
+        }
+    }
+
+    fn clock_envelope(&mut self) {
+        self.envelope_counter += 1;
+        if self.envelope_counter < self.envelope_period.max(1) {
+            return;
+        }
+        self.envelope_counter = 0;
+        if self.envelope_holding {
+            return;
+        }
+        if self.envelope_step < 31 {
+            self.envelope_step += 1;
+            return;
+        }
+
+        let shape = self.envelope_shape;
+        if shape & 0x08 == 0x00 || shape & 0x01 == 0x01 {
+            // Shapes without continue drop to silence, hold keeps the final (or alternated) level
+            if shape & 0x0B == 0x0B {
+                self.envelope_attack = !self.envelope_attack;
+            }
+            self.envelope_holding = true;
+        } else {
+            if shape & 0x02 == 0x02 {
+                self.envelope_attack = !self.envelope_attack;
+            }
+            self.envelope_step = 0;
+        }
+    }
+
+    #[must_use]
+    fn output(&self) -> f32 {
+        let noise = self.noise_shift & 0x01 == 0x01;
+        let envelope_volume = self.envelope_volume();
+        let out: f32 = self
+            .channels
+            .iter()
+            .filter(|channel| {
+                (channel.tone || channel.tone_disabled) && (noise || channel.noise_disabled)
+            })
+            .map(|channel| {
+                let volume = if channel.envelope_enabled {
+                    envelope_volume
+                } else if channel.volume > 0 {
+                    (channel.volume << 1) | 0x01
+                } else {
+                    0
+                };
+                VOLUME_TABLE[usize::from(volume)]
+            })
+            .sum();
+        // A full-volume channel is about as loud as a full-volume pulse channel
+        PULSE_TABLE[PULSE_TABLE.len() - 1] * out
+    }
+}
+
+impl Clock for Sunsoft5bAudio {
+    fn clock(&mut self) -> usize {
+        self.divider += 1;
+        if self.divider < Self::CPU_DIVIDER {
+            return 0;
+        }
+        self.divider = 0;
+
+        for channel in &mut self.channels {
+            channel.counter += 1;
+            if channel.counter >= channel.period.max(1) {
+                channel.counter = 0;
+                channel.tone = !channel.tone;
+            }
+        }
+
+        // Noise is clocked at half the tone rate by a 17-bit LFSR
+        self.noise_counter += 1;
+        if self.noise_counter >= self.noise_period.max(1) << 1 {
+            self.noise_counter = 0;
+            let feedback = (self.noise_shift ^ (self.noise_shift >> 3)) & 0x01;
+            self.noise_shift = (self.noise_shift >> 1) | (feedback << 16);
+        }
+
+        self.clock_envelope();
+        1
+    }
+}
+
+impl Reset for Sunsoft5bAudio {
+    fn reset(&mut self, _kind: Kind) {
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_mapper;
+
+    fn fme7() -> Fme7 {
+        let (_, fme7) = load_mapper(0x40000, 0x40000, Fme7::load);
+        fme7
+    }
+
+    fn write_command(fme7: &mut Fme7, command: u8, val: u8) {
+        let _ = fme7.map_write(0x8000, command);
+        let _ = fme7.map_write(0xA000, val);
+    }
+
+    #[test]
+    fn banks() {
+        let mut fme7 = fme7();
+        assert!(matches!(fme7.map_peek(0xE000), MappedRead::PrgRom(0x3E000)));
+        write_command(&mut fme7, 0x09, 0x01);
+        write_command(&mut fme7, 0x0A, 0x02);
+        write_command(&mut fme7, 0x0B, 0x03);
+        assert!(matches!(fme7.map_peek(0x8000), MappedRead::PrgRom(0x2000)));
+        assert!(matches!(fme7.map_peek(0xA000), MappedRead::PrgRom(0x4000)));
+        assert!(matches!(fme7.map_peek(0xC000), MappedRead::PrgRom(0x6000)));
+
+        write_command(&mut fme7, 0x07, 0xFF);
+        assert!(matches!(fme7.map_peek(0x1C01), MappedRead::Chr(0x3FC01)));
+
+        write_command(&mut fme7, 0x0C, 0x02);
+        assert_eq!(fme7.mirroring(), Mirroring::SingleScreenA);
+    }
+
+    #[test]
+    fn prg_ram_rom_select() {
+        let mut fme7 = fme7();
+        write_command(&mut fme7, 0x08, 0x05);
+        assert!(matches!(fme7.map_peek(0x6001), MappedRead::PrgRom(0xA001)));
+        assert!(matches!(fme7.map_write(0x6001, 0x01), MappedWrite::None));
+
+        write_command(&mut fme7, 0x08, 0x40);
+        assert!(
+            matches!(fme7.map_peek(0x6001), MappedRead::None),
+            "RAM disabled"
+        );
+
+        write_command(&mut fme7, 0x08, 0xC0);
+        assert!(matches!(fme7.map_peek(0x6001), MappedRead::PrgRam(0x0001)));
+        assert!(matches!(
+            fme7.map_write(0x6001, 0x01),
+            MappedWrite::PrgRam(0x0001, 0x01)
+        ));
+    }
+
+    #[test]
+    fn irq() {
+        let mut fme7 = fme7();
+        write_command(&mut fme7, 0x0E, 0x01);
+        write_command(&mut fme7, 0x0F, 0x00);
+        write_command(&mut fme7, 0x0D, 0x81);
+        fme7.clock();
+        assert!(!fme7.irq_pending());
+        fme7.clock();
+        assert!(fme7.irq_pending(), "counter wrapped");
+        write_command(&mut fme7, 0x0D, 0x00);
+        assert!(!fme7.irq_pending(), "acknowledged");
+
+        // The counter keeps running without triggering an IRQ when IRQs are disabled
+        write_command(&mut fme7, 0x0E, 0x00);
+        write_command(&mut fme7, 0x0F, 0x00);
+        write_command(&mut fme7, 0x0D, 0x80);
+        fme7.clock();
+        assert!(!fme7.irq_pending());
+        assert_eq!(fme7.irq.counter, 0xFFFF);
+    }
+
+    #[test]
+    fn audio_output() {
+        let mut fme7 = fme7();
+        let mut write_audio = |reg, val| {
+            let _ = fme7.map_write(0xC000, reg);
+            let _ = fme7.map_write(0xE000, val);
+        };
+        write_audio(0x00, 0x10); // Channel A period
+        write_audio(0x07, 0x3E); // Channel A tone only
+        write_audio(0x08, 0x0F); // Channel A full volume
+
+        let (mut min, mut max) = (f32::MAX, 0.0f32);
+        for _ in 0..16 * 0x40 {
+            fme7.clock();
+            min = min.min(fme7.output());
+            max = max.max(fme7.output());
+        }
+        assert!(min.abs() < f32::EPSILON, "square low: {min}");
+        assert!(max > 0.1, "square high: {max}");
+
+        // Writes are ignored when the upper nibble of the select register is set
+        let _ = fme7.map_write(0xC000, 0x18);
+        let _ = fme7.map_write(0xE000, 0x00);
+        assert_eq!(fme7.audio.channels[0].volume, 0x0F);
+    }
+
+    #[test]
+    fn envelope() {
+        let mut audio = Sunsoft5bAudio::new();
+        audio.write_register(0x0B, 0x01);
+        // Attack then hold
+        audio.write_register(0x0D, 0x0D);
+        assert_eq!(audio.envelope_volume(), 0);
+        for _ in 0..16 * 40 {
+            audio.clock();
+        }
+        assert_eq!(audio.envelope_volume(), 31);
+
+        // Decay then silence
+        audio.write_register(0x0D, 0x00);
+        assert_eq!(audio.envelope_volume(), 31);
+        for _ in 0..16 * 40 {
+            audio.clock();
+        }
+        assert_eq!(audio.envelope_volume(), 0);
+
+        // Sawtooth repeats
+        audio.write_register(0x0D, 0x08);
+        for _ in 0..16 * 32 {
+            audio.clock();
+        }
+        assert_eq!(audio.envelope_volume(), 31);
+    }
+}
@@ -0,0 +1,705 @@
+//! `VRC7` (Mapper 085)
+//!
+//! <https://www.nesdev.org/wiki/VRC7>
+//! <https://www.nesdev.org/wiki/VRC7_audio>
+
+use crate::{
+    apu::PULSE_TABLE,
+    audio::Audio,
+    cart::Cart,
+    common::{Clock, Kind, Regional, Reset},
+    mapper::{vrc_irq::VrcIrq, Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Vrc7Regs {
+    control: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Vrc7 {
+    regs: Vrc7Regs,
+    mirroring: Mirroring,
+    irq: VrcIrq,
+    audio: Vrc7Audio,
+    chr_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+}
+
+impl Vrc7 {
+    const PRG_RAM_SIZE: usize = 8 * 1024;
+    const CHR_RAM_SIZE: usize = 8 * 1024;
+    const PRG_WINDOW: usize = 8 * 1024;
+    const CHR_WINDOW: usize = 1024;
+
+    pub fn load(cart: &mut Cart) -> Mapper {
+        if !cart.has_prg_ram() {
+            cart.add_prg_ram(Self::PRG_RAM_SIZE);
+        }
+        if !cart.has_chr() {
+            cart.add_chr_ram(Self::CHR_RAM_SIZE);
+        }
+        let mut vrc7 = Self {
+            regs: Vrc7Regs::default(),
+            mirroring: cart.mirroring(),
+            irq: VrcIrq::default(),
+            audio: Vrc7Audio::new(),
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_len(), Self::CHR_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_WINDOW),
+        };
+        let last_bank = vrc7.prg_rom_banks.last();
+        vrc7.prg_rom_banks.set(3, last_bank);
+        vrc7.into()
+    }
+
+    #[inline]
+    #[must_use]
+    const fn prg_ram_enabled(&self) -> bool {
+        self.regs.control & 0x80 == 0x80
+    }
+
+    #[inline]
+    #[must_use]
+    const fn audio_silenced(&self) -> bool {
+        self.regs.control & 0x40 == 0x40
+    }
+}
+
+impl Mapped for Vrc7 {
+    #[inline]
+    fn irq_pending(&self) -> bool {
+        self.irq.pending()
+    }
+
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+}
+
+impl MemMap for Vrc7 {
+    // PPU $0000..=$03FF 1K switchable CHR bank
+    // PPU $0400..=$07FF 1K switchable CHR bank
+    // PPU $0800..=$0BFF 1K switchable CHR bank
+    // PPU $0C00..=$0FFF 1K switchable CHR bank
+    // PPU $1000..=$13FF 1K switchable CHR bank
+    // PPU $1400..=$17FF 1K switchable CHR bank
+    // PPU $1800..=$1BFF 1K switchable CHR bank
+    // PPU $1C00..=$1FFF 1K switchable CHR bank
+    //
+    // CPU $6000..=$7FFF 8K PRG-RAM bank, fixed
+    // CPU $8000..=$9FFF 8K switchable PRG-ROM bank
+    // CPU $A000..=$BFFF 8K switchable PRG-ROM bank
+    // CPU $C000..=$DFFF 8K switchable PRG-ROM bank
+    // CPU $E000..=$FFFF 8K PRG-ROM bank, fixed to the last bank
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x6000..=0x7FFF if self.prg_ram_enabled() => MappedRead::PrgRam((addr & 0x1FFF).into()),
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, mut addr: u16, val: u8) -> MappedWrite {
+        match addr {
+            0x0000..=0x1FFF => return MappedWrite::Chr(self.chr_banks.translate(addr), val),
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                return MappedWrite::PrgRam((addr & 0x1FFF).into(), val);
+            }
+            _ => (),
+        }
+
+        // VRC7a uses A4 and VRC7b uses A3 to select between register pairs, so A4 is folded
+        // into A3 except for the audio registers which are only present on VRC7a.
+        if addr & 0x10 == 0x10 && addr & 0xF010 != 0x9010 {
+            addr = (addr | 0x08) & !0x10;
+        }
+        match addr & 0xF038 {
+            0x8000 => self.prg_rom_banks.set(0, (val & 0x3F).into()),
+            0x8008 => self.prg_rom_banks.set(1, (val & 0x3F).into()),
+            0x9000 => self.prg_rom_banks.set(2, (val & 0x3F).into()),
+            0x9010 | 0x9030 => self.audio.write_register(addr & 0xF030, val),
+            0xA000 => self.chr_banks.set(0, val.into()),
+            0xA008 => self.chr_banks.set(1, val.into()),
+            0xB000 => self.chr_banks.set(2, val.into()),
+            0xB008 => self.chr_banks.set(3, val.into()),
+            0xC000 => self.chr_banks.set(4, val.into()),
+            0xC008 => self.chr_banks.set(5, val.into()),
+            0xD000 => self.chr_banks.set(6, val.into()),
+            0xD008 => self.chr_banks.set(7, val.into()),
+            0xE000 => {
+                // [RS.. ..MM]
+                //  ||     ||
+                //  ||     ++- Mirroring (0: vertical; 1: horizontal;
+                //  ||                    2: one-screen, lower bank; 3: one-screen, upper bank)
+                //  |+-------- Silence expansion sound if set
+                //  +--------- WRAM enable (1: enable WRAM, 0: protect)
+                self.regs.control = val;
+                self.mirroring = match val & 0x03 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenA,
+                    _ => Mirroring::SingleScreenB,
+                };
+                if self.audio_silenced() {
+                    self.audio.reset(Kind::Hard);
+                }
+            }
+            0xE008 => self.irq.write_reload(val),
+            0xF000 => self.irq.write_control(val),
+            0xF008 => self.irq.acknowledge(),
+            _ => (),
+        }
+        MappedWrite::None
+    }
+}
+
+impl Audio for Vrc7 {
+    fn output(&self) -> f32 {
+        if self.audio_silenced() {
+            0.0
+        } else {
+            self.audio.output()
+        }
+    }
+}
+
+impl Clock for Vrc7 {
+    fn clock(&mut self) -> usize {
+        self.irq.clock();
+        if !self.audio_silenced() {
+            self.audio.clock();
+        }
+        1
+    }
+}
+
+impl Reset for Vrc7 {
+    fn reset(&mut self, kind: Kind) {
+        self.regs = Vrc7Regs::default();
+        self.irq.reset(kind);
+        self.audio.reset(kind);
+    }
+}
+
+impl Regional for Vrc7 {}
+
+/// Built-in instrument patches. Patch 0 is the user-defined patch stored in registers $00-$07.
+///
+/// <https://www.nesdev.org/wiki/VRC7_audio#Internal_patch_set>
+const PATCHES: [[u8; 8]; 15] = [
+    [0x03, 0x21, 0x05, 0x06, 0xE8, 0x81, 0x42, 0x27], // Buzzy Bell
+    [0x13, 0x41, 0x14, 0x0D, 0xD8, 0xF6, 0x23, 0x12], // Guitar
+    [0x11, 0x11, 0x08, 0x08, 0xFA, 0xB2, 0x20, 0x12], // Wurly
+    [0x31, 0x61, 0x0C, 0x07, 0xA8, 0x64, 0x61, 0x27], // Flute
+    [0x32, 0x21, 0x1E, 0x06, 0xE1, 0x76, 0x01, 0x28], // Clarinet
+    [0x02, 0x01, 0x06, 0x00, 0xA3, 0xE2, 0xF4, 0xF4], // Synth
+    [0x21, 0x61, 0x1D, 0x07, 0x82, 0x81, 0x11, 0x07], // Trumpet
+    [0x23, 0x21, 0x22, 0x17, 0xA2, 0x72, 0x01, 0x17], // Organ
+    [0x35, 0x11, 0x25, 0x00, 0x40, 0x73, 0x72, 0x01], // Bells
+    [0xB5, 0x01, 0x0F, 0x0F, 0xA8, 0xA5, 0x51, 0x02], // Vibes
+    [0x17, 0xC1, 0x24, 0x07, 0xF8, 0xF8, 0x22, 0x12], // Vibraphone
+    [0x71, 0x23, 0x11, 0x06, 0x65, 0x74, 0x18, 0x16], // Tutti
+    [0x01, 0x02, 0xD3, 0x05, 0xC9, 0x95, 0x03, 0x02], // Fretless
+    [0x61, 0x63, 0x0C, 0x00, 0x94, 0xC0, 0x33, 0xF6], // Synth Bass
+    [0x21, 0x72, 0x0D, 0x00, 0xC1, 0xD5, 0x56, 0x06], // Sweep
+];
+/// Frequency multipliers indexed by the MULT patch field.
+const MULTIPLIERS: [f32; 16] = [
+    0.5, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 10.0, 12.0, 12.0, 15.0, 15.0,
+];
+/// Key scale attenuation in dB indexed by the upper 4 bits of the frequency, for block 7.
+const KEY_SCALE_LEVELS: [f32; 16] = [
+    0.0, 18.0, 24.0, 27.75, 30.0, 32.25, 33.75, 35.25, 36.0, 37.5, 38.25, 39.0, 39.75, 40.5, 41.25,
+    42.0,
+];
+/// Attenuation at which an operator is considered silent.
+const MAX_ATTENUATION: f32 = 96.0;
+
+/// A YM2413 (OPLL) derived FM synthesizer with six channels of two operators each.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Vrc7Audio {
+    select: u8,
+    custom_patch: [u8; 8],
+    channels: [Vrc7Channel; 6],
+    divider: u8,
+    vibrato_phase: f32,
+    tremolo_phase: f32,
+    out: f32,
+}
+
+impl Default for Vrc7Audio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vrc7Audio {
+    /// The OPLL is clocked at 3.58 MHz and produces a sample every 72 clocks, or every 36 CPU
+    /// cycles.
+    const CPU_DIVIDER: u8 = 36;
+    const SAMPLE_RATE: f32 = 3_579_545.0 / 72.0;
+    const VIBRATO_RATE: f32 = 6.4;
+    const TREMOLO_RATE: f32 = 3.7;
+
+    pub(crate) const fn new() -> Self {
+        Self {
+            select: 0x00,
+            custom_patch: [0x00; 8],
+            channels: [Vrc7Channel::new(); 6],
+            divider: 0,
+            vibrato_phase: 0.0,
+            tremolo_phase: 0.0,
+            out: 0.0,
+        }
+    }
+
+    pub(crate) fn write_register(&mut self, addr: u16, val: u8) {
+        if addr == 0x9010 {
+            self.select = val;
+            return;
+        }
+        match self.select {
+            0x00..=0x07 => self.custom_patch[self.select as usize] = val,
+            0x10..=0x15 => {
+                let channel = &mut self.channels[(self.select & 0x0F) as usize];
+                channel.freq = (channel.freq & 0x100) | u16::from(val);
+            }
+            0x20..=0x25 => {
+                // [..SK BBBF]
+                //    || ||||
+                //    || |||+- High bit of frequency
+                //    || +++-- Octave
+                //    |+------ Key on
+                //    +------- Sustain on
+                let channel = &mut self.channels[(self.select & 0x0F) as usize];
+                channel.freq = (u16::from(val & 0x01) << 8) | (channel.freq & 0xFF);
+                channel.block = (val >> 1) & 0x07;
+                channel.sustain = val & 0x20 == 0x20;
+                channel.set_key_on(val & 0x10 == 0x10);
+            }
+            0x30..=0x35 => {
+                // [IIII VVVV] Instrument and volume
+                let channel = &mut self.channels[(self.select & 0x0F) as usize];
+                channel.instrument = val >> 4;
+                channel.volume = val & 0x0F;
+            }
+            _ => (),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    const fn patch(&self, instrument: u8) -> [u8; 8] {
+        match instrument {
+            0 => self.custom_patch,
+            _ => PATCHES[(instrument - 1) as usize],
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub(crate) fn output(&self) -> f32 {
+        PULSE_TABLE[PULSE_TABLE.len() - 1] * self.out
+    }
+}
+
+impl Clock for Vrc7Audio {
+    fn clock(&mut self) -> usize {
+        self.divider += 1;
+        if self.divider < Self::CPU_DIVIDER {
+            return 0;
+        }
+        self.divider = 0;
+
+        self.vibrato_phase = (self.vibrato_phase + Self::VIBRATO_RATE / Self::SAMPLE_RATE).fract();
+        self.tremolo_phase = (self.tremolo_phase + Self::TREMOLO_RATE / Self::SAMPLE_RATE).fract();
+        let lfo = Lfo {
+            // Roughly +/- 14 cents
+            vibrato: 1.0 + 0.008 * (TAU * self.vibrato_phase).sin(),
+            // Up to 4.8 dB
+            tremolo: 2.4 * (1.0 + (TAU * self.tremolo_phase).sin()),
+        };
+
+        self.out = 0.0;
+        for i in 0..self.channels.len() {
+            let patch = Patch::new(self.patch(self.channels[i].instrument));
+            self.out += self.channels[i].clock(&patch, lfo);
+        }
+        1
+    }
+}
+
+impl Reset for Vrc7Audio {
+    fn reset(&mut self, _kind: Kind) {
+        *self = Self::new();
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Lfo {
+    vibrato: f32,
+    tremolo: f32,
+}
+
+/// Operator settings decoded from an instrument patch.
+#[derive(Debug, Copy, Clone)]
+struct OperatorPatch {
+    tremolo: bool,
+    vibrato: bool,
+    sustained: bool,
+    key_scale_rate: bool,
+    multiplier: f32,
+    key_scale_level: u8,
+    half_wave: bool,
+    attack: u8,
+    decay: u8,
+    sustain_level: f32,
+    release: u8,
+}
+
+impl OperatorPatch {
+    fn new(flags: u8, key_scale_level: u8, half_wave: bool, rates: u8, levels: u8) -> Self {
+        Self {
+            tremolo: flags & 0x80 == 0x80,
+            vibrato: flags & 0x40 == 0x40,
+            sustained: flags & 0x20 == 0x20,
+            key_scale_rate: flags & 0x10 == 0x10,
+            multiplier: MULTIPLIERS[(flags & 0x0F) as usize],
+            key_scale_level,
+            half_wave,
+            attack: rates >> 4,
+            decay: rates & 0x0F,
+            sustain_level: match levels >> 4 {
+                0x0F => 93.0,
+                level => f32::from(level) * 3.0,
+            },
+            release: levels & 0x0F,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Patch {
+    modulator: OperatorPatch,
+    carrier: OperatorPatch,
+    /// Modulator total level in dB.
+    total_level: f32,
+    feedback: u8,
+}
+
+impl Patch {
+    fn new(patch: [u8; 8]) -> Self {
+        Self {
+            modulator: OperatorPatch::new(
+                patch[0],
+                patch[2] >> 6,
+                patch[3] & 0x08 == 0x08,
+                patch[4],
+                patch[6],
+            ),
+            carrier: OperatorPatch::new(
+                patch[1],
+                patch[3] >> 6,
+                patch[3] & 0x10 == 0x10,
+                patch[5],
+                patch[7],
+            ),
+            total_level: f32::from(patch[2] & 0x3F) * 0.75,
+            feedback: patch[3] & 0x07,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[must_use]
+pub enum EnvelopeState {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Vrc7Operator {
+    phase: f32,
+    state: EnvelopeState,
+    /// Envelope attenuation in dB.
+    envelope: f32,
+    output: f32,
+}
+
+impl Vrc7Operator {
+    const fn new() -> Self {
+        Self {
+            phase: 0.0,
+            state: EnvelopeState::Release,
+            envelope: MAX_ATTENUATION,
+            output: 0.0,
+        }
+    }
+
+    /// Time in seconds to attenuate by 96 dB at the given rate.
+    fn decay_time(rate: u8, key_scale: u8) -> Option<f32> {
+        if rate == 0 {
+            return None;
+        }
+        let rate = (4 * rate + key_scale).min(63);
+        Some(0.0024 * 2f32.powf(f32::from(60u8.saturating_sub(rate)) / 4.0))
+    }
+
+    fn clock_envelope(&mut self, patch: &OperatorPatch, release: u8, key_scale: u8) {
+        let key_scale = if patch.key_scale_rate {
+            key_scale
+        } else {
+            key_scale >> 2
+        };
+        let decay = |envelope: &mut f32, rate| {
+            if let Some(time) = Self::decay_time(rate, key_scale) {
+                *envelope += MAX_ATTENUATION / (time * Vrc7Audio::SAMPLE_RATE);
+            }
+        };
+        match self.state {
+            EnvelopeState::Attack => {
+                if patch.attack == 15 {
+                    self.envelope = 0.0;
+                } else if let Some(time) = Self::decay_time(patch.attack, key_scale) {
+                    // Attack is exponential, rising quickly at first
+                    let samples = (time / 14.0 * Vrc7Audio::SAMPLE_RATE).max(1.0);
+                    self.envelope *= (0.001f32.ln() / samples).exp();
+                }
+                if self.envelope < 0.1 {
+                    self.envelope = 0.0;
+                    self.state = EnvelopeState::Decay;
+                }
+            }
+            EnvelopeState::Decay => {
+                decay(&mut self.envelope, patch.decay);
+                if self.envelope >= patch.sustain_level {
+                    self.envelope = patch.sustain_level;
+                    self.state = EnvelopeState::Sustain;
+                }
+            }
+            EnvelopeState::Sustain => {
+                // Percussive tones keep decaying while the key is held
+                if !patch.sustained {
+                    decay(&mut self.envelope, patch.release);
+                }
+            }
+            EnvelopeState::Release => decay(&mut self.envelope, release),
+        }
+        self.envelope = self.envelope.min(MAX_ATTENUATION);
+    }
+
+    /// Advances the operator one sample and returns its output in the range -1.0..=1.0.
+    fn clock(
+        &mut self,
+        patch: &OperatorPatch,
+        increment: f32,
+        modulation: f32,
+        level: f32,
+        lfo: Lfo,
+    ) -> f32 {
+        let increment = if patch.vibrato {
+            increment * lfo.vibrato
+        } else {
+            increment
+        };
+        self.phase = (self.phase + increment * patch.multiplier).fract();
+
+        let tremolo = if patch.tremolo { lfo.tremolo } else { 0.0 };
+        let attenuation = level + self.envelope + tremolo;
+        self.output = if attenuation >= MAX_ATTENUATION {
+            0.0
+        } else {
+            let wave = (TAU * (self.phase + modulation)).sin();
+            let wave = if patch.half_wave { wave.max(0.0) } else { wave };
+            wave * 10f32.powf(-attenuation / 20.0)
+        };
+        self.output
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Vrc7Channel {
+    freq: u16,
+    block: u8,
+    key_on: bool,
+    sustain: bool,
+    instrument: u8,
+    volume: u8,
+    modulator: Vrc7Operator,
+    carrier: Vrc7Operator,
+    feedback: [f32; 2],
+}
+
+impl Vrc7Channel {
+    const fn new() -> Self {
+        Self {
+            freq: 0,
+            block: 0,
+            key_on: false,
+            sustain: false,
+            instrument: 0,
+            volume: 0,
+            modulator: Vrc7Operator::new(),
+            carrier: Vrc7Operator::new(),
+            feedback: [0.0; 2],
+        }
+    }
+
+    fn set_key_on(&mut self, key_on: bool) {
+        if key_on && !self.key_on {
+            for operator in [&mut self.modulator, &mut self.carrier] {
+                operator.phase = 0.0;
+                operator.state = EnvelopeState::Attack;
+            }
+        } else if !key_on && self.key_on {
+            self.modulator.state = EnvelopeState::Release;
+            self.carrier.state = EnvelopeState::Release;
+        }
+        self.key_on = key_on;
+    }
+
+    /// Attenuation in dB based on how high the note is.
+    fn key_scale_level(&self, key_scale_level: u8) -> f32 {
+        if key_scale_level == 0 {
+            return 0.0;
+        }
+        let level = KEY_SCALE_LEVELS[(self.freq >> 5) as usize] - 6.0 * f32::from(7 - self.block);
+        level.max(0.0) / f32::from(1u8 << (3 - key_scale_level))
+    }
+
+    const fn release_rate(&self, patch: &OperatorPatch) -> u8 {
+        if self.sustain {
+            5
+        } else if patch.sustained {
+            patch.release
+        } else {
+            7
+        }
+    }
+
+    /// Advances the channel one sample and returns its output.
+    fn clock(&mut self, patch: &Patch, lfo: Lfo) -> f32 {
+        let key_scale = (self.block << 1) | (self.freq >> 8) as u8;
+        let release = self.release_rate(&patch.modulator);
+        self.modulator
+            .clock_envelope(&patch.modulator, release, key_scale);
+        let release = self.release_rate(&patch.carrier);
+        self.carrier
+            .clock_envelope(&patch.carrier, release, key_scale);
+
+        // Phase increment as a fraction of a full cycle per sample
+        let increment = f32::from(self.freq) * f32::from(1u16 << self.block) / 2f32.powi(19);
+
+        let feedback = if patch.feedback > 0 {
+            (self.feedback[0] + self.feedback[1]) * 2f32.powi(i32::from(patch.feedback) - 7)
+        } else {
+            0.0
+        };
+        let level = patch.total_level + self.key_scale_level(patch.modulator.key_scale_level);
+        let modulator = self
+            .modulator
+            .clock(&patch.modulator, increment, feedback, level, lfo);
+        self.feedback = [self.feedback[1], modulator];
+
+        let level =
+            f32::from(self.volume) * 3.0 + self.key_scale_level(patch.carrier.key_scale_level);
+        self.carrier
+            .clock(&patch.carrier, increment, 2.0 * modulator, level, lfo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_mapper;
+
+    fn vrc7() -> Vrc7 {
+        let (_, vrc7) = load_mapper(0x10000, 0x8000, Vrc7::load);
+        vrc7
+    }
+
+    #[test]
+    fn prg_chr_banks() {
+        let mut vrc7 = vrc7();
+        assert!(matches!(vrc7.map_peek(0xE000), MappedRead::PrgRom(0xE000)));
+
+        let _ = vrc7.map_write(0x8000, 0x02);
+        let _ = vrc7.map_write(0x8010, 0x03); // VRC7a
+        let _ = vrc7.map_write(0x9000, 0x04);
+        assert!(matches!(vrc7.map_peek(0x8000), MappedRead::PrgRom(0x4000)));
+        assert!(matches!(vrc7.map_peek(0xA000), MappedRead::PrgRom(0x6000)));
+        assert!(matches!(vrc7.map_peek(0xC000), MappedRead::PrgRom(0x8000)));
+        let _ = vrc7.map_write(0x8008, 0x05); // VRC7b
+        assert!(matches!(vrc7.map_peek(0xA000), MappedRead::PrgRom(0xA000)));
+
+        let _ = vrc7.map_write(0xA010, 0x03);
+        let _ = vrc7.map_write(0xD008, 0x1F);
+        assert!(matches!(vrc7.map_peek(0x0400), MappedRead::Chr(0x0C00)));
+        assert!(matches!(vrc7.map_peek(0x1C00), MappedRead::Chr(0x7C00)));
+    }
+
+    #[test]
+    fn control() {
+        let mut vrc7 = vrc7();
+        assert!(matches!(vrc7.map_peek(0x6000), MappedRead::None));
+        let _ = vrc7.map_write(0xE000, 0x83);
+        assert!(matches!(vrc7.map_peek(0x6000), MappedRead::PrgRam(0x0000)));
+        assert_eq!(vrc7.mirroring(), Mirroring::SingleScreenB);
+        let _ = vrc7.map_write(0xE000, 0x01);
+        assert_eq!(vrc7.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn irq() {
+        let mut vrc7 = vrc7();
+        let _ = vrc7.map_write(0xE010, 0xFE);
+        let _ = vrc7.map_write(0xF000, 0x06); // Enabled, cycle mode
+        vrc7.clock();
+        assert!(!vrc7.irq_pending());
+        vrc7.clock();
+        assert!(vrc7.irq_pending());
+        let _ = vrc7.map_write(0xF010, 0x00);
+        assert!(!vrc7.irq_pending());
+    }
+
+    #[test]
+    fn audio_output() {
+        let mut vrc7 = vrc7();
+        let mut write_audio = |reg, val| {
+            let _ = vrc7.map_write(0x9010, reg);
+            let _ = vrc7.map_write(0x9030, val);
+        };
+        write_audio(0x30, 0x30); // Flute, full volume
+        write_audio(0x10, 0xAC);
+        write_audio(0x20, 0x18); // Key on, octave 4
+
+        let mut peak = 0.0f32;
+        for _ in 0..36 * 2000 {
+            vrc7.clock();
+            peak = peak.max(vrc7.output().abs());
+        }
+        assert!(peak > 0.01, "key on produces output: {peak}");
+
+        let _ = vrc7.map_write(0xE000, 0x40);
+        assert!(vrc7.output().abs() < f32::EPSILON, "silenced");
+    }
+}
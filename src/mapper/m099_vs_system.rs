@@ -0,0 +1,170 @@
+//! `VS System` (Mapper 099)
+//!
+//! <https://www.nesdev.org/wiki/INES_Mapper_099>
+//! <https://www.nesdev.org/wiki/VS_System>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct VsSystem {
+    mirroring: Mirroring,
+    chr_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+}
+
+impl VsSystem {
+    const PRG_RAM_SIZE: usize = 2 * 1024;
+    const EX_RAM_SIZE: usize = 4 * 1024;
+    const PRG_ROM_WINDOW: usize = 8 * 1024;
+    const CHR_WINDOW: usize = 8 * 1024;
+
+    const BANK_SELECT: u8 = 0x04; // 0b100
+
+    pub fn load(cart: &mut Cart) -> Mapper {
+        if !cart.has_prg_ram() {
+            cart.add_prg_ram(Self::PRG_RAM_SIZE);
+        }
+        let mirroring = cart.mirroring();
+        if mirroring == Mirroring::FourScreen {
+            cart.add_ex_ram(Self::EX_RAM_SIZE);
+        }
+        let vs = Self {
+            mirroring,
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_len(), Self::CHR_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_ROM_WINDOW),
+        };
+        vs.into()
+    }
+}
+
+impl Mapped for VsSystem {
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+
+    fn cpu_bus_write(&mut self, addr: u16, val: u8) {
+        // $4016 writes also strobe the controllers
+        // 7  bit  0
+        // ---- ----
+        // xxxx xBxS
+        //       | |
+        //       | +- Controller strobe
+        //       +--- Select 8K CHR-ROM bank at $0000-$1FFF and, for 40K PRG-ROM,
+        //            8K PRG-ROM bank at $8000-$9FFF
+        if addr == 0x4016 {
+            let bank = usize::from((val & Self::BANK_SELECT) >> 2);
+            self.chr_banks.set(0, bank);
+            // Only 40K PRG-ROM boards have a fifth bank, otherwise this selects bank 0
+            self.prg_rom_banks.set(0, bank << 2);
+        }
+    }
+}
+
+impl MemMap for VsSystem {
+    // PPU $0000..=$1FFF 8K CHR-ROM Bank Switchable
+    // PPU $2000..=$3EFF FourScreen Mirroring (optional)
+    // CPU $6000..=$7FFF 2K PRG-RAM, mirrored
+    // CPU $8000..=$9FFF 8K PRG-ROM Bank Switchable for 40K PRG-ROM
+    // CPU $A000..=$FFFF 24K PRG-ROM Fixed
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x2000..=0x3EFF if self.mirroring == Mirroring::FourScreen => {
+                MappedRead::ExRam((addr & 0x0FFF) as usize)
+            }
+            0x6000..=0x7FFF => MappedRead::PrgRam((addr & 0x07FF).into()),
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr {
+            0x0000..=0x1FFF => MappedWrite::Chr(self.chr_banks.translate(addr), val),
+            0x2000..=0x3EFF if self.mirroring == Mirroring::FourScreen => {
+                MappedWrite::ExRam((addr & 0x0FFF) as usize, val)
+            }
+            0x6000..=0x7FFF => MappedWrite::PrgRam((addr & 0x07FF).into(), val),
+            _ => MappedWrite::None,
+        }
+    }
+}
+
+impl Clock for VsSystem {}
+impl Regional for VsSystem {}
+impl Reset for VsSystem {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::tests::load_mapper,
+        mem::{Access, Mem},
+        ppu::bus::PpuBus,
+    };
+
+    fn load(prg_rom_len: usize) -> (Cart, VsSystem) {
+        load_mapper(prg_rom_len, 0x4000, VsSystem::load)
+    }
+
+    #[test]
+    fn banks() {
+        let (cart, mut vs) = load(0x8000);
+        assert_eq!(cart.prg_ram.len(), 0x0800);
+        assert_eq!(vs.map_peek(0x6801), MappedRead::PrgRam(0x0001));
+
+        vs.cpu_bus_write(0x4016, 0x04);
+        assert_eq!(vs.map_peek(0x0001), MappedRead::Chr(0x2001));
+        assert_eq!(vs.map_peek(0x8001), MappedRead::PrgRom(0x0001));
+        assert_eq!(vs.map_peek(0xE001), MappedRead::PrgRom(0x6001));
+
+        // Only $4016 selects banks
+        vs.cpu_bus_write(0x4017, 0x00);
+        assert_eq!(vs.map_peek(0x0001), MappedRead::Chr(0x2001));
+        vs.cpu_bus_write(0x4016, 0x01);
+        assert_eq!(vs.map_peek(0x0001), MappedRead::Chr(0x0001));
+    }
+
+    #[test]
+    fn prg_rom_40k() {
+        let (_, mut vs) = load(0xA000);
+        assert_eq!(vs.map_peek(0x8001), MappedRead::PrgRom(0x0001));
+        vs.cpu_bus_write(0x4016, 0x04);
+        assert_eq!(vs.map_peek(0x8001), MappedRead::PrgRom(0x8001));
+        assert_eq!(vs.map_peek(0xA001), MappedRead::PrgRom(0x2001));
+    }
+
+    #[test]
+    fn four_screen_mirror() {
+        let (_, mut vs) = load(0x8000);
+        vs.set_mirroring(Mirroring::FourScreen);
+        assert_eq!(vs.map_peek(0x3C01), MappedRead::ExRam(0x0C01));
+
+        let mut bus = PpuBus::new();
+        bus.load_mapper(vs.into());
+        bus.load_ex_ram(vec![0x00; VsSystem::EX_RAM_SIZE]);
+        bus.write(0x2001, 0x11, Access::Write);
+        bus.write(0x2C01, 0x44, Access::Write);
+        // $3000-$3EFF mirrors $2000-$2EFF
+        bus.write(0x3401, 0x22, Access::Write);
+        assert_eq!(bus.peek(0x3001, Access::Read), 0x11);
+        assert_eq!(bus.peek(0x2401, Access::Read), 0x22);
+        assert_eq!(bus.peek(0x3C01, Access::Read), 0x44);
+        assert_eq!(bus.peek(0x2801, Access::Read), 0x00);
+    }
+}
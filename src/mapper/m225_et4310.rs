@@ -0,0 +1,158 @@
+//! `ET-4310`/`K-1010` 52/64/72-in-1 multicarts (Mapper 225)
+//!
+//! <https://wiki.nesdev.org/w/index.php?title=INES_Mapper_225>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Kind, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Et4310 {
+    bank_select: u16,
+    ram: [u8; 4],
+    mirroring: Mirroring,
+    chr_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+}
+
+impl Et4310 {
+    const PRG_ROM_WINDOW: usize = 16 * 1024;
+    const CHR_WINDOW: usize = 8 * 1024;
+
+    const CHR_BANK_MASK: u16 = 0x003F;
+    const PRG_BANK_MASK: u16 = 0x0FC0;
+    const PRG_MODE_MASK: u16 = 0x1000;
+    const MIRRORING_MASK: u16 = 0x2000;
+    const HIGH_BANK_MASK: u16 = 0x4000;
+
+    pub fn load(cart: &mut Cart) -> Mapper {
+        let mut et4310 = Self {
+            bank_select: 0x0000,
+            ram: [0x00; 4],
+            mirroring: Mirroring::Vertical,
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_rom.len(), Self::CHR_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_ROM_WINDOW),
+        };
+        et4310.update_banks();
+        et4310.into()
+    }
+
+    fn update_banks(&mut self) {
+        let high_bank = usize::from((self.bank_select & Self::HIGH_BANK_MASK) >> 8);
+        let chr_bank = high_bank | usize::from(self.bank_select & Self::CHR_BANK_MASK);
+        self.chr_banks.set(0, chr_bank);
+        let prg_bank = high_bank | usize::from((self.bank_select & Self::PRG_BANK_MASK) >> 6);
+        if self.bank_select & Self::PRG_MODE_MASK == Self::PRG_MODE_MASK {
+            self.prg_rom_banks.set(0, prg_bank);
+            self.prg_rom_banks.set(1, prg_bank);
+        } else {
+            self.prg_rom_banks.set_range(0, 1, prg_bank & !0x01);
+        }
+        self.mirroring = if self.bank_select & Self::MIRRORING_MASK == Self::MIRRORING_MASK {
+            Mirroring::Horizontal
+        } else {
+            Mirroring::Vertical
+        };
+    }
+}
+
+impl MemMap for Et4310 {
+    // PPU $0000..=$1FFF 8K CHR-ROM Bank Switchable
+    // CPU $5800..=$5FFF Four 4-bit RAM registers, mirrored
+    // CPU $8000..=$FFFF 32K or 16K mirrored PRG-ROM Bank Switchable
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x5800..=0x5FFF => MappedRead::Data(self.ram[usize::from(addr & 0x03)]),
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr {
+            0x5800..=0x5FFF => self.ram[usize::from(addr & 0x03)] = val & 0x0F,
+            // $8000-$FFFF: Bank select, latched from the address
+            // 1HMO PPPP PPCC CCCC
+            //  ||| |||| ||++-++++- Select 8K CHR-ROM bank
+            //  ||| ++++-++-------- Select 16K PRG-ROM bank
+            //  ||+---------------- PRG-ROM mode (0: 32K; 1: 16K mirrored)
+            //  |+----------------- Mirroring (0: Vertical; 1: Horizontal)
+            //  +------------------ Select the high bit of the PRG-ROM and CHR-ROM banks
+            0x8000..=0xFFFF => {
+                self.bank_select = addr;
+                self.update_banks();
+            }
+            _ => (),
+        }
+        MappedWrite::None
+    }
+}
+
+impl Mapped for Et4310 {
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+}
+
+impl Reset for Et4310 {
+    // Reset clears the bank register to return to the menu, but not the 4-bit RAM, which the
+    // 52/64/72-in-1 menus use to count resets and pick which list of games to show
+    fn reset(&mut self, kind: Kind) {
+        self.bank_select = 0x0000;
+        if kind == Kind::Hard {
+            self.ram = [0x00; 4];
+        }
+        self.update_banks();
+    }
+}
+
+impl Clock for Et4310 {}
+impl Regional for Et4310 {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_mapper;
+
+    #[test]
+    fn banks() {
+        let (_, mut et4310): (_, Et4310) = load_mapper(0x0020_0000, 0x0010_0000, Et4310::load);
+
+        let _ = et4310.map_write(0x80C5, 0x00);
+        assert_eq!(et4310.map_peek(0x8000), MappedRead::PrgRom(0x8000));
+        assert_eq!(et4310.map_peek(0xC000), MappedRead::PrgRom(0xC000));
+        assert_eq!(et4310.map_peek(0x0000), MappedRead::Chr(0xA000));
+
+        let _ = et4310.map_write(0xF0C5, 0x00);
+        assert_eq!(et4310.map_peek(0x8000), MappedRead::PrgRom(0x0010_C000));
+        assert_eq!(et4310.map_peek(0xC000), MappedRead::PrgRom(0x0010_C000));
+        assert_eq!(et4310.map_peek(0x0000), MappedRead::Chr(0x8A000));
+        assert_eq!(et4310.mirroring(), Mirroring::Horizontal);
+
+        let _ = et4310.map_write(0x5801, 0xA5);
+        assert_eq!(et4310.map_peek(0x5FFD), MappedRead::Data(0x05));
+
+        // Reset returns to the menu, keeping the RAM until power off
+        et4310.reset(Kind::Soft);
+        assert_eq!(et4310.map_peek(0x8000), MappedRead::PrgRom(0x0000));
+        assert_eq!(et4310.map_peek(0x0000), MappedRead::Chr(0x0000));
+        assert_eq!(et4310.mirroring(), Mirroring::Vertical);
+        assert_eq!(et4310.map_peek(0x5801), MappedRead::Data(0x05));
+        et4310.reset(Kind::Hard);
+        assert_eq!(et4310.map_peek(0x5801), MappedRead::Data(0x00));
+    }
+}
@@ -0,0 +1,169 @@
+//! `Action 52`/`Cheetahmen II` (Mapper 228)
+//!
+//! <https://wiki.nesdev.org/w/index.php?title=INES_Mapper_228>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Kind, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Action52 {
+    bank_select: u16,
+    chr_bank: u8,
+    ram: [u8; 4],
+    mirroring: Mirroring,
+    chr_banks: MemBanks,
+    prg_rom_banks: MemBanks,
+}
+
+impl Action52 {
+    const PRG_ROM_WINDOW: usize = 16 * 1024;
+    const CHR_WINDOW: usize = 8 * 1024;
+
+    const CHR_BANK_MASK: u16 = 0x000F;
+    const PRG_MODE_MASK: u16 = 0x0020;
+    const PRG_BANK_MASK: u16 = 0x07C0;
+    const PRG_CHIP_MASK: u16 = 0x1800;
+    const MIRRORING_MASK: u16 = 0x2000;
+
+    pub fn load(cart: &mut Cart) -> Mapper {
+        let mut action52 = Self {
+            bank_select: 0x0000,
+            chr_bank: 0x00,
+            ram: [0x00; 4],
+            mirroring: Mirroring::Vertical,
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, cart.chr_rom.len(), Self::CHR_WINDOW),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_ROM_WINDOW),
+        };
+        action52.update_banks();
+        action52.into()
+    }
+
+    fn update_banks(&mut self) {
+        let chr_bank = (usize::from(self.bank_select & Self::CHR_BANK_MASK) << 2)
+            | usize::from(self.chr_bank & 0x03);
+        self.chr_banks.set(0, chr_bank);
+
+        // Action 52 has three 512K PRG-ROM chips, with the third chip selected as chip 3
+        let chip = match (self.bank_select & Self::PRG_CHIP_MASK) >> 11 {
+            3 => 2,
+            chip => usize::from(chip),
+        };
+        let prg_bank = (chip << 5) | usize::from((self.bank_select & Self::PRG_BANK_MASK) >> 6);
+        if self.bank_select & Self::PRG_MODE_MASK == Self::PRG_MODE_MASK {
+            self.prg_rom_banks.set(0, prg_bank);
+            self.prg_rom_banks.set(1, prg_bank);
+        } else {
+            self.prg_rom_banks.set_range(0, 1, prg_bank & !0x01);
+        }
+        self.mirroring = if self.bank_select & Self::MIRRORING_MASK == Self::MIRRORING_MASK {
+            Mirroring::Horizontal
+        } else {
+            Mirroring::Vertical
+        };
+    }
+}
+
+impl MemMap for Action52 {
+    // PPU $0000..=$1FFF 8K CHR-ROM Bank Switchable
+    // CPU $4020..=$5FFF Four 4-bit RAM registers, mirrored
+    // CPU $8000..=$FFFF 32K or 16K mirrored PRG-ROM Bank Switchable
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(self.chr_banks.translate(addr)),
+            0x4020..=0x5FFF => MappedRead::Data(self.ram[usize::from(addr & 0x03)]),
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr {
+            0x4020..=0x5FFF => self.ram[usize::from(addr & 0x03)] = val & 0x0F,
+            // $8000-$FFFF: Bank select, latched from the address and data
+            // A: 1.MH HPPP PPO. CCCC
+            //      || |||| ||   ++++- Select bits 2-5 of the 8K CHR-ROM bank
+            //      || |||| |+-------- PRG-ROM mode (0: 32K; 1: 16K mirrored)
+            //      || |+++-+--------- Select 16K PRG-ROM bank within the chip
+            //      |+-+-------------- Select 512K PRG-ROM chip
+            //      +----------------- Mirroring (0: Vertical; 1: Horizontal)
+            // D: .... ..CC
+            //           ++- Select bits 0-1 of the 8K CHR-ROM bank
+            0x8000..=0xFFFF => {
+                self.bank_select = addr;
+                self.chr_bank = val;
+                self.update_banks();
+            }
+            _ => (),
+        }
+        MappedWrite::None
+    }
+}
+
+impl Mapped for Action52 {
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+}
+
+impl Reset for Action52 {
+    // Reset returns to the Action 52 menu in the first PRG-ROM chip. Only power clears the
+    // 4-bit RAM
+    fn reset(&mut self, kind: Kind) {
+        self.bank_select = 0x0000;
+        self.chr_bank = 0x00;
+        if kind == Kind::Hard {
+            self.ram = [0x00; 4];
+        }
+        self.update_banks();
+    }
+}
+
+impl Clock for Action52 {}
+impl Regional for Action52 {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_mapper;
+
+    #[test]
+    fn banks() {
+        let (_, mut action52): (_, Action52) = load_mapper(0x0018_0000, 0x80000, Action52::load);
+
+        let _ = action52.map_write(0x80A3, 0x02);
+        assert_eq!(action52.map_peek(0x8000), MappedRead::PrgRom(0x8000));
+        assert_eq!(action52.map_peek(0xC000), MappedRead::PrgRom(0x8000));
+        assert_eq!(action52.map_peek(0x0000), MappedRead::Chr(0x1C000));
+
+        // Chip 3 is the third PRG-ROM chip
+        let _ = action52.map_write(0xB800, 0x00);
+        assert_eq!(action52.map_peek(0x8000), MappedRead::PrgRom(0x0010_0000));
+        assert_eq!(action52.map_peek(0xC000), MappedRead::PrgRom(0x0010_4000));
+        assert_eq!(action52.mirroring(), Mirroring::Horizontal);
+
+        let _ = action52.map_write(0x4020, 0xFF);
+        assert_eq!(action52.map_peek(0x5FFC), MappedRead::Data(0x0F));
+
+        // Reset returns to the menu, keeping the RAM until power off
+        action52.reset(Kind::Soft);
+        assert_eq!(action52.map_peek(0xC000), MappedRead::PrgRom(0x4000));
+        assert_eq!(action52.map_peek(0x0000), MappedRead::Chr(0x0000));
+        assert_eq!(action52.map_peek(0x4020), MappedRead::Data(0x0F));
+        action52.reset(Kind::Hard);
+        assert_eq!(action52.map_peek(0x4020), MappedRead::Data(0x00));
+    }
+}
@@ -0,0 +1,139 @@
+//! `Camerica BF9096`/Quattro multicarts (Mapper 232)
+//!
+//! <https://wiki.nesdev.org/w/index.php?title=INES_Mapper_232>
+
+use crate::{
+    cart::Cart,
+    common::{Clock, Kind, Regional, Reset},
+    mapper::{Mapped, MappedRead, MappedWrite, Mapper, MemMap},
+    mem::MemBanks,
+    ppu::Mirroring,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Bf9096 {
+    outer_bank: u8,
+    inner_bank: u8,
+    // Aladdin Deck Enhancer cartridges swap the outer bank bits
+    swap_outer_bank: bool,
+    mirroring: Mirroring,
+    prg_rom_banks: MemBanks,
+}
+
+impl Bf9096 {
+    const PRG_ROM_WINDOW: usize = 16 * 1024;
+    const CHR_RAM_SIZE: usize = 8 * 1024;
+
+    const OUTER_BANK_MASK: u8 = 0x18; // 0b11000
+    const INNER_BANK_MASK: u8 = 0x03; // 0b11
+
+    pub fn load(cart: &mut Cart) -> Mapper {
+        if !cart.has_chr() {
+            cart.add_chr_ram(Self::CHR_RAM_SIZE);
+        };
+        let mut bf9096 = Self {
+            outer_bank: 0x00,
+            inner_bank: 0x00,
+            swap_outer_bank: cart.submapper_num() == 1,
+            mirroring: cart.mirroring(),
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_ROM_WINDOW),
+        };
+        bf9096.update_banks();
+        bf9096.into()
+    }
+
+    fn update_banks(&mut self) {
+        let outer_bank = usize::from(self.outer_bank) << 2;
+        self.prg_rom_banks
+            .set(0, outer_bank | usize::from(self.inner_bank));
+        self.prg_rom_banks.set(1, outer_bank | 0x03);
+    }
+}
+
+impl MemMap for Bf9096 {
+    // PPU $0000..=$1FFF 8K CHR-RAM Bank Fixed
+    // CPU $8000..=$BFFF 16K PRG-ROM Bank Switchable within the 64K outer bank
+    // CPU $C000..=$FFFF 16K PRG-ROM Fixed to the last bank of the 64K outer bank
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(addr.into()),
+            0x8000..=0xFFFF => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        match addr {
+            0x0000..=0x1FFF => return MappedWrite::Chr(addr.into(), val),
+            // $8000-$BFFF: Select 64K outer PRG-ROM bank with bits 3-4
+            0x8000..=0xBFFF => {
+                let bank = (val & Self::OUTER_BANK_MASK) >> 3;
+                self.outer_bank = if self.swap_outer_bank {
+                    ((bank & 0x01) << 1) | (bank >> 1)
+                } else {
+                    bank
+                };
+                self.update_banks();
+            }
+            // $C000-$FFFF: Select 16K inner PRG-ROM bank with bits 0-1
+            0xC000..=0xFFFF => {
+                self.inner_bank = val & Self::INNER_BANK_MASK;
+                self.update_banks();
+            }
+            _ => (),
+        }
+        MappedWrite::None
+    }
+}
+
+impl Mapped for Bf9096 {
+    #[inline]
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    #[inline]
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+}
+
+impl Reset for Bf9096 {
+    // The BF9096 has no reset input, so a soft reset keeps the outer bank and restarts the
+    // current game from the fixed last bank of its 64K block
+    fn reset(&mut self, kind: Kind) {
+        if kind == Kind::Hard {
+            self.outer_bank = 0x00;
+            self.inner_bank = 0x00;
+            self.update_banks();
+        }
+    }
+}
+
+impl Clock for Bf9096 {}
+impl Regional for Bf9096 {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::load_mapper;
+
+    #[test]
+    fn banks() {
+        let (_, mut bf9096): (_, Bf9096) = load_mapper(0x40000, 0x2000, Bf9096::load);
+        assert_eq!(bf9096.map_peek(0xC000), MappedRead::PrgRom(0xC000));
+
+        let _ = bf9096.map_write(0x8000, 0x10);
+        let _ = bf9096.map_write(0xC000, 0x01);
+        assert_eq!(bf9096.map_peek(0x8000), MappedRead::PrgRom(0x24000));
+        assert_eq!(bf9096.map_peek(0xC000), MappedRead::PrgRom(0x2C000));
+
+        bf9096.reset(Kind::Soft);
+        assert_eq!(bf9096.map_peek(0x8000), MappedRead::PrgRom(0x24000));
+        bf9096.reset(Kind::Hard);
+        assert_eq!(bf9096.map_peek(0x8000), MappedRead::PrgRom(0x0000));
+    }
+}
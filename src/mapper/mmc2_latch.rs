@@ -0,0 +1,165 @@
+//! `Mmc2Latch`
+//!
+//! CHR-ROM latches shared by `MMC2` and `MMC4`, switching 4K CHR banks when the PPU fetches
+//! specific tiles.
+//!
+//! <https://www.nesdev.org/wiki/MMC2#CHR_banking>
+//! <https://www.nesdev.org/wiki/MMC4#CHR_banking>
+
+use crate::{
+    common::{Kind, Reset},
+    mem::MemBanks,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[must_use]
+pub enum Mmc2LatchRevision {
+    /// `MMC2` only triggers latch 0 on reads of exactly $0FD8 or $0FE8.
+    Mmc2,
+    /// `MMC4` triggers latch 0 on reads of $0FD8-$0FDF or $0FE8-$0FEF, like latch 1.
+    Mmc4,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Mmc2Latch {
+    revision: Mmc2LatchRevision,
+    // CHR-ROM $FD/0000 bank select ($B000-$BFFF)
+    // CHR-ROM $FE/0000 bank select ($C000-$CFFF)
+    // CHR-ROM $FD/1000 bank select ($D000-$DFFF)
+    // CHR-ROM $FE/1000 bank select ($E000-$EFFF)
+    // 7  bit  0
+    // ---- ----
+    // xxxC CCCC
+    //    | ||||
+    //    +-++++- Select 4K CHR-ROM bank for PPU $0000/$1000-$0FFF/$1FFF
+    //            used when latch 0/1 = $FD/$FE
+    latch: [usize; 2],
+    latch_banks: [u8; 4],
+    chr_banks: MemBanks,
+}
+
+impl Mmc2Latch {
+    const CHR_ROM_WINDOW: usize = 4 * 1024;
+
+    pub fn new(revision: Mmc2LatchRevision, chr_len: usize) -> Self {
+        Self {
+            revision,
+            latch: [0x00; 2],
+            latch_banks: [0x00; 4],
+            chr_banks: MemBanks::new(0x0000, 0x1FFF, chr_len, Self::CHR_ROM_WINDOW),
+        }
+    }
+
+    /// Writes one of the four bank select registers at $B000-$EFFF.
+    pub fn write_bank(&mut self, addr: u16, val: u8) {
+        self.latch_banks[((addr - 0xB000) >> 12) as usize] = val & 0x1F;
+        self.update_banks();
+    }
+
+    /// Updates the latches after a PPU read. The tile that triggers a latch is still fetched
+    /// from the previous bank.
+    pub fn read(&mut self, addr: u16) {
+        let triggered = match addr {
+            0x0FD8 | 0x0FE8 => true,
+            0x0FD9..=0x0FDF | 0x0FE9..=0x0FEF => self.revision == Mmc2LatchRevision::Mmc4,
+            0x1FD8..=0x1FDF | 0x1FE8..=0x1FEF => true,
+            _ => false,
+        };
+        if triggered {
+            let addr = addr as usize;
+            self.latch[addr >> 12] = ((addr >> 4) & 0xFF) - 0xFD;
+            self.update_banks();
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn translate(&self, addr: u16) -> usize {
+        self.chr_banks.translate(addr)
+    }
+
+    fn update_banks(&mut self) {
+        let bank0 = self.latch_banks[self.latch[0]] as usize;
+        let bank1 = self.latch_banks[self.latch[1] + 2] as usize;
+        self.chr_banks.set(0, bank0);
+        self.chr_banks.set(1, bank1);
+    }
+}
+
+impl Reset for Mmc2Latch {
+    fn reset(&mut self, _kind: Kind) {
+        self.latch = [0x00; 2];
+        self.latch_banks = [0x00; 4];
+        self.update_banks();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn latch(revision: Mmc2LatchRevision) -> Mmc2Latch {
+        let mut latch = Mmc2Latch::new(revision, 0x20000);
+        latch.write_bank(0xB000, 0x01);
+        latch.write_bank(0xC000, 0x02);
+        latch.write_bank(0xD000, 0x03);
+        latch.write_bank(0xE000, 0x04);
+        latch
+    }
+
+    #[test]
+    fn latch_timing() {
+        let mut latch = latch(Mmc2LatchRevision::Mmc2);
+        assert_eq!(latch.translate(0x0000), 0x1000, "latch 0 starts at $FD");
+        assert_eq!(latch.translate(0x1000), 0x3000, "latch 1 starts at $FD");
+
+        // The trigger tile is fetched from the current bank and switches on the next fetch
+        assert_eq!(latch.translate(0x0FE8), 0x1FE8);
+        latch.read(0x0FE8);
+        assert_eq!(latch.translate(0x0FE8), 0x2FE8);
+        latch.read(0x0FD8);
+        assert_eq!(latch.translate(0x0000), 0x1000);
+
+        latch.read(0x1FEF);
+        assert_eq!(latch.translate(0x1000), 0x4000);
+        latch.read(0x1FD9);
+        assert_eq!(latch.translate(0x1000), 0x3000);
+
+        // Bank writes apply to the currently selected latch immediately
+        latch.write_bank(0xB000, 0x05);
+        assert_eq!(latch.translate(0x0000), 0x5000);
+    }
+
+    #[test]
+    fn latch_0_trigger_range() {
+        let mut mmc2 = latch(Mmc2LatchRevision::Mmc2);
+        mmc2.read(0x0FE9);
+        assert_eq!(
+            mmc2.translate(0x0000),
+            0x1000,
+            "MMC2 only triggers on $0FE8"
+        );
+
+        let mut mmc4 = latch(Mmc2LatchRevision::Mmc4);
+        mmc4.read(0x0FE9);
+        assert_eq!(
+            mmc4.translate(0x0000),
+            0x2000,
+            "MMC4 triggers on $0FE8-$0FEF"
+        );
+        mmc4.read(0x0FDF);
+        assert_eq!(mmc4.translate(0x0000), 0x1000);
+        mmc4.read(0x0FF0);
+        assert_eq!(mmc4.translate(0x0000), 0x1000);
+    }
+
+    #[test]
+    fn reset() {
+        let mut latch = latch(Mmc2LatchRevision::Mmc4);
+        latch.read(0x1FE8);
+        latch.reset(Kind::Soft);
+        assert_eq!(latch.translate(0x1000), 0x0000);
+    }
+}
@@ -0,0 +1,529 @@
+//! `NES Sound Format` player
+//!
+//! NSF files contain only music code and data, so a small driver is mapped at $4100 which calls
+//! the file's init routine and then its play routine at the rate given in the header.
+//!
+//! <https://www.nesdev.org/wiki/NSF>
+
+use crate::{
+    audio::Audio,
+    cart::{
+        nsf::{NsfChips, NsfFile, NsfTrack},
+        Cart,
+    },
+    common::{Clock, Kind, NesRegion, Regional, Reset},
+    cpu::Cpu,
+    mapper::{
+        m005_exrom::Mmc5Audio, m024_m026_vrc6::Vrc6Audio, Mapped, MappedRead, MappedWrite, Mapper,
+        MemMap,
+    },
+    mem::MemBanks,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Player driver mapped at $4100. Clears RAM and the APU, calls init with the track in A and the
+/// region in X, then calls play whenever the play timer sets the pending flag.
+const DRIVER: [u8; 0x6D] = [
+    0x78, // $4100 SEI
+    0xD8, // $4101 CLD
+    0xA2, 0xFF, // $4102 LDX #$FF
+    0x9A, // $4104 TXS
+    0xE8, // $4105 INX
+    0x8E, 0x00, 0x20, // $4106 STX $2000
+    0x8E, 0x01, 0x20, // $4109 STX $2001
+    0xA9, 0x60, // $410C LDA #$60
+    0x85, 0x01, // $410E STA $01
+    0x86, 0x00, // $4110 STX $00
+    0x8A, // $4112 TXA
+    0xA8, // $4113 TAY
+    0x91, 0x00, // $4114 STA ($00),Y ; clear PRG-RAM
+    0xC8, // $4116 INY
+    0xD0, 0xFB, // $4117 BNE $4114
+    0xE6, 0x01, // $4119 INC $01
+    0xA6, 0x01, // $411B LDX $01
+    0xE0, 0x80, // $411D CPX #$80
+    0xD0, 0xF3, // $411F BNE $4114
+    0xA2, 0x00, // $4121 LDX #$00
+    0x95, 0x00, // $4123 STA $00,X ; clear WRAM
+    0x9D, 0x00, 0x01, // $4125 STA $0100,X
+    0x9D, 0x00, 0x02, // $4128 STA $0200,X
+    0x9D, 0x00, 0x03, // $412B STA $0300,X
+    0x9D, 0x00, 0x04, // $412E STA $0400,X
+    0x9D, 0x00, 0x05, // $4131 STA $0500,X
+    0x9D, 0x00, 0x06, // $4134 STA $0600,X
+    0x9D, 0x00, 0x07, // $4137 STA $0700,X
+    0xE8, // $413A INX
+    0xD0, 0xE6, // $413B BNE $4123
+    0xA2, 0x13, // $413D LDX #$13
+    0x9D, 0x00, 0x40, // $413F STA $4000,X ; clear APU
+    0xCA, // $4142 DEX
+    0x10, 0xFA, // $4143 BPL $413F
+    0x8D, 0x15, 0x40, // $4145 STA $4015
+    0xA9, 0x0F, // $4148 LDA #$0F
+    0x8D, 0x15, 0x40, // $414A STA $4015
+    0xA9, 0x40, // $414D LDA #$40
+    0x8D, 0x17, 0x40, // $414F STA $4017
+    0xAD, 0xF0, 0x41, // $4152 LDA $41F0 ; track
+    0xAE, 0xF1, 0x41, // $4155 LDX $41F1 ; region
+    0x20, 0x66, 0x41, // $4158 JSR $4166
+    0xAD, 0xF6, 0x41, // $415B LDA $41F6 ; play pending
+    0xF0, 0xFB, // $415E BEQ $415B
+    0x20, 0x69, 0x41, // $4160 JSR $4169
+    0x4C, 0x5B, 0x41, // $4163 JMP $415B
+    0x6C, 0xF2, 0x41, // $4166 JMP ($41F2) ; init
+    0x6C, 0xF4, 0x41, // $4169 JMP ($41F4) ; play
+    0x40, // $416C RTI
+];
+const DRIVER_START: u16 = 0x4100;
+const DRIVER_RESET: u16 = DRIVER_START;
+const DRIVER_RTI: u16 = 0x416C;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[must_use]
+pub struct Nsf {
+    title: String,
+    artist: String,
+    copyright: String,
+    tracks: Vec<NsfTrack>,
+    track: usize,
+    init_addr: u16,
+    play_addr: u16,
+    ntsc_speed: u16,
+    pal_speed: u16,
+    banks: [u8; 8],
+    bankswitched: bool,
+    chips: NsfChips,
+    region: NesRegion,
+    play_pending: bool,
+    play_timer: f32,
+    elapsed: u64,
+    vrc6: Vrc6Audio,
+    mmc5: Mmc5Audio,
+    exram: Vec<u8>,
+    multiplicand: u8,
+    multiplier: u8,
+    prg_rom_banks: MemBanks,
+}
+
+impl Nsf {
+    const PRG_RAM_SIZE: usize = 8 * 1024;
+    const CHR_RAM_SIZE: usize = 8 * 1024;
+    const PRG_WINDOW: usize = 4 * 1024;
+    const EXRAM_SIZE: usize = 1024;
+    /// Default track length, used if an NSFe file doesn't provide one.
+    const DEFAULT_LENGTH: u32 = 150_000;
+    const DEFAULT_FADE: u32 = 8_000;
+
+    pub fn load(cart: &mut Cart, file: NsfFile) -> Mapper {
+        let bankswitched = file.banks.is_some();
+        let mut prg_rom = if bankswitched {
+            // Data is padded so the load address lines up with the start of a 4K bank
+            let mut prg_rom = vec![0x00; usize::from(file.load_addr & 0x0FFF)];
+            prg_rom.extend(&file.data);
+            prg_rom
+        } else {
+            let mut prg_rom = vec![0x00; 0x8000];
+            let start = usize::from(file.load_addr - 0x8000);
+            let len = file.data.len().min(prg_rom.len() - start);
+            prg_rom[start..start + len].copy_from_slice(&file.data[..len]);
+            prg_rom
+        };
+        let page_count = prg_rom.len().max(1).next_power_of_two() / Self::PRG_WINDOW;
+        prg_rom.resize(page_count.max(1) * Self::PRG_WINDOW, 0x00);
+        cart.prg_rom = prg_rom;
+        cart.add_prg_ram(Self::PRG_RAM_SIZE);
+        cart.add_chr_ram(Self::CHR_RAM_SIZE);
+
+        let unsupported = file.chips - (NsfChips::VRC6 | NsfChips::MMC5);
+        if !unsupported.is_empty() {
+            log::warn!("unsupported nsf expansion audio: {unsupported:?}");
+        }
+
+        let mut nsf = Self {
+            title: file.title,
+            artist: file.artist,
+            copyright: file.copyright,
+            tracks: file.tracks,
+            track: file.starting_track,
+            init_addr: file.init_addr,
+            play_addr: file.play_addr,
+            ntsc_speed: file.ntsc_speed,
+            pal_speed: file.pal_speed,
+            banks: file.banks.unwrap_or([0, 1, 2, 3, 4, 5, 6, 7]),
+            bankswitched,
+            chips: file.chips,
+            region: file.region.unwrap_or_default(),
+            play_pending: false,
+            play_timer: 0.0,
+            elapsed: 0,
+            vrc6: Vrc6Audio::new(),
+            mmc5: Mmc5Audio::new(),
+            exram: vec![0x00; Self::EXRAM_SIZE],
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+            prg_rom_banks: MemBanks::new(0x8000, 0xFFFF, cart.prg_rom.len(), Self::PRG_WINDOW),
+        };
+        if nsf.track >= nsf.tracks.len() {
+            nsf.track = 0;
+        }
+        nsf.update_banks();
+        nsf.into()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn artist(&self) -> &str {
+        &self.artist
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn copyright(&self) -> &str {
+        &self.copyright
+    }
+
+    /// The current zero-based track.
+    #[inline]
+    #[must_use]
+    pub const fn track(&self) -> usize {
+        self.track
+    }
+
+    /// Returns a display label for each track.
+    #[must_use]
+    pub fn track_labels(&self) -> Vec<String> {
+        self.tracks
+            .iter()
+            .enumerate()
+            .map(|(i, track)| match track.label {
+                Some(ref label) => format!("{} - {label}", i + 1),
+                None => format!("Track {}", i + 1),
+            })
+            .collect()
+    }
+
+    /// Selects the track played on the next reset.
+    pub fn set_track(&mut self, track: usize) {
+        self.track = track.min(self.tracks.len().saturating_sub(1));
+    }
+
+    /// Time elapsed since the current track started.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        let clock_rate = f64::from(Cpu::region_clock_rate(self.region));
+        Duration::from_secs_f64(self.elapsed as f64 / clock_rate)
+    }
+
+    /// Play time of the current track before fading out.
+    #[must_use]
+    pub fn track_length(&self) -> Duration {
+        let length = self.tracks[self.track]
+            .length
+            .unwrap_or(Self::DEFAULT_LENGTH);
+        Duration::from_millis(length.into())
+    }
+
+    #[must_use]
+    pub fn fade_length(&self) -> Duration {
+        let fade = self.tracks[self.track].fade.unwrap_or(Self::DEFAULT_FADE);
+        Duration::from_millis(fade.into())
+    }
+
+    /// Output volume, fading out linearly once the track length is reached.
+    #[must_use]
+    pub fn volume(&self) -> f32 {
+        let fading = self.elapsed().saturating_sub(self.track_length());
+        if fading.is_zero() {
+            1.0
+        } else if self.fade_length().is_zero() {
+            0.0
+        } else {
+            (1.0 - fading.as_secs_f32() / self.fade_length().as_secs_f32()).max(0.0)
+        }
+    }
+
+    /// Whether the current track has finished playing and fading out.
+    #[must_use]
+    pub fn finished(&self) -> bool {
+        self.elapsed() >= self.track_length() + self.fade_length()
+    }
+
+    fn update_banks(&mut self) {
+        for (slot, &bank) in self.banks.iter().enumerate() {
+            self.prg_rom_banks.set(slot, bank.into());
+        }
+    }
+
+    /// Number of CPU cycles between calls to the play routine.
+    fn play_period(&self) -> f32 {
+        let speed = match self.region {
+            NesRegion::Ntsc => self.ntsc_speed,
+            NesRegion::Pal | NesRegion::Dendy => self.pal_speed,
+        };
+        let speed = if speed == 0 { 16639 } else { speed };
+        f32::from(speed) * Cpu::region_clock_rate(self.region) / 1_000_000.0
+    }
+
+    #[must_use]
+    fn peek_register(&self, addr: u16) -> u8 {
+        match addr {
+            0x41F0 => self.track as u8,
+            0x41F1 => u8::from(self.region == NesRegion::Pal),
+            0x41F2 => self.init_addr.to_le_bytes()[0],
+            0x41F3 => self.init_addr.to_le_bytes()[1],
+            0x41F4 => self.play_addr.to_le_bytes()[0],
+            0x41F5 => self.play_addr.to_le_bytes()[1],
+            0x41F6 => u8::from(self.play_pending),
+            _ => DRIVER
+                .get(usize::from(addr - DRIVER_START))
+                .copied()
+                .unwrap_or(0x00),
+        }
+    }
+}
+
+impl Mapped for Nsf {}
+
+impl MemMap for Nsf {
+    // PPU $0000..=$1FFF 8K CHR-RAM
+    //
+    // CPU $4100..=$41FF Player driver
+    // CPU $5000..=$5015 MMC5 audio
+    // CPU $5205..=$5206 MMC5 multiplier
+    // CPU $5C00..=$5FF5 MMC5 ExRAM
+    // CPU $5FF8..=$5FFF Bank select
+    // CPU $6000..=$7FFF 8K PRG-RAM
+    // CPU $8000..=$FFFF 4K switchable PRG-ROM banks
+    // CPU $FFFA..=$FFFF Vectors pointing to the player driver
+
+    fn map_read(&mut self, addr: u16) -> MappedRead {
+        match addr {
+            0x41F6 => {
+                let val = self.peek_register(addr);
+                self.play_pending = false;
+                MappedRead::Data(val)
+            }
+            0x5010 if self.chips.contains(NsfChips::MMC5) => {
+                let val = self.mmc5.read_register(addr);
+                self.mmc5.acknowledge_irq();
+                MappedRead::Data(val)
+            }
+            _ => self.map_peek(addr),
+        }
+    }
+
+    fn map_peek(&self, addr: u16) -> MappedRead {
+        match addr {
+            0x0000..=0x1FFF => MappedRead::Chr(addr.into()),
+            0x4100..=0x41FF => MappedRead::Data(self.peek_register(addr)),
+            0x5010 | 0x5015 if self.chips.contains(NsfChips::MMC5) => {
+                MappedRead::Data(self.mmc5.read_register(addr))
+            }
+            0x5205 | 0x5206 if self.chips.contains(NsfChips::MMC5) => {
+                let result = u16::from(self.multiplicand) * u16::from(self.multiplier);
+                MappedRead::Data(result.to_le_bytes()[usize::from(addr - 0x5205)])
+            }
+            0x5C00..=0x5FF5 if self.chips.contains(NsfChips::MMC5) => {
+                MappedRead::Data(self.exram[usize::from(addr - 0x5C00)])
+            }
+            0x6000..=0x7FFF => MappedRead::PrgRam((addr - 0x6000).into()),
+            0xFFFA | 0xFFFE => MappedRead::Data(DRIVER_RTI.to_le_bytes()[0]),
+            0xFFFB | 0xFFFF => MappedRead::Data(DRIVER_RTI.to_le_bytes()[1]),
+            0xFFFC => MappedRead::Data(DRIVER_RESET.to_le_bytes()[0]),
+            0xFFFD => MappedRead::Data(DRIVER_RESET.to_le_bytes()[1]),
+            0x8000..=0xFFF9 => MappedRead::PrgRom(self.prg_rom_banks.translate(addr)),
+            _ => MappedRead::None,
+        }
+    }
+
+    fn map_write(&mut self, addr: u16, val: u8) -> MappedWrite {
+        let mmc5 = self.chips.contains(NsfChips::MMC5);
+        match addr {
+            0x0000..=0x1FFF => return MappedWrite::Chr(addr.into(), val),
+            0x5000..=0x5015 if mmc5 => self.mmc5.write_register(addr, val),
+            0x5205 if mmc5 => self.multiplicand = val,
+            0x5206 if mmc5 => self.multiplier = val,
+            0x5C00..=0x5FF5 if mmc5 => self.exram[usize::from(addr - 0x5C00)] = val,
+            0x5FF8..=0x5FFF if self.bankswitched => {
+                let slot = usize::from(addr - 0x5FF8);
+                self.prg_rom_banks.set(slot, val.into());
+            }
+            0x6000..=0x7FFF => return MappedWrite::PrgRam((addr - 0x6000).into(), val),
+            0x9000..=0x9003 | 0xA000..=0xA002 | 0xB000..=0xB002
+                if self.chips.contains(NsfChips::VRC6) =>
+            {
+                self.vrc6.write_register(addr, val);
+            }
+            _ => (),
+        }
+        MappedWrite::None
+    }
+}
+
+impl Audio for Nsf {
+    fn output(&self) -> f32 {
+        let mut output = 0.0;
+        if self.chips.contains(NsfChips::VRC6) {
+            output += self.vrc6.output();
+        }
+        if self.chips.contains(NsfChips::MMC5) {
+            output += self.mmc5.output();
+        }
+        output
+    }
+}
+
+impl Clock for Nsf {
+    fn clock(&mut self) -> usize {
+        self.play_timer -= 1.0;
+        if self.play_timer <= 0.0 {
+            self.play_pending = true;
+            self.play_timer += self.play_period();
+        }
+        self.elapsed += 1;
+        if self.chips.contains(NsfChips::VRC6) {
+            self.vrc6.clock();
+        }
+        if self.chips.contains(NsfChips::MMC5) {
+            self.mmc5.clock();
+        }
+        1
+    }
+}
+
+impl Regional for Nsf {
+    #[inline]
+    fn region(&self) -> NesRegion {
+        self.region
+    }
+
+    fn set_region(&mut self, region: NesRegion) {
+        self.region = region;
+        self.mmc5.set_region(region);
+    }
+}
+
+impl Reset for Nsf {
+    /// Restarts the selected track.
+    fn reset(&mut self, _kind: Kind) {
+        self.update_banks();
+        self.play_pending = false;
+        self.play_timer = self.play_period();
+        self.elapsed = 0;
+        self.vrc6 = Vrc6Audio::new();
+        self.mmc5 = Mmc5Audio::new();
+        self.mmc5.set_region(self.region);
+        self.exram.fill(0x00);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(banks: Option<[u8; 8]>, load_addr: u16) -> (Cart, Nsf) {
+        let mut cart = Cart::empty();
+        let file = NsfFile {
+            load_addr,
+            init_addr: 0x8000,
+            play_addr: 0x8003,
+            ntsc_speed: 16639,
+            pal_speed: 19997,
+            banks,
+            chips: NsfChips::VRC6,
+            starting_track: 1,
+            tracks: vec![
+                NsfTrack::default(),
+                NsfTrack {
+                    label: Some("Theme".to_string()),
+                    length: Some(1_000),
+                    fade: Some(1_000),
+                },
+            ],
+            data: (0..=0xFF).cycle().take(0x2000).collect(),
+            ..NsfFile::default()
+        };
+        let Mapper::Nsf(mut nsf) = Nsf::load(&mut cart, file) else {
+            panic!("expected nsf mapper");
+        };
+        nsf.reset(Kind::Hard);
+        (cart, nsf)
+    }
+
+    #[test]
+    fn driver() {
+        let (_, mut nsf) = load(None, 0x8000);
+        let read = |nsf: &mut Nsf, addr| match nsf.map_read(addr) {
+            MappedRead::Data(val) => val,
+            read => panic!("expected data at ${addr:04X}: {read:?}"),
+        };
+        assert_eq!(read(&mut nsf, 0xFFFC), 0x00);
+        assert_eq!(read(&mut nsf, 0xFFFD), 0x41);
+        assert_eq!(read(&mut nsf, 0xFFFA), 0x6C);
+        assert_eq!(read(&mut nsf, 0x4100), DRIVER[0]);
+        assert_eq!(read(&mut nsf, DRIVER_RTI), 0x40, "RTI");
+        assert_eq!(read(&mut nsf, 0x41F0), 1, "starting track");
+        assert_eq!(read(&mut nsf, 0x41F4), 0x03);
+        assert_eq!(read(&mut nsf, 0x41F5), 0x80);
+
+        assert_eq!(read(&mut nsf, 0x41F6), 0x00);
+        let period = nsf.play_period().ceil() as usize;
+        for _ in 0..period {
+            nsf.clock();
+        }
+        assert_eq!(read(&mut nsf, 0x41F6), 0x01, "play pending");
+        assert_eq!(read(&mut nsf, 0x41F6), 0x00, "cleared on read");
+    }
+
+    #[test]
+    fn load_data() {
+        let (cart, nsf) = load(None, 0x9000);
+        assert_eq!(cart.prg_rom.len(), 0x8000);
+        assert!(matches!(nsf.map_peek(0x8000), MappedRead::PrgRom(0x0000)));
+        assert!(matches!(nsf.map_peek(0x9001), MappedRead::PrgRom(0x1001)));
+        assert_eq!(cart.prg_rom[0x1001], 0x01);
+
+        let (cart, mut nsf) = load(Some([0, 1, 0, 0, 0, 0, 0, 1]), 0x8100);
+        assert_eq!(cart.prg_rom.len(), 0x4000, "rounded up to a power of two");
+        assert_eq!(cart.prg_rom[0x0100], 0x00, "padded to load address");
+        assert!(matches!(nsf.map_peek(0xF000), MappedRead::PrgRom(0x1000)));
+        let _ = nsf.map_write(0x5FFF, 0x02);
+        assert!(matches!(nsf.map_peek(0xF000), MappedRead::PrgRom(0x2000)));
+        nsf.reset(Kind::Soft);
+        assert!(matches!(nsf.map_peek(0xF000), MappedRead::PrgRom(0x1000)));
+    }
+
+    #[test]
+    fn track_fade() {
+        let (_, mut nsf) = load(None, 0x8000);
+        assert_eq!(nsf.track_labels(), ["Track 1", "2 - Theme"]);
+        assert_eq!(nsf.track_length(), Duration::from_secs(1));
+        assert!((nsf.volume() - 1.0).abs() < f32::EPSILON);
+
+        let cycles_per_sec = Cpu::region_clock_rate(NesRegion::Ntsc) as usize;
+        for _ in 0..cycles_per_sec * 3 / 2 {
+            nsf.clock();
+        }
+        assert!((nsf.volume() - 0.5).abs() < 0.01, "half faded");
+        assert!(!nsf.finished());
+        for _ in 0..cycles_per_sec {
+            nsf.clock();
+        }
+        assert!(nsf.finished());
+
+        nsf.set_track(5);
+        assert_eq!(nsf.track(), 1);
+        nsf.set_track(0);
+        nsf.reset(Kind::Soft);
+        assert_eq!(nsf.track(), 0, "track kept on reset");
+        assert_eq!(nsf.elapsed(), Duration::ZERO);
+        assert_eq!(nsf.track_length(), Duration::from_secs(150));
+    }
+}
@@ -18,6 +18,12 @@ pub struct VrcIrq {
 }
 
 impl VrcIrq {
+    #[inline]
+    #[must_use]
+    pub const fn reload(&self) -> u8 {
+        self.reload
+    }
+
     #[inline]
     pub fn write_reload(&mut self, val: u8) {
         self.reload = val;
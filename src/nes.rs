@@ -149,6 +149,7 @@ impl NesBuilder {
         control_deck.set_filter(config.filter);
         control_deck.set_four_player(config.four_player);
         control_deck.connect_zapper(config.zapper);
+        control_deck.set_dip_switches(config.dip_switches);
 
         Ok(Nes::new(
             control_deck,
@@ -255,7 +256,7 @@ impl Nes {
     /// If engine fails to build or run, then an error is returned.
     pub fn run(&mut self) -> NesResult<()> {
         let title = APP_NAME.to_owned();
-        let (width, height) = self.config.get_dimensions();
+        let (width, height) = self.get_dimensions();
         let mut engine = Engine::builder();
         engine
             .dimensions(width, height)
@@ -402,11 +403,14 @@ impl PixEngine for Nes {
                 self.render_status(s, "Rewinding")?;
                 self.rewind();
             }
-            Mode::Playing => match self.replay.mode {
-                ReplayMode::Recording => self.render_status(s, "Recording Replay")?,
-                ReplayMode::Playback => self.render_status(s, "Replay Playback")?,
-                ReplayMode::Off => (),
-            },
+            Mode::Playing => {
+                self.render_nsf_player(s)?;
+                match self.replay.mode {
+                    ReplayMode::Recording => self.render_status(s, "Recording Replay")?,
+                    ReplayMode::Playback => self.render_status(s, "Replay Playback")?,
+                    ReplayMode::Off => (),
+                }
+            }
         }
         if (self.config.speed - 1.0).abs() > f32::EPSILON {
             self.render_status(s, &format!("Speed {:.2}", self.config.speed))?;
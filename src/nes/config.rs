@@ -1,5 +1,5 @@
 use crate::{
-    common::{config_dir, config_path, NesRegion},
+    common::{config_dir, config_path, NesRegion, Regional},
     input::FourPlayer,
     mem::RamState,
     nes::{
@@ -47,6 +47,7 @@ pub(crate) struct Config {
     pub(crate) rewind_buffer_size: usize,
     pub(crate) four_player: FourPlayer,
     pub(crate) zapper: bool,
+    pub(crate) dip_switches: u8,
     pub(crate) audio_sample_rate: f32,
     pub(crate) audio_buffer_size: usize,
     pub(crate) dynamic_rate_control: bool,
@@ -78,6 +79,7 @@ impl Default for Config {
             rewind_buffer_size: 20,
             four_player: FourPlayer::default(),
             zapper: false,
+            dip_switches: 0x00,
             audio_sample_rate: 44_100.0,
             audio_buffer_size: 4096,
             dynamic_rate_control: true,
@@ -155,16 +157,6 @@ impl Config {
     //     self.input_map.remove(&input);
     //     self.bindings.update_from_map(&self.input_map);
     // }
-
-    pub(crate) fn get_dimensions(&self) -> (u32, u32) {
-        let width = match self.region {
-            NesRegion::Ntsc => WINDOW_WIDTH_NTSC,
-            NesRegion::Pal | NesRegion::Dendy => WINDOW_WIDTH_PAL,
-        };
-        let width = (self.scale * width) as u32;
-        let height = (self.scale * WINDOW_HEIGHT) as u32;
-        (width, height)
-    }
 }
 
 impl Nes {
@@ -184,6 +176,16 @@ impl Nes {
         }
     }
 
+    pub(crate) fn get_dimensions(&self) -> (u32, u32) {
+        let width = match self.control_deck.region() {
+            NesRegion::Ntsc => WINDOW_WIDTH_NTSC,
+            NesRegion::Pal | NesRegion::Dendy => WINDOW_WIDTH_PAL,
+        };
+        let width = (self.config.scale * width) as u32;
+        let height = (self.config.scale * WINDOW_HEIGHT) as u32;
+        (width, height)
+    }
+
     pub(crate) fn set_scale(&mut self, s: &mut PixState, scale: f32) {
         self.config.scale = scale;
         let (font_size, fpad, ipad) = match scale as usize {
@@ -209,18 +211,19 @@ impl Nes {
     }
 
     pub(crate) fn update_frame_rate(&mut self, s: &mut PixState) -> PixResult<()> {
-        match self.config.region {
+        let region = self.control_deck.region();
+        match region {
             NesRegion::Ntsc => s.frame_rate(60),
             NesRegion::Pal => s.frame_rate(50),
             NesRegion::Dendy => s.frame_rate(59),
         }
         log::debug!(
             "Updated NES Region and frame rate: {:?}, {:?}",
-            self.config.region,
+            region,
             s.target_frame_rate()
         );
         // TODO: Should actually check current screen refresh rate here instead of region
-        if self.config.vsync && self.config.region != NesRegion::Ntsc {
+        if self.config.vsync && region != NesRegion::Ntsc {
             s.vsync(false)?;
         }
         Ok(())
@@ -133,6 +133,8 @@ pub(crate) enum Action {
     Setting(Setting),
     Joypad(JoypadBtn),
     ZapperTrigger,
+    InsertCoin,
+    ServiceButton,
     ZeroAxis([JoypadBtn; 2]),
     Debug(DebugAction),
 }
@@ -143,6 +145,7 @@ pub(crate) enum NesState {
     TogglePause,
     SoftReset,
     HardReset,
+    SwitchDiskSide,
     MapperRevision(MapperRevision),
 }
 
@@ -402,6 +405,14 @@ impl Nes {
                 self.handle_zapper_trigger();
                 true
             }
+            Action::InsertCoin if pressed => {
+                self.control_deck.insert_coin(slot);
+                true
+            }
+            Action::ServiceButton => {
+                self.control_deck.set_service_button(pressed);
+                true
+            }
             Action::ZeroAxis(buttons) => {
                 let mut handled = false;
                 for button in buttons {
@@ -521,6 +532,13 @@ impl Nes {
                     self.mode = Mode::Paused;
                 }
             }
+            NesState::SwitchDiskSide => match self.control_deck.switch_disk_side() {
+                Some(side) => {
+                    let disk_side = if side % 2 == 0 { 'A' } else { 'B' };
+                    self.add_message(format!("Inserted Disk {} Side {disk_side}", side / 2 + 1));
+                }
+                None => self.add_message("No disk to switch"),
+            },
             NesState::MapperRevision(_) => todo!("mapper revision"),
         }
         Ok(true)
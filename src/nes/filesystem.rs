@@ -1,18 +1,22 @@
 use super::{Menu, Mode, Nes, NesResult};
-use crate::{audio::AudioMixer, cart::NesHeader, common::Regional};
+use crate::{audio::AudioMixer, cart::patch::PatchFormat};
 use anyhow::{anyhow, Context};
 use flate2::{bufread::DeflateDecoder, write::DeflateEncoder, Compression};
 use pix_engine::prelude::PixState;
 use std::{
     ffi::OsStr,
     fs::{self, File},
-    io::{BufReader, BufWriter, Read, Write},
-    path::Path,
+    io::{BufReader, BufWriter, ErrorKind, Read, Seek, Write},
+    path::{Path, PathBuf},
 };
 
 const SAVE_FILE_MAGIC_LEN: usize = 8;
 const SAVE_FILE_MAGIC: [u8; SAVE_FILE_MAGIC_LEN] = *b"TETANES\x1a";
+// Retained so files written by earlier versions keep the same header layout. Save state
+// compatibility is handled by the schema version in `SaveState` instead.
 const MAJOR_VERSION: &str = env!("CARGO_PKG_VERSION_MAJOR");
+/// File extensions that can be loaded as ROMs.
+const ROM_EXTENSIONS: [&str; 8] = ["nes", "unf", "unif", "fds", "nsf", "nsfe", "zip", "gz"];
 
 /// Writes a header including a magic string and a version
 ///
@@ -25,7 +29,8 @@ pub(crate) fn write_save_header<F: Write>(f: &mut F) -> NesResult<()> {
     Ok(())
 }
 
-/// Verifies a `TetaNES` saved state header.
+/// Verifies a `TetaNES` saved state header. The version byte is not checked, so files written
+/// by other versions can still be read and migrated if needed.
 ///
 /// # Errors
 ///
@@ -36,15 +41,7 @@ pub(crate) fn validate_save_header<F: Read>(f: &mut F) -> NesResult<()> {
     if magic == SAVE_FILE_MAGIC {
         let mut version = [0u8];
         f.read_exact(&mut version)?;
-        if version == MAJOR_VERSION.as_bytes() {
-            Ok(())
-        } else {
-            Err(anyhow!(
-                "invalid save file version. current: {}, save file: {}",
-                MAJOR_VERSION,
-                version[0],
-            ))
-        }
+        Ok(())
     } else {
         Err(anyhow!("invalid save file format"))
     }
@@ -132,7 +129,12 @@ pub(crate) fn is_nes_rom<P>(path: P) -> bool
 where
     P: AsRef<Path>,
 {
-    NesHeader::from_path(path.as_ref()).is_ok()
+    let path = path.as_ref();
+    path.is_file()
+        && path
+            .extension()
+            .and_then(OsStr::to_str)
+            .is_some_and(|ext| ROM_EXTENSIONS.contains(&ext))
 }
 
 impl Nes {
@@ -148,15 +150,26 @@ impl Nes {
             })
     }
 
+    /// Finds an `IPS`, `UPS` or `BPS` patch with the same name as the current ROM.
+    fn find_patch(&self) -> Option<(PathBuf, Vec<u8>)> {
+        PatchFormat::EXTENSIONS.iter().find_map(|ext| {
+            let path = self.config.rom_path.with_extension(ext);
+            match fs::read(&path) {
+                Ok(patch) => Some((path, patch)),
+                Err(err) if err.kind() == ErrorKind::NotFound => None,
+                Err(err) => {
+                    log::warn!("failed to read patch {path:?}: {err:?}");
+                    None
+                }
+            }
+        })
+    }
+
     /// Loads a ROM cartridge into memory
     pub(crate) fn load_rom(&mut self, s: &mut PixState) -> NesResult<()> {
         if self.config.rom_path.is_dir() {
             self.mode = Mode::InMenu(Menu::LoadRom);
             return Ok(());
-        } else if let Err(err) = NesHeader::from_path(&self.config.rom_path) {
-            log::error!("{:?}: {:?}", self.config.rom_path, err);
-            self.error = Some(format!("Invalid NES ROM {:?}", self.rom_filename()));
-            return Ok(());
         }
 
         self.error = None;
@@ -184,10 +197,34 @@ impl Nes {
         }
 
         let mut rom = BufReader::new(rom);
-        match self.control_deck.load_rom(&name, &mut rom) {
+        let loaded = match self.find_patch() {
+            Some((path, patch)) => {
+                log::info!("applying patch {path:?}");
+                match self
+                    .control_deck
+                    .load_rom_with_patch(&name, &mut rom, &patch)
+                {
+                    Ok(()) => {
+                        self.add_message("Applied ROM patch");
+                        Ok(())
+                    }
+                    // Fall back to the unpatched ROM so a mismatched patch doesn't prevent play
+                    Err(err) => {
+                        log::error!("{path:?}: {err:?}");
+                        let loaded = rom
+                            .rewind()
+                            .map_err(anyhow::Error::from)
+                            .and_then(|()| self.control_deck.load_rom(&name, &mut rom));
+                        self.add_message("Failed to apply ROM patch");
+                        loaded
+                    }
+                }
+            }
+            None => self.control_deck.load_rom(&name, &mut rom),
+        };
+        match loaded {
             Ok(()) => {
-                self.config.region = self.control_deck.region();
-                s.set_window_dimensions(self.config.get_dimensions())?;
+                s.set_window_dimensions(self.get_dimensions())?;
                 self.update_frame_rate(s)?;
                 self.audio = AudioMixer::new(
                     self.control_deck.sample_rate(),
@@ -232,5 +269,17 @@ mod tests {
             validate_save_header(&mut file.as_slice()).is_ok(),
             "validate save header"
         );
+
+        // Older major versions are accepted
+        let mut file = SAVE_FILE_MAGIC.to_vec();
+        file.push(b'0');
+        assert!(
+            validate_save_header(&mut file.as_slice()).is_ok(),
+            "validate older save header"
+        );
+        assert!(
+            validate_save_header(&mut b"NOTANES\x1a0".as_slice()).is_err(),
+            "invalid save header"
+        );
     }
 }
@@ -15,7 +15,6 @@ use crate::{
 use pix_engine::prelude::*;
 use std::{
     borrow::Cow,
-    ffi::OsStr,
     path::{Component, PathBuf},
 };
 
@@ -85,6 +84,72 @@ impl Nes {
 }
 
 impl Nes {
+    /// Renders track information and playback controls while an `NSF` file is playing.
+    pub(crate) fn render_nsf_player(&mut self, s: &mut PixState) -> PixResult<()> {
+        let Some(nsf) = self.control_deck.nsf() else {
+            return Ok(());
+        };
+        let title = if nsf.title().is_empty() {
+            self.rom_filename().to_string()
+        } else {
+            nsf.title().to_string()
+        };
+        let credits = [nsf.artist(), nsf.copyright()]
+            .into_iter()
+            .filter(|credit| !credit.is_empty())
+            .collect::<Vec<_>>()
+            .join(" - ");
+        let labels = nsf.track_labels();
+        let track = nsf.track();
+        let elapsed = nsf.elapsed().as_secs();
+        let length = nsf.track_length().as_secs();
+        let finished = nsf.finished();
+
+        if finished {
+            // Advance to the next track, or stop after the last one
+            if track + 1 < labels.len() {
+                self.control_deck.play_nsf_track(track + 1);
+            } else {
+                self.control_deck.play_nsf_track(0);
+                self.pause_play();
+            }
+            return Ok(());
+        }
+
+        s.heading(title)?;
+        if !credits.is_empty() {
+            s.text(credits)?;
+        }
+        s.spacing()?;
+
+        let mut selected_track = track;
+        s.next_width(250);
+        if s.select_box("Track", &mut selected_track, &labels, labels.len().min(10))? {
+            self.control_deck.play_nsf_track(selected_track);
+        }
+        s.text(format!(
+            "{:02}:{:02} / {:02}:{:02}",
+            elapsed / 60,
+            elapsed % 60,
+            length / 60,
+            length % 60
+        ))?;
+
+        s.disable(track == 0);
+        if s.button("< Prev")? {
+            self.control_deck.play_nsf_track(track - 1);
+        }
+        s.disable(false);
+        s.same_line(None);
+        s.disable(track + 1 >= labels.len());
+        if s.button("Next >")? {
+            self.control_deck.play_nsf_track(track + 1);
+        }
+        s.disable(false);
+
+        Ok(())
+    }
+
     fn render_heading(&mut self, s: &mut PixState, heading: &str) -> PixResult<()> {
         s.heading(heading)?;
         if self.control_deck.is_running() && s.menu("< Exit")? {
@@ -141,7 +206,7 @@ impl Nes {
         if s.select_box("NES Region", &mut region, NesRegion::as_slice(), 3)? {
             self.config.region = NesRegion::from(region);
             self.control_deck.set_region(self.config.region);
-            s.set_window_dimensions(self.config.get_dimensions())?;
+            s.set_window_dimensions(self.get_dimensions())?;
             self.update_frame_rate(s)?;
             self.audio = AudioMixer::new(
                 self.control_deck.sample_rate(),
@@ -249,7 +314,7 @@ impl Nes {
         s.next_width(80);
         if s.select_box("Scale", &mut scale, &["1", "2", "3", "4"], 4)? {
             self.set_scale(s, scale as f32 + 1.0);
-            let (width, height) = self.config.get_dimensions();
+            let (width, height) = self.get_dimensions();
             s.set_window_dimensions((width, height))?;
             if let Some(debugger) = &self.debugger {
                 s.set_window_target(debugger.window_id())?;
@@ -281,6 +346,25 @@ impl Nes {
         Ok(())
     }
 
+    fn render_config_vs_system(&mut self, s: &mut PixState) -> PixResult<()> {
+        s.text("DIP Switches")?;
+        s.same_line(None);
+        s.help_marker("Cabinet settings read by VS System games, such as difficulty and price.")?;
+        for switch in 0..8 {
+            let mask = 1 << switch;
+            let mut enabled = self.config.dip_switches & mask == mask;
+            if s.checkbox(format!("Switch {}", switch + 1), &mut enabled)? {
+                self.config.dip_switches ^= mask;
+                self.control_deck.set_dip_switches(self.config.dip_switches);
+            }
+            if switch % 4 != 3 {
+                s.same_line(None);
+            }
+        }
+
+        Ok(())
+    }
+
     fn render_main(&mut self, s: &mut PixState) -> PixResult<()> {
         self.render_heading(s, "Menu")?;
 
@@ -312,6 +396,7 @@ impl Nes {
                 ConfigSection::Emulation => self.render_config_emulation(s),
                 ConfigSection::Audio => self.render_config_audio(s),
                 ConfigSection::Video => self.render_config_video(s),
+                ConfigSection::VsSystem => self.render_config_vs_system(s),
             },
         )? {
             self.mode = Mode::InMenu(Menu::Config(section));
@@ -445,7 +530,7 @@ impl Nes {
                     .filter_map(Result::ok)
                     .map(|f| f.path())
                     .filter(|p| {
-                        (p.is_dir() || matches!(p.extension().and_then(OsStr::to_str), Some("nes")))
+                        (p.is_dir() || is_nes_rom(p))
                             && (self.config.show_hidden_files || !hidden_file(p))
                     })
                     .for_each(|p| self.paths.push(p));
@@ -6,13 +6,20 @@ pub(crate) enum ConfigSection {
     Emulation,
     Audio,
     Video,
+    VsSystem,
 }
 
 impl ConfigSection {
     #[inline]
     #[must_use]
     pub(crate) const fn as_slice() -> &'static [Self] {
-        &[Self::General, Self::Emulation, Self::Audio, Self::Video]
+        &[
+            Self::General,
+            Self::Emulation,
+            Self::Audio,
+            Self::Video,
+            Self::VsSystem,
+        ]
     }
 }
 
@@ -23,6 +30,7 @@ impl AsRef<str> for ConfigSection {
             Self::Emulation => "Emulation",
             Self::Audio => "Audio",
             Self::Video => "Video",
+            Self::VsSystem => "VS System",
         }
     }
 }
@@ -1,6 +1,5 @@
 use crate::{
     common::config_dir,
-    cpu::Cpu,
     nes::{
         event::ActionEvent,
         filesystem::{decode_data, encode_data, load_data, save_data},
@@ -27,7 +26,7 @@ pub(crate) enum ReplayMode {
 #[must_use]
 pub(crate) struct Replay {
     pub(crate) mode: ReplayMode,
-    pub(crate) start: Option<Cpu>,
+    pub(crate) start: Option<Vec<u8>>,
     pub(crate) buffer: Vec<ActionEvent>,
 }
 
@@ -111,9 +110,9 @@ impl Nes {
             return;
         }
         match self.save_path(slot).and_then(|save_path| {
-            bincode::serialize(self.control_deck.cpu())
-                .context("failed to serialize save state")
-                .map(|data| save_data(save_path, &data))
+            self.control_deck
+                .save_state()
+                .and_then(|data| save_data(save_path, &data))
         }) {
             Ok(_) => self.add_message(format!("Saved slot {slot}")),
             Err(err) => {
@@ -128,11 +127,7 @@ impl Nes {
         match self.save_path(slot) {
             Ok(path) => {
                 if path.exists() {
-                    match load_data(path).and_then(|data| {
-                        bincode::deserialize(&data)
-                            .context("failed to deserialize load state")
-                            .map(|cpu| self.control_deck.load_cpu(cpu))
-                    }) {
+                    match load_data(path).and_then(|data| self.control_deck.load_state(&data)) {
                         Ok(_) => self.add_message(format!("Loaded slot {slot}")),
                         Err(err) => {
                             log::error!("{:?}", err);
@@ -170,7 +165,9 @@ impl Nes {
         self.rewind_frame = self.rewind_frame.wrapping_add(1);
         if self.rewind_frame >= self.config.rewind_frames {
             self.rewind_frame = 0;
-            if let Err(err) = bincode::serialize(self.control_deck.cpu())
+            if let Err(err) = self
+                .control_deck
+                .save_state()
                 .context("failed to serialize rewind state")
                 .and_then(|data| encode_data(&data))
                 .map(|data| self.rewind_buffer.push_front(data))
@@ -193,9 +190,9 @@ impl Nes {
     pub(crate) fn rewind(&mut self) {
         if let Some(data) = self.rewind_buffer.pop_front() {
             if let Err(err) = decode_data(&data).and_then(|data| {
-                bincode::deserialize(&data)
+                self.control_deck
+                    .load_state(&data)
                     .context("failed to deserialize rewind state")
-                    .map(|cpu| self.control_deck.load_cpu(cpu))
             }) {
                 log::error!("{err:?}");
                 self.config.rewind = false;
@@ -216,9 +213,9 @@ impl Nes {
             if let Some(data) = self.rewind_buffer.pop_front() {
                 self.add_message("Rewind");
                 if let Err(err) = decode_data(&data).and_then(|data| {
-                    bincode::deserialize(&data)
+                    self.control_deck
+                        .load_state(&data)
                         .context("failed to deserialize rewind state")
-                        .map(|cpu| self.control_deck.load_cpu(cpu))
                 }) {
                     log::error!("{err:?}");
                     self.config.rewind = false;
@@ -234,7 +231,7 @@ impl Nes {
     pub(crate) fn save_sram(&self) -> NesResult<()> {
         if self.control_deck.cart_battery_backed() {
             let sram_path = self.sram_path()?;
-            save_data(sram_path, self.control_deck.sram())?;
+            save_data(sram_path, &self.control_deck.sram())?;
         }
         Ok(())
     }
@@ -249,9 +246,17 @@ impl Nes {
     }
 
     pub(crate) fn start_replay(&mut self) {
-        self.replay.start = Some(self.control_deck.cpu().clone());
-        self.replay.mode = ReplayMode::Recording;
-        self.add_message("Replay Recording Started");
+        match self.control_deck.save_state() {
+            Ok(state) => {
+                self.replay.start = Some(state);
+                self.replay.mode = ReplayMode::Recording;
+                self.add_message("Replay Recording Started");
+            }
+            Err(err) => {
+                log::error!("{err:?}");
+                self.add_message("Failed to start replay recording");
+            }
+        }
     }
 
     pub(crate) fn stop_replay(&mut self) {
@@ -292,11 +297,15 @@ impl Nes {
             match load_data(replay_path).and_then(|data| {
                 bincode::deserialize::<Replay>(&data)
                     .context("failed to deserialize replay recording")
-                    .map(|mut replay| {
-                        self.control_deck
-                            .load_cpu(replay.start.take().expect("valid replay start"));
+                    .and_then(|mut replay| {
+                        let start = replay
+                            .start
+                            .take()
+                            .ok_or_else(|| anyhow!("replay recording has no start state"))?;
+                        self.control_deck.load_state(&start)?;
                         self.replay = replay;
                         self.replay.mode = ReplayMode::Playback;
+                        Ok(())
                     })
             }) {
                 Ok(_) => self.add_message("Loaded replay recording"),
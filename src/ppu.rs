@@ -6,6 +6,7 @@ use crate::{
 };
 use ctrl::PpuCtrl;
 use mask::PpuMask;
+use model::PpuModel;
 use scroll::PpuScroll;
 use serde::{Deserialize, Serialize};
 use sprite::Sprite;
@@ -16,6 +17,7 @@ pub mod bus;
 pub mod ctrl;
 pub mod frame;
 pub mod mask;
+pub mod model;
 pub mod scroll;
 pub mod sprite;
 pub mod status;
@@ -54,6 +56,7 @@ pub trait PpuRegisters {
 #[must_use]
 pub struct Ppu {
     region: NesRegion,
+    model: PpuModel,
     cycle_count: usize,
     // Internal signal that clears status registers and prevents writes and cleared at the end of VBlank
     // https://www.nesdev.org/wiki/PPU_power_up_state
@@ -174,9 +177,24 @@ impl Ppu {
         (0xA0, 0xD6, 0xE4), (0xA0, 0xA2, 0xA0), (0x00, 0x00, 0x00), (0x00, 0x00, 0x00), // $3C-$3F
     ];
 
+    // RGB master palette of the 2C03, 2C04 and 2C05, as 3-bit RGB levels
+    // https://www.nesdev.org/wiki/PPU_palettes#2C04
+    #[rustfmt::skip]
+    const RGB_PALETTE: [u16; 64] = [
+        0o333, 0o014, 0o006, 0o326, 0o403, 0o503, 0o510, 0o420, // $00-$07
+        0o320, 0o120, 0o031, 0o040, 0o022, 0o111, 0o003, 0o020, // $08-$0F
+        0o555, 0o036, 0o027, 0o407, 0o507, 0o704, 0o700, 0o630, // $10-$17
+        0o430, 0o140, 0o040, 0o053, 0o044, 0o222, 0o200, 0o310, // $18-$1F
+        0o777, 0o357, 0o447, 0o637, 0o707, 0o737, 0o740, 0o750, // $20-$27
+        0o660, 0o360, 0o070, 0o276, 0o077, 0o444, 0o000, 0o000, // $28-$2F
+        0o777, 0o567, 0o657, 0o757, 0o747, 0o755, 0o764, 0o772, // $30-$37
+        0o773, 0o572, 0o473, 0o276, 0o467, 0o666, 0o653, 0o760, // $38-$3F
+    ];
+
     pub fn new() -> Self {
         let mut ppu = Self {
             region: NesRegion::default(),
+            model: PpuModel::default(),
             cycle_count: 0,
             reset_signal: false,
             bus: PpuBus::new(),
@@ -236,6 +254,36 @@ impl Ppu {
         Self::SYSTEM_PALETTE[(pixel as usize) & (Self::SYSTEM_PALETTE.len() - 1)]
     }
 
+    /// Returns the color for a pixel from a RGB PPU. Emphasis bits drive their color channel at
+    /// full intensity instead of darkening the others.
+    #[inline]
+    #[must_use]
+    pub const fn rgb_palette(pixel: u16) -> (u8, u8, u8) {
+        const fn level(rgb: u16, shift: u16, emphasis: bool) -> u8 {
+            if emphasis {
+                0xFF
+            } else {
+                (((rgb >> shift) & 0x07) * 0xFF / 0x07) as u8
+            }
+        }
+        let rgb = Self::RGB_PALETTE[(pixel as usize) & (Self::RGB_PALETTE.len() - 1)];
+        (
+            level(rgb, 6, pixel & 0x40 == 0x40),
+            level(rgb, 3, pixel & 0x80 == 0x80),
+            level(rgb, 0, pixel & 0x100 == 0x100),
+        )
+    }
+
+    #[inline]
+    pub const fn model(&self) -> PpuModel {
+        self.model
+    }
+
+    #[inline]
+    pub const fn set_model(&mut self, model: PpuModel) {
+        self.model = model;
+    }
+
     #[inline]
     #[must_use]
     pub const fn cycle(&self) -> u32 {
@@ -285,6 +333,11 @@ impl Ppu {
         self.bus.load_chr_rom(chr_rom);
     }
 
+    #[inline]
+    pub fn take_chr_rom(&mut self) -> Vec<u8> {
+        self.bus.take_chr_rom()
+    }
+
     #[inline]
     pub fn load_chr_ram(&mut self, chr_ram: Vec<u8>) {
         self.bus.load_chr_ram(chr_ram);
@@ -686,9 +739,9 @@ impl Ppu {
         };
         let mut color = self
             .bus
-            .read(Self::PALETTE_START + palette_addr, Access::Read)
-            .into();
+            .read(Self::PALETTE_START + palette_addr, Access::Read);
         color &= if self.mask.grayscale() { 0x30 } else { 0x3F };
+        let mut color = u16::from(self.model.palette_index(color));
         color |= u16::from(self.mask.emphasis(self.region)) << 1;
         self.frame.set_pixel(x, y, color);
     }
@@ -906,8 +959,10 @@ impl PpuRegisters for Ppu {
     // Non-mutating version of `read_status`.
     #[inline]
     fn peek_status(&self) -> u8 {
-        // Only upper 3 bits are connected for this register
-        (self.status.read() & 0xE0) | (self.open_bus & 0x1F)
+        // Only upper 3 bits are connected for this register, except for 2C05 PPUs which return
+        // an identifier in the lower bits
+        let lower = self.model.status_id().unwrap_or(self.open_bus & 0x1F);
+        (self.status.read() & 0xE0) | lower
     }
 
     // $2003 | W   | OAMADDR
@@ -1191,6 +1246,7 @@ impl std::fmt::Debug for Ppu {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Ppu")
             .field("region", &self.region)
+            .field("model", &self.model)
             .field("cycle_count", &self.cycle_count)
             .field("bus", &self.bus)
             .field("ctrl", &self.ctrl)
@@ -1403,6 +1459,25 @@ mod tests {
         assert_eq!(ppu.status.read() >> 7, 0);
     }
 
+    #[test]
+    fn read_status_2c05_id() {
+        let mut ppu = Ppu::default();
+        ppu.set_open_bus(0x1F);
+        assert_eq!(ppu.read_status() & 0x1F, 0x1F);
+
+        ppu.set_model(PpuModel::Rc2c05_03);
+        ppu.set_open_bus(0x00);
+        assert_eq!(ppu.read_status() & 0x1F, 0x1C);
+    }
+
+    #[test]
+    fn rgb_palette() {
+        assert_eq!(Ppu::rgb_palette(0x20), (0xFF, 0xFF, 0xFF));
+        assert_eq!(Ppu::rgb_palette(0x0A), (0x00, 0x6D, 0x24));
+        // Emphasis drives a channel at full intensity
+        assert_eq!(Ppu::rgb_palette(0x0A | 0x40), (0xFF, 0x6D, 0x24));
+    }
+
     #[test]
     fn oam_read_write() {
         let mut ppu = Ppu::default();
@@ -27,10 +27,12 @@ impl PpuAddr for u16 {
 #[derive(Clone, Serialize, Deserialize)]
 #[must_use]
 pub struct PpuBus {
+    #[serde(skip)] // Serialized as a separate save state section
     mapper: Mapper,
     mirror_shift: usize,
     ciram: Vec<u8>, // $2007 PPUDATA
     palette: [u8; Self::PALETTE_SIZE],
+    #[serde(skip)]
     chr_rom: Vec<u8>,
     chr_ram: Vec<u8>,
     exram: Vec<u8>,
@@ -75,6 +77,11 @@ impl PpuBus {
         self.chr_rom = chr_rom;
     }
 
+    #[inline]
+    pub fn take_chr_rom(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.chr_rom)
+    }
+
     #[inline]
     pub fn load_chr_ram(&mut self, chr_ram: Vec<u8>) {
         self.chr_ram = chr_ram;
@@ -132,26 +139,32 @@ impl PpuBus {
             addr
         }
     }
+
+    #[inline]
+    fn read_chr(&self, addr: usize) -> u8 {
+        if self.chr_rom.is_empty() {
+            self.chr_ram[addr]
+        } else {
+            self.chr_rom[addr]
+        }
+    }
 }
 
 impl Mem for PpuBus {
     fn read(&mut self, addr: u16, _access: Access) -> u8 {
         let val = match addr {
-            0x0000..=0x1FFF => {
-                let addr = if let MappedRead::Chr(addr) = self.mapper.map_read(addr) {
-                    addr
-                } else {
-                    addr.into()
-                };
-                if self.chr_rom.is_empty() {
-                    self.chr_ram[addr]
-                } else {
-                    self.chr_rom[addr]
-                }
-            }
+            0x0000..=0x1FFF => match self.mapper.map_read(addr) {
+                MappedRead::Chr(addr) => self.read_chr(addr),
+                MappedRead::ChrRam(addr) => self.chr_ram[addr],
+                MappedRead::CIRam(addr) => self.ciram[addr & 0x07FF],
+                MappedRead::Data(data) => data,
+                _ => self.read_chr(addr.into()),
+            },
             0x2000..=0x3EFF => match self.mapper.map_read(addr) {
+                MappedRead::Chr(addr) => self.read_chr(addr),
+                MappedRead::ChrRam(addr) => self.chr_ram[addr],
                 MappedRead::CIRam(addr) => self.ciram[addr & 0x07FF],
-                MappedRead::ExRam(addr) => self.exram[addr & 0x03FF],
+                MappedRead::ExRam(addr) => self.exram[addr],
                 MappedRead::Data(data) => data,
                 _ => {
                     if self.mirroring() == Mirroring::FourScreen {
@@ -174,8 +187,10 @@ impl Mem for PpuBus {
     fn peek(&self, addr: u16, _access: Access) -> u8 {
         match addr {
             0x2000..=0x3EFF => match self.mapper.map_peek(addr) {
+                MappedRead::Chr(addr) => self.read_chr(addr),
+                MappedRead::ChrRam(addr) => self.chr_ram[addr],
                 MappedRead::CIRam(addr) => self.ciram[addr & 0x07FF],
-                MappedRead::ExRam(addr) => self.exram[addr & 0x03FF],
+                MappedRead::ExRam(addr) => self.exram[addr],
                 MappedRead::Data(data) => data,
                 _ => {
                     if self.mirroring() == Mirroring::FourScreen {
@@ -185,18 +200,13 @@ impl Mem for PpuBus {
                     }
                 }
             },
-            0x0000..=0x1FFF => {
-                let addr = if let MappedRead::Chr(addr) = self.mapper.map_peek(addr) {
-                    addr
-                } else {
-                    addr.into()
-                };
-                if !self.chr_ram.is_empty() {
-                    self.chr_ram[addr]
-                } else {
-                    self.chr_rom[addr]
-                }
-            }
+            0x0000..=0x1FFF => match self.mapper.map_peek(addr) {
+                MappedRead::Chr(addr) => self.read_chr(addr),
+                MappedRead::ChrRam(addr) => self.chr_ram[addr],
+                MappedRead::CIRam(addr) => self.ciram[addr & 0x07FF],
+                MappedRead::Data(data) => data,
+                _ => self.read_chr(addr.into()),
+            },
             0x3F00..=0x3FFF => self.palette[self.palette_mirror(addr as usize)],
             _ => {
                 log::error!("unexpected PPU memory access at ${:04X}", addr);
@@ -208,6 +218,7 @@ impl Mem for PpuBus {
     fn write(&mut self, addr: u16, val: u8, _access: Access) {
         match addr {
             0x2000..=0x3EFF => match self.mapper.map_write(addr, val) {
+                MappedWrite::ChrRam(addr, val) => self.chr_ram[addr] = val,
                 MappedWrite::CIRam(addr, val) => self.ciram[addr] = val,
                 MappedWrite::ExRam(addr, val) => self.exram[addr] = val,
                 _ => {
@@ -217,13 +228,12 @@ impl Mem for PpuBus {
                     }
                 }
             },
-            0x0000..=0x1FFF => {
-                if !self.chr_ram.is_empty() {
-                    if let MappedWrite::Chr(addr, val) = self.mapper.map_write(addr, val) {
-                        self.chr_ram[addr] = val;
-                    }
-                }
-            }
+            0x0000..=0x1FFF => match self.mapper.map_write(addr, val) {
+                MappedWrite::Chr(addr, val) if !self.chr_ram.is_empty() => self.chr_ram[addr] = val,
+                MappedWrite::ChrRam(addr, val) => self.chr_ram[addr] = val,
+                MappedWrite::CIRam(addr, val) => self.ciram[addr & 0x07FF] = val,
+                _ => (),
+            },
             0x3F00..=0x3FFF => {
                 self.palette[self.palette_mirror(addr as usize)] = val;
             }
@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+
+/// PPU chip revision, which selects how palette colors are generated.
+///
+/// `VS System` and `PlayChoice-10` arcade boards use RGB PPUs with their own master palette. The
+/// `2C04` variants scramble the palette order and the `2C05` variants swap `PPUCTRL` and
+/// `PPUMASK` as copy protection.
+///
+/// <https://www.nesdev.org/wiki/PPU_palettes#2C03_and_2C05>
+/// <https://www.nesdev.org/wiki/PPU_palettes#2C04>
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[must_use]
+pub enum PpuModel {
+    /// Composite video NES/Famicom PPU.
+    #[default]
+    Rp2c02,
+    /// RGB PPU with the standard palette order.
+    Rp2c03,
+    Rp2c04_0001,
+    Rp2c04_0002,
+    Rp2c04_0003,
+    Rp2c04_0004,
+    Rc2c05_01,
+    Rc2c05_02,
+    Rc2c05_03,
+    Rc2c05_04,
+    Rc2c05_05,
+}
+
+impl PpuModel {
+    /// Returns the PPU model from the NES 2.0 `VS System` PPU type in the lower nibble of header
+    /// byte 13.
+    pub const fn from_vs_data(vs_data: u8) -> Self {
+        match vs_data & 0x0F {
+            0x02 => Self::Rp2c04_0001,
+            0x03 => Self::Rp2c04_0002,
+            0x04 => Self::Rp2c04_0003,
+            0x05 => Self::Rp2c04_0004,
+            0x08 => Self::Rc2c05_01,
+            0x09 => Self::Rc2c05_02,
+            0x0A => Self::Rc2c05_03,
+            0x0B => Self::Rc2c05_04,
+            0x0C => Self::Rc2c05_05,
+            // RP2C03B, RP2C03G, RC2C03B, RC2C03C and reserved values
+            _ => Self::Rp2c03,
+        }
+    }
+
+    /// Whether this PPU outputs colors from the RGB master palette.
+    #[inline]
+    #[must_use]
+    pub const fn is_rgb(self) -> bool {
+        !matches!(self, Self::Rp2c02)
+    }
+
+    /// Whether this PPU has `PPUCTRL` at $2001 and `PPUMASK` at $2000.
+    #[inline]
+    #[must_use]
+    pub const fn swaps_ctrl_mask(self) -> bool {
+        matches!(
+            self,
+            Self::Rc2c05_01 | Self::Rc2c05_02 | Self::Rc2c05_03 | Self::Rc2c05_04 | Self::Rc2c05_05
+        )
+    }
+
+    /// Identifier returned in the lower bits of `PPUSTATUS` instead of open bus.
+    #[inline]
+    #[must_use]
+    pub const fn status_id(self) -> Option<u8> {
+        match self {
+            Self::Rc2c05_01 | Self::Rc2c05_04 => Some(0x1B),
+            Self::Rc2c05_02 => Some(0x3D),
+            Self::Rc2c05_03 => Some(0x1C),
+            _ => None,
+        }
+    }
+
+    /// Translates a palette RAM color into an index into the palette output by this PPU. RGB
+    /// PPUs index the RGB master palette, which has no duplicate blacks.
+    #[must_use]
+    pub const fn palette_index(self, color: u8) -> u8 {
+        let color = color & 0x3F;
+        match self {
+            Self::Rp2c02 => color,
+            Self::Rp2c04_0001 => RP2C04_0001[color as usize],
+            Self::Rp2c04_0002 => RP2C04_0002[color as usize],
+            Self::Rp2c04_0003 => RP2C04_0003[color as usize],
+            Self::Rp2c04_0004 => RP2C04_0004[color as usize],
+            _ => match color {
+                0x0D | 0x0E | 0x0F | 0x1E | 0x1F | 0x2E | 0x2F | 0x3E | 0x3F => 0x2E,
+                0x1D => 0x0D,
+                0x2D => 0x1D,
+                0x3D => 0x2D,
+                _ => color,
+            },
+        }
+    }
+}
+
+#[rustfmt::skip]
+const RP2C04_0001: [u8; 64] = [
+    0x35, 0x23, 0x16, 0x22, 0x1C, 0x09, 0x1D, 0x15, 0x20, 0x00, 0x27, 0x05, 0x04, 0x28, 0x08, 0x20,
+    0x21, 0x3E, 0x1F, 0x29, 0x3C, 0x32, 0x36, 0x12, 0x3F, 0x2B, 0x2E, 0x1E, 0x3D, 0x2D, 0x24, 0x01,
+    0x0E, 0x31, 0x33, 0x2A, 0x2C, 0x0C, 0x1B, 0x14, 0x2E, 0x07, 0x34, 0x06, 0x13, 0x02, 0x26, 0x2E,
+    0x2E, 0x19, 0x10, 0x0A, 0x39, 0x03, 0x37, 0x17, 0x0F, 0x11, 0x0B, 0x0D, 0x38, 0x25, 0x18, 0x3A,
+];
+
+#[rustfmt::skip]
+const RP2C04_0002: [u8; 64] = [
+    0x2E, 0x27, 0x18, 0x39, 0x3A, 0x25, 0x1C, 0x31, 0x16, 0x13, 0x38, 0x34, 0x20, 0x23, 0x3C, 0x0B,
+    0x0F, 0x21, 0x06, 0x3D, 0x1B, 0x29, 0x1E, 0x22, 0x1D, 0x24, 0x0E, 0x2B, 0x32, 0x08, 0x2E, 0x03,
+    0x04, 0x36, 0x26, 0x33, 0x11, 0x1F, 0x10, 0x02, 0x14, 0x3F, 0x00, 0x09, 0x12, 0x2E, 0x28, 0x20,
+    0x3E, 0x0D, 0x2A, 0x17, 0x0C, 0x01, 0x15, 0x19, 0x2E, 0x2C, 0x07, 0x37, 0x35, 0x05, 0x0A, 0x2D,
+];
+
+#[rustfmt::skip]
+const RP2C04_0003: [u8; 64] = [
+    0x14, 0x25, 0x3A, 0x10, 0x0B, 0x20, 0x31, 0x09, 0x01, 0x2E, 0x36, 0x08, 0x15, 0x3D, 0x3E, 0x3C,
+    0x22, 0x1C, 0x05, 0x12, 0x19, 0x18, 0x17, 0x1B, 0x00, 0x03, 0x2E, 0x02, 0x16, 0x06, 0x34, 0x35,
+    0x23, 0x0F, 0x0E, 0x37, 0x0D, 0x27, 0x26, 0x20, 0x29, 0x04, 0x21, 0x24, 0x11, 0x2D, 0x2E, 0x1F,
+    0x2C, 0x1E, 0x39, 0x33, 0x07, 0x2A, 0x28, 0x1D, 0x0A, 0x2E, 0x32, 0x38, 0x13, 0x2B, 0x3F, 0x0C,
+];
+
+#[rustfmt::skip]
+const RP2C04_0004: [u8; 64] = [
+    0x18, 0x03, 0x1C, 0x28, 0x2E, 0x35, 0x01, 0x17, 0x10, 0x1F, 0x2A, 0x0E, 0x36, 0x37, 0x0B, 0x39,
+    0x25, 0x1E, 0x12, 0x34, 0x2E, 0x1D, 0x06, 0x26, 0x3E, 0x1B, 0x22, 0x19, 0x04, 0x2E, 0x3A, 0x21,
+    0x05, 0x0A, 0x07, 0x02, 0x13, 0x14, 0x00, 0x15, 0x0C, 0x3D, 0x11, 0x0F, 0x0D, 0x38, 0x2D, 0x24,
+    0x33, 0x20, 0x08, 0x16, 0x3F, 0x2B, 0x20, 0x3C, 0x2E, 0x27, 0x23, 0x31, 0x29, 0x32, 0x2C, 0x09,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_vs_data() {
+        assert_eq!(PpuModel::from_vs_data(0x00), PpuModel::Rp2c03);
+        assert_eq!(PpuModel::from_vs_data(0x13), PpuModel::Rp2c04_0002);
+        assert_eq!(PpuModel::from_vs_data(0x0C), PpuModel::Rc2c05_05);
+        assert_eq!(PpuModel::from_vs_data(0x0F), PpuModel::Rp2c03);
+    }
+
+    #[test]
+    fn palette_index() {
+        assert_eq!(PpuModel::Rp2c02.palette_index(0x0D), 0x0D);
+        assert_eq!(PpuModel::Rp2c03.palette_index(0x0F), 0x2E);
+        assert_eq!(PpuModel::Rp2c03.palette_index(0x3D), 0x2D);
+        assert_eq!(PpuModel::Rp2c04_0001.palette_index(0x00), 0x35);
+        assert_eq!(PpuModel::Rp2c04_0004.palette_index(0x7F), 0x09);
+    }
+}
@@ -0,0 +1,290 @@
+//! Versioned save state container.
+//!
+//! A save state is split into sections for each major component of the console so they can be
+//! serialized independently. Older save states are upgraded through a chain of migrations, one
+//! per schema version, before being loaded.
+
+use crate::NesResult;
+use anyhow::{anyhow, bail, Context};
+use bincode::Options;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Current save state schema version. Bumping this requires adding a [`Migration`] that upgrades
+/// the previous version.
+///
+/// Sections are serialized with `bincode`, which has no field names or variant names, so adding,
+/// removing or reordering a field of any serialized struct changes the schema.
+///
+/// - 1: Initial sectioned save state. No longer supported.
+/// - 2: Adds the PPU model, `VS System` input, `TxROM` board variants and the `MMC2` latch
+///   revision.
+pub const SAVE_STATE_VERSION: u32 = 2;
+
+/// Oldest save state schema version that can be loaded. Older versions predate schema changes
+/// that can't be upgraded without the original types, so they're rejected.
+pub const MIN_SAVE_STATE_VERSION: u32 = 2;
+
+/// Upgrades a save state from one schema version to the next.
+pub type Migration = fn(&mut SaveState) -> NesResult<()>;
+
+/// Save state migrations, where `MIGRATIONS[n]` upgrades version `MIN_SAVE_STATE_VERSION + n` to
+/// the next version.
+const MIGRATIONS: [Migration; (SAVE_STATE_VERSION - MIN_SAVE_STATE_VERSION) as usize] = [];
+
+/// A component of the console stored in a save state.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[must_use]
+pub enum Section {
+    Cpu,
+    Ppu,
+    Apu,
+    Mapper,
+    Input,
+}
+
+/// A versioned save state. Cartridge ROM is not included, only a hash of it, so a save state
+/// can only be loaded with the same ROM it was created with.
+///
+/// The layout of this struct must not change, as it is used to determine the schema version of
+/// the sections it contains.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[must_use]
+pub struct SaveState {
+    version: u32,
+    rom_hash: u32,
+    sections: Vec<(Section, Vec<u8>)>,
+}
+
+impl SaveState {
+    /// Create an empty `SaveState` for the current schema version.
+    pub const fn new(rom_hash: u32) -> Self {
+        Self {
+            version: SAVE_STATE_VERSION,
+            rom_hash,
+            sections: vec![],
+        }
+    }
+
+    /// Deserialize a `SaveState`, migrating it to the current schema version.
+    ///
+    /// # Errors
+    ///
+    /// If the data is not a valid save state, e.g. one saved before save states were versioned,
+    /// is from an unsupported version, or fails to migrate, then an error is returned.
+    pub fn from_bytes(data: &[u8]) -> NesResult<Self> {
+        // Matches `bincode::serialize`, but requires all data to be consumed so unversioned save
+        // states aren't mistaken for a valid container
+        let mut state: Self = bincode::options()
+            .with_fixint_encoding()
+            .reject_trailing_bytes()
+            .deserialize(data)
+            .context("failed to deserialize save state")?;
+        state.migrate(MIN_SAVE_STATE_VERSION, &MIGRATIONS)?;
+        Ok(state)
+    }
+
+    /// Serialize a `SaveState`.
+    ///
+    /// # Errors
+    ///
+    /// If the save state fails to serialize, then an error is returned.
+    pub fn to_bytes(&self) -> NesResult<Vec<u8>> {
+        bincode::serialize(self).context("failed to serialize save state")
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn version(&self) -> u32 {
+        self.version
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn rom_hash(&self) -> u32 {
+        self.rom_hash
+    }
+
+    /// Get the raw data of a section.
+    #[must_use]
+    pub fn section_data(&self, section: Section) -> Option<&[u8]> {
+        self.sections
+            .iter()
+            .find(|(s, _)| *s == section)
+            .map(|(_, data)| data.as_slice())
+    }
+
+    /// Replace the raw data of a section, adding it if it doesn't exist.
+    pub fn set_section_data(&mut self, section: Section, data: Vec<u8>) {
+        match self.sections.iter_mut().find(|(s, _)| *s == section) {
+            Some((_, existing)) => *existing = data,
+            None => self.sections.push((section, data)),
+        }
+    }
+
+    /// Deserialize a section.
+    ///
+    /// # Errors
+    ///
+    /// If the section is missing or fails to deserialize, then an error is returned.
+    pub fn section<T: DeserializeOwned>(&self, section: Section) -> NesResult<T> {
+        let data = self
+            .section_data(section)
+            .ok_or_else(|| anyhow!("save state is missing section {section:?}"))?;
+        bincode::deserialize(data)
+            .with_context(|| format!("failed to deserialize save state section {section:?}"))
+    }
+
+    /// Serialize a section.
+    ///
+    /// # Errors
+    ///
+    /// If the section fails to serialize, then an error is returned.
+    pub fn set_section<T: Serialize>(&mut self, section: Section, value: &T) -> NesResult<()> {
+        let data = bincode::serialize(value)
+            .with_context(|| format!("failed to serialize save state section {section:?}"))?;
+        self.set_section_data(section, data);
+        Ok(())
+    }
+
+    fn migrate(&mut self, min_version: u32, migrations: &[Migration]) -> NesResult<()> {
+        let current = min_version + migrations.len() as u32;
+        if self.version < min_version {
+            bail!(
+                "save state version {} is no longer supported. oldest supported: {min_version}",
+                self.version
+            );
+        }
+        if self.version > current {
+            bail!(
+                "unsupported save state version. current: {current}, save state: {}",
+                self.version
+            );
+        }
+        for migration in &migrations[(self.version - min_version) as usize..] {
+            let version = self.version;
+            migration(self)
+                .with_context(|| format!("failed to migrate save state version {version}"))?;
+            self.version = version + 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{control_deck::ControlDeck, mem::RamState};
+    use flate2::read::GzDecoder;
+    use std::{
+        fs::File,
+        io::{BufReader, Read},
+    };
+
+    fn load_control_deck(path: &str) -> ControlDeck {
+        let mut rom = BufReader::new(File::open(path).expect("valid rom"));
+        let mut deck = ControlDeck::new(RamState::AllZeros);
+        deck.load_rom(path, &mut rom).expect("valid load");
+        deck
+    }
+
+    fn clock_frames(deck: &mut ControlDeck, frames: u32) -> Vec<u8> {
+        for _ in 0..frames {
+            let _ = deck.clock_frame().expect("valid frame clock");
+        }
+        deck.frame_buffer().to_vec()
+    }
+
+    #[test]
+    fn sections() {
+        let mut state = SaveState::new(0x1234_5678);
+        state.set_section(Section::Cpu, &0xAAu8).expect("valid cpu");
+        state
+            .set_section(Section::Ppu, &[1u16, 2, 3])
+            .expect("valid ppu");
+        state.set_section(Section::Cpu, &0xBBu8).expect("valid cpu");
+
+        let state = SaveState::from_bytes(&state.to_bytes().expect("valid serialize"))
+            .expect("valid deserialize");
+        assert_eq!(state.version(), SAVE_STATE_VERSION);
+        assert_eq!(state.rom_hash(), 0x1234_5678);
+        assert_eq!(state.section::<u8>(Section::Cpu).expect("cpu"), 0xBB);
+        assert_eq!(
+            state.section::<[u16; 3]>(Section::Ppu).expect("ppu"),
+            [1, 2, 3]
+        );
+        assert!(state.section::<u8>(Section::Apu).is_err(), "missing apu");
+    }
+
+    #[test]
+    fn migrate() {
+        // Version 2 copied the Ppu section to Apu, version 3 added an Input section
+        let migrations: [Migration; 2] = [
+            |state| {
+                let data = state
+                    .section_data(Section::Ppu)
+                    .ok_or_else(|| anyhow!("missing ppu"))?
+                    .to_vec();
+                state.set_section_data(Section::Apu, data);
+                Ok(())
+            },
+            |state| state.set_section(Section::Input, &0x02u8),
+        ];
+
+        let mut state = SaveState::new(0);
+        state.version = 1;
+        state.set_section(Section::Ppu, &0x01u8).expect("valid ppu");
+        state.migrate(1, &migrations).expect("valid migration");
+        assert_eq!(state.version(), 3);
+        assert_eq!(state.section::<u8>(Section::Apu).expect("apu"), 0x01);
+        assert_eq!(state.section::<u8>(Section::Input).expect("input"), 0x02);
+
+        state.version = 4;
+        assert!(state.migrate(1, &migrations).is_err(), "newer version");
+        state.version = 0;
+        assert!(state.migrate(1, &migrations).is_err(), "older version");
+    }
+
+    #[test]
+    fn control_deck_round_trip() {
+        let mut deck = load_control_deck("test_roms/cpu/branch_basics.nes");
+        clock_frames(&mut deck, 30);
+        let state = deck.save_state().expect("valid save state");
+        let expected = clock_frames(&mut deck, 10);
+
+        deck.load_state(&state).expect("valid load state");
+        assert_eq!(deck.frame_number(), 30, "frame number");
+        assert_eq!(clock_frames(&mut deck, 10), expected, "frame buffer");
+
+        deck.set_dip_switches(0x81);
+        deck.load_state(&state).expect("valid load state");
+        assert_eq!(deck.dip_switches(), 0x81, "dip switches kept");
+
+        let mut other = load_control_deck("test_roms/cpu/branch_forward.nes");
+        assert!(other.load_state(&state).is_err(), "different rom");
+    }
+
+    #[test]
+    fn reject_unsupported() {
+        // A version 1 state saved after 30 frames
+        let mut data = vec![];
+        GzDecoder::new(
+            File::open("test_roms/save_state/branch_basics_v1.save.gz").expect("valid state file"),
+        )
+        .read_to_end(&mut data)
+        .expect("valid gzip");
+        let err = SaveState::from_bytes(&data).expect_err("unsupported v1");
+        assert!(
+            err.to_string().contains("no longer supported"),
+            "unexpected error: {err:?}"
+        );
+
+        // Unversioned save states serialized the `Cpu` directly
+        let deck = load_control_deck("test_roms/cpu/branch_basics.nes");
+        let cpu = bincode::serialize(deck.cpu()).expect("valid cpu");
+        assert!(SaveState::from_bytes(&cpu).is_err(), "unversioned");
+
+        let mut deck = load_control_deck("test_roms/cpu/branch_basics.nes");
+        assert!(deck.load_state(&data).is_err(), "load unsupported");
+        assert!(deck.load_state(&cpu).is_err(), "load unversioned");
+    }
+}
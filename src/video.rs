@@ -87,10 +87,18 @@ impl Video {
     }
 
     pub fn decode_buffer(&mut self, buffer: &[u16]) {
+        self.decode_palette(buffer, Ppu::system_palette);
+    }
+
+    pub fn decode_rgb_buffer(&mut self, buffer: &[u16]) {
+        self.decode_palette(buffer, Ppu::rgb_palette);
+    }
+
+    fn decode_palette(&mut self, buffer: &[u16], palette: fn(u16) -> (u8, u8, u8)) {
         assert!(buffer.len() * 4 == self.output.len());
         for (pixel, colors) in buffer.iter().zip(self.output.chunks_exact_mut(4)) {
             assert!(colors.len() > 2);
-            let (red, green, blue) = Ppu::system_palette(*pixel);
+            let (red, green, blue) = palette(*pixel);
             colors[0] = red;
             colors[1] = green;
             colors[2] = blue;
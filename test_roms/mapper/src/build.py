@@ -0,0 +1,262 @@
+"""Builds the self-checking mapper test ROMs from their assembly source.
+
+USAGE:
+    python3 test_roms/mapper/src/build.py
+
+Each ROM is an iNES file with 8K PRG-ROM banks that start with their bank number and CHR-ROM
+where every byte holds its 1K bank number, so reading either shows which bank is mapped. The
+source is assembled at $E010 in the last 8K PRG-ROM bank and must define `reset`, `nmi` and `irq`.
+
+The assembler supports the subset of ca65 syntax used by the sources: labels, `NAME = expr`
+constants, `.include`, `.byte` and `.word`, `<` and `>` for the low and high byte of an
+expression, and all official 6502 instructions. Zero page addressing is used when the operand
+is a number or an already defined symbol below $100.
+"""
+
+import os
+import re
+import sys
+
+SRC_DIR = os.path.dirname(os.path.abspath(__file__))
+OUT_DIR = os.path.dirname(SRC_DIR)
+
+ORIGIN = 0xE010
+PRG_BANK_SIZE = 0x2000
+CHR_BANK_SIZE = 0x0400
+
+# (output, source, mapper, prg_size, chr_size, vertical mirroring)
+ROMS = [
+    ("m009_pxrom/mmc2_latch.nes", "mmc2_latch.s", 9, 0x20000, 0x20000, True),
+    ("m010_fxrom/mmc4_latch.nes", "mmc2_latch.s", 10, 0x20000, 0x20000, True),
+    ("m018_jaleco_ss88006/ss88006.nes", "ss88006.s", 18, 0x20000, 0x20000, False),
+    ("m032_irem_g101/g101.nes", "g101.s", 32, 0x20000, 0x20000, True),
+    ("m033_m048_taito_tc0190/tc0190.nes", "tc0190.s", 33, 0x20000, 0x20000, True),
+    ("m033_m048_taito_tc0190/tc0690.nes", "tc0690.s", 48, 0x20000, 0x20000, True),
+    ("m064_rambo1/rambo1.nes", "rambo1.s", 64, 0x20000, 0x20000, True),
+]
+
+ALU = ("imm", "zp", "zpx", "abs", "absx", "absy", "indx", "indy")
+SHIFT = ("acc", "zp", "zpx", "abs", "absx")
+OPCODES = {
+    "adc": dict(zip(ALU, (0x69, 0x65, 0x75, 0x6D, 0x7D, 0x79, 0x61, 0x71))),
+    "and": dict(zip(ALU, (0x29, 0x25, 0x35, 0x2D, 0x3D, 0x39, 0x21, 0x31))),
+    "cmp": dict(zip(ALU, (0xC9, 0xC5, 0xD5, 0xCD, 0xDD, 0xD9, 0xC1, 0xD1))),
+    "eor": dict(zip(ALU, (0x49, 0x45, 0x55, 0x4D, 0x5D, 0x59, 0x41, 0x51))),
+    "lda": dict(zip(ALU, (0xA9, 0xA5, 0xB5, 0xAD, 0xBD, 0xB9, 0xA1, 0xB1))),
+    "ora": dict(zip(ALU, (0x09, 0x05, 0x15, 0x0D, 0x1D, 0x19, 0x01, 0x11))),
+    "sbc": dict(zip(ALU, (0xE9, 0xE5, 0xF5, 0xED, 0xFD, 0xF9, 0xE1, 0xF1))),
+    "sta": dict(zip(ALU[1:], (0x85, 0x95, 0x8D, 0x9D, 0x99, 0x81, 0x91))),
+    "asl": dict(zip(SHIFT, (0x0A, 0x06, 0x16, 0x0E, 0x1E))),
+    "lsr": dict(zip(SHIFT, (0x4A, 0x46, 0x56, 0x4E, 0x5E))),
+    "rol": dict(zip(SHIFT, (0x2A, 0x26, 0x36, 0x2E, 0x3E))),
+    "ror": dict(zip(SHIFT, (0x6A, 0x66, 0x76, 0x6E, 0x7E))),
+    "dec": {"zp": 0xC6, "zpx": 0xD6, "abs": 0xCE, "absx": 0xDE},
+    "inc": {"zp": 0xE6, "zpx": 0xF6, "abs": 0xEE, "absx": 0xFE},
+    "bit": {"zp": 0x24, "abs": 0x2C},
+    "cpx": {"imm": 0xE0, "zp": 0xE4, "abs": 0xEC},
+    "cpy": {"imm": 0xC0, "zp": 0xC4, "abs": 0xCC},
+    "ldx": {"imm": 0xA2, "zp": 0xA6, "zpy": 0xB6, "abs": 0xAE, "absy": 0xBE},
+    "ldy": {"imm": 0xA0, "zp": 0xA4, "zpx": 0xB4, "abs": 0xAC, "absx": 0xBC},
+    "stx": {"zp": 0x86, "zpy": 0x96, "abs": 0x8E},
+    "sty": {"zp": 0x84, "zpx": 0x94, "abs": 0x8C},
+    "jmp": {"abs": 0x4C, "ind": 0x6C},
+    "jsr": {"abs": 0x20},
+    "bcc": {"rel": 0x90},
+    "bcs": {"rel": 0xB0},
+    "beq": {"rel": 0xF0},
+    "bmi": {"rel": 0x30},
+    "bne": {"rel": 0xD0},
+    "bpl": {"rel": 0x10},
+    "bvc": {"rel": 0x50},
+    "bvs": {"rel": 0x70},
+}
+IMPLIED = {
+    "brk": 0x00, "clc": 0x18, "cld": 0xD8, "cli": 0x58, "clv": 0xB8, "dex": 0xCA, "dey": 0x88,
+    "inx": 0xE8, "iny": 0xC8, "nop": 0xEA, "pha": 0x48, "php": 0x08, "pla": 0x68, "plp": 0x28,
+    "rti": 0x40, "rts": 0x60, "sec": 0x38, "sed": 0xF8, "sei": 0x78, "tax": 0xAA, "tay": 0xA8,
+    "tsx": 0xBA, "txa": 0x8A, "txs": 0x9A, "tya": 0x98,
+}
+SIZES = {"imp": 1, "acc": 1, "imm": 2, "zp": 2, "zpx": 2, "zpy": 2, "rel": 2, "indx": 2, "indy": 2}
+
+
+class AsmError(Exception):
+    pass
+
+
+def read_lines(path):
+    """Reads a source file, expanding `.include` directives relative to the source directory."""
+    lines = []
+    with open(path) as f:
+        for number, line in enumerate(f, 1):
+            line = line.split(";", 1)[0].strip()
+            include = re.fullmatch(r'\.include\s+"([^"]+)"', line)
+            if include:
+                lines += read_lines(os.path.join(SRC_DIR, include.group(1)))
+            elif line:
+                lines.append((f"{os.path.basename(path)}:{number}", line))
+    return lines
+
+
+def evaluate(expr, symbols):
+    """Evaluates an expression, returning `None` if it references an undefined symbol."""
+    expr = expr.strip()
+    if expr.startswith("<"):
+        val = evaluate(expr[1:], symbols)
+        return None if val is None else val & 0xFF
+    if expr.startswith(">"):
+        val = evaluate(expr[1:], symbols)
+        return None if val is None else (val >> 8) & 0xFF
+    expr = re.sub(r"\$([0-9A-Fa-f]+)", r"0x\1", expr)
+    expr = re.sub(r"%([01]+)", r"0b\1", expr)
+    if not re.fullmatch(r"[\w\s+\-*/()|&^]*", expr):
+        raise AsmError(f"invalid expression: {expr}")
+    try:
+        return eval(expr.replace("/", "//"), {"__builtins__": {}}, dict(symbols))
+    except NameError:
+        return None
+
+
+def parse_operand(mnemonic, operand):
+    """Returns the addressing mode candidates and the operand expression."""
+    if mnemonic in IMPLIED:
+        return ["imp"], None
+    modes = OPCODES[mnemonic]
+    if "rel" in modes:
+        return ["rel"], operand
+    if operand in ("", "a"):
+        return ["acc"], None
+    if operand.startswith("#"):
+        return ["imm"], operand[1:]
+    indirect = re.fullmatch(r"\((.+)\)\s*,\s*y", operand, re.I)
+    if indirect:
+        return ["indy"], indirect.group(1)
+    indirect = re.fullmatch(r"\((.+)\s*,\s*x\)", operand, re.I)
+    if indirect:
+        return ["indx"], indirect.group(1)
+    indirect = re.fullmatch(r"\((.+)\)", operand)
+    if indirect:
+        return ["ind"], indirect.group(1)
+    indexed = re.fullmatch(r"(.+?)\s*,\s*([xy])", operand, re.I)
+    if indexed:
+        index = indexed.group(2).lower()
+        return [f"zp{index}", f"abs{index}"], indexed.group(1)
+    return ["zp", "abs"], operand
+
+
+def assemble(path, origin):
+    lines = read_lines(path)
+    symbols = {}
+
+    # Pass 1: sizes, label addresses and addressing modes
+    program = []
+    pc = origin
+    for location, line in lines:
+        try:
+            label = re.match(r"(\w+):\s*", line)
+            if label:
+                symbols[label.group(1)] = pc
+                line = line[label.end() :]
+                if not line:
+                    continue
+            constant = re.fullmatch(r"(\w+)\s*=\s*(.+)", line)
+            if constant:
+                val = evaluate(constant.group(2), symbols)
+                if val is None:
+                    raise AsmError(f"undefined constant value: {line}")
+                symbols[constant.group(1)] = val
+                continue
+            directive = re.fullmatch(r"\.(byte|word)\s+(.+)", line)
+            if directive:
+                kind, args = directive.groups()
+                args = [arg.strip() for arg in args.split(",")]
+                program.append((location, kind, args))
+                pc += len(args) * (1 if kind == "byte" else 2)
+                continue
+            mnemonic, _, operand = line.partition(" ")
+            mnemonic = mnemonic.lower()
+            if mnemonic not in OPCODES and mnemonic not in IMPLIED:
+                raise AsmError(f"unknown instruction: {line}")
+            modes, expr = parse_operand(mnemonic, operand.strip())
+            if len(modes) > 1:
+                val = evaluate(expr, symbols)
+                zero_page = val is not None and val < 0x100 and modes[0] in OPCODES[mnemonic]
+                modes = modes[:1] if zero_page else modes[1:]
+            mode = modes[0]
+            if mode != "imp" and mode not in OPCODES[mnemonic]:
+                raise AsmError(f"invalid addressing mode {mode}: {line}")
+            program.append((location, mnemonic, (mode, expr)))
+            pc += SIZES.get(mode, 3)
+        except AsmError as err:
+            raise AsmError(f"{location}: {err}") from None
+
+    # Pass 2: encoding
+    code = bytearray()
+    for location, kind, args in program:
+        pc = origin + len(code)
+
+        def value(expr):
+            val = evaluate(expr, symbols)
+            if val is None:
+                raise AsmError(f"{location}: undefined symbol in {expr}")
+            return val
+
+        if kind == "byte":
+            code += bytes(value(arg) & 0xFF for arg in args)
+        elif kind == "word":
+            for arg in args:
+                code += (value(arg) & 0xFFFF).to_bytes(2, "little")
+        elif kind in IMPLIED:
+            code.append(IMPLIED[kind])
+        else:
+            mode, expr = args
+            code.append(OPCODES[kind][mode])
+            if mode == "rel":
+                offset = value(expr) - (pc + 2)
+                if not -128 <= offset <= 127:
+                    raise AsmError(f"{location}: branch out of range")
+                code.append(offset & 0xFF)
+            elif SIZES.get(mode, 3) == 2:
+                val = value(expr)
+                if not 0 <= val <= 0xFF:
+                    raise AsmError(f"{location}: operand out of range: {expr}")
+                code.append(val)
+            elif mode != "acc":
+                code += (value(expr) & 0xFFFF).to_bytes(2, "little")
+    return bytes(code), symbols
+
+
+def build_rom(source, mapper, prg_size, chr_size, vertical):
+    code, symbols = assemble(os.path.join(SRC_DIR, source), ORIGIN)
+    if ORIGIN + len(code) > 0xFFFA:
+        raise AsmError(f"{source}: code overlaps the vectors")
+
+    prg = bytearray()
+    for bank in range(prg_size // PRG_BANK_SIZE):
+        prg += bytes([bank]) + bytes([0xFF] * (PRG_BANK_SIZE - 1))
+    start = len(prg) - PRG_BANK_SIZE + (ORIGIN - 0xE000)
+    prg[start : start + len(code)] = code
+    for offset, vector in zip((6, 4, 2), ("nmi", "reset", "irq")):
+        prg[len(prg) - offset : len(prg) - offset + 2] = symbols[vector].to_bytes(2, "little")
+
+    chr_rom = bytes((offset // CHR_BANK_SIZE) & 0xFF for offset in range(chr_size))
+
+    flags6 = ((mapper & 0x0F) << 4) | (0x01 if vertical else 0x00)
+    flags7 = mapper & 0xF0
+    header = bytes([0x4E, 0x45, 0x53, 0x1A, prg_size // 0x4000, chr_size // 0x2000, flags6, flags7])
+    return header + bytes(8) + bytes(prg) + chr_rom
+
+
+def main():
+    for output, *rom in ROMS:
+        path = os.path.join(OUT_DIR, output)
+        os.makedirs(os.path.dirname(path), exist_ok=True)
+        with open(path, "wb") as f:
+            f.write(build_rom(*rom))
+        print(f"built {output}", file=sys.stderr)
+
+
+if __name__ == "__main__":
+    try:
+        main()
+    except AsmError as err:
+        sys.exit(f"error: {err}")
@@ -0,0 +1,48 @@
+; Irem G-101 (mapper 032) PRG/CHR banking and mirroring test.
+;
+; Records, in order:
+;
+; 1. PRG-ROM banks after writing 4 to $8000 and 6 to $A000 in PRG mode 0: 4 6 14 15.
+; 2. PRG-ROM banks after setting $9000 bit 1, which swaps $8000 and $C000: 14 6 4 15.
+; 3. CHR-ROM banks after writing 17, 34, 51, 68, 85, 102, 119 and 127 to $B000-$B007.
+; 4. Mirroring after writing $02 then $03 to $9000, see `check_mirroring`: C B C B, then B B C C.
+
+.include "common.inc"
+
+main:
+    lda #4
+    sta $8000
+    lda #6
+    sta $A000
+    lda #$00
+    sta $9000
+    jsr record_prg_banks
+    lda #$02
+    sta $9000
+    jsr record_prg_banks
+
+    ldx #$00
+write_chr_bank:
+    lda chr_banks,x
+    sta $B000,x
+    inx
+    cpx #8
+    bne write_chr_bank
+    jsr record_chr_banks
+
+    lda #$02
+    sta $9000
+    jsr check_mirroring
+    lda #$03
+    sta $9000
+    jsr check_mirroring
+
+forever:
+    jmp forever
+
+chr_banks:
+    .byte 17, 34, 51, 68, 85, 102, 119, 127
+
+nmi:
+irq:
+    rti
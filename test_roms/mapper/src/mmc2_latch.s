@@ -0,0 +1,100 @@
+; MMC2 (mapper 009) and MMC4 (mapper 010) PRG banking and CHR latch test.
+;
+; 4K CHR-ROM bank N reads as 4N at $x000 and 4N + 3 at $xFD8-$xFEF. Records, in order:
+;
+; 1. PRG-ROM banks after writing 3 to $A000. MMC2 selects an 8K bank followed by the last three:
+;    3 13 14 15. MMC4 selects a 16K bank followed by the last one: 6 7 14 15.
+; 2. $0000 after reading $0FE8, which selects the $FE bank: 8.
+; 3. $0FD8 and then $0000. The byte that triggers the latch still comes from the $FE bank: 11 4.
+; 4. $0FE9 and then $0000. Only MMC4 triggers latch 0 on $0FE9-$0FEF: MMC2 7 4, MMC4 7 8.
+; 5. $0000 after reading $0FE8 then $0FDF. Only MMC4 triggers latch 0 on $0FD9-$0FDF: MMC2 8,
+;    MMC4 4.
+; 6. $1000 after reading $1FE8, then $1FDF and $1000. Both trigger latch 1 on $1FD8-$1FDF and
+;    $1FE8-$1FEF: 16 19 12.
+; 7. $0000 after selecting the $FD latch and writing 5 to $B000. Bank writes apply to the
+;    selected latch immediately: 20.
+; 8. Mirroring after writing 0 then 1 to $F000, see `check_mirroring`: C B C B, then B B C C.
+
+.include "common.inc"
+
+main:
+    lda #3
+    sta $A000
+    jsr record_prg_banks
+
+    lda #1
+    sta $B000
+    lda #2
+    sta $C000
+    lda #3
+    sta $D000
+    lda #4
+    sta $E000
+
+    lda #$0F
+    ldx #$E8
+    jsr read_ppu
+    jsr record_chr0
+
+    lda #$0F
+    ldx #$D8
+    jsr read_ppu
+    jsr record
+    jsr record_chr0
+
+    lda #$0F
+    ldx #$E9
+    jsr read_ppu
+    jsr record
+    jsr record_chr0
+
+    lda #$0F
+    ldx #$E8
+    jsr read_ppu
+    lda #$0F
+    ldx #$DF
+    jsr read_ppu
+    jsr record_chr0
+
+    lda #$1F
+    ldx #$E8
+    jsr read_ppu
+    jsr record_chr1
+    lda #$1F
+    ldx #$DF
+    jsr read_ppu
+    jsr record
+    jsr record_chr1
+
+    lda #$0F
+    ldx #$D8
+    jsr read_ppu
+    lda #5
+    sta $B000
+    jsr record_chr0
+
+    lda #0
+    sta $F000
+    jsr check_mirroring
+    lda #1
+    sta $F000
+    jsr check_mirroring
+
+forever:
+    jmp forever
+
+record_chr0:
+    lda #$00
+    ldx #$00
+    jsr read_ppu
+    jmp record
+
+record_chr1:
+    lda #$10
+    ldx #$00
+    jsr read_ppu
+    jmp record
+
+nmi:
+irq:
+    rti
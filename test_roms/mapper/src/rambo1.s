@@ -0,0 +1,113 @@
+; Tengen RAMBO-1 (mapper 064) PRG/CHR banking, mirroring and scanline IRQ test.
+;
+; Records, in order:
+;
+; 1. PRG-ROM banks after writing 2, 3 and 4 to R6, R7 and RF: 2 3 4 15.
+; 2. PRG-ROM banks after setting $8000 bit 6, which rotates R6, R7 and RF: 4 2 3 15.
+; 3. CHR-ROM banks after writing 10, 20, 30, 40, 50, 60, 13 and 23 to R0-R5, R8 and R9, with
+;    2K banks at $0000-$0FFF: 10 11 20 21 30 40 50 60.
+; 4. CHR-ROM banks after setting $8000 bit 5, which maps R8 and R9 at $0400 and $0C00:
+;    10 13 20 23 30 40 50 60.
+; 5. CHR-ROM banks after also setting $8000 bit 7, which swaps the pattern tables:
+;    30 40 50 60 10 13 20 23.
+; 6. Mirroring after writing 0 then 1 to $A000, see `check_mirroring`: C B C B, then B B C C.
+;
+; It then enables rendering with background tiles at $0000 and sprites at $1000, so PPU A12
+; rises once per scanline around dot 260. Each NMI sets an IRQ latch of 100 and the first IRQ of
+; the frame sets a latch of 50. After a write to $C001 the next A12 rise reloads the counter
+; with the latch plus one, one scanline more than the MMC3, so the IRQs fire on scanlines 100
+; and 152 of every frame. The Rust test watches the IRQ line for these.
+
+.include "common.inc"
+
+irq_count = $01
+
+main:
+    ldx #$00
+write_prg_bank:
+    lda prg_registers,x
+    sta $8000
+    lda prg_banks,x
+    sta $8001
+    inx
+    cpx #3
+    bne write_prg_bank
+    jsr record_prg_banks
+    lda #$40
+    sta $8000
+    jsr record_prg_banks
+
+    ldx #$00
+write_chr_bank:
+    lda chr_registers,x
+    sta $8000
+    lda chr_banks,x
+    sta $8001
+    inx
+    cpx #8
+    bne write_chr_bank
+    lda #$00
+    sta $8000
+    jsr record_chr_banks
+    lda #$20
+    sta $8000
+    jsr record_chr_banks
+    lda #$A0
+    sta $8000
+    jsr record_chr_banks
+    lda #$00
+    sta $8000
+
+    lda #0
+    sta $A000
+    jsr check_mirroring
+    lda #1
+    sta $A000
+    jsr check_mirroring
+
+    bit PPUSTATUS
+wait_vblank:
+    bit PPUSTATUS
+    bpl wait_vblank
+    lda #$88
+    sta PPUCTRL
+    lda #$18
+    sta PPUMASK
+    cli
+
+forever:
+    jmp forever
+
+prg_registers:
+    .byte $06, $07, $0F
+prg_banks:
+    .byte 2, 3, 4
+chr_registers:
+    .byte $00, $01, $02, $03, $04, $05, $08, $09
+chr_banks:
+    .byte 10, 20, 30, 40, 50, 60, 13, 23
+
+nmi:
+    pha
+    lda #0
+    sta irq_count
+    lda #100
+    sta $C000
+    sta $C001
+    sta $E001
+    pla
+    rti
+
+irq:
+    pha
+    sta $E000
+    lda irq_count
+    bne irq_done
+    inc irq_count
+    lda #50
+    sta $C000
+    sta $C001
+    sta $E001
+irq_done:
+    pla
+    rti
@@ -0,0 +1,93 @@
+; Jaleco SS88006 (mapper 018) PRG/CHR banking, mirroring and CPU cycle IRQ test.
+;
+; Bank registers are written a nibble at a time, low nibble to the even address of each pair.
+; Records, in order:
+;
+; 1. PRG-ROM banks after writing 3, 5 and 9 to $8000/$8001, $8002/$8003 and $9000/$9001:
+;    3 5 9 15.
+; 2. CHR-ROM banks after writing 10, 21, 32, 43, 54, 65, 76 and 87 to the pairs at $A000-$D003.
+; 3. Mirroring after writing 0, 1 then 2 to $F002, see `check_mirroring`: B B C C, C B C B, then
+;    C C C C.
+; 4. The loop count when a 16-bit IRQ counter reloaded with 1002 fires. The counter decrements
+;    every CPU cycle once enabled by $F001, and each loop iteration is an `inc` (5 cycles) and a
+;    taken `bne` (3 cycles). The counter reaches 0 1002 cycles after the `sta $F001`, during the
+;    `inc` of the 126th iteration, so the IRQ is taken with a count of 126.
+
+.include "common.inc"
+
+ptr = $02
+count = $04
+
+main:
+    ldx #$00
+write_bank:
+    lda registers_lo,x
+    sta ptr
+    lda registers_hi,x
+    sta ptr + 1
+    lda banks,x
+    jsr write_nibbles
+    inx
+    cpx #11
+    bne write_bank
+    jsr record_prg_banks
+    jsr record_chr_banks
+
+    lda #0
+    sta $F002
+    jsr check_mirroring
+    lda #1
+    sta $F002
+    jsr check_mirroring
+    lda #2
+    sta $F002
+    jsr check_mirroring
+
+    lda #$0A
+    sta $E000
+    lda #$0E
+    sta $E001
+    lda #$03
+    sta $E002
+    lda #$00
+    sta $E003
+    sta count
+    sta $F000
+    cli
+    lda #$01
+    sta $F001
+count_cycles:
+    inc count
+    bne count_cycles
+
+forever:
+    jmp forever
+
+; Writes the low nibble of A to the register at `ptr` and the high nibble to the next one.
+write_nibbles:
+    ldy #0
+    sta (ptr),y
+    lsr
+    lsr
+    lsr
+    lsr
+    iny
+    sta (ptr),y
+    rts
+
+registers_lo:
+    .byte $00, $02, $00, $00, $02, $00, $02, $00, $02, $00, $02
+registers_hi:
+    .byte $80, $80, $90, $A0, $A0, $B0, $B0, $C0, $C0, $D0, $D0
+banks:
+    .byte 3, 5, 9, 10, 21, 32, 43, 54, 65, 76, 87
+
+nmi:
+    rti
+
+irq:
+    lda count
+    jsr record
+    lda #$00
+    sta $F001
+    jmp forever
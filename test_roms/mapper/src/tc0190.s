@@ -0,0 +1,24 @@
+; Taito TC0190 (mapper 033) PRG/CHR banking and mirroring test.
+;
+; Records the banks listed in `taito_banking.inc`, then the mirroring after writing $05 then $45
+; to $8000, see `check_mirroring`: C B C B, then B B C C.
+
+.include "common.inc"
+.include "taito_banking.inc"
+
+main:
+    jsr check_taito_banking
+
+    lda #$05
+    sta $8000
+    jsr check_mirroring
+    lda #$45
+    sta $8000
+    jsr check_mirroring
+
+forever:
+    jmp forever
+
+nmi:
+irq:
+    rti
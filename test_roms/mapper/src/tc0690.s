@@ -0,0 +1,63 @@
+; Taito TC0690 (mapper 048) PRG/CHR banking, mirroring and scanline IRQ test.
+;
+; Records the banks listed in `taito_banking.inc`, then the mirroring after writing $00 then $40
+; to $E000, see `check_mirroring`: C B C B, then B B C C.
+;
+; It then enables rendering with background tiles at $0000 and sprites at $1000, so PPU A12
+; rises once per scanline around dot 260. Each NMI sets an IRQ latch of 100 and the first IRQ of
+; the frame sets a latch of 50. The latch is written inverted to $C000 and the counter reloads
+; on the first A12 rise after $C001, then counts down once per scanline, so the IRQs fire on
+; scanlines 99 and 150 of every frame. The Rust test watches the IRQ line for these.
+
+.include "common.inc"
+.include "taito_banking.inc"
+
+irq_count = $01
+
+main:
+    jsr check_taito_banking
+
+    lda #$00
+    sta $E000
+    jsr check_mirroring
+    lda #$40
+    sta $E000
+    jsr check_mirroring
+
+    bit PPUSTATUS
+wait_vblank:
+    bit PPUSTATUS
+    bpl wait_vblank
+    lda #$88
+    sta PPUCTRL
+    lda #$18
+    sta PPUMASK
+    cli
+
+forever:
+    jmp forever
+
+nmi:
+    pha
+    lda #0
+    sta irq_count
+    lda #100 ^ $FF
+    sta $C000
+    sta $C001
+    sta $C002
+    pla
+    rti
+
+irq:
+    pha
+    sta $C003
+    lda irq_count
+    bne irq_done
+    inc irq_count
+    lda #50 ^ $FF
+    sta $C000
+    sta $C001
+    sta $C002
+irq_done:
+    pla
+    rti
@@ -1,10 +1,27 @@
-use anyhow::Context;
+//! Generates `config/game_database.txt`.
+//!
+//! Entries are added or updated in place, keyed by CRC32 and SHA-1 of PRG+CHR ROM. Sources are
+//! `.nes` ROM files and `nes20db` or `NstDatabase` style XML files. Columns found in XML take
+//! precedence over those derived from ROM headers. Only `NstDatabase` provides the `Board`, `PCB`
+//! and `Chip` columns.
+//!
+//! USAGE:
+//!     generate_db [OPTIONS] [path]
+//!
+//! OPTIONS:
+//!     -x, --xml <xml>...    A `nes20db` or `NstDatabase` style XML file to import.
+//!
+//! ARGS:
+//!     <path>    The NES ROM or a directory containing `.nes` ROM files. [default: current
+//!               directory, unless `--xml` is provided]
+
+use anyhow::{anyhow, Context};
 use std::{
-    collections::hash_map::DefaultHasher,
+    collections::BTreeMap,
     env,
     ffi::OsStr,
-    fs::File,
-    hash::{Hash, Hasher},
+    fmt,
+    fs::{self, File},
     io::{BufWriter, Write},
     path::{Path, PathBuf},
 };
@@ -12,94 +29,509 @@ use structopt::StructOpt;
 use tetanes::{cart::Cart, mem::RamState, NesResult};
 
 const GAME_DB: &str = "config/game_database.txt";
+const HEADER: &str = "# Fields: Crc32, Sha1, Region, Board, PCB, Chip, Mapper, PrgRomSize, ChrRomSize, ChrRamSize, PrgRamSize, Battery, Mirroring, SubMapper, Title";
 
 fn main() -> NesResult<()> {
     let opt = Opt::from_args();
-    let path = opt
-        .path
-        .unwrap_or_else(|| env::current_dir().unwrap_or_default());
-    let header = "# Fields: Hash, Region, Board, PCB, Chip, Mapper, PrgRomSize, ChrRomSize, ChrRamSize, PrgRamSize, Battery, Mirroring, SubMapper, Title";
-    if path.is_dir() {
-        let mut db_file =
-            BufWriter::new(File::create(GAME_DB).context("failed to open game_database.txt")?);
-        let paths: Vec<PathBuf> = path
-            .read_dir()
-            .unwrap_or_else(|err| panic!("unable read directory {path:?}: {err}"))
-            .filter_map(Result::ok)
-            .filter(|f| f.path().extension() == Some(OsStr::new("nes")))
-            .map(|f| f.path())
-            .collect();
-        let mut boards: Vec<(u64, String)> =
-            paths.iter().map(get_info).filter_map(Result::ok).collect();
-        boards.sort_by_key(|board| board.0);
-        writeln!(db_file, "{header}")?;
-        let mut last_hash = 0;
-        for board in &boards {
-            if board.0 != last_hash {
-                writeln!(db_file, "{}", board.1)?;
+    let path = match opt.path {
+        Some(path) => Some(path),
+        None if opt.xml.is_empty() => Some(env::current_dir().unwrap_or_default()),
+        None => None,
+    };
+
+    let mut db = load_db()?;
+    for xml in &opt.xml {
+        for entry in load_xml(xml)? {
+            upsert(&mut db, entry);
+        }
+    }
+
+    if let Some(path) = path {
+        let paths: Vec<PathBuf> = if path.is_dir() {
+            path.read_dir()
+                .unwrap_or_else(|err| panic!("unable read directory {path:?}: {err}"))
+                .filter_map(Result::ok)
+                .filter(|f| f.path().extension() == Some(OsStr::new("nes")))
+                .map(|f| f.path())
+                .collect()
+        } else if path.is_file() {
+            vec![path]
+        } else {
+            return Err(anyhow!("invalid path: {path:?}"));
+        };
+        for path in &paths {
+            match get_info(path) {
+                Ok(entry) => upsert(&mut db, entry),
+                Err(err) => eprintln!("skipping {path:?}: {err:?}"),
             }
-            last_hash = board.0;
         }
-    } else if path.is_file() {
-        todo!("adding individual files is not yet supported");
     }
-    Ok(())
+
+    save_db(&db)
 }
 
-fn get_info<P: AsRef<Path>>(path: P) -> NesResult<(u64, String)> {
-    let path = path.as_ref();
-    let cart = Cart::from_path(path, RamState::default())?;
-    let mut hasher = DefaultHasher::new();
-    cart.prg_rom().hash(&mut hasher);
-    let filename = path.file_name().unwrap_or_default();
-    let hash = hasher.finish();
-    let region = match filename.to_str() {
-        Some(filename) => {
-            if filename.contains("Europe") || filename.contains("PAL") {
-                "PAL"
-            } else {
-                "NTSC"
-            }
+/// A game database entry. Columns that are unknown are `None` and written as empty fields.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[must_use]
+struct DbEntry {
+    crc32: u32,
+    sha1: String,
+    region: Option<String>,
+    board: Option<String>,
+    pcb: Option<String>,
+    chip: Option<String>,
+    mapper: Option<u16>,
+    prg_rom_size: Option<usize>,
+    chr_rom_size: Option<usize>,
+    chr_ram_size: Option<usize>,
+    prg_ram_size: Option<usize>,
+    battery: Option<bool>,
+    mirroring: Option<String>,
+    submapper: Option<u8>,
+    title: Option<String>,
+}
+
+impl DbEntry {
+    fn key(&self) -> (u32, String) {
+        (self.crc32, self.sha1.to_ascii_lowercase())
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(15, ',').map(str::trim);
+        let mut next = || fields.next().filter(|field| !field.is_empty());
+        let crc32 = u32::from_str_radix(next()?, 16).ok()?;
+        let sha1 = next().unwrap_or_default().to_string();
+        Some(Self {
+            crc32,
+            sha1,
+            region: next().map(ToString::to_string),
+            board: next().map(ToString::to_string),
+            pcb: next().map(ToString::to_string),
+            chip: next().map(ToString::to_string),
+            mapper: next().and_then(|field| field.parse().ok()),
+            prg_rom_size: next().and_then(|field| field.parse().ok()),
+            chr_rom_size: next().and_then(|field| field.parse().ok()),
+            chr_ram_size: next().and_then(|field| field.parse().ok()),
+            prg_ram_size: next().and_then(|field| field.parse().ok()),
+            battery: next().and_then(|field| field.parse().ok()),
+            mirroring: next().map(ToString::to_string),
+            submapper: next().and_then(|field| field.parse().ok()),
+            title: next().map(|field| unquote(field).to_string()),
+        })
+    }
+
+    /// Fills in any unknown columns from `other`.
+    fn merge(&mut self, other: Self) {
+        macro_rules! merge {
+            ($($field:ident),*) => {$(
+                if self.$field.is_none() {
+                    self.$field = other.$field;
+                }
+            )*};
         }
-        None => "NTSC",
-    };
-    let board = "";
-    let pcb = "";
-    let chip = "";
-
-    let chr_rom_banks = cart.chr_rom().len() / (8 * 1024);
-    let chr_ram_banks = cart.chr_ram().len() / (8 * 1024);
-    let prg_rom_banks = cart.prg_ram().len() / (16 * 1024);
-    let prg_ram_banks = cart.prg_ram().len() / (16 * 1024);
-    let mirroring = cart.mirroring();
-
-    Ok((
-        hash,
-        format!(
-            "{},{},{},{},{},{},{},{},{},{},{},{:?},{},{:?}",
-            hash,
+        if self.sha1.is_empty() {
+            self.sha1 = other.sha1;
+        }
+        merge!(
             region,
             board,
             pcb,
             chip,
-            cart.mapper_num(),
-            prg_rom_banks,
-            chr_rom_banks,
-            chr_ram_banks,
-            prg_ram_banks,
-            cart.battery_backed(),
+            mapper,
+            prg_rom_size,
+            chr_rom_size,
+            chr_ram_size,
+            prg_ram_size,
+            battery,
             mirroring,
-            cart.submapper_num(),
-            filename
-        ),
-    ))
+            submapper,
+            title
+        );
+    }
+}
+
+impl fmt::Display for DbEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn opt<T: ToString>(value: &Option<T>) -> String {
+            value.as_ref().map(ToString::to_string).unwrap_or_default()
+        }
+        write!(
+            f,
+            "{:08X},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.crc32,
+            self.sha1.to_ascii_lowercase(),
+            opt(&self.region),
+            opt(&self.board),
+            opt(&self.pcb),
+            opt(&self.chip),
+            opt(&self.mapper),
+            opt(&self.prg_rom_size),
+            opt(&self.chr_rom_size),
+            opt(&self.chr_ram_size),
+            opt(&self.prg_ram_size),
+            opt(&self.battery),
+            opt(&self.mirroring),
+            opt(&self.submapper),
+            self.title
+                .as_ref()
+                .map(|title| format!("\"{title}\""))
+                .unwrap_or_default(),
+        )
+    }
+}
+
+/// Strips the quotes around a title. Titles are the last column and are written as-is, so they
+/// may contain commas and quotes.
+fn unquote(title: &str) -> &str {
+    title
+        .strip_prefix('"')
+        .and_then(|title| title.strip_suffix('"'))
+        .unwrap_or(title)
+}
+
+/// Adds or updates an entry. Known columns in `entry` replace existing ones.
+fn upsert(db: &mut BTreeMap<(u32, String), DbEntry>, mut entry: DbEntry) {
+    if let Some(existing) = db.remove(&entry.key()) {
+        entry.merge(existing);
+    }
+    db.insert(entry.key(), entry);
+}
+
+fn load_db() -> NesResult<BTreeMap<(u32, String), DbEntry>> {
+    let db = fs::read_to_string(GAME_DB).context("failed to read game_database.txt")?;
+    Ok(db
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(DbEntry::parse)
+        .map(|entry| (entry.key(), entry))
+        .collect())
+}
+
+fn save_db(db: &BTreeMap<(u32, String), DbEntry>) -> NesResult<()> {
+    let mut db_file =
+        BufWriter::new(File::create(GAME_DB).context("failed to open game_database.txt")?);
+    writeln!(db_file, "{HEADER}")?;
+    for entry in db.values() {
+        writeln!(db_file, "{entry}")?;
+    }
+    Ok(())
+}
+
+/// Loads entries from `nes20db` `<game>` or `NstDatabase` `<cartridge>` elements.
+///
+/// <https://forums.nesdev.org/viewtopic.php?t=19940>
+fn load_xml(path: &Path) -> NesResult<Vec<DbEntry>> {
+    let xml = fs::read_to_string(path).with_context(|| format!("failed to read {path:?}"))?;
+    let doc = roxmltree::Document::parse(&xml).with_context(|| format!("invalid xml {path:?}"))?;
+    let entries: Vec<DbEntry> = doc
+        .descendants()
+        .filter_map(|node| match node.tag_name().name() {
+            "game" if child(node, "rom").is_some() => parse_nes20db_game(node),
+            "cartridge" => parse_nstdb_cartridge(node),
+            _ => None,
+        })
+        .collect();
+    println!("imported {} entries from {path:?}", entries.len());
+    Ok(entries)
+}
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn attr<T: std::str::FromStr>(node: Option<roxmltree::Node<'_, '_>>, name: &str) -> Option<T> {
+    node.and_then(|node| node.attribute(name))
+        .and_then(|value| value.parse().ok())
+}
+
+fn size_sum(sizes: &[Option<usize>]) -> Option<usize> {
+    sizes
+        .iter()
+        .flatten()
+        .copied()
+        .reduce(|total, size| total + size)
+}
+
+fn parse_nes20db_game(game: roxmltree::Node<'_, '_>) -> Option<DbEntry> {
+    let rom = child(game, "rom")?;
+    let pcb = child(game, "pcb");
+    let console = child(game, "console");
+    let title = game
+        .children()
+        .find(roxmltree::Node::is_comment)
+        .and_then(|comment| comment.text())
+        .and_then(|path| path.trim().rsplit(['\\', '/']).next())
+        .map(ToString::to_string);
+    Some(DbEntry {
+        crc32: u32::from_str_radix(rom.attribute("crc32")?, 16).ok()?,
+        sha1: rom
+            .attribute("sha1")
+            .unwrap_or_default()
+            .to_ascii_lowercase(),
+        // Multi-region games are left unknown so user configuration decides
+        region: attr::<u8>(console, "region").and_then(|region| {
+            match region {
+                0 => Some("NTSC"),
+                1 => Some("PAL"),
+                3 => Some("Dendy"),
+                _ => None,
+            }
+            .map(ToString::to_string)
+        }),
+        // nes20db has no board, PCB or chip names, those are only imported from NstDatabase
+        board: None,
+        pcb: None,
+        chip: None,
+        mapper: attr(pcb, "mapper"),
+        prg_rom_size: attr(child(game, "prgrom"), "size"),
+        chr_rom_size: attr(child(game, "chrrom"), "size"),
+        chr_ram_size: size_sum(&[
+            attr(child(game, "chrram"), "size"),
+            attr(child(game, "chrnvram"), "size"),
+        ]),
+        prg_ram_size: size_sum(&[
+            attr(child(game, "prgram"), "size"),
+            attr(child(game, "prgnvram"), "size"),
+        ]),
+        battery: attr::<u8>(pcb, "battery").map(|battery| battery == 1),
+        mirroring: pcb
+            .and_then(|pcb| pcb.attribute("mirroring"))
+            .and_then(parse_mirroring),
+        submapper: attr(pcb, "submapper"),
+        title,
+    })
+}
+
+fn parse_nstdb_cartridge(cartridge: roxmltree::Node<'_, '_>) -> Option<DbEntry> {
+    let board = child(cartridge, "board");
+    let parse_size = |name: &str| {
+        board
+            .map(|board| {
+                board
+                    .children()
+                    .filter(|child| child.has_tag_name(name))
+                    .filter_map(|child| child.attribute("size"))
+                    .filter_map(|size| size.trim_end_matches(['k', 'K']).parse::<usize>().ok())
+                    .map(|size| size * 1024)
+                    .sum::<usize>()
+            })
+            .filter(|size| *size > 0)
+    };
+    let pad = board.and_then(|board| child(board, "pad"));
+    let chips: Vec<&str> = board
+        .map(|board| {
+            board
+                .children()
+                .filter(|child| child.has_tag_name("chip"))
+                .filter_map(|chip| chip.attribute("type"))
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(DbEntry {
+        crc32: u32::from_str_radix(cartridge.attribute("crc")?, 16).ok()?,
+        sha1: cartridge
+            .attribute("sha1")
+            .unwrap_or_default()
+            .to_ascii_lowercase(),
+        region: cartridge.attribute("system").map(|system| {
+            if system.contains("PAL") {
+                "PAL"
+            } else if system.contains("Dendy") {
+                "Dendy"
+            } else {
+                "NTSC"
+            }
+            .to_string()
+        }),
+        board: board
+            .and_then(|board| board.attribute("type"))
+            .map(ToString::to_string),
+        pcb: board
+            .and_then(|board| board.attribute("pcb"))
+            .map(ToString::to_string),
+        chip: (!chips.is_empty()).then(|| chips.join(" ")),
+        mapper: attr(board, "mapper"),
+        prg_rom_size: parse_size("prg"),
+        chr_rom_size: parse_size("chr"),
+        chr_ram_size: parse_size("vram"),
+        prg_ram_size: parse_size("wram"),
+        battery: board.map(|board| {
+            board
+                .descendants()
+                .any(|node| node.attribute("battery") == Some("1"))
+        }),
+        mirroring: pad.and_then(|pad| match (pad.attribute("h"), pad.attribute("v")) {
+            (Some("1"), _) => Some("Vertical".to_string()),
+            (_, Some("1")) => Some("Horizontal".to_string()),
+            _ => None,
+        }),
+        submapper: None,
+        title: None,
+    })
+}
+
+/// Maps `nes20db` mirroring to `Mirroring` names. Mapper-controlled mirroring is left unknown.
+fn parse_mirroring(mirroring: &str) -> Option<String> {
+    match mirroring {
+        "H" => Some("Horizontal"),
+        "V" => Some("Vertical"),
+        "4" => Some("FourScreen"),
+        _ => None,
+    }
+    .map(ToString::to_string)
+}
+
+fn get_info<P: AsRef<Path>>(path: P) -> NesResult<DbEntry> {
+    let path = path.as_ref();
+    let cart = Cart::from_path(path, RamState::default())?;
+    let filename = path.file_name().and_then(OsStr::to_str).unwrap_or_default();
+    let header = cart.header();
+    let region = match header.region() {
+        Some(region) => region.as_ref().to_string(),
+        None if filename.contains("Europe") || filename.contains("PAL") => "PAL".to_string(),
+        None => "NTSC".to_string(),
+    };
+    let ram_size = |shift: u8| {
+        [shift & 0x0F, shift >> 4]
+            .iter()
+            .filter(|shift| **shift > 0)
+            .map(|shift| 64usize << shift)
+            .sum::<usize>()
+    };
+    // Only NES 2.0 headers specify RAM sizes, mappers allocate RAM otherwise
+    let (prg_ram_size, chr_ram_size) = if header.version == 2 {
+        (
+            Some(ram_size(header.prg_ram_shift)),
+            Some(ram_size(header.chr_ram_shift)),
+        )
+    } else {
+        (None, None)
+    };
+
+    Ok(DbEntry {
+        crc32: cart.crc32(),
+        sha1: cart.sha1().to_string(),
+        region: Some(region),
+        board: None,
+        pcb: None,
+        chip: None,
+        mapper: Some(cart.mapper_num()),
+        prg_rom_size: Some(cart.prg_rom().len()),
+        chr_rom_size: Some(cart.chr_rom().len()),
+        chr_ram_size,
+        prg_ram_size,
+        battery: Some(cart.battery_backed()),
+        mirroring: Some(format!("{:?}", cart.mirroring())),
+        submapper: Some(cart.submapper_num()),
+        title: Some(filename.to_string()),
+    })
 }
 
 #[derive(StructOpt, Debug)]
 #[must_use]
 struct Opt {
     #[structopt(
-        help = "The NES ROM or a directory containing `.nes` ROM files. [default: current directory]"
+        short = "x",
+        long = "xml",
+        help = "A `nes20db` or `NstDatabase` style XML file to import."
+    )]
+    xml: Vec<PathBuf>,
+    #[structopt(
+        help = "The NES ROM or a directory containing `.nes` ROM files. [default: current directory, unless `--xml` is provided]"
     )]
     path: Option<PathBuf>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_nes20db() {
+        let xml = r#"<nes20db>
+            <game>
+                <!-- Licensed\Super Mario Bros. (World).nes -->
+                <prgrom size="32768" crc32="5CF548D3" sha1="FACEE9C577A5262DBE33AC4930BB0B58C8C037F7"/>
+                <chrrom size="8192" crc32="867B51AD" sha1="1C8D1D3D3C9E5E3B9C9B5E7D5E6F4E8A8C3E1B2D"/>
+                <rom size="40960" crc32="3337EC46" sha1="EA343F4E445A9050D4B4FBAC2C77D0693B1D0922"/>
+                <pcb mapper="0" submapper="0" mirroring="V" battery="0"/>
+                <console type="0" region="0"/>
+            </game>
+        </nes20db>"#;
+        let doc = roxmltree::Document::parse(xml).expect("valid xml");
+        let game = doc
+            .descendants()
+            .find(|node| node.has_tag_name("game"))
+            .expect("game");
+        let entry = parse_nes20db_game(game).expect("valid entry");
+        assert_eq!(
+            entry.to_string(),
+            r#"3337EC46,ea343f4e445a9050d4b4fbac2c77d0693b1d0922,NTSC,,,,0,32768,8192,,,false,Vertical,0,"Super Mario Bros. (World).nes""#
+        );
+        assert_eq!(DbEntry::parse(&entry.to_string()), Some(entry));
+    }
+
+    #[test]
+    fn title_round_trip() {
+        let entry = DbEntry {
+            crc32: 0x1234_5678,
+            title: Some(r#"Zelda, The "Legend" of \ (Europe).nes"#.to_string()),
+            ..DbEntry::default()
+        };
+        assert_eq!(DbEntry::parse(&entry.to_string()), Some(entry));
+    }
+
+    #[test]
+    fn parse_nstdb() {
+        let xml = r#"<database>
+            <game>
+                <cartridge system="NES-PAL" crc="A0D8A1C4" sha1="D1E2D1F5E1C0A6B1A3E5D7C3B2A1F0E9D8C7B6A5">
+                    <board type="NES-SNROM" pcb="NES-SNROM-05" mapper="1">
+                        <prg size="128k"/>
+                        <wram size="8k" battery="1"/>
+                        <vram size="8k"/>
+                        <chip type="MMC1B2"/>
+                    </board>
+                </cartridge>
+            </game>
+        </database>"#;
+        let doc = roxmltree::Document::parse(xml).expect("valid xml");
+        let cartridge = doc
+            .descendants()
+            .find(|node| node.has_tag_name("cartridge"))
+            .expect("cartridge");
+        let entry = parse_nstdb_cartridge(cartridge).expect("valid entry");
+        assert_eq!(
+            entry.to_string(),
+            "A0D8A1C4,d1e2d1f5e1c0a6b1a3e5d7c3b2a1f0e9d8c7b6a5,PAL,NES-SNROM,NES-SNROM-05,MMC1B2,1,131072,,8192,8192,true,,,"
+        );
+    }
+
+    #[test]
+    fn merge_entries() {
+        let mut db = BTreeMap::new();
+        let entry = DbEntry {
+            crc32: 0x1234_5678,
+            mapper: Some(1),
+            title: Some("Rom.nes".to_string()),
+            ..DbEntry::default()
+        };
+        upsert(&mut db, entry.clone());
+        upsert(
+            &mut db,
+            DbEntry {
+                mapper: Some(4),
+                board: Some("NES-TLROM".to_string()),
+                title: None,
+                ..entry
+            },
+        );
+        let entry = db.values().next().expect("entry");
+        assert_eq!(entry.mapper, Some(4));
+        assert_eq!(entry.board.as_deref(), Some("NES-TLROM"));
+        assert_eq!(entry.title.as_deref(), Some("Rom.nes"));
+    }
+}
@@ -1,5 +1,6 @@
 use tetanes::{
     audio::{AudioMixer, NesAudioCallback},
+    common::{NesRegion, Regional},
     control_deck::ControlDeck,
     input::{JoypadBtnState, Slot},
     mem::RamState,
@@ -73,6 +74,14 @@ impl Nes {
         self.audio.output_frequency()
     }
 
+    pub fn frame_rate(&self) -> f32 {
+        match self.control_deck.region() {
+            NesRegion::Ntsc => 60.0,
+            NesRegion::Pal => 50.0,
+            NesRegion::Dendy => 59.0,
+        }
+    }
+
     pub fn clock_frame(&mut self) {
         self.control_deck.clock_frame().expect("valid clock");
         if self.sound {
@@ -87,7 +96,13 @@ impl Nes {
         self.control_deck
             .load_rom("ROM", &mut bytes)
             .expect("valid rom");
-        self.callback.clear();
+        // Region may have changed the input sample rate
+        self.audio = AudioMixer::new(
+            self.control_deck.sample_rate(),
+            self.audio.output_frequency(),
+            4096,
+        );
+        self.callback = self.audio.open_callback().expect("valid callback");
     }
 
     pub fn handle_event(&mut self, key: &str, pressed: bool, repeat: bool) -> bool {
@@ -6,7 +6,6 @@ const CLIP_TOP = 8;
 const CLIP_BOTTOM = 8;
 const CANVAS_ID = "view";
 const BACK_CANVAS_ID = "backView";
-const TARGET_FPS = 1000 / 60;
 
 type Rom = {
   name: string;
@@ -369,8 +368,9 @@ class State {
 
       const now = performance.now();
       const elapsed = now - this.lastFrameTime;
-      if (elapsed >= TARGET_FPS) {
-        this.lastFrameTime = now - (elapsed % TARGET_FPS);
+      const frameTime = 1000 / this.nes.frame_rate();
+      if (elapsed >= frameTime) {
+        this.lastFrameTime = now - (elapsed % frameTime);
         this.fps.tick();
 
         this.nes.clock_frame();